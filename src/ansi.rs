@@ -88,6 +88,8 @@ pub enum EscSequence {
     NextLine,
     /// ESC c - Reset
     Reset,
+    /// ESC H - Horizontal Tab Set (HTS) at the cursor column
+    TabSet,
     /// ESC = - Application Keypad
     ApplicationKeypad,
     /// ESC > - Normal Keypad
@@ -183,6 +185,11 @@ impl AnsiParser {
         }
     }
 
+    /// Whether an escape sequence has started but not yet completed
+    pub fn in_escape_sequence(&self) -> bool {
+        !matches!(self.state, ParserState::Ground | ParserState::Utf8)
+    }
+
     /// Reset the parser to ground state
     pub fn reset(&mut self) {
         self.state = ParserState::Ground;
@@ -373,6 +380,10 @@ impl AnsiParser {
                 self.state = ParserState::Ground;
                 Some(AnsiEvent::Esc(EscSequence::Reset))
             }
+            b'H' => {
+                self.state = ParserState::Ground;
+                Some(AnsiEvent::Esc(EscSequence::TabSet))
+            }
             b'=' => {
                 self.state = ParserState::Ground;
                 Some(AnsiEvent::Esc(EscSequence::ApplicationKeypad))
@@ -404,20 +415,16 @@ impl AnsiParser {
     fn escape_intermediate(&mut self, byte: u8) -> Option<AnsiEvent> {
         self.state = ParserState::Ground;
 
-        if self.esc_bytes.first() == Some(&b'(') {
-            return Some(AnsiEvent::Esc(EscSequence::DesignateG0(byte)));
-        }
-        if self.esc_bytes.first() == Some(&b')') {
-            return Some(AnsiEvent::Esc(EscSequence::DesignateG1(byte)));
-        }
-        if self.esc_bytes.first() == Some(&b'#') && byte == b'8' {
-            return Some(AnsiEvent::Esc(EscSequence::DecAlignmentTest));
+        let mut bytes = std::mem::take(&mut self.esc_bytes);
+        match (bytes.first(), byte) {
+            (Some(b'('), _) => Some(AnsiEvent::Esc(EscSequence::DesignateG0(byte))),
+            (Some(b')'), _) => Some(AnsiEvent::Esc(EscSequence::DesignateG1(byte))),
+            (Some(b'#'), b'8') => Some(AnsiEvent::Esc(EscSequence::DecAlignmentTest)),
+            _ => {
+                bytes.push(byte);
+                Some(AnsiEvent::Esc(EscSequence::Unknown(bytes)))
+            }
         }
-
-        self.esc_bytes.push(byte);
-        Some(AnsiEvent::Esc(EscSequence::Unknown(std::mem::take(
-            &mut self.esc_bytes,
-        ))))
     }
 
     fn csi_entry(&mut self, byte: u8) -> Option<AnsiEvent> {
@@ -874,6 +881,21 @@ mod tests {
         assert_eq!(events[1], AnsiEvent::Esc(EscSequence::RestoreCursor));
     }
 
+    #[test]
+    fn parse_consecutive_charset_designations() {
+        let mut parser = AnsiParser::new();
+        let events = parser.parse(b"\x1b(0\x1b)0\x1b(B");
+
+        assert_eq!(
+            events,
+            [
+                AnsiEvent::Esc(EscSequence::DesignateG0(b'0')),
+                AnsiEvent::Esc(EscSequence::DesignateG1(b'0')),
+                AnsiEvent::Esc(EscSequence::DesignateG0(b'B')),
+            ]
+        );
+    }
+
     #[test]
     fn malformed_sequence_recovery() {
         let mut parser = AnsiParser::new();
@@ -1081,6 +1103,7 @@ impl<'a> crate::vtparse::Handler for AnsiEventHandler<'a> {
             b'M' => AnsiEvent::Esc(EscSequence::ReverseIndex),
             b'E' => AnsiEvent::Esc(EscSequence::NextLine),
             b'c' => AnsiEvent::Esc(EscSequence::Reset),
+            b'H' => AnsiEvent::Esc(EscSequence::TabSet),
             b'=' => AnsiEvent::Esc(EscSequence::ApplicationKeypad),
             b'>' => AnsiEvent::Esc(EscSequence::NormalKeypad),
             _ => {
@@ -1164,7 +1187,7 @@ mod v2_tests {
         parser.parse(b"\x1b[5A");
         let events = parser.take_events();
 
-        assert!(events.len() >= 1);
+        assert!(!events.is_empty());
         if let Some(AnsiEvent::Csi(csi)) = events.first() {
             assert_eq!(csi.final_byte, b'A');
             assert_eq!(csi.params.len(), 1);
@@ -0,0 +1,137 @@
+//! asciinema Cast Export
+//!
+//! This module converts an execution trace into an asciinema v2 `.cast`
+//! file so a recorded run can be played back with `asciinema play`.
+//!
+//! Traces have no real timestamps, so event times are derived from ticks
+//! using a fixed tick duration.
+
+use crate::scenario::Step;
+use crate::trace::Trace;
+use serde_json::json;
+use std::io;
+use std::path::Path;
+
+/// Default playback duration of one tick, in milliseconds
+pub const DEFAULT_CAST_TICK_MS: u64 = 10;
+
+/// Render a trace as an asciinema v2 cast
+///
+/// The first line is the header; each following line is an event of the
+/// form `[time, code, data]`. Output recorded during a step is emitted at
+/// the step's end tick, and `resize` steps become `"r"` events.
+pub fn to_cast(trace: &Trace, tick_ms: u64) -> String {
+    let terminal = &trace.scenario.terminal;
+    let header = json!({
+        "version": 2,
+        "width": terminal.cols,
+        "height": terminal.rows,
+        "title": trace.scenario.name,
+    });
+
+    let mut lines = vec![header.to_string()];
+    // Bytes of a UTF-8 sequence split across two steps
+    let mut pending: Vec<u8> = Vec::new();
+
+    for step in &trace.steps {
+        let time = (step.end_tick * tick_ms) as f64 / 1000.0;
+
+        if !step.pty_output.is_empty() {
+            pending.extend_from_slice(&step.pty_output);
+            let data = take_utf8(&mut pending);
+            if !data.is_empty() {
+                lines.push(json!([time, "o", data]).to_string());
+            }
+        }
+
+        if let Step::Resize { cols, rows } = &step.step {
+            lines.push(json!([time, "r", format!("{}x{}", cols, rows)]).to_string());
+        }
+    }
+
+    if !pending.is_empty() {
+        let time = (trace.total_ticks * tick_ms) as f64 / 1000.0;
+        let data = String::from_utf8_lossy(&pending).into_owned();
+        lines.push(json!([time, "o", data]).to_string());
+    }
+
+    let mut cast = lines.join("\n");
+    cast.push('\n');
+    cast
+}
+
+/// Decode as much of `buf` as possible, leaving an incomplete trailing
+/// UTF-8 sequence in place for the next chunk
+fn take_utf8(buf: &mut Vec<u8>) -> String {
+    let complete = match std::str::from_utf8(buf) {
+        Ok(_) => buf.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        // Invalid (not merely truncated) bytes are replaced below
+        Err(_) => buf.len(),
+    };
+    let rest = buf.split_off(complete);
+    let data = String::from_utf8_lossy(buf).into_owned();
+    *buf = rest;
+    data
+}
+
+/// Write a trace as an asciinema v2 cast file
+pub fn write_cast(trace: &Trace, path: &Path, tick_ms: u64) -> Result<(), io::Error> {
+    std::fs::write(path, to_cast(trace, tick_ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::determinism::{BoundaryKind, DeterministicScheduler};
+    use crate::scenario::Scenario;
+    use crate::trace::TraceBuilder;
+
+    fn advance(scheduler: &DeterministicScheduler, ticks: u64) {
+        for _ in 0..ticks {
+            scheduler.boundary(BoundaryKind::AfterPtyRead);
+        }
+    }
+
+    #[test]
+    fn cast_has_header_and_output_events() {
+        let scenario = Scenario {
+            name: "cast".to_string(),
+            ..Scenario::default()
+        };
+        let mut builder = TraceBuilder::new(scenario, 42);
+        let scheduler = DeterministicScheduler::new(42);
+
+        builder.start_step(Step::WaitTicks { ticks: 5 }, None, &scheduler);
+        advance(&scheduler, 5);
+        // "é" split across two steps
+        builder.record_pty_output(b"hello \xc3");
+        builder.end_step(None, &scheduler);
+
+        builder.start_step(
+            Step::Resize {
+                cols: 100,
+                rows: 30,
+            },
+            None,
+            &scheduler,
+        );
+        advance(&scheduler, 5);
+        builder.record_pty_output(b"\xa9");
+        builder.end_step(None, &scheduler);
+
+        let cast = to_cast(&builder.build(), 10);
+        let lines: Vec<serde_json::Value> = cast
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("each line is JSON"))
+            .collect();
+
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines[0]["width"], 80);
+        assert_eq!(lines[0]["height"], 24);
+        assert_eq!(lines[1], json!([0.05, "o", "hello "]));
+        assert_eq!(lines[2], json!([0.1, "o", "é"]));
+        assert_eq!(lines[3], json!([0.1, "r", "100x30"]));
+        assert_eq!(lines.len(), 4);
+    }
+}
@@ -0,0 +1,343 @@
+//! Trace Diffing
+//!
+//! This module compares two traces: their checkpoint screen hashes and,
+//! structurally, the step lists of the scenarios they embed. The step diff
+//! aligns the two lists on their longest common subsequence, so one inserted
+//! step shows up as a single addition rather than shifting every later step.
+//! Text, such as a screen against its golden file, is diffed the same way
+//! and rendered as a unified diff.
+
+use crate::scenario::ScenarioStep;
+use crate::trace::Trace;
+use serde_json::Value;
+
+/// One difference between two step lists
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepChange {
+    /// Step present only in the second trace, at `index` in that trace
+    Added { index: usize, action: String },
+    /// Step present only in the first trace, at `index` in that trace
+    Removed { index: usize, action: String },
+    /// Same action at the same place in both traces, with different fields
+    Modified {
+        old_index: usize,
+        new_index: usize,
+        action: String,
+    },
+}
+
+impl std::fmt::Display for StepChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StepChange::Added { index, action } => write!(f, "+ [{}] {}", index, action),
+            StepChange::Removed { index, action } => write!(f, "- [{}] {}", index, action),
+            StepChange::Modified {
+                old_index,
+                new_index,
+                action,
+            } if old_index == new_index => write!(f, "~ [{}] {}", old_index, action),
+            StepChange::Modified {
+                old_index,
+                new_index,
+                action,
+            } => write!(f, "~ [{} -> {}] {}", old_index, new_index, action),
+        }
+    }
+}
+
+/// Differences between two traces
+#[derive(Debug, Clone, Default)]
+pub struct TraceDiff {
+    /// Index of the first checkpoint whose screen hash differs, or where one
+    /// trace runs out of checkpoints
+    pub first_checkpoint_mismatch: Option<usize>,
+    /// Added, removed and modified scenario steps
+    pub step_changes: Vec<StepChange>,
+}
+
+impl TraceDiff {
+    /// Whether the two traces matched
+    pub fn is_empty(&self) -> bool {
+        self.first_checkpoint_mismatch.is_none() && self.step_changes.is_empty()
+    }
+}
+
+/// Compare two traces
+pub fn diff_traces(old: &Trace, new: &Trace) -> TraceDiff {
+    let first_checkpoint_mismatch =
+        (0..old.checkpoints.len().max(new.checkpoints.len())).find(|&i| {
+            old.checkpoints.get(i).map(|c| c.screen_hash)
+                != new.checkpoints.get(i).map(|c| c.screen_hash)
+        });
+
+    TraceDiff {
+        first_checkpoint_mismatch,
+        step_changes: diff_steps(&old.scenario.steps, &new.scenario.steps),
+    }
+}
+
+/// Structurally diff two step lists
+///
+/// Steps are compared by their serialized form. Between two common steps, a
+/// removed and an added step with the same action are reported as one
+/// modification.
+pub fn diff_steps(old: &[ScenarioStep], new: &[ScenarioStep]) -> Vec<StepChange> {
+    let old: Vec<Value> = old.iter().map(step_value).collect();
+    let new: Vec<Value> = new.iter().map(step_value).collect();
+
+    let lcs = lcs_table(&old, &new);
+
+    let mut changes = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            flush_changes(&mut removed, &mut added, &old, &new, &mut changes);
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            added.push(j);
+            j += 1;
+        } else {
+            removed.push(i);
+            i += 1;
+        }
+    }
+    flush_changes(&mut removed, &mut added, &old, &new, &mut changes);
+
+    changes
+}
+
+/// Emit one run of removals and additions, pairing them up in order as
+/// modifications while their actions agree
+fn flush_changes(
+    removed: &mut Vec<usize>,
+    added: &mut Vec<usize>,
+    old: &[Value],
+    new: &[Value],
+    changes: &mut Vec<StepChange>,
+) {
+    let paired = removed
+        .iter()
+        .zip(added.iter())
+        .take_while(|(&r, &a)| action(&old[r]) == action(&new[a]))
+        .count();
+
+    for (&r, &a) in removed.iter().zip(added.iter()).take(paired) {
+        changes.push(StepChange::Modified {
+            old_index: r,
+            new_index: a,
+            action: action(&old[r]),
+        });
+    }
+    for &r in &removed[paired..] {
+        changes.push(StepChange::Removed {
+            index: r,
+            action: action(&old[r]),
+        });
+    }
+    for &a in &added[paired..] {
+        changes.push(StepChange::Added {
+            index: a,
+            action: action(&new[a]),
+        });
+    }
+
+    removed.clear();
+    added.clear();
+}
+
+/// `lcs[i][j]`: length of the common subsequence of `old[i..]` and `new[j..]`
+fn lcs_table<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Vec<usize>> {
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    lcs
+}
+
+/// Lines of unchanged context kept around each change in a unified diff
+const UNIFIED_CONTEXT: usize = 3;
+
+/// Render a line diff of two texts in unified diff format
+///
+/// Returns an empty string when the texts are equal.
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let lcs = lcs_table(&old, &new);
+
+    // (marker, old line number, new line number, text) for every line
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push((' ', i, j, old[i]));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(('-', i, j, old[i]));
+            i += 1;
+        } else {
+            lines.push(('+', i, j, new[j]));
+            j += 1;
+        }
+    }
+
+    let changed: Vec<usize> = (0..lines.len()).filter(|&k| lines[k].0 != ' ').collect();
+    if changed.is_empty() {
+        return String::new();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    let mut k = 0;
+    while k < changed.len() {
+        // Grow the hunk while the next change is within reach of its context
+        let start = changed[k].saturating_sub(UNIFIED_CONTEXT);
+        let mut last = changed[k];
+        while k + 1 < changed.len() && changed[k + 1] <= last + 2 * UNIFIED_CONTEXT + 1 {
+            k += 1;
+            last = changed[k];
+        }
+        let end = (last + UNIFIED_CONTEXT + 1).min(lines.len());
+        k += 1;
+
+        let hunk = &lines[start..end];
+        let old_count = hunk.iter().filter(|l| l.0 != '+').count();
+        let new_count = hunk.iter().filter(|l| l.0 != '-').count();
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(hunk[0].1, old_count),
+            hunk_range(hunk[0].2, new_count)
+        ));
+        for (marker, _, _, text) in hunk {
+            out.push(*marker);
+            out.push_str(text);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// A unified diff hunk range: 1-based start line and line count
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        // An empty range names the line before it
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, count),
+    }
+}
+
+fn step_value(step: &ScenarioStep) -> Value {
+    serde_json::to_value(step).unwrap_or(Value::Null)
+}
+
+fn action(step: &Value) -> String {
+    step.get("action")
+        .and_then(Value::as_str)
+        .unwrap_or("unknown")
+        .to_string()
+}
+
+/// Print a diff in a human-readable form
+pub fn print_trace_diff(diff: &TraceDiff) {
+    println!("=== Trace Diff ===");
+    match diff.first_checkpoint_mismatch {
+        Some(index) => println!("Checkpoints: differ from checkpoint {}", index),
+        None => println!("Checkpoints: identical"),
+    }
+
+    if diff.step_changes.is_empty() {
+        println!("Steps: identical");
+    } else {
+        println!("Steps: {} change(s)", diff.step_changes.len());
+        for change in &diff.step_changes {
+            println!("  {}", change);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::{KeySequence, Scenario, Step};
+    use crate::trace::TraceBuilder;
+
+    fn keys(text: &str) -> ScenarioStep {
+        Step::SendKeys {
+            keys: KeySequence::Text(text.to_string()),
+        }
+        .into()
+    }
+
+    fn wait(ticks: u64) -> ScenarioStep {
+        Step::WaitTicks { ticks }.into()
+    }
+
+    fn trace(steps: Vec<ScenarioStep>) -> Trace {
+        let scenario = Scenario {
+            name: "diff".to_string(),
+            steps,
+            ..Scenario::default()
+        };
+        TraceBuilder::new(scenario, 42).build()
+    }
+
+    #[test]
+    fn inserted_step_is_a_single_addition() {
+        let old = trace(vec![keys("a"), wait(1), keys("b")]);
+        let new = trace(vec![keys("a"), wait(1), wait(5), keys("b")]);
+
+        let diff = diff_traces(&old, &new);
+        assert_eq!(diff.first_checkpoint_mismatch, None);
+        assert_eq!(
+            diff.step_changes,
+            vec![StepChange::Added {
+                index: 2,
+                action: "wait_ticks".to_string(),
+            }]
+        );
+        assert!(diff_traces(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn changed_fields_are_modifications() {
+        let old = vec![keys("a"), wait(1)];
+        let new = vec![keys("b"), wait(1), keys("c")];
+
+        assert_eq!(
+            diff_steps(&old, &new),
+            vec![
+                StepChange::Modified {
+                    old_index: 0,
+                    new_index: 0,
+                    action: "send_keys".to_string(),
+                },
+                StepChange::Added {
+                    index: 2,
+                    action: "send_keys".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn unified_diff_shows_changed_lines_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let new = "a\nb\nc\nd\nE\nf\ng\nh\ni\n";
+
+        assert_eq!(
+            unified_diff(old, new, "expected", "actual"),
+            "--- expected\n+++ actual\n@@ -2,7 +2,8 @@\n b\n c\n d\n-e\n+E\n f\n g\n h\n+i\n"
+        );
+        assert_eq!(unified_diff(old, old, "expected", "actual"), "");
+    }
+}
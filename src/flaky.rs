@@ -542,7 +542,7 @@ steps:
             exit_codes: vec![0, 1, 0, 0, 1, 0, 0, 0, 0, 0],
         };
 
-        save_flaky_history(&[entry.clone()], &history_path).unwrap();
+        save_flaky_history(std::slice::from_ref(&entry), &history_path).unwrap();
         let loaded = load_flaky_history(&history_path).unwrap();
 
         assert_eq!(loaded.len(), 1);
@@ -2,6 +2,7 @@
 //!
 //! This module provides various algorithms for fuzzy/fuzzy string matching:
 //! - Levenshtein distance (edit distance)
+//! - Damerau-Levenshtein distance (edit distance counting transpositions)
 //! - Jaro-Winkler similarity
 //! - Token-based similarity (for multi-word patterns)
 //!
@@ -20,6 +21,48 @@
 //! assert!(fuzzy_match("hello world", "goodbye world", 3).is_none());
 //! ```
 
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// How a candidate is scored against a fuzzy pattern
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FuzzyAlgorithm {
+    /// Insertions, deletions and substitutions
+    #[default]
+    Levenshtein,
+    /// Similarity favouring shared prefixes; tolerant of scattered typos
+    JaroWinkler,
+    /// Levenshtein plus adjacent transpositions ("recieve" is one edit)
+    Damerau,
+}
+
+impl FuzzyAlgorithm {
+    /// Score `candidate` against `pattern` as `(distance, similarity)`
+    ///
+    /// Jaro-Winkler has no native edit distance, so its distance is the
+    /// dissimilarity scaled to the longer string's length, which keeps
+    /// `max_distance` meaningful for every algorithm.
+    pub fn score(self, candidate: &str, pattern: &str) -> (usize, f64) {
+        let max_len = candidate.chars().count().max(pattern.chars().count());
+        match self {
+            FuzzyAlgorithm::Levenshtein => {
+                let distance = levenshtein_distance(candidate, pattern);
+                (distance, similarity_from_distance(distance, max_len))
+            }
+            FuzzyAlgorithm::Damerau => {
+                let distance = damerau_levenshtein_distance(candidate, pattern);
+                (distance, similarity_from_distance(distance, max_len))
+            }
+            FuzzyAlgorithm::JaroWinkler => {
+                let similarity = jaro_winkler_similarity(candidate, pattern);
+                let distance = ((1.0 - similarity) * max_len as f64).round() as usize;
+                (distance, similarity)
+            }
+        }
+    }
+}
+
 /// Result of a fuzzy match operation
 #[derive(Debug, Clone, PartialEq)]
 pub struct FuzzyMatch {
@@ -99,6 +142,51 @@ pub fn levenshtein_distance(a: &str, b: &str) -> usize {
     prev_row[b_len]
 }
 
+/// Calculate Damerau-Levenshtein distance between two strings.
+///
+/// Like [`levenshtein_distance`], but swapping two adjacent characters
+/// counts as a single edit (the optimal string alignment variant, where no
+/// substring is edited twice).
+pub fn damerau_levenshtein_distance(a: &str, b: &str) -> usize {
+    let a_chars: Vec<char> = a.chars().collect();
+    let b_chars: Vec<char> = b.chars().collect();
+    let (a_len, b_len) = (a_chars.len(), b_chars.len());
+
+    if a_len == 0 {
+        return b_len;
+    }
+    if b_len == 0 {
+        return a_len;
+    }
+
+    // Transpositions look two rows back, so keep three rows
+    let mut before_prev: Vec<usize> = vec![0; b_len + 1];
+    let mut prev_row: Vec<usize> = (0..=b_len).collect();
+    let mut current_row: Vec<usize> = vec![0; b_len + 1];
+
+    for i in 1..=a_len {
+        current_row[0] = i;
+        for j in 1..=b_len {
+            let cost = usize::from(a_chars[i - 1] != b_chars[j - 1]);
+            let mut min = (prev_row[j] + 1)
+                .min(current_row[j - 1] + 1)
+                .min(prev_row[j - 1] + cost);
+            if i > 1
+                && j > 1
+                && a_chars[i - 1] == b_chars[j - 2]
+                && a_chars[i - 2] == b_chars[j - 1]
+            {
+                min = min.min(before_prev[j - 2] + 1);
+            }
+            current_row[j] = min;
+        }
+        std::mem::swap(&mut before_prev, &mut prev_row);
+        std::mem::swap(&mut prev_row, &mut current_row);
+    }
+
+    prev_row[b_len]
+}
+
 /// Calculate Jaro-Winkler similarity between two strings.
 ///
 /// Jaro-Winkler similarity gives more weight to prefixes that match.
@@ -299,6 +387,16 @@ pub fn fuzzy_match(text: &str, pattern: &str, max_distance: usize) -> Option<Fuz
 ///
 /// `Some(FuzzyMatch)` if a close match is found, `None` otherwise.
 pub fn contains_fuzzy(text: &str, pattern: &str, max_distance: usize) -> Option<FuzzyMatch> {
+    contains_fuzzy_with(text, pattern, max_distance, FuzzyAlgorithm::Levenshtein)
+}
+
+/// Like [`contains_fuzzy`], scoring candidates with `algorithm`
+pub fn contains_fuzzy_with(
+    text: &str,
+    pattern: &str,
+    max_distance: usize,
+    algorithm: FuzzyAlgorithm,
+) -> Option<FuzzyMatch> {
     if pattern.is_empty() {
         return Some(FuzzyMatch {
             text: String::new(),
@@ -308,7 +406,6 @@ pub fn contains_fuzzy(text: &str, pattern: &str, max_distance: usize) -> Option<
         });
     }
 
-    let pattern_chars: Vec<char> = pattern.chars().collect();
     let text_chars: Vec<char> = text.chars().collect();
 
     let mut best_match: Option<FuzzyMatch> = None;
@@ -317,12 +414,9 @@ pub fn contains_fuzzy(text: &str, pattern: &str, max_distance: usize) -> Option<
     for start in 0..text_chars.len() {
         for end in (start + 1)..=text_chars.len() {
             let window: String = text_chars[start..end].iter().collect();
-            let distance = levenshtein_distance(&window, pattern);
+            let (distance, similarity) = algorithm.score(&window, pattern);
 
             if distance <= max_distance {
-                let similarity =
-                    similarity_from_distance(distance, pattern_chars.len().max(window.len()));
-
                 let candidate = FuzzyMatch {
                     text: window,
                     distance,
@@ -463,6 +557,34 @@ mod tests {
         assert!(sim > 0.3 && sim < 1.0); // Jaccard: 1/3 ≈ 0.333
     }
 
+    #[test]
+    fn test_algorithms_on_transposition() {
+        let score = |algorithm: FuzzyAlgorithm| algorithm.score("recieve", "receive");
+
+        // Swapping "ie" is two substitutions, but one transposition
+        assert_eq!(score(FuzzyAlgorithm::Levenshtein).0, 2);
+        assert_eq!(score(FuzzyAlgorithm::Damerau).0, 1);
+        let (distance, similarity) = score(FuzzyAlgorithm::JaroWinkler);
+        assert!(similarity > 0.95, "{}", similarity);
+        assert_eq!(distance, 0);
+
+        let text = "please recieve the package";
+        assert!(contains_fuzzy_with(text, "receive", 1, FuzzyAlgorithm::Levenshtein).is_none());
+        let found = contains_fuzzy_with(text, "receive", 1, FuzzyAlgorithm::Damerau).unwrap();
+        assert_eq!((found.text.as_str(), found.position), ("recieve", 7));
+        assert!(contains_fuzzy_with(text, "receive", 1, FuzzyAlgorithm::JaroWinkler).is_some());
+    }
+
+    #[test]
+    fn test_damerau_distance() {
+        assert_eq!(damerau_levenshtein_distance("", "abc"), 3);
+        assert_eq!(damerau_levenshtein_distance("abc", "abc"), 0);
+        assert_eq!(damerau_levenshtein_distance("ab", "ba"), 1);
+        assert_eq!(damerau_levenshtein_distance("kitten", "sitting"), 3);
+        // Optimal string alignment: no substring is edited twice
+        assert_eq!(damerau_levenshtein_distance("ca", "abc"), 3);
+    }
+
     #[test]
     fn test_similarity_from_distance() {
         assert_eq!(similarity_from_distance(0, 10), 1.0);
@@ -5,8 +5,22 @@
 
 use crate::process::{ExitReason, PtyProcess};
 use crate::screen::Screen;
+use crate::timing::REAL_TIME_TICK_MS;
+use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+/// How a violated invariant affects the run
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, Hash, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Recorded in the trace, but execution continues
+    Warning,
+    /// Halts execution and fails the run
+    #[default]
+    Error,
+}
+
 /// Result of an invariant evaluation
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct InvariantResult {
@@ -22,6 +36,9 @@ pub struct InvariantResult {
     pub step: usize,
     /// Tick at which this was checked
     pub tick: u64,
+    /// Severity of a violation
+    #[serde(default)]
+    pub severity: Severity,
 }
 
 impl InvariantResult {
@@ -40,12 +57,18 @@ impl InvariantResult {
             details,
             step,
             tick,
+            severity: Severity::Error,
         }
     }
 
     pub fn violation(&self) -> bool {
         !self.satisfied
     }
+
+    /// Whether this is a violation that should halt execution
+    pub fn is_error(&self) -> bool {
+        self.violation() && self.severity == Severity::Error
+    }
 }
 
 /// Context available when evaluating invariants
@@ -66,6 +89,23 @@ pub struct InvariantContext<'a> {
     pub no_output_ticks: u64,
     /// Expected signal for SignalHandled invariant (if applicable)
     pub expected_signal: Option<String>,
+    /// When that signal was sent and whether the screen has changed since
+    pub signal_delivery: Option<SignalDelivery>,
+    /// Whether the step about to run asserts on the screen
+    pub asserting: bool,
+    /// Whether this is the evaluation after the last step
+    pub run_ended: bool,
+}
+
+/// Delivery of the most recent `send_signal` step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalDelivery {
+    /// Tick the signal was sent at
+    pub tick: u64,
+    /// Ticks that have passed since
+    pub elapsed_ticks: u64,
+    /// Whether the screen has changed since the signal was sent
+    pub screen_changed: bool,
 }
 
 /// Trait for invariants that can be evaluated
@@ -85,6 +125,14 @@ pub enum BuiltInInvariant {
     /// Cursor must stay within screen bounds
     #[serde(rename = "cursor_bounds")]
     CursorBounds,
+    /// Cursor must stay inside a rectangular area (bounds inclusive)
+    #[serde(rename = "cursor_in_region")]
+    CursorInRegion {
+        top: usize,
+        left: usize,
+        bottom: usize,
+        right: usize,
+    },
     /// No deadlock (output within timeout)
     #[serde(rename = "no_deadlock")]
     NoDeadlock {
@@ -130,9 +178,27 @@ pub enum BuiltInInvariant {
         #[serde(default)]
         allowed_signals: Vec<i32>,
     },
+    /// Process did not exit while still on the alternate screen
+    #[serde(rename = "alt_screen_restored")]
+    AltScreenRestored,
     /// Viewport is valid (cursor in bounds, no scroll issues)
     #[serde(rename = "viewport_valid")]
     ViewportValid,
+    /// Application never rang the terminal bell
+    #[serde(rename = "no_bell")]
+    NoBell,
+    /// No control characters left in the rendered screen text
+    #[serde(rename = "no_control_chars")]
+    NoControlChars,
+    /// No escape sequence is half-received when an assertion runs
+    #[serde(rename = "no_pending_escape_at_assert")]
+    NoPendingEscapeAtAssert,
+    /// Scrollback history stays within a line limit
+    #[serde(rename = "scrollback_bounded")]
+    ScrollbackBounded {
+        /// Maximum number of scrollback lines
+        max_lines: usize,
+    },
     /// Response time constraint
     #[serde(rename = "response_time")]
     ResponseTime {
@@ -166,7 +232,172 @@ pub enum BuiltInInvariant {
         /// Custom description for this invariant
         #[serde(default)]
         description: Option<String>,
+        /// Failure message template (see [`interpolate_message`])
+        #[serde(default)]
+        message: Option<String>,
+    },
+
+    /// Regex invariant with constraints on named captures
+    #[serde(rename = "custom_regex")]
+    CustomRegex(CustomRegexSpec),
+
+    /// JSON output invariant evaluated on a path into the last JSON value on screen
+    #[serde(rename = "json_path")]
+    JsonPath(JsonPathSpec),
+
+    /// Process reacts to a delivered signal with the expected behavior
+    #[serde(rename = "signal_handled_correctly")]
+    SignalHandledCorrectly {
+        /// Signal the behavior applies to (e.g. "SIGHUP")
+        signal: String,
+        /// Expected reaction once the signal has been sent
+        behavior: SignalBehavior,
+    },
+
+    /// Screen or exit state changes within a tick budget after a signal
+    #[serde(rename = "signal_acknowledged")]
+    SignalAcknowledged {
+        /// Signal to watch for (e.g. "SIGINT")
+        signal: String,
+        /// Ticks the process has to react once the signal is sent
+        within_ticks: u64,
+    },
+
+    /// Something appears on the screen within a tick budget of the start
+    #[serde(rename = "startup_output")]
+    StartupOutput {
+        /// Ticks the process has to draw anything
+        within_ticks: u64,
+    },
+}
+
+/// Expected reaction of a process to a signal
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SignalBehavior {
+    /// Process must terminate (by exiting or being killed by the signal)
+    Exit {
+        /// Required exit code, if the process exits normally
+        #[serde(default)]
+        exit_code: Option<i32>,
+        /// Ticks the process has to exit once the signal is sent
+        #[serde(default = "default_signal_exit_ticks")]
+        within_ticks: u64,
     },
+    /// Process must keep running as if nothing happened
+    Ignore,
+    /// Process must keep running and show that it reloaded/restarted
+    Restart {
+        /// Pattern that must appear on screen after the restart
+        #[serde(default)]
+        pattern: Option<String>,
+    },
+    /// Screen must show a pattern, with an optional liveness requirement
+    Custom {
+        /// Pattern that must appear on screen
+        pattern: String,
+        /// Whether the process must be running (true) or gone (false)
+        #[serde(default)]
+        running: Option<bool>,
+    },
+}
+
+/// Specification for a regex invariant with capture constraints
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct CustomRegexSpec {
+    /// Name of the invariant
+    pub name: String,
+    /// Regex matched against the screen text (may define named captures)
+    pub pattern: String,
+    /// Constraints applied to named captures of the first match
+    #[serde(default)]
+    pub constraints: Vec<RegexConstraint>,
+    /// Custom description for this invariant
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+impl CustomRegexSpec {
+    /// Compile the pattern and each constraint's condition, in constraint order
+    pub fn compile(&self) -> Result<(Regex, Vec<Regex>), String> {
+        let regex = Regex::new(&self.pattern).map_err(|e| format!("Invalid pattern: {}", e))?;
+        let conditions = self
+            .constraints
+            .iter()
+            .map(|constraint| {
+                Regex::new(&constraint.condition).map_err(|e| {
+                    format!(
+                        "Invalid condition for capture '{}': {}",
+                        constraint.capture, e
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok((regex, conditions))
+    }
+}
+
+/// A condition on a single named capture
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct RegexConstraint {
+    /// Name of the capture group
+    pub capture: String,
+    /// Regex the captured text is checked against
+    pub condition: String,
+    /// Whether the capture must match (true) or must not match (false)
+    #[serde(default = "default_contains")]
+    pub must_match: bool,
+}
+
+/// Specification for an invariant on JSON emitted to the screen
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct JsonPathSpec {
+    /// Name of the invariant
+    pub name: String,
+    /// Path into the JSON value (subset of JSONPath: `$.a.b[0]['c']`)
+    pub path: String,
+    /// Condition the value at `path` must satisfy
+    pub condition: JsonCondition,
+    /// Custom description for this invariant
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+/// Condition applied to the value selected by a JSON path
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonCondition {
+    /// Value must equal the given JSON value
+    Equals(serde_json::Value),
+    /// String value must contain the substring (arrays: must contain the string element)
+    Contains(String),
+    /// Value must be of the given JSON type
+    TypeIs(JsonType),
+}
+
+/// JSON value types for `JsonCondition::TypeIs`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JsonType {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl JsonType {
+    fn of(value: &serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Null => JsonType::Null,
+            serde_json::Value::Bool(_) => JsonType::Bool,
+            serde_json::Value::Number(_) => JsonType::Number,
+            serde_json::Value::String(_) => JsonType::String,
+            serde_json::Value::Array(_) => JsonType::Array,
+            serde_json::Value::Object(_) => JsonType::Object,
+        }
+    }
 }
 
 fn default_contains() -> bool {
@@ -181,11 +412,21 @@ fn default_stable_ticks() -> u64 {
     10
 }
 
+fn default_signal_exit_ticks() -> u64 {
+    50
+}
+
 impl BuiltInInvariant {
     /// Create an invariant evaluator from this specification
     pub fn to_evaluator(&self) -> Box<dyn Invariant> {
         match self {
             BuiltInInvariant::CursorBounds => Box::new(CursorBoundsInvariant),
+            BuiltInInvariant::CursorInRegion {
+                top,
+                left,
+                bottom,
+                right,
+            } => Box::new(CursorInRegionInvariant::new(*top, *left, *bottom, *right)),
             BuiltInInvariant::NoDeadlock { timeout_ticks } => {
                 Box::new(NoDeadlockInvariant::new(*timeout_ticks))
             }
@@ -206,7 +447,14 @@ impl BuiltInInvariant {
             BuiltInInvariant::ProcessTerminatedCleanly { allowed_signals } => Box::new(
                 ProcessTerminatedCleanlyInvariant::new(allowed_signals.clone()),
             ),
+            BuiltInInvariant::AltScreenRestored => Box::new(AltScreenRestoredInvariant),
             BuiltInInvariant::ViewportValid => Box::new(ViewportValidInvariant),
+            BuiltInInvariant::NoBell => Box::new(NoBellInvariant),
+            BuiltInInvariant::NoControlChars => Box::new(NoControlCharsInvariant),
+            BuiltInInvariant::NoPendingEscapeAtAssert => Box::new(NoPendingEscapeAtAssertInvariant),
+            BuiltInInvariant::ScrollbackBounded { max_lines } => {
+                Box::new(ScrollbackBoundedInvariant::new(*max_lines))
+            }
             BuiltInInvariant::ResponseTime { max_ticks } => {
                 Box::new(ResponseTimeInvariant::new(*max_ticks))
             }
@@ -220,16 +468,37 @@ impl BuiltInInvariant {
                 expected_row,
                 expected_col,
                 description,
-            } => Box::new(CustomInvariant::new(
-                name.clone(),
-                pattern.clone(),
-                *should_contain,
-                *expected_row,
-                *expected_col,
-                description
-                    .clone()
-                    .or(Some(format!("Custom invariant: {}", name))),
+                message,
+            } => Box::new(
+                CustomInvariant::new(
+                    name.clone(),
+                    pattern.clone(),
+                    *should_contain,
+                    *expected_row,
+                    *expected_col,
+                    description
+                        .clone()
+                        .or(Some(format!("Custom invariant: {}", name))),
+                )
+                .with_message(message.clone()),
+            ),
+            BuiltInInvariant::CustomRegex(spec) => {
+                Box::new(CustomRegexInvariant::new(spec.clone()))
+            }
+            BuiltInInvariant::JsonPath(spec) => Box::new(JsonPathInvariant::new(spec.clone())),
+            BuiltInInvariant::SignalHandledCorrectly { signal, behavior } => Box::new(
+                SignalHandledCorrectlyInvariant::new(signal.clone(), behavior.clone()),
+            ),
+            BuiltInInvariant::SignalAcknowledged {
+                signal,
+                within_ticks,
+            } => Box::new(SignalAcknowledgedInvariant::new(
+                signal.clone(),
+                *within_ticks,
             )),
+            BuiltInInvariant::StartupOutput { within_ticks } => {
+                Box::new(StartupOutputInvariant::new(*within_ticks))
+            }
         }
     }
 }
@@ -283,6 +552,68 @@ impl Invariant for CursorBoundsInvariant {
     }
 }
 
+/// Cursor region invariant - keeps the cursor inside a pane
+pub struct CursorInRegionInvariant {
+    top: usize,
+    left: usize,
+    bottom: usize,
+    right: usize,
+}
+
+impl CursorInRegionInvariant {
+    pub fn new(top: usize, left: usize, bottom: usize, right: usize) -> Self {
+        Self {
+            top,
+            left,
+            bottom,
+            right,
+        }
+    }
+}
+
+impl Invariant for CursorInRegionInvariant {
+    fn name(&self) -> &str {
+        "cursor_in_region"
+    }
+
+    fn description(&self) -> &str {
+        "Cursor must stay within the configured region"
+    }
+
+    fn evaluate(&self, ctx: &mut InvariantContext) -> InvariantResult {
+        let Some(screen) = ctx.screen else {
+            return InvariantResult::new(
+                self.name(),
+                true,
+                "No screen available - skipping cursor region check",
+                None,
+                ctx.step,
+                ctx.tick,
+            );
+        };
+
+        let cursor = screen.cursor();
+        let inside = (self.top..=self.bottom).contains(&cursor.row)
+            && (self.left..=self.right).contains(&cursor.col);
+
+        InvariantResult::new(
+            self.name(),
+            inside,
+            self.description(),
+            if inside {
+                None
+            } else {
+                Some(format!(
+                    "Cursor at row {}, col {} is outside rows {}-{}, cols {}-{}",
+                    cursor.row, cursor.col, self.top, self.bottom, self.left, self.right
+                ))
+            },
+            ctx.step,
+            ctx.tick,
+        )
+    }
+}
+
 /// No deadlock invariant - detects if process is stuck
 pub struct NoDeadlockInvariant {
     timeout_ticks: u64,
@@ -401,6 +732,298 @@ impl Invariant for SignalHandledInvariant {
     }
 }
 
+/// Signal behavior invariant - checks how the process reacted to a sent signal
+///
+/// Only evaluated once `ctx.expected_signal` reports that the configured
+/// signal was delivered; before that the invariant is trivially satisfied.
+/// An `Exit` behavior gives the process `within_ticks` to go; a run that
+/// ends inside that window waits out the rest of it before judging.
+pub struct SignalHandledCorrectlyInvariant {
+    signal: String,
+    behavior: SignalBehavior,
+}
+
+impl SignalHandledCorrectlyInvariant {
+    pub fn new(signal: String, behavior: SignalBehavior) -> Self {
+        Self {
+            signal: signal.to_uppercase(),
+            behavior,
+        }
+    }
+
+    /// Check the observed state, returning a failure reason on mismatch
+    fn check(&self, exit_reason: Option<ExitReason>, screen_text: &str) -> Result<String, String> {
+        let running = matches!(exit_reason, None | Some(ExitReason::Running));
+        let state = match exit_reason {
+            Some(ExitReason::Exited(code)) => format!("exited with code {}", code),
+            Some(ExitReason::Signaled(sig)) => format!("killed by signal {}", sig),
+            _ => "running".to_string(),
+        };
+        let require_pattern = |pattern: &str| {
+            if screen_text.contains(pattern) {
+                Ok(())
+            } else {
+                Err(format!("pattern '{}' not on screen ({})", pattern, state))
+            }
+        };
+
+        match &self.behavior {
+            SignalBehavior::Exit { exit_code, .. } => {
+                if running {
+                    return Err(format!("still running after {}", self.signal));
+                }
+                if let (Some(expected), Some(ExitReason::Exited(code))) = (exit_code, exit_reason) {
+                    if *expected != code {
+                        return Err(format!("expected exit code {}, {}", expected, state));
+                    }
+                }
+            }
+            SignalBehavior::Ignore => {
+                if !running {
+                    return Err(format!("{} after {}", state, self.signal));
+                }
+            }
+            SignalBehavior::Restart { pattern } => {
+                if !running {
+                    return Err(format!("{} after {}", state, self.signal));
+                }
+                if let Some(pattern) = pattern {
+                    require_pattern(pattern)?;
+                }
+            }
+            SignalBehavior::Custom {
+                pattern,
+                running: expected,
+            } => {
+                if let Some(expected) = expected {
+                    if *expected != running {
+                        return Err(format!(
+                            "expected process to be {}, {}",
+                            if *expected { "running" } else { "gone" },
+                            state
+                        ));
+                    }
+                }
+                require_pattern(pattern)?;
+            }
+        }
+
+        Ok(state)
+    }
+}
+
+impl Invariant for SignalHandledCorrectlyInvariant {
+    fn name(&self) -> &str {
+        "signal_handled_correctly"
+    }
+
+    fn description(&self) -> &str {
+        "Process must react to signals with the expected behavior"
+    }
+
+    fn evaluate(&self, ctx: &mut InvariantContext) -> InvariantResult {
+        let delivered = ctx
+            .expected_signal
+            .as_deref()
+            .is_some_and(|s| s.eq_ignore_ascii_case(&self.signal));
+        if !delivered {
+            return InvariantResult::new(
+                self.name(),
+                true,
+                self.description(),
+                Some(format!("{} not sent yet", self.signal)),
+                ctx.step,
+                ctx.tick,
+            );
+        }
+
+        let mut exit_reason = ctx.process.try_wait().ok().flatten();
+        if let SignalBehavior::Exit { within_ticks, .. } = &self.behavior {
+            let elapsed = ctx.signal_delivery.map_or(u64::MAX, |d| d.elapsed_ticks);
+            let remaining = within_ticks.saturating_sub(elapsed);
+            if remaining > 0 && matches!(exit_reason, None | Some(ExitReason::Running)) {
+                if !ctx.run_ended {
+                    return InvariantResult::new(
+                        self.name(),
+                        true,
+                        self.description(),
+                        Some(format!(
+                            "Waiting for exit after {} ({} of {} ticks)",
+                            self.signal, elapsed, within_ticks
+                        )),
+                        ctx.step,
+                        ctx.tick,
+                    );
+                }
+                exit_reason = wait_for_exit(ctx.process, remaining * REAL_TIME_TICK_MS);
+            }
+        }
+        let screen_text = ctx.screen.map(|s| s.text()).unwrap_or_default();
+        let (satisfied, details) = match self.check(exit_reason, screen_text) {
+            Ok(state) => (
+                true,
+                format!("{:?} after {}: {}", self.behavior, self.signal, state),
+            ),
+            Err(reason) => (
+                false,
+                format!(
+                    "Expected {:?} after {}: {}",
+                    self.behavior, self.signal, reason
+                ),
+            ),
+        };
+
+        InvariantResult::new(
+            self.name(),
+            satisfied,
+            self.description(),
+            Some(details),
+            ctx.step,
+            ctx.tick,
+        )
+    }
+}
+
+/// Poll for the process to exit, for up to `timeout_ms` of real time
+fn wait_for_exit(process: &mut PtyProcess, timeout_ms: u64) -> Option<ExitReason> {
+    for _ in 0..timeout_ms {
+        match process.try_wait() {
+            Ok(None | Some(ExitReason::Running)) => {
+                std::thread::sleep(std::time::Duration::from_millis(1))
+            }
+            Ok(reason) => return reason,
+            Err(_) => return None,
+        }
+    }
+    process.try_wait().ok().flatten()
+}
+
+/// Signal responsiveness invariant - the screen changes or the process
+/// exits within a tick budget after the signal is sent
+pub struct SignalAcknowledgedInvariant {
+    signal: String,
+    within_ticks: u64,
+}
+
+impl SignalAcknowledgedInvariant {
+    pub fn new(signal: String, within_ticks: u64) -> Self {
+        Self {
+            signal: signal.to_uppercase(),
+            within_ticks,
+        }
+    }
+}
+
+impl Invariant for SignalAcknowledgedInvariant {
+    fn name(&self) -> &str {
+        "signal_acknowledged"
+    }
+
+    fn description(&self) -> &str {
+        "Process must visibly react to a signal within a tick budget"
+    }
+
+    fn evaluate(&self, ctx: &mut InvariantContext) -> InvariantResult {
+        let delivery = ctx.signal_delivery.filter(|_| {
+            ctx.expected_signal
+                .as_deref()
+                .is_some_and(|s| s.eq_ignore_ascii_case(&self.signal))
+        });
+        let Some(delivery) = delivery else {
+            return InvariantResult::new(
+                self.name(),
+                true,
+                self.description(),
+                Some(format!("{} not sent yet", self.signal)),
+                ctx.step,
+                ctx.tick,
+            );
+        };
+
+        let exited = matches!(ctx.process.try_wait(), Ok(Some(_)));
+        let (satisfied, details) = if delivery.screen_changed || exited {
+            (
+                true,
+                format!(
+                    "{} acknowledged ({})",
+                    self.signal,
+                    if exited { "exited" } else { "screen changed" }
+                ),
+            )
+        } else if delivery.elapsed_ticks <= self.within_ticks {
+            (
+                true,
+                format!(
+                    "Waiting on {}: {} of {} ticks",
+                    self.signal, delivery.elapsed_ticks, self.within_ticks
+                ),
+            )
+        } else {
+            (
+                false,
+                format!(
+                    "No reaction to {} sent at tick {} within {} ticks",
+                    self.signal, delivery.tick, self.within_ticks
+                ),
+            )
+        };
+
+        InvariantResult::new(
+            self.name(),
+            satisfied,
+            self.description(),
+            Some(details),
+            ctx.step,
+            ctx.tick,
+        )
+    }
+}
+
+/// Startup invariant - the screen must stop being blank within a tick
+/// budget of the start, to catch programs that hang before drawing anything
+pub struct StartupOutputInvariant {
+    within_ticks: u64,
+}
+
+impl StartupOutputInvariant {
+    pub fn new(within_ticks: u64) -> Self {
+        Self { within_ticks }
+    }
+}
+
+impl Invariant for StartupOutputInvariant {
+    fn name(&self) -> &str {
+        "startup_output"
+    }
+
+    fn description(&self) -> &str {
+        "Process must draw something within a tick budget of starting"
+    }
+
+    fn evaluate(&self, ctx: &mut InvariantContext) -> InvariantResult {
+        // Blank means identical to a fresh screen: no text, cursor at home
+        let blank = ctx.screen.is_some_and(|screen| {
+            let (cols, rows) = screen.size();
+            screen.state_hash() == Screen::new(cols, rows).state_hash()
+        });
+        let satisfied = !blank || ctx.tick <= self.within_ticks;
+
+        InvariantResult::new(
+            self.name(),
+            satisfied,
+            self.description(),
+            (!satisfied).then(|| {
+                format!(
+                    "Screen still blank at tick {} (budget {} ticks)",
+                    ctx.tick, self.within_ticks
+                )
+            }),
+            ctx.step,
+            ctx.tick,
+        )
+    }
+}
+
 /// Screen content invariant - checks for pattern presence/absence
 pub struct ScreenContainsInvariant {
     pattern: String,
@@ -620,6 +1243,38 @@ impl Invariant for ProcessTerminatedCleanlyInvariant {
     }
 }
 
+/// Alternate screen invariant - a full-screen app that exits without
+/// leaving the alternate buffer leaves the user's terminal wrecked
+pub struct AltScreenRestoredInvariant;
+
+impl Invariant for AltScreenRestoredInvariant {
+    fn name(&self) -> &str {
+        "alt_screen_restored"
+    }
+
+    fn description(&self) -> &str {
+        "Process must leave the alternate screen before exiting"
+    }
+
+    fn evaluate(&self, ctx: &mut InvariantContext) -> InvariantResult {
+        let exited = matches!(
+            ctx.process.try_wait(),
+            Ok(Some(ExitReason::Exited(_) | ExitReason::Signaled(_)))
+        );
+        let alternate = ctx.screen.is_some_and(|s| s.is_alternate_screen());
+        let violated = exited && alternate;
+
+        InvariantResult::new(
+            self.name(),
+            !violated,
+            self.description(),
+            violated.then(|| "Process exited while on the alternate screen".to_string()),
+            ctx.step,
+            ctx.tick,
+        )
+    }
+}
+
 pub struct ViewportValidInvariant;
 
 impl Invariant for ViewportValidInvariant {
@@ -651,13 +1306,153 @@ impl Invariant for ViewportValidInvariant {
     }
 }
 
-pub struct ResponseTimeInvariant {
-    max_ticks: u64,
-}
+/// No bell invariant - fails once the application rings the bell (BEL, 0x07)
+pub struct NoBellInvariant;
 
-impl ResponseTimeInvariant {
-    pub fn new(max_ticks: u64) -> Self {
-        Self { max_ticks }
+impl Invariant for NoBellInvariant {
+    fn name(&self) -> &str {
+        "no_bell"
+    }
+
+    fn description(&self) -> &str {
+        "Application must not ring the terminal bell"
+    }
+
+    fn evaluate(&self, ctx: &mut InvariantContext) -> InvariantResult {
+        let bells = ctx.screen.map(|s| s.bell_count()).unwrap_or(0);
+
+        InvariantResult::new(
+            self.name(),
+            bells == 0,
+            self.description(),
+            Some(format!("Bell rang {} time(s)", bells)),
+            ctx.step,
+            ctx.tick,
+        )
+    }
+}
+
+/// No control chars invariant - the screen interprets real escape
+/// sequences, so a control code point left in a cell means raw bytes leaked
+/// through rendering
+pub struct NoControlCharsInvariant;
+
+impl Invariant for NoControlCharsInvariant {
+    fn name(&self) -> &str {
+        "no_control_chars"
+    }
+
+    fn description(&self) -> &str {
+        "Screen must not contain control characters"
+    }
+
+    fn evaluate(&self, ctx: &mut InvariantContext) -> InvariantResult {
+        let Some(screen) = ctx.screen else {
+            return InvariantResult::new(
+                self.name(),
+                true,
+                "No screen available - skipping control character check",
+                None,
+                ctx.step,
+                ctx.tick,
+            );
+        };
+
+        let stray = screen.text().lines().enumerate().find_map(|(row, line)| {
+            line.chars()
+                .enumerate()
+                .find(|(_, ch)| ch.is_control() && *ch != '\t')
+                .map(|(col, ch)| (row, col, ch))
+        });
+
+        InvariantResult::new(
+            self.name(),
+            stray.is_none(),
+            self.description(),
+            stray.map(|(row, col, ch)| {
+                format!(
+                    "Control character U+{:04X} at row {}, col {}",
+                    ch as u32, row, col
+                )
+            }),
+            ctx.step,
+            ctx.tick,
+        )
+    }
+}
+
+/// No pending escape invariant - fails when an assertion is about to run
+/// while the parser holds an incomplete escape sequence, which usually means
+/// the test did not wait for the application to finish drawing
+pub struct NoPendingEscapeAtAssertInvariant;
+
+impl Invariant for NoPendingEscapeAtAssertInvariant {
+    fn name(&self) -> &str {
+        "no_pending_escape_at_assert"
+    }
+
+    fn description(&self) -> &str {
+        "Assertions must not run while an escape sequence is incomplete"
+    }
+
+    fn evaluate(&self, ctx: &mut InvariantContext) -> InvariantResult {
+        let pending = ctx.asserting && ctx.screen.is_some_and(Screen::has_pending_escape);
+
+        InvariantResult::new(
+            self.name(),
+            !pending,
+            self.description(),
+            Some("Assertion ran with a partial escape sequence buffered".to_string()),
+            ctx.step,
+            ctx.tick,
+        )
+    }
+}
+
+/// Scrollback bounded invariant - catches runaway history accumulation
+pub struct ScrollbackBoundedInvariant {
+    max_lines: usize,
+}
+
+impl ScrollbackBoundedInvariant {
+    pub fn new(max_lines: usize) -> Self {
+        Self { max_lines }
+    }
+}
+
+impl Invariant for ScrollbackBoundedInvariant {
+    fn name(&self) -> &str {
+        "scrollback_bounded"
+    }
+
+    fn description(&self) -> &str {
+        "Scrollback must not exceed the configured number of lines"
+    }
+
+    fn evaluate(&self, ctx: &mut InvariantContext) -> InvariantResult {
+        let lines = ctx.screen.map(|s| s.scrollback_len()).unwrap_or(0);
+
+        InvariantResult::new(
+            self.name(),
+            lines <= self.max_lines,
+            self.description(),
+            Some(format!(
+                "Scrollback: {} lines (max: {})",
+                lines, self.max_lines
+            )),
+            ctx.step,
+            ctx.tick,
+        )
+    }
+}
+
+pub struct ResponseTimeInvariant {
+    max_ticks: u64,
+}
+
+impl ResponseTimeInvariant {
+    pub fn new(max_ticks: u64) -> Self {
+        Self { max_ticks }
     }
 }
 
@@ -739,6 +1534,7 @@ pub struct CustomInvariant {
     expected_row: Option<usize>,
     expected_col: Option<usize>,
     description: Option<String>,
+    message: Option<String>,
 }
 
 impl CustomInvariant {
@@ -757,8 +1553,15 @@ impl CustomInvariant {
             expected_row,
             expected_col,
             description,
+            message: None,
         }
     }
+
+    /// Set a failure message template, interpolated when the check fails
+    pub fn with_message(mut self, message: Option<String>) -> Self {
+        self.message = message;
+        self
+    }
 }
 
 impl Invariant for CustomInvariant {
@@ -851,6 +1654,12 @@ impl Invariant for CustomInvariant {
             }
         }
 
+        if !satisfied {
+            if let Some(template) = &self.message {
+                details.insert(0, interpolate_message(template, ctx));
+            }
+        }
+
         InvariantResult::new(
             self.name(),
             satisfied,
@@ -866,9 +1675,275 @@ impl Invariant for CustomInvariant {
     }
 }
 
+/// Maximum length of the `{screen_preview}` placeholder
+const MESSAGE_PREVIEW_LEN: usize = 80;
+
+/// Interpolate a failure message template with evaluation context
+///
+/// Supported placeholders: `{tick}`, `{step}`, `{cursor_row}`, `{cursor_col}`
+/// and `{screen_preview}` (non-blank rows joined by ` | `, truncated).
+/// Unknown placeholders are left as-is.
+pub fn interpolate_message(template: &str, ctx: &InvariantContext) -> String {
+    let (cursor_row, cursor_col, preview) = match ctx.screen {
+        Some(screen) => {
+            let cursor = screen.cursor();
+            let text = screen.text();
+            let rows: Vec<&str> = text
+                .lines()
+                .map(str::trim_end)
+                .filter(|l| !l.is_empty())
+                .collect();
+            let preview = rows.join(" | ");
+            let preview = match preview.char_indices().nth(MESSAGE_PREVIEW_LEN) {
+                Some((end, _)) => format!("{}...", &preview[..end]),
+                None => preview,
+            };
+            (cursor.row.to_string(), cursor.col.to_string(), preview)
+        }
+        None => ("?".to_string(), "?".to_string(), String::new()),
+    };
+
+    template
+        .replace("{tick}", &ctx.tick.to_string())
+        .replace("{step}", &ctx.step.to_string())
+        .replace("{cursor_row}", &cursor_row)
+        .replace("{cursor_col}", &cursor_col)
+        .replace("{screen_preview}", &preview)
+}
+
+/// Regex invariant that checks named captures against per-capture conditions
+pub struct CustomRegexInvariant {
+    spec: CustomRegexSpec,
+    description: String,
+    /// Pattern and conditions, compiled once at construction
+    compiled: Result<(Regex, Vec<Regex>), String>,
+}
+
+impl CustomRegexInvariant {
+    pub fn new(spec: CustomRegexSpec) -> Self {
+        let description = spec
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("Screen must match '{}'", spec.pattern));
+        let compiled = spec.compile();
+        Self {
+            spec,
+            description,
+            compiled,
+        }
+    }
+
+    /// Check the screen text, returning a failure reason if the spec is violated
+    fn check(&self, text: &str) -> Result<String, String> {
+        let (regex, conditions) = self.compiled.as_ref().map_err(Clone::clone)?;
+        let captures = regex
+            .captures(text)
+            .ok_or_else(|| format!("Pattern '{}' not found on screen", self.spec.pattern))?;
+
+        for (constraint, condition) in self.spec.constraints.iter().zip(conditions) {
+            let value = captures
+                .name(&constraint.capture)
+                .map(|m| m.as_str())
+                .ok_or_else(|| format!("Capture '{}' missing from match", constraint.capture))?;
+
+            if condition.is_match(value) != constraint.must_match {
+                return Err(format!(
+                    "Capture '{}' = '{}' {} condition '{}'",
+                    constraint.capture,
+                    value,
+                    if constraint.must_match {
+                        "does not match"
+                    } else {
+                        "unexpectedly matches"
+                    },
+                    constraint.condition
+                ));
+            }
+        }
+
+        Ok(format!(
+            "Matched '{}' ({} constraints satisfied)",
+            captures.get(0).map(|m| m.as_str()).unwrap_or_default(),
+            self.spec.constraints.len()
+        ))
+    }
+}
+
+impl Invariant for CustomRegexInvariant {
+    fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn evaluate(&self, ctx: &mut InvariantContext) -> InvariantResult {
+        let screen_text = ctx.screen.map(|s| s.text()).unwrap_or_default();
+        let (satisfied, details) = match self.check(screen_text) {
+            Ok(details) => (true, details),
+            Err(details) => (false, details),
+        };
+
+        InvariantResult::new(
+            self.name(),
+            satisfied,
+            self.description(),
+            Some(details),
+            ctx.step,
+            ctx.tick,
+        )
+    }
+}
+
+/// JSON path invariant for CLIs that emit JSON (objects or JSON lines)
+pub struct JsonPathInvariant {
+    spec: JsonPathSpec,
+    description: String,
+}
+
+impl JsonPathInvariant {
+    pub fn new(spec: JsonPathSpec) -> Self {
+        let description = spec
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("JSON value at '{}' must satisfy condition", spec.path));
+        Self { spec, description }
+    }
+
+    /// Check the screen text, returning a failure reason if the spec is violated
+    fn check(&self, text: &str) -> Result<String, String> {
+        let document =
+            last_json_value(text).ok_or_else(|| "No JSON value found on screen".to_string())?;
+        let value = json_path_lookup(&document, &self.spec.path)?
+            .ok_or_else(|| format!("Path '{}' not found", self.spec.path))?;
+
+        let satisfied = match &self.spec.condition {
+            JsonCondition::Equals(expected) => value == expected,
+            JsonCondition::Contains(needle) => match value {
+                serde_json::Value::String(s) => s.contains(needle.as_str()),
+                serde_json::Value::Array(items) => items.iter().any(|i| i.as_str() == Some(needle)),
+                _ => false,
+            },
+            JsonCondition::TypeIs(expected) => JsonType::of(value) == *expected,
+        };
+
+        if satisfied {
+            Ok(format!("'{}' = {}", self.spec.path, value))
+        } else {
+            Err(format!(
+                "'{}' = {} does not satisfy {:?}",
+                self.spec.path, value, self.spec.condition
+            ))
+        }
+    }
+}
+
+impl Invariant for JsonPathInvariant {
+    fn name(&self) -> &str {
+        &self.spec.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn evaluate(&self, ctx: &mut InvariantContext) -> InvariantResult {
+        let screen_text = ctx.screen.map(|s| s.text()).unwrap_or_default();
+        let (satisfied, details) = match self.check(screen_text) {
+            Ok(details) => (true, details),
+            Err(details) => (false, details),
+        };
+
+        InvariantResult::new(
+            self.name(),
+            satisfied,
+            self.description(),
+            Some(details),
+            ctx.step,
+            ctx.tick,
+        )
+    }
+}
+
+/// Find the last complete JSON object or array on screen
+///
+/// Lines are tried bottom-up (JSON lines output); if none parses, the whole
+/// screen is joined without row breaks to recover objects that wrapped.
+fn last_json_value(text: &str) -> Option<serde_json::Value> {
+    let is_json_start = |s: &str| s.starts_with('{') || s.starts_with('[');
+
+    for line in text.lines().rev() {
+        let line = line.trim();
+        if is_json_start(line) {
+            if let Ok(value) = serde_json::from_str(line) {
+                return Some(value);
+            }
+        }
+    }
+
+    let joined: String = text.lines().map(|l| l.trim_end()).collect();
+    let start = joined.find(['{', '['])?;
+    serde_json::Deserializer::from_str(&joined[start..])
+        .into_iter::<serde_json::Value>()
+        .map_while(Result::ok)
+        .last()
+}
+
+/// Resolve a simple JSONPath (`$`, `.key`, `[index]`, `['key']`) against a value
+fn json_path_lookup<'v>(
+    root: &'v serde_json::Value,
+    path: &str,
+) -> Result<Option<&'v serde_json::Value>, String> {
+    let mut rest = path
+        .strip_prefix('$')
+        .ok_or_else(|| format!("JSON path '{}' must start with '$'", path))?;
+    let mut current = root;
+
+    while !rest.is_empty() {
+        let next = if let Some(after_dot) = rest.strip_prefix('.') {
+            let end = after_dot.find(['.', '[']).unwrap_or(after_dot.len());
+            let key = &after_dot[..end];
+            if key.is_empty() {
+                return Err(format!("Empty key in JSON path '{}'", path));
+            }
+            rest = &after_dot[end..];
+            current.get(key)
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let end = after_bracket
+                .find(']')
+                .ok_or_else(|| format!("Unclosed '[' in JSON path '{}'", path))?;
+            let selector = &after_bracket[..end];
+            rest = &after_bracket[end + 1..];
+            let quoted = selector
+                .strip_prefix('\'')
+                .and_then(|s| s.strip_suffix('\''))
+                .or_else(|| selector.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+            match quoted {
+                Some(key) => current.get(key),
+                None => {
+                    let index: usize = selector.parse().map_err(|_| {
+                        format!("Invalid index '{}' in JSON path '{}'", selector, path)
+                    })?;
+                    current.get(index)
+                }
+            }
+        } else {
+            return Err(format!("Unexpected '{}' in JSON path '{}'", rest, path));
+        };
+
+        match next {
+            Some(value) => current = value,
+            None => return Ok(None),
+        }
+    }
+
+    Ok(Some(current))
+}
+
 /// Engine for evaluating invariants
 pub struct InvariantEngine {
-    invariants: Vec<Box<dyn Invariant>>,
+    invariants: Vec<(Box<dyn Invariant>, Severity)>,
     results: Vec<InvariantResult>,
 }
 
@@ -883,7 +1958,16 @@ impl InvariantEngine {
 
     /// Add an invariant to the engine
     pub fn add_invariant(&mut self, invariant: Box<dyn Invariant>) {
-        self.invariants.push(invariant);
+        self.add_invariant_with_severity(invariant, Severity::Error);
+    }
+
+    /// Add an invariant whose violations are reported at the given severity
+    pub fn add_invariant_with_severity(
+        &mut self,
+        invariant: Box<dyn Invariant>,
+        severity: Severity,
+    ) {
+        self.invariants.push((invariant, severity));
     }
 
     /// Add built-in invariants from specification
@@ -896,16 +1980,17 @@ impl InvariantEngine {
     /// Evaluate all invariants
     pub fn evaluate(&mut self, ctx: &mut InvariantContext) -> &[InvariantResult] {
         self.results.clear();
-        for invariant in &self.invariants {
-            let result = invariant.evaluate(ctx);
+        for (invariant, severity) in &self.invariants {
+            let mut result = invariant.evaluate(ctx);
+            result.severity = *severity;
             self.results.push(result);
         }
         &self.results
     }
 
-    /// Check if all invariants are satisfied
+    /// Check if all invariants are satisfied (warnings are ignored)
     pub fn all_satisfied(&self) -> bool {
-        self.results.iter().all(|r| r.satisfied)
+        !self.results.iter().any(|r| r.is_error())
     }
 
     /// Get all violations
@@ -913,6 +1998,11 @@ impl InvariantEngine {
         self.results.iter().filter(|r| r.violation()).collect()
     }
 
+    /// Get violations with `Error` severity
+    pub fn errors(&self) -> Vec<&InvariantResult> {
+        self.results.iter().filter(|r| r.is_error()).collect()
+    }
+
     /// Get all results
     pub fn _results(&self) -> &[InvariantResult] {
         &self.results
@@ -950,6 +2040,9 @@ mod tests {
             last_screen_hash: None,
             no_output_ticks: 0,
             expected_signal: None,
+            signal_delivery: None,
+            asserting: false,
+            run_ended: false,
         }
     }
 
@@ -1050,6 +2143,9 @@ mod tests {
             last_screen_hash: None,
             no_output_ticks: 0,
             expected_signal: None,
+            signal_delivery: None,
+            asserting: false,
+            run_ended: false,
         };
 
         let results = engine.evaluate(&mut ctx);
@@ -1058,6 +2154,35 @@ mod tests {
         assert!(engine.violations().is_empty());
     }
 
+    #[test]
+    fn engine_ignores_warnings_in_all_satisfied() {
+        let mut engine = InvariantEngine::new();
+        engine.add_invariant(Box::new(CursorBoundsInvariant));
+        engine.add_invariant_with_severity(
+            Box::new(ScreenContainsInvariant::new("missing".to_string(), true)),
+            Severity::Warning,
+        );
+
+        let screen = Screen::new(80, 24);
+        let mut ctx = create_test_context(&screen, 0, 0);
+
+        let results = engine.evaluate(&mut ctx);
+        assert_eq!(results[0].severity, Severity::Error);
+        assert_eq!(results[1].severity, Severity::Warning);
+        assert!(results[1].violation());
+        assert!(engine.all_satisfied());
+        assert_eq!(engine.violations().len(), 1);
+        assert!(engine.errors().is_empty());
+
+        engine.add_invariant(Box::new(ScreenContainsInvariant::new(
+            "missing".to_string(),
+            true,
+        )));
+        engine.evaluate(&mut ctx);
+        assert!(!engine.all_satisfied());
+        assert_eq!(engine.errors().len(), 1);
+    }
+
     #[test]
     fn no_deadlock_with_output() {
         let mut screen = Screen::new(80, 24);
@@ -1077,6 +2202,9 @@ mod tests {
             last_screen_hash: None,
             no_output_ticks: 0,
             expected_signal: None,
+            signal_delivery: None,
+            asserting: false,
+            run_ended: false,
         };
 
         let result = inv.evaluate(&mut ctx);
@@ -1099,9 +2227,549 @@ mod tests {
             last_screen_hash: None,
             no_output_ticks: 100, // More than timeout
             expected_signal: None,
+            signal_delivery: None,
+            asserting: false,
+            run_ended: false,
+        };
+
+        let result = inv.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+    }
+
+    fn progress_spec(constraints: Vec<RegexConstraint>) -> CustomRegexSpec {
+        CustomRegexSpec {
+            name: "progress".to_string(),
+            pattern: r"(?P<pct>\d+)%".to_string(),
+            constraints,
+            description: None,
+        }
+    }
+
+    fn percent_constraint() -> RegexConstraint {
+        RegexConstraint {
+            capture: "pct".to_string(),
+            condition: r"^(100|[1-9]?\d)$".to_string(),
+            must_match: true,
+        }
+    }
+
+    #[test]
+    fn custom_regex_capture_satisfied() {
+        let mut screen = Screen::new(80, 24);
+        screen.process(b"Downloading... 42%");
+        let inv = CustomRegexInvariant::new(progress_spec(vec![percent_constraint()]));
+        let mut ctx = create_test_context(&screen, 0, 0);
+
+        let result = inv.evaluate(&mut ctx);
+        assert!(result.satisfied, "{:?}", result.details);
+        assert_eq!(result.name, "progress");
+    }
+
+    #[test]
+    fn custom_regex_missing_capture() {
+        let mut screen = Screen::new(80, 24);
+        screen.process(b"Downloading... 42%");
+        let inv = CustomRegexInvariant::new(progress_spec(vec![RegexConstraint {
+            capture: "total".to_string(),
+            condition: r"\d+".to_string(),
+            must_match: true,
+        }]));
+        let mut ctx = create_test_context(&screen, 0, 0);
+
+        let result = inv.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+        assert!(result.details.unwrap().contains("Capture 'total' missing"));
+    }
+
+    #[test]
+    fn custom_regex_violated_constraint() {
+        let mut screen = Screen::new(80, 24);
+        screen.process(b"Downloading... 142%");
+        let inv = CustomRegexInvariant::new(progress_spec(vec![percent_constraint()]));
+        let mut ctx = create_test_context(&screen, 0, 0);
+
+        let result = inv.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+        let details = result.details.unwrap();
+        assert!(details.contains("Capture 'pct' = '142'"), "{}", details);
+    }
+
+    #[test]
+    fn custom_regex_must_not_match() {
+        let mut screen = Screen::new(80, 24);
+        screen.process(b"Downloading... 0%");
+        let inv = CustomRegexInvariant::new(progress_spec(vec![RegexConstraint {
+            capture: "pct".to_string(),
+            condition: "^0$".to_string(),
+            must_match: false,
+        }]));
+        let mut ctx = create_test_context(&screen, 0, 0);
+
+        let result = inv.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+        assert!(result.details.unwrap().contains("unexpectedly matches"));
+    }
+
+    #[test]
+    fn custom_regex_pattern_not_found() {
+        let screen = Screen::new(80, 24);
+        let inv = CustomRegexInvariant::new(progress_spec(vec![]));
+        let mut ctx = create_test_context(&screen, 0, 0);
+
+        let result = inv.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+        assert!(result.details.unwrap().contains("not found"));
+    }
+
+    fn json_spec(path: &str, condition: JsonCondition) -> JsonPathSpec {
+        JsonPathSpec {
+            name: "json_status".to_string(),
+            path: path.to_string(),
+            condition,
+            description: None,
+        }
+    }
+
+    #[test]
+    fn json_path_equals_passes() {
+        let mut screen = Screen::new(80, 24);
+        screen.process(br#"{"status":"ok"}"#);
+        let inv = JsonPathInvariant::new(json_spec(
+            "$.status",
+            JsonCondition::Equals(serde_json::json!("ok")),
+        ));
+        let mut ctx = create_test_context(&screen, 0, 0);
+
+        let result = inv.evaluate(&mut ctx);
+        assert!(result.satisfied, "{:?}", result.details);
+    }
+
+    #[test]
+    fn json_path_missing_fails() {
+        let mut screen = Screen::new(80, 24);
+        screen.process(br#"{"status":"ok"}"#);
+        let inv = JsonPathInvariant::new(json_spec(
+            "$.missing",
+            JsonCondition::TypeIs(JsonType::String),
+        ));
+        let mut ctx = create_test_context(&screen, 0, 0);
+
+        let result = inv.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+        assert!(result.details.unwrap().contains("'$.missing' not found"));
+    }
+
+    #[test]
+    fn json_path_uses_last_json_line() {
+        let mut screen = Screen::new(80, 24);
+        screen.process(b"{\"status\":\"starting\"}\r\n");
+        screen.process(b"{\"status\":\"ok\",\"items\":[{\"id\":7}],\"tags\":[\"a\"]}\r\n");
+
+        let check = |path: &str, condition: JsonCondition| {
+            let inv = JsonPathInvariant::new(json_spec(path, condition));
+            let mut ctx = create_test_context(&screen, 0, 0);
+            inv.evaluate(&mut ctx).satisfied
+        };
+
+        assert!(check(
+            "$.status",
+            JsonCondition::Equals(serde_json::json!("ok"))
+        ));
+        assert!(check(
+            "$.items[0].id",
+            JsonCondition::Equals(serde_json::json!(7))
+        ));
+        assert!(check(
+            "$['items'][0]",
+            JsonCondition::TypeIs(JsonType::Object)
+        ));
+        assert!(check("$.status", JsonCondition::Contains("o".to_string())));
+        assert!(check("$.tags", JsonCondition::Contains("a".to_string())));
+        assert!(!check("$.items[1]", JsonCondition::TypeIs(JsonType::Null)));
+    }
+
+    #[test]
+    fn json_path_recovers_wrapped_object() {
+        let mut screen = Screen::new(10, 5);
+        screen.process(br#"{"status":"ok"}"#);
+
+        let inv = JsonPathInvariant::new(json_spec(
+            "$.status",
+            JsonCondition::Equals(serde_json::json!("ok")),
+        ));
+        let mut ctx = create_test_context(&screen, 0, 0);
+        assert!(inv.evaluate(&mut ctx).satisfied);
+    }
+
+    #[test]
+    fn json_path_rejects_invalid_path() {
+        let value = serde_json::json!({"a": 1});
+        assert!(json_path_lookup(&value, "a").is_err());
+        assert!(json_path_lookup(&value, "$[0").is_err());
+        assert_eq!(json_path_lookup(&value, "$").unwrap(), Some(&value));
+    }
+
+    #[test]
+    fn custom_message_interpolates_context() {
+        let mut screen = Screen::new(80, 24);
+        screen.process(b"Welcome\r\n\x1b[3;5H");
+        let inv = CustomInvariant::new(
+            "login_button".to_string(),
+            Some("[Login]".to_string()),
+            true,
+            None,
+            None,
+            None,
+        )
+        .with_message(Some(
+            "login button missing at tick {tick} (cursor {cursor_row},{cursor_col}): {screen_preview}"
+                .to_string(),
+        ));
+        let mut ctx = create_test_context(&screen, 2, 57);
+
+        let result = inv.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+        let details = result.details.unwrap();
+        assert!(
+            details.starts_with("login button missing at tick 57 (cursor 2,4): Welcome"),
+            "{}",
+            details
+        );
+    }
+
+    #[test]
+    fn custom_message_unused_when_satisfied() {
+        let mut screen = Screen::new(80, 24);
+        screen.process(b"[Login]");
+        let inv = CustomInvariant::new(
+            "login_button".to_string(),
+            Some("[Login]".to_string()),
+            true,
+            None,
+            None,
+            None,
+        )
+        .with_message(Some("missing at tick {tick}".to_string()));
+        let mut ctx = create_test_context(&screen, 0, 3);
+
+        let result = inv.evaluate(&mut ctx);
+        assert!(result.satisfied);
+        assert!(result.details.is_none());
+    }
+
+    fn signal_context<'a>(
+        screen: &'a Screen,
+        process: &'a mut PtyProcess,
+        signal: Option<&str>,
+    ) -> InvariantContext<'a> {
+        InvariantContext {
+            screen: Some(screen),
+            process,
+            step: 1,
+            tick: 10,
+            _is_replay: false,
+            last_screen_hash: None,
+            no_output_ticks: 0,
+            expected_signal: signal.map(str::to_string),
+            signal_delivery: None,
+            asserting: false,
+            run_ended: false,
+        }
+    }
+
+    #[test]
+    fn signal_handled_correctly_exit() {
+        use crate::process::ProcessConfig;
+        let screen = Screen::new(80, 24);
+        let mut process = PtyProcess::spawn(&ProcessConfig::shell("sleep 60")).unwrap();
+        let inv = SignalHandledCorrectlyInvariant::new(
+            "SIGTERM".to_string(),
+            SignalBehavior::Exit {
+                exit_code: None,
+                within_ticks: 0,
+            },
+        );
+
+        // Running process violates Exit once the signal was sent
+        let mut ctx = signal_context(&screen, &mut process, Some("SIGTERM"));
+        let result = inv.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+        assert!(result.details.unwrap().contains("still running"));
+
+        process.signal_term().unwrap();
+        process.wait().unwrap();
+        let mut ctx = signal_context(&screen, &mut process, Some("SIGTERM"));
+        let result = inv.evaluate(&mut ctx);
+        assert!(result.satisfied, "{:?}", result.details);
+    }
+
+    #[test]
+    fn signal_handled_correctly_exit_waits_within_ticks() {
+        use crate::process::ProcessConfig;
+        let screen = Screen::new(80, 24);
+        let mut process = PtyProcess::spawn(&ProcessConfig::shell("sleep 60")).unwrap();
+        let inv = SignalHandledCorrectlyInvariant::new(
+            "SIGTERM".to_string(),
+            SignalBehavior::Exit {
+                exit_code: None,
+                within_ticks: 5,
+            },
+        );
+        let delivered = |elapsed_ticks| {
+            Some(SignalDelivery {
+                tick: 2,
+                elapsed_ticks,
+                screen_changed: false,
+            })
         };
 
+        let mut ctx = signal_context(&screen, &mut process, Some("SIGTERM"));
+        ctx.signal_delivery = delivered(4);
+        let result = inv.evaluate(&mut ctx);
+        assert!(result.satisfied);
+        assert!(result.details.unwrap().contains("Waiting for exit"));
+
+        ctx.signal_delivery = delivered(5);
+        assert!(!inv.evaluate(&mut ctx).satisfied);
+
+        // At run end the rest of the window is waited out, then judged
+        ctx.signal_delivery = delivered(4);
+        ctx.run_ended = true;
+        let result = inv.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+        assert!(result.details.unwrap().contains("still running"));
+        process.signal_kill().ok();
+    }
+
+    #[test]
+    fn signal_acknowledged_within_budget() {
+        use crate::process::ProcessConfig;
+        let screen = Screen::new(80, 24);
+        let mut process = PtyProcess::spawn(&ProcessConfig::shell("sleep 60")).unwrap();
+        let inv = SignalAcknowledgedInvariant::new("SIGINT".to_string(), 5);
+        let delivered = |elapsed_ticks, screen_changed| {
+            Some(SignalDelivery {
+                tick: 2,
+                elapsed_ticks,
+                screen_changed,
+            })
+        };
+
+        let mut ctx = signal_context(&screen, &mut process, None);
+        assert!(inv.evaluate(&mut ctx).satisfied);
+
+        let mut ctx = signal_context(&screen, &mut process, Some("SIGINT"));
+        ctx.signal_delivery = delivered(5, false);
+        assert!(inv.evaluate(&mut ctx).satisfied);
+
+        ctx.signal_delivery = delivered(6, false);
+        let result = inv.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+        assert!(result.details.unwrap().contains("tick 2"));
+
+        ctx.signal_delivery = delivered(6, true);
+        assert!(inv.evaluate(&mut ctx).satisfied);
+
+        process.signal_kill().unwrap();
+        process.wait().unwrap();
+        let mut ctx = signal_context(&screen, &mut process, Some("SIGINT"));
+        ctx.signal_delivery = delivered(6, false);
+        assert!(inv.evaluate(&mut ctx).satisfied);
+    }
+
+    #[test]
+    fn alt_screen_restored_checks_buffer_at_exit() {
+        use crate::process::ProcessConfig;
+        let mut screen = Screen::new(80, 24);
+        screen.process(b"\x1b[?1049h");
+        let mut process = PtyProcess::spawn(&ProcessConfig::shell("sleep 60")).unwrap();
+
+        // Being on the alternate screen is fine while the app runs
+        let mut ctx = signal_context(&screen, &mut process, None);
+        assert!(AltScreenRestoredInvariant.evaluate(&mut ctx).satisfied);
+
+        process.signal_kill().unwrap();
+        process.wait().unwrap();
+        let mut ctx = signal_context(&screen, &mut process, None);
+        let result = AltScreenRestoredInvariant.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+
+        screen.process(b"\x1b[?1049l");
+        let mut ctx = signal_context(&screen, &mut process, None);
+        assert!(AltScreenRestoredInvariant.evaluate(&mut ctx).satisfied);
+    }
+
+    #[test]
+    fn signal_handled_correctly_ignore() {
+        use crate::process::ProcessConfig;
+        let screen = Screen::new(80, 24);
+        let mut process = PtyProcess::spawn(&ProcessConfig::shell("sleep 60")).unwrap();
+        let inv =
+            SignalHandledCorrectlyInvariant::new("SIGHUP".to_string(), SignalBehavior::Ignore);
+
+        let mut ctx = signal_context(&screen, &mut process, Some("SIGHUP"));
+        assert!(inv.evaluate(&mut ctx).satisfied);
+
+        process.signal_kill().unwrap();
+        process.wait().unwrap();
+        let mut ctx = signal_context(&screen, &mut process, Some("SIGHUP"));
+        let result = inv.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+        assert!(result.details.unwrap().contains("killed by signal 9"));
+    }
+
+    #[test]
+    fn signal_handled_correctly_waits_for_signal() {
+        use crate::process::ProcessConfig;
+        let screen = Screen::new(80, 24);
+        let mut process = PtyProcess::spawn(&ProcessConfig::shell("sleep 60")).unwrap();
+        let inv = SignalHandledCorrectlyInvariant::new(
+            "SIGTERM".to_string(),
+            SignalBehavior::Exit {
+                exit_code: None,
+                within_ticks: 0,
+            },
+        );
+
+        // Not applicable before the signal is sent, or for other signals
+        let mut ctx = signal_context(&screen, &mut process, None);
+        assert!(inv.evaluate(&mut ctx).satisfied);
+        let mut ctx = signal_context(&screen, &mut process, Some("SIGINT"));
+        assert!(inv.evaluate(&mut ctx).satisfied);
+        process.signal_kill().ok();
+    }
+
+    #[test]
+    fn signal_handled_correctly_restart_requires_pattern() {
+        use crate::process::ProcessConfig;
+        let mut screen = Screen::new(80, 24);
+        let mut process = PtyProcess::spawn(&ProcessConfig::shell("sleep 60")).unwrap();
+        let inv = SignalHandledCorrectlyInvariant::new(
+            "SIGHUP".to_string(),
+            SignalBehavior::Restart {
+                pattern: Some("config reloaded".to_string()),
+            },
+        );
+
+        let mut ctx = signal_context(&screen, &mut process, Some("SIGHUP"));
+        assert!(!inv.evaluate(&mut ctx).satisfied);
+
+        screen.process(b"config reloaded");
+        let mut ctx = signal_context(&screen, &mut process, Some("SIGHUP"));
+        assert!(inv.evaluate(&mut ctx).satisfied);
+        process.signal_kill().ok();
+    }
+
+    #[test]
+    fn no_bell_flags_bells() {
+        let mut screen = Screen::new(80, 24);
+        screen.process(b"quiet");
+        let mut ctx = create_test_context(&screen, 0, 0);
+        assert!(NoBellInvariant.evaluate(&mut ctx).satisfied);
+
+        screen.process(b"\x07\x07");
+        assert_eq!(screen.bell_count(), 2);
+        let mut ctx = create_test_context(&screen, 1, 5);
+        let result = NoBellInvariant.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+        assert_eq!(result.details.as_deref(), Some("Bell rang 2 time(s)"));
+    }
+
+    #[test]
+    fn no_control_chars_flags_leaked_control_code_points() {
+        // A lone ESC is held by the parser, not written to the screen
+        let mut screen = Screen::new(80, 24);
+        screen.process(b"clean\tline\r\nnext\x1b");
+        assert!(screen.has_pending_escape());
+        let mut ctx = create_test_context(&screen, 0, 0);
+        assert!(NoControlCharsInvariant.evaluate(&mut ctx).satisfied);
+
+        // A UTF-8 encoded C1 control (U+0085) is printed as a character
+        let mut screen = Screen::new(80, 24);
+        screen.process("ok\r\nbad\u{85}".as_bytes());
+        let mut ctx = create_test_context(&screen, 0, 0);
+        let result = NoControlCharsInvariant.evaluate(&mut ctx);
+        assert!(!result.satisfied);
+        assert_eq!(
+            result.details.as_deref(),
+            Some("Control character U+0085 at row 1, col 3")
+        );
+    }
+
+    #[test]
+    fn scrollback_bounded_within_limit() {
+        let mut screen = Screen::new(80, 5);
+        for i in 0..10 {
+            screen.process(format!("Line {}\r\n", i).as_bytes());
+        }
+        assert_eq!(screen.scrollback_len(), 6);
+
+        let inv = ScrollbackBoundedInvariant::new(6);
+        let mut ctx = create_test_context(&screen, 0, 0);
+        assert!(inv.evaluate(&mut ctx).satisfied);
+    }
+
+    #[test]
+    fn scrollback_bounded_exceeded() {
+        let mut screen = Screen::new(80, 5);
+        for i in 0..100 {
+            screen.process(format!("Line {}\r\n", i).as_bytes());
+        }
+
+        let inv = ScrollbackBoundedInvariant::new(50);
+        let mut ctx = create_test_context(&screen, 0, 0);
         let result = inv.evaluate(&mut ctx);
         assert!(!result.satisfied);
+        assert_eq!(
+            result.details.as_deref(),
+            Some("Scrollback: 96 lines (max: 50)")
+        );
+    }
+
+    #[test]
+    fn cursor_in_region_bounds_are_inclusive() {
+        let inv = CursorInRegionInvariant::new(2, 10, 5, 20);
+        let check = |row: usize, col: usize| {
+            let mut screen = Screen::new(80, 24);
+            screen.process(format!("\x1b[{};{}H", row + 1, col + 1).as_bytes());
+            let mut ctx = create_test_context(&screen, 0, 0);
+            inv.evaluate(&mut ctx)
+        };
+
+        assert!(check(3, 15).satisfied);
+        // Corners are inside
+        assert!(check(2, 10).satisfied);
+        assert!(check(5, 20).satisfied);
+        // One past each edge is outside
+        assert!(!check(1, 15).satisfied);
+        assert!(!check(6, 15).satisfied);
+        assert!(!check(3, 9).satisfied);
+        let result = check(3, 21);
+        assert!(!result.satisfied);
+        assert_eq!(
+            result.details.as_deref(),
+            Some("Cursor at row 3, col 21 is outside rows 2-5, cols 10-20")
+        );
+    }
+
+    #[test]
+    fn startup_output_needs_a_drawn_screen_after_budget() {
+        let inv = StartupOutputInvariant::new(5);
+        let blank = Screen::new(80, 24);
+        assert!(
+            inv.evaluate(&mut create_test_context(&blank, 0, 5))
+                .satisfied
+        );
+
+        let result = inv.evaluate(&mut create_test_context(&blank, 1, 6));
+        assert!(!result.satisfied);
+        assert!(result.details.unwrap().contains("tick 6"));
+
+        let mut drawn = Screen::new(80, 24);
+        drawn.process(b"$ ");
+        assert!(
+            inv.evaluate(&mut create_test_context(&drawn, 1, 6))
+                .satisfied
+        );
     }
 }
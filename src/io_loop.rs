@@ -3,11 +3,14 @@
 //! This module provides an epoll-based IO loop that handles
 //! reading and writing to the PTY without deadlocks.
 
+use crate::determinism::SeededRng;
 use crate::process::{ProcessError, PtyProcess};
 use crate::pty::PtyError;
 use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
-use std::os::fd::BorrowedFd;
+use std::os::fd::AsRawFd;
 
 /// Default buffer size for reading from PTY
 const DEFAULT_READ_BUFFER_SIZE: usize = 4096;
@@ -15,6 +18,9 @@ const DEFAULT_READ_BUFFER_SIZE: usize = 4096;
 /// Default maximum buffer size before backpressure kicks in
 const DEFAULT_MAX_BUFFER_SIZE: usize = 1024 * 1024; // 1MB
 
+/// Maximum size of the run-level output transcript; later output is not kept
+pub const MAX_TRANSCRIPT_SIZE: usize = 16 * 1024 * 1024; // 16MB
+
 /// Maximum poll timeout supported by the system (u16::MAX milliseconds ≈ 65.5 seconds).
 /// Timeouts larger than this will be capped. Callers needing longer waits should
 /// call poll() in a loop.
@@ -33,6 +39,8 @@ pub enum IoError {
     ReadFailed(nix::Error),
     /// Write error
     WriteFailed(nix::Error),
+    /// The process printed more than the configured output limit
+    OutputLimitExceeded(u64),
 }
 
 impl std::fmt::Display for IoError {
@@ -43,6 +51,9 @@ impl std::fmt::Display for IoError {
             IoError::BufferOverflow => write!(f, "Buffer overflow (backpressure triggered)"),
             IoError::ReadFailed(e) => write!(f, "Read failed: {}", e),
             IoError::WriteFailed(e) => write!(f, "Write failed: {}", e),
+            IoError::OutputLimitExceeded(limit) => {
+                write!(f, "Output limit exceeded ({} bytes)", limit)
+            }
         }
     }
 }
@@ -162,6 +173,88 @@ impl BoundedBuffer {
     }
 }
 
+/// Network-style delivery model for process output
+///
+/// Output is cut into chunks whose sizes come from a seeded RNG, one chunk
+/// per take, with an optional seeded pause between chunks. Boundaries are
+/// positions in the output stream, so the same seed and output always give
+/// the same chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct JitterConfig {
+    /// Smallest chunk handed out
+    #[serde(default = "default_min_chunk")]
+    pub min_chunk: usize,
+    /// Largest chunk handed out
+    #[serde(default = "default_max_chunk")]
+    pub max_chunk: usize,
+    /// Most takes a chunk may be held back after the previous one
+    #[serde(default)]
+    pub max_delay_ticks: u64,
+}
+
+fn default_min_chunk() -> usize {
+    1
+}
+
+fn default_max_chunk() -> usize {
+    64
+}
+
+impl Default for JitterConfig {
+    fn default() -> Self {
+        Self {
+            min_chunk: default_min_chunk(),
+            max_chunk: default_max_chunk(),
+            max_delay_ticks: 0,
+        }
+    }
+}
+
+/// Chunking state for [`JitterConfig`]
+#[derive(Debug)]
+struct Jitter {
+    config: JitterConfig,
+    rng: SeededRng,
+    /// Bytes left before the next chunk boundary
+    remaining: usize,
+    /// Takes still to skip before the next chunk is released
+    delay: u64,
+}
+
+impl Jitter {
+    fn new(config: JitterConfig, seed: u64) -> Self {
+        Self {
+            config,
+            rng: SeededRng::new(seed),
+            remaining: 0,
+            delay: 0,
+        }
+    }
+
+    fn take(&mut self, buffer: &mut BoundedBuffer) -> Vec<u8> {
+        if buffer.is_empty() {
+            return Vec::new();
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            return Vec::new();
+        }
+        if self.remaining == 0 {
+            let min = self.config.min_chunk.max(1);
+            let max = self.config.max_chunk.max(min);
+            self.remaining = self.rng.usize_range(min, max + 1);
+        }
+        let chunk = buffer.take(self.remaining);
+        self.remaining -= chunk.len();
+        if self.remaining == 0 {
+            self.delay = self
+                .rng
+                .next_u64_max(self.config.max_delay_ticks.saturating_add(1));
+        }
+        chunk
+    }
+}
+
 /// Result of a poll operation
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PollResult {
@@ -191,6 +284,15 @@ pub struct IoLoop {
     bytes_written: u64,
     /// Bytes dropped due to backpressure
     bytes_dropped: u64,
+    /// Copy of everything read, when a run-level transcript is wanted
+    transcript: Option<Vec<u8>>,
+    /// Everything read from a separate stderr pipe (up to
+    /// [`MAX_TRANSCRIPT_SIZE`])
+    stderr: Vec<u8>,
+    /// Chunked, delayed delivery of output, when enabled
+    jitter: Option<Jitter>,
+    /// Total output allowed before reading stops
+    output_limit: Option<u64>,
 }
 
 impl IoLoop {
@@ -210,9 +312,30 @@ impl IoLoop {
             bytes_read: 0,
             bytes_written: 0,
             bytes_dropped: 0,
+            transcript: None,
+            stderr: Vec::new(),
+            jitter: None,
+            output_limit: None,
         }
     }
 
+    /// Keep a copy of all output read (up to [`MAX_TRANSCRIPT_SIZE`])
+    pub fn with_transcript(mut self, enabled: bool) -> Self {
+        self.transcript = enabled.then(Vec::new);
+        self
+    }
+
+    /// Everything read so far, if a transcript is being kept
+    pub fn transcript(&self) -> Option<&[u8]> {
+        self.transcript.as_deref()
+    }
+
+    /// Everything the process wrote to stderr so far, when stderr is kept
+    /// off the PTY
+    pub fn stderr(&self) -> &[u8] {
+        &self.stderr
+    }
+
     /// Set the read buffer size
     pub fn with_read_buffer_size(mut self, size: usize) -> Self {
         self.read_buffer_size = size;
@@ -231,6 +354,27 @@ impl IoLoop {
         self
     }
 
+    /// Hand output out in seeded chunks instead of all at once
+    pub fn with_jitter(mut self, config: JitterConfig, seed: u64) -> Self {
+        self.jitter = Some(Jitter::new(config, seed));
+        self
+    }
+
+    /// Stop reading once more than `bytes` of output have been read in total
+    ///
+    /// Unlike the output buffer size, this caps the whole run, so a process
+    /// that never stops printing can't keep the run busy forever.
+    pub fn with_output_limit(mut self, bytes: u64) -> Self {
+        self.output_limit = Some(bytes);
+        self
+    }
+
+    /// Whether the process has printed more than the output limit
+    pub fn output_limit_exceeded(&self) -> bool {
+        self.output_limit
+            .is_some_and(|limit| self.bytes_read > limit)
+    }
+
     /// Poll the PTY for readiness.
     ///
     /// # Arguments
@@ -257,21 +401,19 @@ impl IoLoop {
     /// }
     /// ```
     pub fn poll(&self, process: &PtyProcess, timeout_ms: i32) -> Result<PollResult, IoError> {
-        let master_fd = process.pty().master_fd()?;
-
-        // SAFETY: BorrowedFd::borrow_raw is safe because:
-        // - master_fd is a valid fd from Pty (checked by master_fd()?)
-        // - We hold &process, so the fd remains valid for poll duration
-        // - poll() does not take ownership of the fd
-        let borrowed_fd = unsafe { BorrowedFd::borrow_raw(master_fd) };
-
-        let mut poll_flags = PollFlags::POLLIN;
+        // A PTY reads and writes through the master; pipes have separate
+        // fds for each direction
+        let output_fd = process.output_fd()?;
+        let mut poll_fds = vec![PollFd::new(output_fd, PollFlags::POLLIN)];
         if !self.input_buffer.is_empty() {
-            poll_flags |= PollFlags::POLLOUT;
+            let input_fd = process.input_fd()?;
+            if input_fd.as_raw_fd() == output_fd.as_raw_fd() {
+                poll_fds[0] = PollFd::new(output_fd, PollFlags::POLLIN | PollFlags::POLLOUT);
+            } else {
+                poll_fds.push(PollFd::new(input_fd, PollFlags::POLLOUT));
+            }
         }
 
-        let mut poll_fds = [PollFd::new(borrowed_fd, poll_flags)];
-
         // Convert timeout_ms to PollTimeout
         let timeout = if timeout_ms < 0 {
             PollTimeout::NONE // Block forever
@@ -287,10 +429,13 @@ impl IoLoop {
         poll(&mut poll_fds, timeout).map_err(IoError::PollFailed)?;
 
         let revents = poll_fds[0].revents().unwrap_or(PollFlags::empty());
+        let writable = poll_fds
+            .iter()
+            .any(|fd| fd.revents().is_some_and(|r| r.contains(PollFlags::POLLOUT)));
 
         Ok(PollResult {
             readable: revents.contains(PollFlags::POLLIN),
-            writable: revents.contains(PollFlags::POLLOUT),
+            writable,
             error: revents.contains(PollFlags::POLLERR),
             hangup: revents.contains(PollFlags::POLLHUP),
         })
@@ -298,10 +443,14 @@ impl IoLoop {
 
     /// Read available data from the PTY into the output buffer
     pub fn read_available(&mut self, process: &PtyProcess) -> Result<usize, IoError> {
+        if let Some(limit) = self.output_limit.filter(|_| self.output_limit_exceeded()) {
+            return Err(IoError::OutputLimitExceeded(limit));
+        }
+
         let mut temp_buf = vec![0u8; self.read_buffer_size];
         let mut total_read = 0;
 
-        loop {
+        while !self.output_limit_exceeded() {
             match process.read(&mut temp_buf) {
                 Ok(0) => break, // No more data (non-blocking returned EAGAIN)
                 Ok(n) => {
@@ -311,6 +460,10 @@ impl IoLoop {
                     } else {
                         self.output_buffer.push(&temp_buf[..n])?;
                     }
+                    if let Some(transcript) = self.transcript.as_mut() {
+                        let room = MAX_TRANSCRIPT_SIZE.saturating_sub(transcript.len());
+                        transcript.extend_from_slice(&temp_buf[..n.min(room)]);
+                    }
                     total_read += n;
                     self.bytes_read += n as u64;
                 }
@@ -318,6 +471,18 @@ impl IoLoop {
             }
         }
 
+        // Stderr never reaches the screen, so it is only collected
+        loop {
+            match process.read_stderr(&mut temp_buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    let room = MAX_TRANSCRIPT_SIZE.saturating_sub(self.stderr.len());
+                    self.stderr.extend_from_slice(&temp_buf[..n.min(room)]);
+                }
+                Err(e) => return Err(IoError::Process(e)),
+            }
+        }
+
         Ok(total_read)
     }
 
@@ -360,9 +525,13 @@ impl IoLoop {
         }
     }
 
-    /// Take all output data from the buffer
+    /// Take output data from the buffer: everything, or the next chunk when
+    /// jitter is enabled
     pub fn take_output(&mut self) -> Vec<u8> {
-        self.output_buffer.take_all()
+        match self.jitter.as_mut() {
+            Some(jitter) => jitter.take(&mut self.output_buffer),
+            None => self.output_buffer.take_all(),
+        }
     }
 
     /// Get a reference to the output buffer
@@ -512,6 +681,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn output_limit_stops_reading() {
+        let config = ProcessConfig::shell("yes");
+        let process = PtyProcess::spawn(&config).unwrap();
+
+        thread::sleep(std::time::Duration::from_millis(100));
+
+        let mut io = IoLoop::new().with_output_limit(16);
+        let _ = io.read_available(&process);
+        assert!(io.output_limit_exceeded());
+
+        let read = io.bytes_read();
+        let result = io.read_available(&process);
+        assert!(matches!(result, Err(IoError::OutputLimitExceeded(16))));
+        assert_eq!(io.bytes_read(), read);
+    }
+
     #[test]
     fn io_loop_writes_input() {
         let config = ProcessConfig::shell("cat");
@@ -655,4 +841,63 @@ mod tests {
             assert_eq!(front.len() + back.len(), 500);
         }
     }
+
+    /// Chunk lengths from feeding `data` in `pieces` and taking once per tick
+    fn jitter_chunks(seed: u64, data: &[u8], pieces: usize) -> Vec<usize> {
+        let config = JitterConfig {
+            min_chunk: 2,
+            max_chunk: 9,
+            max_delay_ticks: 3,
+        };
+        let mut io = IoLoop::new().with_jitter(config, seed);
+        let mut chunks = Vec::new();
+        for piece in data.chunks(data.len().div_ceil(pieces)) {
+            io.output_buffer.push(piece).unwrap();
+            chunks.push(io.take_output().len());
+        }
+        while !io.output_buffer.is_empty() {
+            chunks.push(io.take_output().len());
+        }
+        chunks
+    }
+
+    #[test]
+    fn jitter_chunks_are_reproducible() {
+        let data = vec![b'x'; 200];
+
+        let first = jitter_chunks(7, &data, 1);
+        assert_eq!(first, jitter_chunks(7, &data, 1));
+        assert_ne!(first, jitter_chunks(8, &data, 1));
+        assert_eq!(first.iter().sum::<usize>(), 200);
+        assert!(first.contains(&0), "delays should hold chunks back");
+        assert!(first.iter().all(|&n| n <= 9));
+    }
+
+    #[test]
+    fn jitter_boundaries_do_not_depend_on_read_sizes() {
+        let data: Vec<u8> = (0..=255).collect();
+
+        let boundaries = |chunks: Vec<usize>| {
+            chunks
+                .iter()
+                .scan(0, |pos, &n| {
+                    *pos += n;
+                    Some(*pos)
+                })
+                .collect::<std::collections::BTreeSet<_>>()
+        };
+        // Everything up front: every boundary is a seeded one
+        let seeded = boundaries(jitter_chunks(3, &data, 1));
+        // Trickled in: extra cuts where reads ran dry, but the seeded
+        // boundaries are all still there
+        let trickled = boundaries(jitter_chunks(3, &data, 256));
+        assert!(seeded.is_subset(&trickled));
+    }
+
+    #[test]
+    fn take_output_without_jitter_takes_everything() {
+        let mut io = IoLoop::new();
+        io.output_buffer.push(b"hello").unwrap();
+        assert_eq!(io.take_output(), b"hello");
+    }
 }
@@ -7,13 +7,21 @@
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 
+// The PTY and process layers are built on Unix APIs (nix, fork, execvpe) and
+// there is no ConPTY backend, so stop here rather than deep inside nix
+#[cfg(windows)]
+compile_error!("bte does not support Windows: it needs a Unix PTY and has no ConPTY backend");
+
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::ops::Range;
 use std::path::PathBuf;
 
 mod ansi;
 mod bench;
+mod cast;
 mod determinism;
+mod diff;
 mod flaky;
 mod fuzzy;
 mod invariants;
@@ -23,7 +31,9 @@ mod parallel;
 mod platform;
 mod process;
 mod pty;
+mod record;
 mod replay;
+mod report;
 mod runner;
 mod scenario;
 mod screen;
@@ -48,22 +58,124 @@ struct Args {
     #[arg(short, long)]
     seed: Option<u64>,
 
-    #[arg(long, default_value = "10000")]
-    max_ticks: u64,
+    /// Tick budget for the run (default: the scenario's timeout_ms, else
+    /// 10000)
+    #[arg(long)]
+    max_ticks: Option<u64>,
+
+    /// End the run with an error once the process prints more than this
+    #[arg(long, value_name = "BYTES")]
+    max_output_bytes: Option<u64>,
+
+    /// Compile step patterns without the regex crate's size limit
+    #[arg(long)]
+    lift_regex_size_limit: bool,
+
+    /// Milliseconds a process still running at the end of a run gets to exit
+    /// after SIGTERM before it is killed
+    #[arg(long, value_name = "MS", default_value_t = process::SIGTERM_TIMEOUT_MS)]
+    term_timeout_ms: u64,
+
+    /// Sleep this many milliseconds per tick (slow-motion playback)
+    #[arg(long, value_name = "MS", default_value_t = 0)]
+    tick_delay: u64,
+
+    /// Let ticks follow the wall clock instead of advancing deterministically
+    /// (runs can't be replayed)
+    #[arg(long)]
+    real_time: bool,
 
     #[arg(short, long)]
     verbose: bool,
 }
 
+/// Output format for `bte run` results
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    /// Human-readable summary
+    Text,
+    /// One JSON object with the outcome, counts and invariant results
+    Json,
+    /// TAP version 13 stream (same as `--tap`)
+    Tap,
+}
+
 #[derive(Subcommand, Debug)]
 enum Command {
     #[command(name = "run")]
     Run {
+        /// Scenario file, or a directory whose scenarios are all run
         #[arg(value_name = "FILE")]
         scenario: PathBuf,
 
         #[arg(short, long, value_name = "FILE")]
         output: Option<PathBuf>,
+
+        /// Rewrite pinned screen hashes and snapshot files that no longer
+        /// match
+        #[arg(long)]
+        update_snapshots: bool,
+
+        /// Overwrite screenshot baselines that no longer match
+        #[arg(long)]
+        update_baselines: bool,
+
+        /// Derive the seed from the scenario content when it sets none
+        #[arg(long)]
+        seed_from_content: bool,
+
+        /// Write a JUnit XML report to this path
+        #[arg(long, value_name = "FILE")]
+        junit: Option<PathBuf>,
+
+        /// Record wall-clock step timings in the trace (not deterministic)
+        #[arg(long)]
+        record_wallclock: bool,
+
+        /// Capture the terminal's tab stops at each trace checkpoint
+        #[arg(long)]
+        record_tab_stops: bool,
+
+        /// Record the cursor position after each step that moves it
+        #[arg(long)]
+        record_cursor_trail: bool,
+
+        /// Print results as a TAP version 13 stream
+        #[arg(long)]
+        tap: bool,
+
+        /// How to print the run result
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+
+        /// Re-run up to N more times on an error or timeout (invariant
+        /// violations and unexpected exit codes are never retried)
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        retries: u32,
+
+        /// Run with plain pipes instead of a PTY (resize and assert_no_echo are skipped)
+        #[arg(long)]
+        no_pty: bool,
+
+        /// Mask env vars whose names match this regex in saved traces
+        /// (repeatable; replaces the default token/secret/password/key rule)
+        #[arg(long, value_name = "REGEX")]
+        redact_env: Vec<String>,
+
+        /// In a directory run, only run scenarios with one of these tags
+        /// (repeatable)
+        #[arg(long, value_name = "TAG")]
+        tag: Vec<String>,
+
+        /// In a directory run, skip scenarios with any of these tags
+        /// (repeatable)
+        #[arg(long, value_name = "TAG")]
+        exclude_tag: Vec<String>,
+
+        /// Validate the scenario and print the steps it would run and its
+        /// seed, without starting the process
+        #[arg(long)]
+        dry_run: bool,
     },
 
     #[command(name = "replay")]
@@ -73,18 +185,79 @@ enum Command {
 
         #[arg(long)]
         halt_on_divergence: bool,
+
+        /// Re-run the scenario and verify every step's screen hashes
+        #[arg(long)]
+        verify_steps: bool,
+
+        /// Re-run the scenario and compare checkpoint hashes and exit status
+        #[arg(long)]
+        live: bool,
     },
 
     #[command(name = "validate")]
     Validate {
         #[arg(value_name = "FILE")]
         scenario: PathBuf,
+
+        /// Reject fields the scenario format does not know, e.g. typos
+        #[arg(long)]
+        strict: bool,
     },
 
     #[command(name = "info")]
     Info {
         #[arg(value_name = "FILE")]
         trace: PathBuf,
+
+        /// Export the trace as an asciinema v2 cast file
+        #[arg(long, value_name = "FILE")]
+        cast: Option<PathBuf>,
+
+        /// Playback duration of one tick in the cast, in milliseconds
+        #[arg(long, default_value_t = cast::DEFAULT_CAST_TICK_MS)]
+        cast_tick_ms: u64,
+
+        /// Print the recorded cursor trail
+        #[arg(long)]
+        cursor_trail: bool,
+
+        /// Print the full record of a single step
+        #[arg(long, value_name = "N", conflicts_with = "steps")]
+        step: Option<usize>,
+
+        /// Print the full record of a range of steps (`A..B`, `A..=B` or `A..`)
+        #[arg(long, value_name = "RANGE", value_parser = parse_step_range)]
+        steps: Option<Range<usize>>,
+    },
+
+    /// Drive a program by hand and save the session as a scenario
+    #[command(name = "record")]
+    Record {
+        /// Shell command to run
+        #[arg(value_name = "COMMAND")]
+        command: String,
+
+        /// Where to write the scenario YAML
+        #[arg(short, long, value_name = "FILE")]
+        output: PathBuf,
+    },
+
+    /// Print the JSON Schema of the scenario format, for editors
+    #[command(name = "schema")]
+    Schema {
+        /// Write the schema here instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+
+    #[command(name = "diff")]
+    Diff {
+        #[arg(value_name = "OLD")]
+        old: PathBuf,
+
+        #[arg(value_name = "NEW")]
+        new: PathBuf,
     },
 }
 
@@ -122,24 +295,155 @@ fn run() -> Result<i32> {
     let config = runner::RunnerConfig {
         seed: args.seed,
         max_ticks: args.max_ticks,
+        max_output_bytes: args.max_output_bytes,
+        lift_regex_size_limit: args.lift_regex_size_limit,
+        term_timeout_ms: args.term_timeout_ms,
+        tick_delay_ms: args.tick_delay,
+        timing_mode: if args.real_time {
+            timing::TimingMode::RealTime
+        } else {
+            timing::TimingMode::Deterministic
+        },
         verbose: args.verbose,
         ..runner::RunnerConfig::default()
     };
 
     match args.command {
-        Command::Run { scenario, output } => cmd_run(scenario, output, &config),
+        Command::Run {
+            scenario,
+            output,
+            update_snapshots,
+            update_baselines,
+            seed_from_content,
+            junit,
+            record_wallclock,
+            record_tab_stops,
+            record_cursor_trail,
+            tap,
+            format,
+            retries,
+            no_pty,
+            redact_env,
+            tag,
+            exclude_tag,
+            dry_run,
+        } => {
+            let redaction = if redact_env.is_empty() {
+                trace::RedactionPolicy::default()
+            } else {
+                trace::RedactionPolicy::from_patterns(&redact_env)
+                    .with_context(|| "Invalid --redact-env pattern")?
+            };
+            let config = runner::RunnerConfig {
+                redaction,
+                update_snapshots,
+                update_baselines,
+                record_wallclock,
+                record_tab_stops,
+                record_cursor_trail,
+                no_pty,
+                ..config
+            };
+            if scenario.is_dir() {
+                if output.is_some()
+                    || junit.is_some()
+                    || tap
+                    || format != OutputFormat::Text
+                    || update_snapshots
+                    || retries > 0
+                    || dry_run
+                {
+                    anyhow::bail!(
+                        "--output, --junit, --tap, --format, --update-snapshots, --retries and \
+                         --dry-run need a single scenario file"
+                    );
+                }
+                let filter = tags::TagFilter::include_exclude(&tag, &exclude_tag);
+                return cmd_run_dir(scenario, filter, seed_from_content, &config);
+            }
+            if !tag.is_empty() || !exclude_tag.is_empty() {
+                anyhow::bail!("--tag and --exclude-tag need a scenario directory");
+            }
+            if tap && format != OutputFormat::Text {
+                anyhow::bail!("--tap and --format can't be combined");
+            }
+            let format = if tap { OutputFormat::Tap } else { format };
+            cmd_run(
+                scenario,
+                output,
+                seed_from_content,
+                junit,
+                format,
+                retries,
+                dry_run,
+                &config,
+            )
+        }
         Command::Replay {
             trace,
             halt_on_divergence,
-        } => cmd_replay(trace, halt_on_divergence).map(|_| 0),
-        Command::Validate { scenario } => cmd_validate(scenario).map(|_| 0),
-        Command::Info { trace } => cmd_info(trace).map(|_| 0),
+            verify_steps,
+            live,
+        } => cmd_replay(trace, halt_on_divergence, verify_steps, live, &config).map(|_| 0),
+        Command::Validate { scenario, strict } => {
+            cmd_validate(scenario, strict, config.lift_regex_size_limit).map(|_| 0)
+        }
+        Command::Info {
+            trace,
+            cast,
+            cast_tick_ms,
+            cursor_trail,
+            step,
+            steps,
+        } => {
+            let steps = steps.or(step.map(|n| n..n + 1));
+            cmd_info(trace, cast, cast_tick_ms, cursor_trail, steps).map(|_| 0)
+        }
+        Command::Diff { old, new } => cmd_diff(old, new),
+        Command::Record { command, output } => cmd_record(&command, output).map(|_| 0),
+        Command::Schema { output } => cmd_schema(output).map(|_| 0),
     }
 }
 
+fn cmd_run_dir(
+    dir: PathBuf,
+    tag_filter: tags::TagFilter,
+    seed_from_content: bool,
+    config: &runner::RunnerConfig,
+) -> Result<i32> {
+    let paths = parallel::discover_scenarios(&dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+    if paths.is_empty() {
+        anyhow::bail!(
+            "No scenarios (*.yaml, *.yml, *.json, *.toml) in {}",
+            dir.display()
+        );
+    }
+
+    let parallel_config = parallel::ParallelConfig {
+        seed: config.seed,
+        seed_from_content,
+        runner_config: config.clone(),
+        tag_filter,
+        ..parallel::ParallelConfig::default()
+    };
+    let result = parallel::run_parallel_from_paths(&paths, &parallel_config).map_err(|errors| {
+        anyhow::anyhow!("Failed to load scenarios:\n  - {}", errors.join("\n  - "))
+    })?;
+
+    parallel::print_parallel_summary(&result);
+    Ok(result.exit_code())
+}
+
+#[allow(clippy::too_many_arguments)]
 fn cmd_run(
     scenario_path: PathBuf,
     output_path: Option<PathBuf>,
+    seed_from_content: bool,
+    junit_path: Option<PathBuf>,
+    format: OutputFormat,
+    retries: u32,
+    dry_run: bool,
     config: &runner::RunnerConfig,
 ) -> Result<i32> {
     if config.verbose {
@@ -149,17 +453,13 @@ fn cmd_run(
     let scenario_content = std::fs::read_to_string(&scenario_path)
         .with_context(|| format!("Failed to read scenario: {}", scenario_path.display()))?;
 
-    let scenario: scenario::Scenario = if scenario_path
-        .extension()
-        .map(|e| e.to_string_lossy().to_lowercase())
-        == Some("json".to_string())
-    {
-        serde_json::from_str(&scenario_content)
-            .with_context(|| "Failed to parse scenario as JSON")?
-    } else {
-        serde_yaml::from_str(&scenario_content)
-            .with_context(|| "Failed to parse scenario as YAML")?
-    };
+    let file_format = scenario::ScenarioFormat::from_path(&scenario_path);
+    let mut scenario = scenario::Scenario::parse(&scenario_content, file_format).map_err(|e| {
+        anyhow::anyhow!("Failed to parse scenario as {}: {}", file_format.name(), e)
+    })?;
+    let content_seed = scenario.content_seed();
+
+    scenario.resolve_paths(&scenario_path);
 
     if config.verbose {
         eprintln!("Scenario: {}", scenario.name);
@@ -169,38 +469,106 @@ fn cmd_run(
         }
     }
 
-    if let Err(errors) = scenario.validate() {
-        let error_msg: String = errors
-            .iter()
-            .map(|e| format!("  - {}", e))
-            .collect::<Vec<_>>()
-            .join("\n");
-        anyhow::bail!("Scenario validation failed:\n{}", error_msg);
-    }
+    // Secrets interpolated into the command or patterns are masked in the
+    // saved trace like sensitive `env` values
+    let secrets = scenario
+        .resolve_env_secrets(
+            |name| std::env::var(name).ok(),
+            |name| config.redaction.is_sensitive(name),
+        )
+        .and_then(|secrets| {
+            scenario
+                .validate_with(config.lift_regex_size_limit)
+                .map(|()| secrets)
+        });
+    let secrets = match secrets {
+        Ok(secrets) => secrets,
+        Err(errors) => {
+            let error_msg: String = errors
+                .iter()
+                .map(|e| format!("  - {}", e))
+                .collect::<Vec<_>>()
+                .join("\n");
+            anyhow::bail!("Scenario validation failed:\n{}", error_msg);
+        }
+    };
 
     let seed = config
         .seed
         .or(scenario.seed)
-        .unwrap_or_else(|| fastrand::u64(..));
+        .or(seed_from_content.then_some(content_seed));
+
+    if dry_run {
+        let plan = runner::format_step_plan(&scenario)
+            .map_err(|e| anyhow::anyhow!("Failed to expand steps: {}", e))?;
+        println!("Scenario: {}", scenario.name);
+        match seed {
+            Some(seed) => println!("Seed: {}", seed),
+            None => println!("Seed: random (chosen when the run starts)"),
+        }
+        println!("Steps:");
+        print!("{}", plan);
+        return Ok(0);
+    }
+
+    let seed = seed.unwrap_or_else(|| fastrand::u64(..));
 
     let config = runner::RunnerConfig {
         seed: Some(seed),
         trace_path: output_path.map(|p| p.to_string_lossy().to_string()),
-        verbose: config.verbose,
-        max_ticks: config.max_ticks,
-        tick_delay_ms: config.tick_delay_ms,
+        redaction: config.redaction.clone().with_values(secrets),
+        ..config.clone()
     };
 
     if config.verbose {
         eprintln!("Running with seed: {}", seed);
     }
 
-    let result = runner::run_scenario(&scenario, &config);
+    let result = runner::run_scenario_with_retries(&scenario, &config, retries);
+
+    if config.update_snapshots {
+        let updates = runner::screen_hash_updates(&result.trace)
+            .map_err(|e| anyhow::anyhow!("Failed to update snapshots: {}", e))?;
+        if !updates.is_empty() {
+            if file_format == scenario::ScenarioFormat::Toml {
+                // TOML integers are signed, so most hashes don't fit anyway
+                anyhow::bail!("Screen hashes can't be updated in TOML scenarios");
+            }
+            let rewritten = scenario::rewrite_screen_hashes(&scenario_content, &updates)
+                .map_err(|e| anyhow::anyhow!("Failed to update snapshots: {}", e))?;
+            std::fs::write(&scenario_path, rewritten)
+                .with_context(|| format!("Failed to write: {}", scenario_path.display()))?;
+            eprintln!(
+                "Updated {} screen hash(es) in {}",
+                updates.len(),
+                scenario_path.display()
+            );
+        }
+    }
+
+    if let Some(path) = &junit_path {
+        report::write_junit(&result.trace, path)
+            .with_context(|| format!("Failed to write JUnit report: {}", path.display()))?;
+    }
+
+    if format == OutputFormat::Tap {
+        print!("{}", report::to_tap(&result.trace));
+        return Ok(result.exit_code.max(-1));
+    }
+
+    if format == OutputFormat::Json {
+        let json = report::to_json(&result.trace, result.exit_code);
+        println!("{}", serde_json::to_string_pretty(&json)?);
+        return Ok(result.exit_code.max(-1));
+    }
 
     println!("=== Run Result ===");
     println!("Exit code: {}", result.exit_code);
     println!("Steps executed: {}", result.trace.steps.len());
     println!("Ticks: {}", result.trace.total_ticks);
+    if result.trace.attempts > 1 {
+        println!("Attempts: {}", result.trace.attempts);
+    }
 
     match &result.trace.outcome {
         trace::TraceOutcome::Success {
@@ -223,6 +591,7 @@ fn cmd_run(
         trace::TraceOutcome::Timeout {
             max_ticks,
             elapsed_ticks,
+            ..
         } => {
             println!("Status: TIMEOUT");
             println!("Max ticks: {}, Elapsed: {}", max_ticks, elapsed_ticks);
@@ -230,10 +599,14 @@ fn cmd_run(
         trace::TraceOutcome::Error {
             message,
             step_index,
+            ..
         } => {
             println!("Status: ERROR");
             println!("Message: {}", message);
             println!("Step: {}", step_index);
+            if let Some(location) = trace::failed_step_description(&result.trace) {
+                println!("Failed {}", location);
+            }
         }
         trace::TraceOutcome::Signaled {
             signal,
@@ -274,7 +647,13 @@ fn cmd_run(
     Ok(result.exit_code.max(-1))
 }
 
-fn cmd_replay(trace_path: PathBuf, halt_on_divergence: bool) -> Result<()> {
+fn cmd_replay(
+    trace_path: PathBuf,
+    halt_on_divergence: bool,
+    verify_steps: bool,
+    live: bool,
+    config: &runner::RunnerConfig,
+) -> Result<()> {
     if halt_on_divergence {
         eprintln!("Loading trace: {}", trace_path.display());
     }
@@ -288,6 +667,49 @@ fn cmd_replay(trace_path: PathBuf, halt_on_divergence: bool) -> Result<()> {
         eprintln!("Steps: {}", trace.steps.len());
     }
 
+    if verify_steps {
+        println!("=== Replay Result ===");
+        return match runner::replay_trace_steps(&trace, config, halt_on_divergence) {
+            Ok(None) => {
+                println!("Status: REPLAY SUCCESSFUL");
+                println!("All {} steps matched.", trace.steps.len());
+                Ok(())
+            }
+            Ok(Some(step_index)) => {
+                println!("Status: REPLAY DIVERGENCE DETECTED");
+                println!("First divergent step: {}", step_index);
+                Err(anyhow::anyhow!(
+                    "Replay failed - step {} diverged",
+                    step_index
+                ))
+            }
+            Err(e) => Err(anyhow::anyhow!(e)),
+        };
+    }
+
+    if live {
+        println!("=== Replay Result ===");
+        return match runner::replay_live(&trace, config) {
+            Ok(trace::TraceOutcome::ReplayDivergence {
+                expected,
+                actual,
+                context,
+            }) => {
+                println!("Status: REPLAY DIVERGENCE DETECTED");
+                println!("Expected: {}", expected);
+                println!("Actual: {}", actual);
+                println!("Context: {}", context);
+                Err(anyhow::anyhow!("Replay failed - live run diverged"))
+            }
+            Ok(_) => {
+                println!("Status: REPLAY SUCCESSFUL");
+                println!("Live run matched {} checkpoints.", trace.checkpoints.len());
+                Ok(())
+            }
+            Err(e) => Err(anyhow::anyhow!(e)),
+        };
+    }
+
     let mut replay = trace::ReplayEngine::new(&trace);
     replay.set_halt_on_divergence(halt_on_divergence);
 
@@ -315,23 +737,27 @@ fn cmd_replay(trace_path: PathBuf, halt_on_divergence: bool) -> Result<()> {
     }
 }
 
-fn cmd_validate(scenario_path: PathBuf) -> Result<()> {
+fn cmd_validate(scenario_path: PathBuf, strict: bool, lift_regex_size_limit: bool) -> Result<()> {
     println!("Validating scenario: {}", scenario_path.display());
 
     let content = std::fs::read_to_string(&scenario_path)
         .with_context(|| format!("Failed to read: {}", scenario_path.display()))?;
 
-    let scenario: scenario::Scenario = if scenario_path
-        .extension()
-        .map(|e| e.to_string_lossy().to_lowercase())
-        == Some("json".to_string())
-    {
-        serde_json::from_str(&content).with_context(|| "Failed to parse scenario as JSON")?
-    } else {
-        serde_yaml::from_str(&content).with_context(|| "Failed to parse scenario as YAML")?
-    };
+    let format = scenario::ScenarioFormat::from_path(&scenario_path);
+    let (scenario, unknown_fields) = scenario::Scenario::parse_strict(&content, format)
+        .map_err(|e| anyhow::anyhow!("Failed to parse scenario as {}: {}", format.name(), e))?;
 
-    match scenario.validate() {
+    let result = match scenario.validate_with(lift_regex_size_limit) {
+        Ok(()) if !strict || unknown_fields.is_empty() => Ok(()),
+        Ok(()) => Err(unknown_fields),
+        Err(mut errors) => {
+            if strict {
+                errors.extend(unknown_fields);
+            }
+            Err(errors)
+        }
+    };
+    match result {
         Ok(()) => {
             println!("Scenario is valid.");
             println!("  Name: {}", scenario.name);
@@ -352,11 +778,88 @@ fn cmd_validate(scenario_path: PathBuf) -> Result<()> {
     }
 }
 
-fn cmd_info(trace_path: PathBuf) -> Result<()> {
+fn cmd_info(
+    trace_path: PathBuf,
+    cast_path: Option<PathBuf>,
+    cast_tick_ms: u64,
+    cursor_trail: bool,
+    steps: Option<Range<usize>>,
+) -> Result<()> {
     let trace = trace::load_trace(&trace_path)
         .with_context(|| format!("Failed to load trace: {}", trace_path.display()))?;
 
+    if let Some(path) = &cast_path {
+        cast::write_cast(&trace, path, cast_tick_ms)
+            .with_context(|| format!("Failed to write cast: {}", path.display()))?;
+        println!("Wrote cast: {}", path.display());
+    }
+
     trace::print_trace_summary(&trace);
+    if cursor_trail {
+        println!();
+        trace::print_cursor_trail(&trace);
+    }
+    if let Some(range) = steps {
+        println!();
+        print!("{}", trace::format_trace_steps(&trace, range));
+    }
+
+    Ok(())
+}
+
+/// Parse a `--steps` range: `A..B` (exclusive), `A..=B` (inclusive) or `A..`
+fn parse_step_range(value: &str) -> Result<Range<usize>, String> {
+    let (start, end) = value
+        .split_once("..")
+        .ok_or_else(|| format!("expected A..B, A..=B or A.., got '{}'", value))?;
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("invalid step index '{}'", n))
+    };
+    let start = parse(start)?;
+    let end = match end.strip_prefix('=') {
+        Some(last) => parse(last)?.saturating_add(1),
+        None if end.is_empty() => usize::MAX,
+        None => parse(end)?,
+    };
+    Ok(start..end)
+}
 
+fn cmd_diff(old_path: PathBuf, new_path: PathBuf) -> Result<i32> {
+    let old = trace::load_trace(&old_path)
+        .with_context(|| format!("Failed to load trace: {}", old_path.display()))?;
+    let new = trace::load_trace(&new_path)
+        .with_context(|| format!("Failed to load trace: {}", new_path.display()))?;
+
+    let diff = diff::diff_traces(&old, &new);
+    diff::print_trace_diff(&diff);
+
+    Ok(if diff.is_empty() { 0 } else { 1 })
+}
+
+fn cmd_record(command: &str, output_path: PathBuf) -> Result<()> {
+    let scenario = record::record(command).with_context(|| "Recording failed")?;
+    let yaml = scenario
+        ._to_yaml()
+        .with_context(|| "Failed to serialize scenario")?;
+    std::fs::write(&output_path, yaml)
+        .with_context(|| format!("Failed to write scenario: {}", output_path.display()))?;
+
+    eprintln!(
+        "Recorded {} steps to {}",
+        scenario.steps.len(),
+        output_path.display()
+    );
+    Ok(())
+}
+
+fn cmd_schema(output_path: Option<PathBuf>) -> Result<()> {
+    let schema = scenario::Scenario::json_schema().with_context(|| "Failed to serialize schema")?;
+    match output_path {
+        Some(path) => std::fs::write(&path, schema + "\n")
+            .with_context(|| format!("Failed to write schema: {}", path.display()))?,
+        None => println!("{}", schema),
+    }
     Ok(())
 }
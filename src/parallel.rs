@@ -10,9 +10,10 @@
 #![allow(clippy::disallowed_types)]
 
 use crate::runner::{run_scenario, RunnerConfig};
-use crate::scenario::Scenario;
+use crate::scenario::{Scenario, ScenarioFormat};
+use crate::tags::{filter_scenarios, TagFilter};
 use rayon::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -34,6 +35,17 @@ pub struct ParallelResult {
     pub results: Vec<ScenarioResult>,
 }
 
+impl ParallelResult {
+    /// Process exit code for the whole run: nonzero if any scenario failed
+    pub fn exit_code(&self) -> i32 {
+        if self.failed > 0 {
+            1
+        } else {
+            0
+        }
+    }
+}
+
 /// Result of a single scenario in parallel execution
 #[derive(Debug, Clone)]
 pub struct ScenarioResult {
@@ -64,8 +76,12 @@ pub struct ParallelConfig {
     pub fail_fast: bool,
     /// Seed for deterministic execution
     pub seed: Option<u64>,
+    /// Seed scenarios without a `seed` from a hash of their content
+    pub seed_from_content: bool,
     /// Default runner config
     pub runner_config: RunnerConfig,
+    /// Only scenarios whose tags match are run
+    pub tag_filter: TagFilter,
 }
 
 impl Default for ParallelConfig {
@@ -79,7 +95,9 @@ impl Default for ParallelConfig {
             timeout: None,
             fail_fast: false,
             seed: None,
+            seed_from_content: false,
             runner_config: RunnerConfig::default(),
+            tag_filter: TagFilter::All,
         }
     }
 }
@@ -126,10 +144,7 @@ pub fn run_parallel(scenarios: &[(Scenario, PathBuf)], config: &ParallelConfig)
 
                 let runner_config = RunnerConfig {
                     seed: config.seed.or(scenario.seed),
-                    verbose: config.runner_config.verbose,
-                    max_ticks: config.runner_config.max_ticks,
-                    tick_delay_ms: config.runner_config.tick_delay_ms,
-                    trace_path: config.runner_config.trace_path.clone(),
+                    ..config.runner_config.clone()
                 };
 
                 let result = run_scenario(scenario, &runner_config);
@@ -167,10 +182,7 @@ pub fn run_parallel(scenarios: &[(Scenario, PathBuf)], config: &ParallelConfig)
 
                 let runner_config = RunnerConfig {
                     seed: config.seed.or(scenario.seed),
-                    verbose: config.runner_config.verbose,
-                    max_ticks: config.runner_config.max_ticks,
-                    tick_delay_ms: config.runner_config.tick_delay_ms,
-                    trace_path: config.runner_config.trace_path.clone(),
+                    ..config.runner_config.clone()
                 };
 
                 let result = run_scenario(scenario, &runner_config);
@@ -217,6 +229,24 @@ pub fn run_parallel(scenarios: &[(Scenario, PathBuf)], config: &ParallelConfig)
     }
 }
 
+/// Find the scenario files (`*.yaml`, `*.yml`, `*.json`, `*.toml`) directly inside a
+/// directory, sorted by path
+pub fn discover_scenarios(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_scenario = path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .is_some_and(|e| matches!(e.as_str(), "yaml" | "yml" | "json" | "toml"));
+        if is_scenario && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
 /// Run scenarios in parallel from file paths
 ///
 /// # Arguments
@@ -237,20 +267,20 @@ pub fn run_parallel_from_paths(
     for path in paths {
         match std::fs::read_to_string(path) {
             Ok(content) => {
-                let parse_result: Result<Scenario, String> = if path
-                    .extension()
-                    .map(|e| e.to_string_lossy().to_lowercase())
-                    == Some("json".to_string())
-                {
-                    serde_json::from_str(&content).map_err(|e| format!("JSON parse error: {}", e))
-                } else {
-                    serde_yaml::from_str(&content).map_err(|e| format!("YAML parse error: {}", e))
-                };
+                let format = ScenarioFormat::from_path(path);
+                let parse_result = Scenario::parse(&content, format)
+                    .map_err(|e| format!("{} parse error: {}", format.name(), e));
 
                 match parse_result {
-                    Ok(scenario) => {
+                    Ok(mut scenario) => {
+                        if config.seed_from_content && scenario.seed.is_none() {
+                            scenario.seed = Some(scenario.content_seed());
+                        }
+                        scenario.resolve_paths(path);
                         // Validate scenario
-                        if let Err(validation_errors) = scenario.validate() {
+                        if let Err(validation_errors) = scenario.resolve_env().and_then(|()| {
+                            scenario.validate_with(config.runner_config.lift_regex_size_limit)
+                        }) {
                             errors.push(format!(
                                 "Validation failed for {}: {}",
                                 path.display(),
@@ -275,6 +305,7 @@ pub fn run_parallel_from_paths(
         return Err(errors);
     }
 
+    let scenarios = filter_scenarios(&scenarios, &config.tag_filter);
     Ok(run_parallel(&scenarios, config))
 }
 
@@ -305,7 +336,7 @@ pub fn print_parallel_summary(result: &ParallelResult) {
     // Print timing info
     if !result.results.is_empty() {
         let mut timing: Vec<_> = result.results.iter().collect();
-        timing.sort_by(|a, b| b.duration.cmp(&a.duration));
+        timing.sort_by_key(|r| std::cmp::Reverse(r.duration));
 
         println!("Top 5 slowest scenarios:");
         for r in timing.iter().take(5) {
@@ -411,6 +442,89 @@ steps:
         assert!(result.passed + result.failed + result.skipped == 1);
     }
 
+    #[test]
+    fn test_run_directory_counts_failures() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("pass.yaml"),
+            "name: pass\ncommand: \"true\"\nsteps:\n  - action: wait_ticks\n    ticks: 1\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("fail.json"),
+            r#"{"name": "fail", "command": "sh -c 'exit 2'", "steps": [{"action": "wait_ticks", "ticks": 1}]}"#,
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), "not a scenario").unwrap();
+
+        let paths = discover_scenarios(temp_dir.path()).unwrap();
+        assert_eq!(paths.len(), 2);
+
+        let result = run_parallel_from_paths(&paths, &ParallelConfig::default()).unwrap();
+        assert_eq!((result.total, result.passed, result.failed), (2, 1, 1));
+        let failed: Vec<_> = result.results.iter().filter(|r| !r.passed).collect();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].name, "fail");
+        assert_eq!(failed[0].exit_code, 2);
+        assert_eq!(result.exit_code(), 1);
+    }
+
+    #[test]
+    fn test_run_directory_resolves_steps_file() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("steps.ndjson"),
+            "{\"action\": \"wait_ticks\", \"ticks\": 1}\n",
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("streamed.yaml"),
+            "name: streamed\ncommand: \"true\"\nsteps_file: steps.ndjson\n",
+        )
+        .unwrap();
+
+        let paths = discover_scenarios(temp_dir.path()).unwrap();
+        let result = run_parallel_from_paths(&paths, &ParallelConfig::default()).unwrap();
+        assert_eq!(result.passed, 1);
+        assert_eq!(result.results[0].steps_executed, 1);
+    }
+
+    #[test]
+    fn test_run_directory_filters_by_tag() {
+        let temp_dir = TempDir::new().unwrap();
+        for (name, tags) in [
+            ("smoke", "[{name: smoke}]"),
+            ("smoke-slow", "[{name: smoke}, {name: slow}]"),
+            ("other", "[{name: other}]"),
+            ("untagged", "[]"),
+        ] {
+            fs::write(
+                temp_dir.path().join(format!("{}.yaml", name)),
+                format!(
+                    "name: {}\ncommand: \"true\"\ntags: {}\nsteps:\n  - action: wait_ticks\n    ticks: 1\n",
+                    name, tags
+                ),
+            )
+            .unwrap();
+        }
+        let paths = discover_scenarios(temp_dir.path()).unwrap();
+        let config = ParallelConfig {
+            tag_filter: TagFilter::include_exclude(&["smoke".into()], &["slow".into()]),
+            ..ParallelConfig::default()
+        };
+
+        let result = run_parallel_from_paths(&paths, &config).unwrap();
+        let names: Vec<_> = result.results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, ["smoke"]);
+
+        let config = ParallelConfig {
+            tag_filter: TagFilter::include_exclude(&[], &["smoke".into()]),
+            ..ParallelConfig::default()
+        };
+        let result = run_parallel_from_paths(&paths, &config).unwrap();
+        assert_eq!(result.total, 2);
+    }
+
     #[test]
     fn test_calculate_stats() {
         let results = vec![
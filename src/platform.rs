@@ -6,7 +6,7 @@
 // Current State:
 // - Linux: Full support via nix crate
 // - macOS: Partial support (see implementation notes)
-// - Windows: Not yet implemented (ConPTY support planned)
+// - Windows: Not supported; builds stop with a compile error (no ConPTY backend)
 //
 // Design Principles:
 // 1. Trait-based abstraction for terminal operations
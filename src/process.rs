@@ -1,25 +1,29 @@
 //! Process launch and management inside PTY
 //!
 //! This module handles forking and executing binaries inside a PTY,
-//! with proper stdio routing and environment isolation.
+//! with proper stdio routing and environment isolation. Where no PTY can be
+//! allocated, the child can be connected to plain pipes instead.
 
 // Process termination requires real-time timeout
 #![allow(clippy::disallowed_types)]
 
 use crate::pty::{Pty, PtyConfig, PtyError};
+use nix::fcntl::{fcntl, FcntlArg, FdFlag, OFlag};
 use nix::libc;
 use nix::sys::signal::{kill, Signal};
 use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
 use nix::unistd::{close, dup2, execvpe, fork, setsid, ForkResult, Pid};
 use std::collections::HashMap;
 use std::ffi::CString;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
 
 // ============================================================================
 // Constants
 // ============================================================================
 
-/// Timeout for graceful SIGTERM termination in milliseconds
-const SIGTERM_TIMEOUT_MS: u64 = 500;
+/// Default time a child gets to exit after SIGTERM before it is sent SIGKILL,
+/// in milliseconds
+pub const SIGTERM_TIMEOUT_MS: u64 = 500;
 
 /// Polling interval while waiting for process exit in milliseconds
 const POLL_INTERVAL_MS: u64 = 10;
@@ -69,6 +73,14 @@ pub enum ProcessError {
     StillRunning,
     /// Unexpected ptrace event
     UnexpectedPtraceEvent,
+    /// Stderr pipe could not be created or read
+    StderrPipeFailed(nix::Error),
+    /// Stdin or output pipe could not be created, read or written
+    PipeFailed(nix::Error),
+    /// The operation needs a PTY, but the process was spawned with pipes
+    NoTerminal,
+    /// The process's stdin has been closed
+    InputClosed,
     /// Timeout waiting for process
     Timeout,
 }
@@ -88,6 +100,12 @@ impl std::fmt::Display for ProcessError {
             ProcessError::SignalFailed(e) => write!(f, "Signal failed: {}", e),
             ProcessError::StillRunning => write!(f, "Process is still running"),
             ProcessError::UnexpectedPtraceEvent => write!(f, "Unexpected ptrace event"),
+            ProcessError::StderrPipeFailed(e) => write!(f, "Stderr pipe failed: {}", e),
+            ProcessError::PipeFailed(e) => write!(f, "Pipe failed: {}", e),
+            ProcessError::NoTerminal => {
+                write!(f, "Process has no terminal (spawned without a PTY)")
+            }
+            ProcessError::InputClosed => write!(f, "Process stdin has been closed"),
             ProcessError::Timeout => write!(f, "Timeout waiting for process"),
         }
     }
@@ -114,6 +132,38 @@ pub struct ProcessConfig {
     pub cwd: Option<String>,
     /// PTY configuration
     pub pty_config: PtyConfig,
+    /// Route stderr to a separate pipe instead of the PTY
+    pub separate_stderr: bool,
+    /// Connect stdin and stdout to plain pipes instead of a PTY; the child
+    /// then has no terminal to resize or query
+    pub pipes: bool,
+    /// CPU time limit in seconds (`RLIMIT_CPU`); the child gets SIGXCPU
+    /// when it runs out, then SIGKILL a second later
+    pub cpu_limit_secs: Option<u64>,
+    /// Address space limit in bytes (`RLIMIT_AS`)
+    pub memory_limit_bytes: Option<u64>,
+    /// How long a still-running child gets to exit after SIGTERM when the
+    /// process is dropped, before it is sent SIGKILL
+    pub term_timeout_ms: u64,
+}
+
+/// The environment a child actually runs with: the configured variables,
+/// or a minimal isolated environment when none are set
+pub fn resolve_environment(env: &Option<HashMap<String, String>>) -> HashMap<String, String> {
+    match env {
+        Some(e) => e.clone(),
+        None => {
+            // Create minimal isolated environment
+            let mut minimal = HashMap::new();
+            minimal.insert("TERM".to_string(), "xterm-256color".to_string());
+            minimal.insert("PATH".to_string(), "/usr/bin:/bin".to_string());
+            minimal.insert("HOME".to_string(), "/tmp".to_string());
+            minimal.insert("LANG".to_string(), "C.UTF-8".to_string());
+            // Disable PS1 customization for more predictable prompts
+            minimal.insert("PS1".to_string(), "$ ".to_string());
+            minimal
+        }
+    }
 }
 
 impl ProcessConfig {
@@ -127,6 +177,11 @@ impl ProcessConfig {
             env: None,
             cwd: None,
             pty_config: PtyConfig::default(),
+            separate_stderr: false,
+            pipes: false,
+            cpu_limit_secs: None,
+            memory_limit_bytes: None,
+            term_timeout_ms: SIGTERM_TIMEOUT_MS,
         }
     }
 
@@ -138,6 +193,11 @@ impl ProcessConfig {
             env: None,
             cwd: None,
             pty_config: PtyConfig::default(),
+            separate_stderr: false,
+            pipes: false,
+            cpu_limit_secs: None,
+            memory_limit_bytes: None,
+            term_timeout_ms: SIGTERM_TIMEOUT_MS,
         }
     }
 
@@ -150,6 +210,11 @@ impl ProcessConfig {
             env: None,
             cwd: None,
             pty_config: PtyConfig::default(),
+            separate_stderr: false,
+            pipes: false,
+            cpu_limit_secs: None,
+            memory_limit_bytes: None,
+            term_timeout_ms: SIGTERM_TIMEOUT_MS,
         }
     }
 
@@ -180,6 +245,18 @@ impl ProcessConfig {
         self.pty_config.size = (cols, rows);
         self
     }
+
+    /// Capture stderr through its own pipe (see [`PtyProcess::read_stderr`])
+    pub fn with_separate_stderr(mut self) -> Self {
+        self.separate_stderr = true;
+        self
+    }
+
+    /// Run the child on plain pipes instead of a PTY
+    pub fn with_pipes(mut self) -> Self {
+        self.pipes = true;
+        self
+    }
 }
 
 /// Exit reason for a process
@@ -193,21 +270,53 @@ pub enum ExitReason {
     Running,
 }
 
+/// The parent's side of the child's stdin and stdout
+enum Terminal {
+    /// A PTY whose slave is the child's controlling terminal
+    Pty(Pty),
+    /// Plain pipes, for hosts where no PTY can be allocated
+    Pipes {
+        /// Write end of the child's stdin; `None` once closed
+        input: Option<OwnedFd>,
+        /// Read end of the child's stdout, non-blocking like the PTY master
+        output: OwnedFd,
+        /// Configured size, reported in place of a window size
+        size: (u16, u16),
+    },
+}
+
 /// A process running inside a PTY
 pub struct PtyProcess {
-    /// The PTY
-    pty: Pty,
+    /// The PTY, or the pipes standing in for it
+    terminal: Terminal,
     /// Child process ID
     pid: Pid,
     /// Exit reason (if known)
     exit_reason: Option<ExitReason>,
+    /// Read end of the stderr pipe, when stderr is kept off the PTY
+    stderr: Option<OwnedFd>,
+    /// Grace period between SIGTERM and SIGKILL in `terminate`
+    term_timeout_ms: u64,
 }
 
 impl PtyProcess {
-    /// Spawn a new process inside a PTY
+    /// Spawn a new process inside a PTY, or on pipes with
+    /// [`ProcessConfig::pipes`]
     pub fn spawn(config: &ProcessConfig) -> Result<Self, ProcessError> {
-        // Create PTY
-        let mut pty = Pty::open(&config.pty_config)?;
+        // Create the PTY, or the pipes standing in for it along with the
+        // child's ends of them (stdin read end, stdout write end)
+        let (mut terminal, child_stdio) = if config.pipes {
+            let (stdin_read, stdin_write) = Self::pipe(false)?;
+            let (output_read, output_write) = Self::pipe(true)?;
+            let terminal = Terminal::Pipes {
+                input: Some(stdin_write),
+                output: output_read,
+                size: config.pty_config.size,
+            };
+            (terminal, Some((stdin_read, output_write)))
+        } else {
+            (Terminal::Pty(Pty::open(&config.pty_config)?), None)
+        };
 
         // Prepare arguments for execvpe
         let program = CString::new(config.program.as_bytes()).map_err(ProcessError::InvalidPath)?;
@@ -221,6 +330,15 @@ impl PtyProcess {
         // Prepare environment
         let env_vars: Vec<CString> = Self::prepare_environment(&config.env)?;
 
+        let stderr_pipe = if config.separate_stderr {
+            Some(Self::pipe(true).map_err(|e| match e {
+                ProcessError::PipeFailed(e) => ProcessError::StderrPipeFailed(e),
+                other => other,
+            })?)
+        } else {
+            None
+        };
+
         // Fork
         //
         // SAFETY: fork() is a POSIX system call that duplicates the current process.
@@ -242,71 +360,64 @@ impl PtyProcess {
             ForkResult::Parent { child } => {
                 // Parent process
                 // Close the slave fd - child owns it now
-                pty.close_slave()?;
+                if let Terminal::Pty(pty) = &mut terminal {
+                    pty.close_slave()?;
+                }
 
+                // Dropping the child's pipe ends (and the stderr write end)
+                // leaves the child as their only holder
+                drop(child_stdio);
                 Ok(Self {
-                    pty,
+                    terminal,
                     pid: child,
                     exit_reason: None,
+                    stderr: stderr_pipe.map(|(read, _write)| read),
+                    term_timeout_ms: config.term_timeout_ms,
                 })
             }
             ForkResult::Child => {
                 // Child process - this code runs in the child
-                // We need to set up the PTY as our controlling terminal
+                // A PTY is set up as our controlling terminal; pipes just
+                // become stdin and stdout
 
                 // Create a new session
                 setsid().map_err(ProcessError::SetsidFailed)?;
 
-                // Get the slave fd
-                let slave_fd = pty.slave_fd().map_err(ProcessError::Pty)?;
-
-                // Set the slave as controlling terminal
-                //
-                // SAFETY: ioctl(TIOCSCTTY) sets the controlling terminal for the process.
-                // This is safe because:
-                // 1. slave_fd is a valid file descriptor from openpty()
-                // 2. We are the session leader (setsid() called above, required by TIOCSCTTY)
-                // 3. The second argument is 0 = don't steal from another session
-                //
-                // TIOCSCTTY can fail with:
-                // - EBADF: invalid fd
-                // - EINVAL: second argument not 0 when not owner of terminal
-                // - EPERM: not session leader and not forcing (we set force=false)
-                // We check the return value to catch any unexpected failures.
-                let ctlty_result = unsafe { libc::ioctl(slave_fd, libc::TIOCSCTTY as _, 0) };
-                if ctlty_result != 0 {
-                    // Capture errno safely - use nix errno accessor which is portable
-                    let errno = nix::errno::Errno::last_raw();
-                    let msg = match errno {
-                        libc::EBADF => "bte: TIOCSCTTY failed: Bad file descriptor\n".to_string(),
-                        libc::EINVAL => {
-                            "bte: TIOCSCTTY failed: Invalid argument (not session leader?)\n"
-                                .to_string()
-                        }
-                        libc::EPERM => "bte: TIOCSCTTY failed: Not session leader\n".to_string(),
-                        libc::ENOTTY => "bte: TIOCSCTTY failed: Not a terminal\n".to_string(),
-                        _ => format!("bte: TIOCSCTTY failed: Unknown error (errno {})\n", errno),
-                    };
-                    let _ =
-                        unsafe { libc::write(2, msg.as_ptr() as *const libc::c_void, msg.len()) };
-                    unsafe {
-                        libc::_exit(1);
+                let (stdin_fd, stdout_fd) = match (&terminal, &child_stdio) {
+                    (Terminal::Pipes { .. }, Some((input, output))) => {
+                        (input.as_raw_fd(), output.as_raw_fd())
                     }
-                }
+                    (Terminal::Pipes { .. }, None) => unreachable!("pipes without child ends"),
+                    (Terminal::Pty(pty), _) => {
+                        // Get the slave fd
+                        let slave_fd = pty.slave_fd().map_err(ProcessError::Pty)?;
 
-                // Redirect stdio to the slave
-                dup2(slave_fd, libc::STDIN_FILENO).map_err(ProcessError::IoRedirectFailed)?;
-                dup2(slave_fd, libc::STDOUT_FILENO).map_err(ProcessError::IoRedirectFailed)?;
-                dup2(slave_fd, libc::STDERR_FILENO).map_err(ProcessError::IoRedirectFailed)?;
+                        Self::set_controlling_terminal(slave_fd);
 
-                // Close the original slave fd if it's not one of the standard fds
-                if slave_fd > libc::STDERR_FILENO {
-                    let _ = close(slave_fd);
-                }
+                        (slave_fd, slave_fd)
+                    }
+                };
+
+                // Redirect stdio to the slave or the pipes; the pipes' own
+                // fds close on exec
+                dup2(stdin_fd, libc::STDIN_FILENO).map_err(ProcessError::IoRedirectFailed)?;
+                dup2(stdout_fd, libc::STDOUT_FILENO).map_err(ProcessError::IoRedirectFailed)?;
+                let stderr_fd = match &stderr_pipe {
+                    Some((_, write)) => write.as_raw_fd(),
+                    None => stdout_fd,
+                };
+                dup2(stderr_fd, libc::STDERR_FILENO).map_err(ProcessError::IoRedirectFailed)?;
+
+                if let Terminal::Pty(pty) = &terminal {
+                    // Close the original slave fd if it's not one of the standard fds
+                    if stdin_fd > libc::STDERR_FILENO {
+                        let _ = close(stdin_fd);
+                    }
 
-                // Close the master fd in child
-                if let Ok(master_fd) = pty.master_fd() {
-                    let _ = close(master_fd);
+                    // Close the master fd in child
+                    if let Ok(master_fd) = pty.master_fd() {
+                        let _ = close(master_fd);
+                    }
                 }
 
                 // Change directory if specified
@@ -350,6 +461,40 @@ impl PtyProcess {
                     }
                 }
 
+                // Apply resource limits so a runaway child can't take down the host
+                let limits = [
+                    (
+                        libc::RLIMIT_CPU,
+                        config.cpu_limit_secs.map(|s| (s, s.saturating_add(1))),
+                        &b"bte: setting the CPU limit failed\n"[..],
+                    ),
+                    (
+                        libc::RLIMIT_AS,
+                        config.memory_limit_bytes.map(|b| (b, b)),
+                        &b"bte: setting the memory limit failed\n"[..],
+                    ),
+                ];
+                for (resource, limit, msg) in limits {
+                    let Some((soft, hard)) = limit else {
+                        continue;
+                    };
+                    let rlim = libc::rlimit {
+                        rlim_cur: soft as libc::rlim_t,
+                        rlim_max: hard as libc::rlim_t,
+                    };
+                    // SAFETY: setrlimit() is async-signal-safe and only reads `rlim`.
+                    if unsafe { libc::setrlimit(resource, &rlim) } != 0 {
+                        // SAFETY: write() to fd 2 and _exit() are async-signal-safe;
+                        // the message is static, so nothing allocates after fork.
+                        let _ = unsafe {
+                            libc::write(2, msg.as_ptr() as *const libc::c_void, msg.len())
+                        };
+                        unsafe {
+                            libc::_exit(1);
+                        }
+                    }
+                }
+
                 // Execute the program
                 let args_ref: Vec<&std::ffi::CStr> = args.iter().map(|s| s.as_c_str()).collect();
                 let env_ref: Vec<&std::ffi::CStr> = env_vars.iter().map(|s| s.as_c_str()).collect();
@@ -367,26 +512,61 @@ impl PtyProcess {
         }
     }
 
+    /// Make the PTY slave the child's controlling terminal, or `_exit(1)`
+    ///
+    /// Runs in the forked child, so only async-signal-safe calls are made.
+    fn set_controlling_terminal(slave_fd: RawFd) {
+        // SAFETY: ioctl(TIOCSCTTY) sets the controlling terminal for the process.
+        // This is safe because:
+        // 1. slave_fd is a valid file descriptor from openpty()
+        // 2. We are the session leader (setsid() called above, required by TIOCSCTTY)
+        // 3. The second argument is 0 = don't steal from another session
+        //
+        // TIOCSCTTY can fail with:
+        // - EBADF: invalid fd
+        // - EINVAL: second argument not 0 when not owner of terminal
+        // - EPERM: not session leader and not forcing (we set force=false)
+        // We check the return value to catch any unexpected failures.
+        let ctlty_result = unsafe { libc::ioctl(slave_fd, libc::TIOCSCTTY as _, 0) };
+        if ctlty_result != 0 {
+            // Capture errno safely - use nix errno accessor which is portable
+            let errno = nix::errno::Errno::last_raw();
+            let msg = match errno {
+                libc::EBADF => "bte: TIOCSCTTY failed: Bad file descriptor\n".to_string(),
+                libc::EINVAL => {
+                    "bte: TIOCSCTTY failed: Invalid argument (not session leader?)\n".to_string()
+                }
+                libc::EPERM => "bte: TIOCSCTTY failed: Not session leader\n".to_string(),
+                libc::ENOTTY => "bte: TIOCSCTTY failed: Not a terminal\n".to_string(),
+                _ => format!("bte: TIOCSCTTY failed: Unknown error (errno {})\n", errno),
+            };
+            let _ = unsafe { libc::write(2, msg.as_ptr() as *const libc::c_void, msg.len()) };
+            unsafe {
+                libc::_exit(1);
+            }
+        }
+    }
+
+    /// Create a pipe whose ends both close on exec; with `nonblocking_read`
+    /// the read end is non-blocking like the PTY master
+    fn pipe(nonblocking_read: bool) -> Result<(OwnedFd, OwnedFd), ProcessError> {
+        let (read, write) = nix::unistd::pipe().map_err(ProcessError::PipeFailed)?;
+        for fd in [&read, &write] {
+            fcntl(fd.as_raw_fd(), FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC))
+                .map_err(ProcessError::PipeFailed)?;
+        }
+        if nonblocking_read {
+            fcntl(read.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK))
+                .map_err(ProcessError::PipeFailed)?;
+        }
+        Ok((read, write))
+    }
+
     /// Prepare environment variables
     fn prepare_environment(
         env: &Option<HashMap<String, String>>,
     ) -> Result<Vec<CString>, ProcessError> {
-        let env_map = match env {
-            Some(e) => e.clone(),
-            None => {
-                // Create minimal isolated environment
-                let mut minimal = HashMap::new();
-                minimal.insert("TERM".to_string(), "xterm-256color".to_string());
-                minimal.insert("PATH".to_string(), "/usr/bin:/bin".to_string());
-                minimal.insert("HOME".to_string(), "/tmp".to_string());
-                minimal.insert("LANG".to_string(), "C.UTF-8".to_string());
-                // Disable PS1 customization for more predictable prompts
-                minimal.insert("PS1".to_string(), "$ ".to_string());
-                minimal
-            }
-        };
-
-        env_map
+        resolve_environment(env)
             .iter()
             .map(|(k, v)| {
                 let s = format!("{}={}", k, v);
@@ -405,14 +585,69 @@ impl PtyProcess {
         self.pid.as_raw()
     }
 
-    /// Get a reference to the PTY
-    pub fn pty(&self) -> &Pty {
-        &self.pty
+    /// Get a reference to the PTY, unless the process runs on pipes
+    pub fn pty(&self) -> Option<&Pty> {
+        match &self.terminal {
+            Terminal::Pty(pty) => Some(pty),
+            Terminal::Pipes { .. } => None,
+        }
+    }
+
+    /// Get a mutable reference to the PTY, unless the process runs on pipes
+    pub fn pty_mut(&mut self) -> Option<&mut Pty> {
+        match &mut self.terminal {
+            Terminal::Pty(pty) => Some(pty),
+            Terminal::Pipes { .. } => None,
+        }
     }
 
-    /// Get a mutable reference to the PTY
-    pub fn pty_mut(&mut self) -> &mut Pty {
-        &mut self.pty
+    /// Whether the child has a terminal (a PTY rather than pipes)
+    pub fn has_terminal(&self) -> bool {
+        matches!(self.terminal, Terminal::Pty(_))
+    }
+
+    /// Current terminal size (columns, rows); the configured size on pipes
+    pub fn size(&self) -> (u16, u16) {
+        match &self.terminal {
+            Terminal::Pty(pty) => pty.size(),
+            Terminal::Pipes { size, .. } => *size,
+        }
+    }
+
+    /// The fd the child's output is read from: the PTY master or the
+    /// stdout pipe
+    pub fn output_fd(&self) -> Result<BorrowedFd<'_>, ProcessError> {
+        match &self.terminal {
+            Terminal::Pty(pty) => Ok(pty.master_borrowed()?),
+            Terminal::Pipes { output, .. } => Ok(output.as_fd()),
+        }
+    }
+
+    /// The fd input is written to: the PTY master or the stdin pipe
+    pub fn input_fd(&self) -> Result<BorrowedFd<'_>, ProcessError> {
+        match &self.terminal {
+            Terminal::Pty(pty) => Ok(pty.master_borrowed()?),
+            Terminal::Pipes { input, .. } => input
+                .as_ref()
+                .map(|fd| fd.as_fd())
+                .ok_or(ProcessError::InputClosed),
+        }
+    }
+
+    /// Close the stdin pipe so line-reading programs see EOF; a PTY is left
+    /// open
+    pub fn close_input(&mut self) {
+        if let Terminal::Pipes { input, .. } = &mut self.terminal {
+            *input = None;
+        }
+    }
+
+    /// Wrap a read or write failure on the PTY master or the pipes
+    fn io_error(&self, e: nix::Error) -> ProcessError {
+        match &self.terminal {
+            Terminal::Pty(_) => ProcessError::Pty(PtyError::ConfigurationFailed(e)),
+            Terminal::Pipes { .. } => ProcessError::PipeFailed(e),
+        }
     }
 
     /// Check if the process is still running (non-blocking)
@@ -476,24 +711,35 @@ impl PtyProcess {
         self.exit_reason
     }
 
-    /// Read from the PTY master (non-blocking)
+    /// Read from the PTY master or stdout pipe (non-blocking)
     pub fn read(&self, buf: &mut [u8]) -> Result<usize, ProcessError> {
-        let master_fd = self.pty.master_fd()?;
-        match nix::unistd::read(master_fd, buf) {
+        match nix::unistd::read(self.output_fd()?.as_raw_fd(), buf) {
+            Ok(n) => Ok(n),
+            Err(nix::Error::EAGAIN) => Ok(0),
+            Err(e) => Err(self.io_error(e)),
+        }
+    }
+
+    /// Read from the separate stderr pipe (non-blocking)
+    ///
+    /// Always returns 0 when stderr goes to the PTY.
+    pub fn read_stderr(&self, buf: &mut [u8]) -> Result<usize, ProcessError> {
+        let Some(fd) = &self.stderr else {
+            return Ok(0);
+        };
+        match nix::unistd::read(fd.as_raw_fd(), buf) {
             Ok(n) => Ok(n),
             Err(nix::Error::EAGAIN) => Ok(0),
-            Err(e) => Err(ProcessError::Pty(PtyError::ConfigurationFailed(e))),
+            Err(e) => Err(ProcessError::StderrPipeFailed(e)),
         }
     }
 
-    /// Write to the PTY master
+    /// Write to the PTY master or stdin pipe
     pub fn write(&self, data: &[u8]) -> Result<usize, ProcessError> {
-        let master_fd = self.pty.master_borrowed()?;
-        nix::unistd::write(master_fd, data)
-            .map_err(|e| ProcessError::Pty(PtyError::ConfigurationFailed(e)))
+        nix::unistd::write(self.input_fd()?, data).map_err(|e| self.io_error(e))
     }
 
-    /// Write all data to the PTY master
+    /// Write all data to the PTY master or stdin pipe
     pub fn write_all(&self, data: &[u8]) -> Result<(), ProcessError> {
         let mut written = 0;
         while written < data.len() {
@@ -506,6 +752,14 @@ impl PtyProcess {
         Ok(())
     }
 
+    /// Whether the child's terminal currently echoes input
+    pub fn echo_enabled(&self) -> Result<bool, ProcessError> {
+        match &self.terminal {
+            Terminal::Pty(pty) => Ok(pty.echo_enabled()?),
+            Terminal::Pipes { .. } => Err(ProcessError::NoTerminal),
+        }
+    }
+
     /// Send a signal to the process
     pub fn send_signal(&self, signal: Signal) -> Result<(), ProcessError> {
         if self.exit_reason.is_some() {
@@ -536,7 +790,10 @@ impl PtyProcess {
 
     /// Resize the PTY and send SIGWINCH to the process
     pub fn resize(&mut self, cols: u16, rows: u16) -> Result<(), ProcessError> {
-        self.pty.resize(cols, rows)?;
+        match &mut self.terminal {
+            Terminal::Pty(pty) => pty.resize(cols, rows)?,
+            Terminal::Pipes { .. } => return Err(ProcessError::NoTerminal),
+        }
         // Only send SIGWINCH if process is still running
         if self.exit_reason.is_none() {
             self.signal_winch()?;
@@ -544,6 +801,17 @@ impl PtyProcess {
         Ok(())
     }
 
+    /// Remember why the child exited, given a wait status that says it has
+    fn record_exit(&mut self, status: WaitStatus) {
+        match status {
+            WaitStatus::Exited(_, code) => self.exit_reason = Some(ExitReason::Exited(code)),
+            WaitStatus::Signaled(_, signal, _) => {
+                self.exit_reason = Some(ExitReason::Signaled(signal as i32))
+            }
+            _ => {}
+        }
+    }
+
     /// Gracefully terminate the process and reap it.
     /// Returns true if the process was terminated, false if it was already dead.
     fn terminate(&mut self) -> bool {
@@ -580,36 +848,31 @@ impl PtyProcess {
 
         // Polling loop with actual timeout
         let start = std::time::Instant::now();
-        let timeout = std::time::Duration::from_millis(SIGTERM_TIMEOUT_MS);
+        let timeout = std::time::Duration::from_millis(self.term_timeout_ms);
 
         loop {
             match waitpid(self.pid, Some(WaitPidFlag::WNOHANG)) {
-                Ok(WaitStatus::Exited(_, _)) | Ok(WaitStatus::Signaled(_, _, _)) => {
+                Ok(status @ (WaitStatus::Exited(..) | WaitStatus::Signaled(..))) => {
                     // Process exited successfully
+                    self.record_exit(status);
                     return true;
                 }
                 Ok(WaitStatus::StillAlive) => {
                     if start.elapsed() > timeout {
                         // Timeout reached, force kill
-                        let sigkill_result = kill(self.pid, Signal::SIGKILL);
-                        if sigkill_result.is_ok() {
-                            // SIGKILL sent, now reap the zombie
-                            // Use WNOHANG again to avoid blocking forever
-                            let mut sigchild_count = 0;
+                        // SIGKILL can't be ignored, so a blocking wait reaps the child
+                        if kill(self.pid, Signal::SIGKILL).is_ok() {
+                            let mut retries = 0;
                             loop {
-                                match waitpid(self.pid, Some(WaitPidFlag::WNOHANG)) {
-                                    Ok(_) | Err(Errno::ECHILD) => {
-                                        // Either reaped or no more children
-                                        break;
+                                match waitpid(self.pid, None) {
+                                    Err(Errno::EINTR) if retries < MAX_EINTR_RETRIES => {
+                                        retries += 1;
                                     }
-                                    Err(Errno::EINTR) => {
-                                        // Interrupted, retry
-                                        sigchild_count += 1;
-                                        if sigchild_count > MAX_EINTR_RETRIES {
-                                            break; // Give up after max retries
-                                        }
+                                    Ok(status) => {
+                                        self.record_exit(status);
+                                        break;
                                     }
-                                    Err(_) => break, // Other error, give up
+                                    Err(_) => break,
                                 }
                             }
                         }
@@ -657,14 +920,10 @@ impl PtyProcess {
 
 impl Drop for PtyProcess {
     fn drop(&mut self) {
-        // Do NOT manage process lifecycle in Drop.
-        // - Sending signals in Drop is unreliable (can't handle errors properly)
-        // - Waiting in Drop can block indefinitely
-        // - Process cleanup should be explicit via terminate() or wait()
-        //
-        // The PTY will be dropped automatically when self.pty is dropped.
-        // If the process is still running, it becomes orphaned and will be
-        // reaped by init/PID 1 when this process exits.
+        // A child still running here (e.g. a scenario that ended early or
+        // panicked) gets SIGTERM, then SIGKILL after term_timeout_ms, and
+        // is reaped so it neither leaks nor lingers as a zombie
+        self.terminate();
     }
 }
 
@@ -796,6 +1055,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn pipes_carry_input_and_output_without_a_terminal() {
+        let config = ProcessConfig::shell("read line; echo \"got $line\"").with_pipes();
+        let mut process = PtyProcess::spawn(&config).unwrap();
+        assert!(!process.has_terminal());
+        assert!(process.pty().is_none());
+        assert!(matches!(
+            process.echo_enabled(),
+            Err(ProcessError::NoTerminal)
+        ));
+        assert!(matches!(
+            process.resize(100, 50),
+            Err(ProcessError::NoTerminal)
+        ));
+        assert_eq!(process.size(), (80, 24));
+
+        process.write_all(b"hello\n").unwrap();
+        process.close_input();
+        assert_eq!(process.wait().unwrap(), ExitReason::Exited(0));
+
+        let mut buf = [0u8; 1024];
+        let n = process.read(&mut buf).unwrap();
+        assert_eq!(&buf[..n], b"got hello\n");
+    }
+
     #[test]
     fn sigint_stops_running_program() {
         // Run a long sleep
@@ -872,13 +1156,13 @@ mod tests {
         thread::sleep(std::time::Duration::from_millis(TEST_SLEEP_MS));
 
         // Verify initial size
-        assert_eq!(process.pty().size(), (80, 24));
+        assert_eq!(process.size(), (80, 24));
 
         // Resize the PTY - this should also send SIGWINCH
         process.resize(100, 50).unwrap();
 
         // Verify size changed
-        assert_eq!(process.pty().size(), (100, 50));
+        assert_eq!(process.size(), (100, 50));
 
         // Verify we can send SIGWINCH directly
         assert!(process.signal_winch().is_ok());
@@ -910,31 +1194,53 @@ mod tests {
     }
 
     #[test]
-    fn drop_does_not_manage_process() {
-        // Test that Drop does NOT send signals or manage process lifecycle
-        // Process cleanup must be explicit via terminate() or wait()
-        let config = ProcessConfig::shell("sleep 60");
-        let pid = {
+    fn drop_terminates_and_reaps_process() {
+        use nix::errno::Errno;
+        use std::time::{Duration, Instant};
+
+        // The second child ignores SIGTERM, so it's only gone after SIGKILL
+        for command in ["sleep 30", "trap '' TERM; exec sleep 30"] {
+            let process = PtyProcess::spawn(&ProcessConfig::shell(command)).unwrap();
+            let pid = process.pid();
+            thread::sleep(Duration::from_millis(SIGNAL_SLEEP_MS));
+
+            let start = Instant::now();
+            drop(process);
+            assert!(start.elapsed() < Duration::from_millis(SIGTERM_TIMEOUT_MS * 4));
+
+            // Reaped: neither running nor a zombie
+            assert_eq!(
+                waitpid(pid, Some(WaitPidFlag::WNOHANG)),
+                Err(Errno::ECHILD),
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn term_timeout_gives_slow_shutdowns_time_to_finish() {
+        // Takes ~300ms to shut down after SIGTERM, then exits 7
+        let command = "trap 'sleep 0.3; exit 7' TERM; while :; do sleep 0.05; done";
+        let spawn = |term_timeout_ms| {
+            let config = ProcessConfig {
+                term_timeout_ms,
+                ..ProcessConfig::shell(command)
+            };
             let process = PtyProcess::spawn(&config).unwrap();
-            let pid = process.pid_raw();
-            assert!(pid > 0);
-            pid
+            thread::sleep(std::time::Duration::from_millis(SIGNAL_SLEEP_MS));
+            process
         };
-        // Process is now dropped - Drop should NOT send any signals
-        // The process is now orphaned and will be reaped by init/PID 1
 
-        // Process should still be running (Drop doesn't kill it)
-        // SAFETY: kill(pid, 0) is a probe that checks if process exists without sending signal.
-        // It's safe because we're just checking process liveness.
-        let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
-        assert_eq!(result, 0, "Process should still be running after drop");
+        let mut generous = spawn(3000);
+        assert!(generous.terminate());
+        assert_eq!(generous.exit_reason(), Some(ExitReason::Exited(7)));
 
-        // Clean up explicitly (in real code, caller should do this)
-        let mut process = PtyProcess::spawn(&config).unwrap();
-        let terminated = process.terminate();
-        assert!(
-            terminated,
-            "terminate() should successfully kill the process"
+        let mut short = spawn(50);
+        assert!(short.terminate());
+        assert_eq!(
+            short.exit_reason(),
+            Some(ExitReason::Signaled(Signal::SIGKILL as i32))
         );
     }
 
@@ -205,6 +205,16 @@ impl Pty {
         Ok(())
     }
 
+    /// Whether the terminal currently echoes input (the `ECHO` termios flag).
+    ///
+    /// Queried through the master, which reflects the settings the child
+    /// applied to its side (e.g. `stty -echo` or `read -s`).
+    pub fn echo_enabled(&self) -> Result<bool, PtyError> {
+        let master = self.master_borrowed()?;
+        let termios = termios::tcgetattr(master).map_err(PtyError::ConfigurationFailed)?;
+        Ok(termios.local_flags.contains(LocalFlags::ECHO))
+    }
+
     /// Check if the master fd is still valid (PTY hasn't been closed).
     pub fn is_open(&self) -> bool {
         self.master.is_some()
@@ -0,0 +1,415 @@
+//! Interactive Recording
+//!
+//! `bte record` runs a program on a PTY wired to the real terminal so it can
+//! be driven by hand, and turns the session into a scenario. Each burst of
+//! keystrokes becomes `send_keys` steps, preceded by a `wait_for` on the last
+//! line the program printed, or by a `wait_ticks` covering the pause when
+//! nothing new appeared on screen.
+
+// Recording stamps keys with real time while a person types
+#![allow(clippy::disallowed_types)]
+
+use crate::process::{ProcessConfig, ProcessError, PtyProcess};
+use crate::scenario::{
+    Command, KeySequence, Scenario, ScenarioStep, SpecialKey, Step, TerminalConfig,
+};
+use crate::screen::Screen;
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use nix::sys::termios::{self, SetArg, Termios};
+use std::io::{IsTerminal, Write};
+use std::os::fd::AsFd;
+use std::time::Instant;
+
+/// Wall-clock length of one recorded tick, matching the runner's 10 ms tick
+pub const RECORD_TICK_MS: u64 = 10;
+
+/// Keys recognised by their escape sequence; Ctrl combinations are decoded
+/// separately
+const NAMED_KEYS: [SpecialKey; 26] = [
+    SpecialKey::Enter,
+    SpecialKey::Tab,
+    SpecialKey::Backspace,
+    SpecialKey::Escape,
+    SpecialKey::Up,
+    SpecialKey::Down,
+    SpecialKey::Left,
+    SpecialKey::Right,
+    SpecialKey::Home,
+    SpecialKey::End,
+    SpecialKey::PageUp,
+    SpecialKey::PageDown,
+    SpecialKey::Insert,
+    SpecialKey::Delete,
+    SpecialKey::F1,
+    SpecialKey::F2,
+    SpecialKey::F3,
+    SpecialKey::F4,
+    SpecialKey::F5,
+    SpecialKey::F6,
+    SpecialKey::F7,
+    SpecialKey::F8,
+    SpecialKey::F9,
+    SpecialKey::F10,
+    SpecialKey::F11,
+    SpecialKey::F12,
+];
+
+/// Errors from an interactive recording session
+#[derive(Debug)]
+pub enum RecordError {
+    /// The program could not be spawned or driven
+    Process(ProcessError),
+    /// The real terminal could not be configured or read
+    Terminal(nix::Error),
+}
+
+impl std::fmt::Display for RecordError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RecordError::Process(e) => write!(f, "{}", e),
+            RecordError::Terminal(e) => write!(f, "Terminal error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+impl From<ProcessError> for RecordError {
+    fn from(e: ProcessError) -> Self {
+        RecordError::Process(e)
+    }
+}
+
+/// Builds a scenario from a program's output and the keys typed into it
+pub struct Recorder {
+    command: String,
+    terminal: TerminalConfig,
+    screen: Screen,
+    steps: Vec<ScenarioStep>,
+    /// Keys typed since the last wait step
+    pending_keys: Vec<u8>,
+    last_input_tick: u64,
+    /// Screen text hash when keys were last typed
+    last_input_screen: u64,
+}
+
+impl Recorder {
+    /// Start recording `command` on a terminal of the given size
+    pub fn new(command: &str, cols: u16, rows: u16) -> Self {
+        let screen = Screen::new(cols as usize, rows as usize);
+        Self {
+            command: command.to_string(),
+            terminal: TerminalConfig {
+                cols,
+                rows,
+                ..TerminalConfig::default()
+            },
+            last_input_screen: screen.text_hash(),
+            screen,
+            steps: Vec::new(),
+            pending_keys: Vec::new(),
+            last_input_tick: 0,
+        }
+    }
+
+    /// Feed output the program wrote
+    pub fn output(&mut self, data: &[u8]) {
+        self.screen.process(data);
+    }
+
+    /// Record keys typed at `tick`
+    ///
+    /// Keys typed in the same tick with no new output in between are kept
+    /// together. Otherwise the keys so far are flushed and a wait step is
+    /// inserted: a `wait_for` on the screen's last non-blank line if the
+    /// screen changed, or a `wait_ticks` for the pause.
+    pub fn input(&mut self, tick: u64, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+
+        let screen_hash = self.screen.text_hash();
+        let wait = if screen_hash != self.last_input_screen {
+            last_line(&self.screen).map(|line| Step::WaitFor {
+                pattern: regex::escape(&line),
+                timeout_ms: None,
+                case_insensitive: false,
+            })
+        } else {
+            None
+        };
+        let wait = wait.or_else(|| {
+            (tick > self.last_input_tick).then(|| Step::WaitTicks {
+                ticks: tick - self.last_input_tick,
+            })
+        });
+
+        if let Some(wait) = wait {
+            self.flush_keys();
+            self.steps.push(wait.into());
+        }
+        self.pending_keys.extend_from_slice(data);
+        self.last_input_tick = tick;
+        self.last_input_screen = screen_hash;
+    }
+
+    /// Finish recording and produce the scenario
+    pub fn finish(mut self) -> Scenario {
+        self.flush_keys();
+        Scenario {
+            name: "recorded".to_string(),
+            description: format!("Recorded with bte record: {}", self.command),
+            command: Command::Simple(self.command),
+            terminal: self.terminal,
+            steps: self.steps,
+            ..Scenario::default()
+        }
+    }
+
+    fn flush_keys(&mut self) {
+        let keys = std::mem::take(&mut self.pending_keys);
+        self.steps.extend(
+            decode_keys(&keys)
+                .into_iter()
+                .map(|keys| ScenarioStep::from(Step::SendKeys { keys })),
+        );
+    }
+}
+
+/// The last non-blank line on screen, trimmed
+fn last_line(screen: &Screen) -> Option<String> {
+    screen
+        .text()
+        .lines()
+        .map(str::trim)
+        .rfind(|line| !line.is_empty())
+        .map(String::from)
+}
+
+/// Split raw input into runs of text and recognised special keys
+pub fn decode_keys(bytes: &[u8]) -> Vec<KeySequence> {
+    let mut sequences = Vec::new();
+    let mut text: Vec<u8> = Vec::new();
+    let mut special: Vec<SpecialKey> = Vec::new();
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some((key, len)) = match_special_key(&bytes[i..]) {
+            if !text.is_empty() {
+                sequences.push(KeySequence::Text(
+                    String::from_utf8_lossy(&std::mem::take(&mut text)).into_owned(),
+                ));
+            }
+            special.push(key);
+            i += len;
+        } else {
+            if !special.is_empty() {
+                sequences.push(KeySequence::Special(std::mem::take(&mut special)));
+            }
+            text.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    if !text.is_empty() {
+        sequences.push(KeySequence::Text(
+            String::from_utf8_lossy(&text).into_owned(),
+        ));
+    }
+    if !special.is_empty() {
+        sequences.push(KeySequence::Special(special));
+    }
+    sequences
+}
+
+/// Match the longest special key at the start of `bytes`
+fn match_special_key(bytes: &[u8]) -> Option<(SpecialKey, usize)> {
+    let named = NAMED_KEYS
+        .iter()
+        .map(|key| (key, key.to_bytes()))
+        .filter(|(_, seq)| bytes.starts_with(seq))
+        .max_by_key(|(_, seq)| seq.len())
+        .map(|(key, seq)| (key.clone(), seq.len()));
+    if named.is_some() {
+        return named;
+    }
+
+    match bytes.first() {
+        Some(&byte @ 0x01..=0x1a) => Some((SpecialKey::Ctrl((b'a' + byte - 1) as char), 1)),
+        _ => None,
+    }
+}
+
+/// Puts the real terminal in raw mode, restoring it when dropped
+struct RawMode {
+    original: Termios,
+}
+
+impl RawMode {
+    /// Enable raw mode on stdin; does nothing when stdin isn't a terminal
+    fn enable() -> Result<Option<Self>, nix::Error> {
+        let stdin = std::io::stdin();
+        if !stdin.is_terminal() {
+            return Ok(None);
+        }
+        let original = termios::tcgetattr(stdin.as_fd())?;
+        let mut raw = original.clone();
+        termios::cfmakeraw(&mut raw);
+        termios::tcsetattr(stdin.as_fd(), SetArg::TCSANOW, &raw)?;
+        Ok(Some(Self { original }))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = termios::tcsetattr(std::io::stdin().as_fd(), SetArg::TCSANOW, &self.original);
+    }
+}
+
+/// Size of the real terminal, if stdout is one
+fn terminal_size() -> Option<(u16, u16)> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    // SAFETY: TIOCGWINSZ only writes a winsize into the struct we pass, and
+    // fails harmlessly with ENOTTY when stdout isn't a terminal.
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+    (result == 0 && size.ws_col > 0 && size.ws_row > 0).then_some((size.ws_col, size.ws_row))
+}
+
+/// Run `command` interactively and record the session until it exits
+///
+/// The program's output is mirrored to stdout and everything typed on
+/// stdin is forwarded to it. The terminal is in raw mode meanwhile, so
+/// keys like Ctrl+C go to the program rather than to bte.
+pub fn record(command: &str) -> Result<Scenario, RecordError> {
+    let defaults = TerminalConfig::default();
+    let (cols, rows) = terminal_size().unwrap_or((defaults.cols, defaults.rows));
+
+    let mut process = PtyProcess::spawn(&ProcessConfig::shell(command).with_size(cols, rows))?;
+    let _raw_mode = RawMode::enable().map_err(RecordError::Terminal)?;
+
+    let mut recorder = Recorder::new(command, cols, rows);
+    let mut stdout = std::io::stdout();
+    let stdin = std::io::stdin();
+    let start = Instant::now();
+    let mut buf = [0u8; 4096];
+    let mut stdin_open = true;
+
+    loop {
+        let (input_ready, output_ready) = {
+            let master = process.output_fd()?;
+            let mut fds = vec![PollFd::new(master, PollFlags::POLLIN)];
+            if stdin_open {
+                fds.push(PollFd::new(stdin.as_fd(), PollFlags::POLLIN));
+            }
+            poll(&mut fds, PollTimeout::from(RECORD_TICK_MS as u16))
+                .map_err(RecordError::Terminal)?;
+            let ready = |fd: &PollFd| {
+                fd.revents()
+                    .is_some_and(|r| r.intersects(PollFlags::POLLIN | PollFlags::POLLHUP))
+            };
+            (fds.get(1).is_some_and(ready), ready(&fds[0]))
+        };
+        let tick = start.elapsed().as_millis() as u64 / RECORD_TICK_MS;
+
+        if output_ready {
+            // EIO once the program's side of the PTY is closed
+            let Ok(n) = process.read(&mut buf) else {
+                break;
+            };
+            if n > 0 {
+                let _ = stdout.write_all(&buf[..n]);
+                let _ = stdout.flush();
+                recorder.output(&buf[..n]);
+            }
+        }
+
+        if input_ready {
+            let n =
+                nix::unistd::read(libc::STDIN_FILENO, &mut buf).map_err(RecordError::Terminal)?;
+            if n == 0 {
+                stdin_open = false;
+            } else {
+                process.write_all(&buf[..n])?;
+                recorder.input(tick, &buf[..n]);
+            }
+        }
+
+        if !output_ready && process.try_wait()?.is_some() {
+            break;
+        }
+    }
+
+    Ok(recorder.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send_keys(steps: &[ScenarioStep]) -> Vec<Vec<u8>> {
+        steps
+            .iter()
+            .filter_map(|s| match &s.step {
+                Step::SendKeys { keys } => Some(keys.to_bytes()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn scripted_session_produces_send_keys() {
+        let mut recorder = Recorder::new("cat", 20, 5);
+        recorder.output(b"Name? ");
+        recorder.input(3, b"bob");
+        recorder.input(3, b"\r");
+        recorder.output(b"bob\r\nHello bob\r\n");
+        recorder.input(12, b"\x1b[A");
+        recorder.input(20, b"\x03");
+        let scenario = recorder.finish();
+
+        assert_eq!(scenario.terminal.cols, 20);
+        assert_eq!(
+            send_keys(&scenario.steps),
+            vec![
+                b"bob".to_vec(),
+                b"\r".to_vec(),
+                b"\x1b[A".to_vec(),
+                b"\x03".to_vec()
+            ]
+        );
+        let waits: Vec<_> = scenario
+            .steps
+            .iter()
+            .filter(|s| !matches!(s.step, Step::SendKeys { .. }))
+            .map(|s| s.step.clone())
+            .collect();
+        assert!(matches!(&waits[0], Step::WaitFor { pattern, .. } if pattern == r"Name\?"));
+        assert!(matches!(&waits[1], Step::WaitFor { pattern, .. } if pattern == "Hello bob"));
+        assert!(matches!(&waits[2], Step::WaitTicks { ticks: 8 }));
+        assert_eq!(waits.len(), 3);
+
+        // The generated scenario survives a YAML round trip
+        let yaml = scenario._to_yaml().unwrap();
+        let parsed = Scenario::_from_yaml(&yaml).unwrap();
+        assert_eq!(send_keys(&parsed.steps), send_keys(&scenario.steps));
+    }
+
+    #[test]
+    fn decode_keys_splits_text_and_special_keys() {
+        let keys = decode_keys(b"ls\x1b[5~\x1b\x01x");
+        assert!(matches!(&keys[0], KeySequence::Text(t) if t == "ls"));
+        assert!(matches!(
+            &keys[1],
+            KeySequence::Special(k) if matches!(
+                k.as_slice(),
+                [SpecialKey::PageUp, SpecialKey::Escape, SpecialKey::Ctrl('a')]
+            )
+        ));
+        assert!(matches!(&keys[2], KeySequence::Text(t) if t == "x"));
+    }
+}
@@ -310,7 +310,7 @@ impl TraceBuilder {
         let excerpt = if text.len() > 200 {
             Some(text[..200].to_string())
         } else {
-            Some(text)
+            Some(text.to_string())
         };
 
         self.trace.checkpoints.push(ScreenCheckpoint {
@@ -577,6 +577,7 @@ mod tests {
             seed: Some(42),
             timeout_ms: Some(10000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let mut builder = TraceBuilder::new(scenario, 42, (80, 24));
@@ -609,6 +610,7 @@ mod tests {
             seed: Some(42),
             timeout_ms: Some(10000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let mut builder = TraceBuilder::new(scenario, 42, (80, 24));
@@ -656,6 +658,7 @@ mod tests {
             seed: Some(42),
             timeout_ms: Some(10000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let mut builder = TraceBuilder::new(scenario, 42, (80, 24));
@@ -0,0 +1,411 @@
+//! CI Report Formats
+//!
+//! This module serializes execution traces into formats understood by CI
+//! systems (JUnit XML and TAP), so a scenario run can be rendered as
+//! pass/fail test cases, and into a JSON run summary for other tooling.
+
+use crate::invariants::InvariantResult;
+use crate::scenario::Step;
+use crate::trace::{Trace, TraceOutcome};
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+
+/// A single test case in a report
+struct ReportCase {
+    name: String,
+    failure: Option<ReportFailure>,
+    /// Name/value pairs emitted as JUnit `<properties>`
+    properties: Vec<(String, String)>,
+}
+
+/// Why a test case failed
+struct ReportFailure {
+    kind: &'static str,
+    message: String,
+    details: Option<String>,
+}
+
+/// Get the `action` name of a step (e.g. "wait_for")
+fn step_action(step: &Step) -> String {
+    serde_json::to_value(step)
+        .ok()
+        .and_then(|v| v.get("action").and_then(|a| a.as_str()).map(String::from))
+        .unwrap_or_else(|| "step".to_string())
+}
+
+/// Flatten a trace into test cases: one per step, one per invariant, and one
+/// for the overall outcome
+fn report_cases(trace: &Trace) -> Vec<ReportCase> {
+    let mut cases = Vec::new();
+
+    for step in &trace.steps {
+        cases.push(ReportCase {
+            name: format!("step {}: {}", step.index, step_action(&step.step)),
+            failure: step.error.as_ref().map(|e| ReportFailure {
+                kind: "step_error",
+                message: e.clone(),
+                details: None,
+            }),
+            properties: invariant_properties(trace, |r| r.step == step.index),
+        });
+    }
+
+    // One case per invariant, failing on its first violation
+    let mut names: Vec<&str> = Vec::new();
+    for result in &trace.invariant_results {
+        if !names.contains(&result.name.as_str()) {
+            names.push(&result.name);
+        }
+    }
+    for name in names {
+        let violation = trace
+            .invariant_results
+            .iter()
+            .find(|r| r.name == name && r.is_error());
+        cases.push(ReportCase {
+            name: format!("invariant: {}", name),
+            failure: violation.map(|r| ReportFailure {
+                kind: "invariant_violation",
+                message: r.details.clone().unwrap_or_else(|| r.description.clone()),
+                details: Some(format!(
+                    "{} (step {}, tick {})",
+                    r.description, r.step, r.tick
+                )),
+            }),
+            properties: Vec::new(),
+        });
+    }
+
+    let outcome_failure = match &trace.outcome {
+        TraceOutcome::Success { exit_code: 0, .. } => None,
+        TraceOutcome::Success { exit_code, .. } => Some(format!("Exited with code {}", exit_code)),
+        TraceOutcome::InvariantViolation { invariant_name, .. } => {
+            Some(format!("Invariant violated: {}", invariant_name))
+        }
+        TraceOutcome::Timeout {
+            max_ticks,
+            elapsed_ticks,
+            ..
+        } => Some(format!(
+            "Timed out after {} ticks (max {})",
+            elapsed_ticks, max_ticks
+        )),
+        TraceOutcome::Error { message, .. } => Some(message.clone()),
+        TraceOutcome::Signaled { signal_name, .. } => {
+            Some(format!("Terminated by {}", signal_name))
+        }
+        TraceOutcome::ReplayDivergence { context, .. } => {
+            Some(format!("Replay divergence: {}", context))
+        }
+    };
+    // Results checked after the last step (final evaluation) go on the outcome
+    let after_steps = |r: &InvariantResult| trace.steps.iter().all(|s| s.index != r.step);
+    cases.push(ReportCase {
+        name: "outcome".to_string(),
+        failure: outcome_failure.map(|message| ReportFailure {
+            kind: "outcome",
+            message,
+            details: None,
+        }),
+        properties: invariant_properties(trace, after_steps),
+    });
+
+    cases
+}
+
+/// One property per invariant result selected by `filter`, e.g.
+/// `invariant: cursor_bounds` = `pass (tick 3): Cursor stays within bounds`
+fn invariant_properties(
+    trace: &Trace,
+    filter: impl Fn(&InvariantResult) -> bool,
+) -> Vec<(String, String)> {
+    trace
+        .invariant_results
+        .iter()
+        .filter(|r| filter(r))
+        .map(|r| {
+            let status = if r.satisfied { "pass" } else { "fail" };
+            (
+                format!("invariant: {}", r.name),
+                format!("{} (tick {}): {}", status, r.tick, r.description),
+            )
+        })
+        .collect()
+}
+
+/// Escape text for use in XML attributes and content
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            // Control characters other than tab/newline are not valid XML 1.0
+            c if c.is_control() && c != '\t' && c != '\n' && c != '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Render a trace as a JUnit XML report
+pub fn to_junit(trace: &Trace) -> String {
+    let cases = report_cases(trace);
+    let failures = cases.iter().filter(|c| c.failure.is_some()).count();
+    let suite = xml_escape(&trace.scenario.name);
+
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites tests=\"{}\" failures=\"{}\">",
+        cases.len(),
+        failures
+    );
+    let _ = writeln!(
+        xml,
+        "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">",
+        suite,
+        cases.len(),
+        failures
+    );
+    for case in &cases {
+        let _ = write!(
+            xml,
+            "    <testcase classname=\"{}\" name=\"{}\"",
+            suite,
+            xml_escape(&case.name)
+        );
+        if case.failure.is_none() && case.properties.is_empty() {
+            xml.push_str("/>\n");
+            continue;
+        }
+        xml.push_str(">\n");
+        if !case.properties.is_empty() {
+            xml.push_str("      <properties>\n");
+            for (name, value) in &case.properties {
+                let _ = writeln!(
+                    xml,
+                    "        <property name=\"{}\" value=\"{}\"/>",
+                    xml_escape(name),
+                    xml_escape(value)
+                );
+            }
+            xml.push_str("      </properties>\n");
+        }
+        if let Some(failure) = &case.failure {
+            let _ = write!(
+                xml,
+                "      <failure type=\"{}\" message=\"{}\"",
+                failure.kind,
+                xml_escape(&failure.message)
+            );
+            match &failure.details {
+                Some(details) => {
+                    let _ = writeln!(xml, ">{}</failure>", xml_escape(details));
+                }
+                None => xml.push_str("/>\n"),
+            }
+        }
+        xml.push_str("    </testcase>\n");
+    }
+    xml.push_str("  </testsuite>\n");
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// Write a JUnit XML report for a trace
+pub fn write_junit(trace: &Trace, path: &Path) -> Result<(), io::Error> {
+    std::fs::write(path, to_junit(trace))
+}
+
+/// Render a trace as a TAP version 13 stream
+///
+/// Failing cases carry a YAML diagnostic block with the failure type,
+/// message and (for invariants) where the violation happened.
+pub fn to_tap(trace: &Trace) -> String {
+    let cases = report_cases(trace);
+
+    let mut tap = String::new();
+    tap.push_str("TAP version 13\n");
+    let _ = writeln!(tap, "1..{}", cases.len());
+    for (i, case) in cases.iter().enumerate() {
+        // '#' starts a directive in TAP, so keep it out of descriptions
+        let name = case.name.replace('#', "\\#");
+        match &case.failure {
+            None => {
+                let _ = writeln!(tap, "ok {} - {}", i + 1, name);
+            }
+            Some(failure) => {
+                let _ = writeln!(tap, "not ok {} - {}", i + 1, name);
+                tap.push_str("  ---\n");
+                let _ = writeln!(tap, "  type: {}", failure.kind);
+                let _ = writeln!(tap, "  message: {}", yaml_string(&failure.message));
+                if let Some(details) = &failure.details {
+                    let _ = writeln!(tap, "  details: {}", yaml_string(details));
+                }
+                tap.push_str("  ...\n");
+            }
+        }
+    }
+    tap
+}
+
+/// Summarize a run as a JSON object
+///
+/// Holds the outcome (tagged by `status`, as in traces), the exit code `bte
+/// run` returns, tick and step counts, and every invariant result.
+pub fn to_json(trace: &Trace, exit_code: i32) -> serde_json::Value {
+    serde_json::json!({
+        "scenario": trace.scenario.name,
+        "outcome": trace.outcome,
+        "exit_code": exit_code,
+        "ticks": trace.total_ticks,
+        "steps": trace.steps.len(),
+        "attempts": trace.attempts,
+        "seed": trace.seed,
+        "invariants": trace.invariant_results,
+    })
+}
+
+/// Quote a string as a YAML scalar (JSON strings are valid YAML)
+fn yaml_string(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invariants::InvariantResult;
+    use crate::scenario::Scenario;
+    use crate::trace::TraceBuilder;
+
+    fn failing_trace() -> Trace {
+        let scenario = Scenario {
+            name: "login <flow>".to_string(),
+            ..Scenario::default()
+        };
+        let mut builder = TraceBuilder::new(scenario, 42);
+        let scheduler = crate::determinism::DeterministicScheduler::new(42);
+        builder.start_step(Step::WaitTicks { ticks: 1 }, None, &scheduler);
+        builder.end_step(None, &scheduler);
+
+        builder.record_invariant_result(&InvariantResult::new(
+            "cursor_bounds",
+            true,
+            "Cursor stays within bounds",
+            None,
+            0,
+            0,
+        ));
+        builder.record_invariant_result(&InvariantResult::new(
+            "screen_not_contains",
+            false,
+            "Screen must not contain 'error'",
+            Some("Found \"error\" on screen".to_string()),
+            1,
+            3,
+        ));
+        builder.set_outcome(TraceOutcome::InvariantViolation {
+            invariant_name: "screen_not_contains".to_string(),
+            checkpoint_index: 0,
+        });
+        builder.build()
+    }
+
+    #[test]
+    fn failing_invariant_becomes_failure_node() {
+        let xml = to_junit(&failing_trace());
+
+        assert!(xml.starts_with("<?xml"));
+        assert!(xml.contains("<testsuite name=\"login &lt;flow&gt;\" tests=\"4\" failures=\"2\">"));
+        assert!(xml.contains(
+            "name=\"step 0: wait_ticks\">\n      <properties>\n        \
+             <property name=\"invariant: cursor_bounds\" \
+             value=\"pass (tick 0): Cursor stays within bounds\"/>\n      \
+             </properties>\n    </testcase>"
+        ));
+        assert!(xml.contains("name=\"invariant: cursor_bounds\"/>"));
+        assert!(xml.contains(
+            "<testcase classname=\"login &lt;flow&gt;\" name=\"invariant: screen_not_contains\">\n      \
+             <failure type=\"invariant_violation\" message=\"Found &quot;error&quot; on screen\">\
+             Screen must not contain &apos;error&apos; (step 1, tick 3)</failure>"
+        ));
+        assert!(xml.contains("message=\"Invariant violated: screen_not_contains\""));
+    }
+
+    #[test]
+    fn invariant_results_become_properties() {
+        let xml = to_junit(&failing_trace());
+
+        // The step-1 result was checked after the only step, so it lands on
+        // the outcome case
+        let properties: Vec<&str> = xml
+            .lines()
+            .map(str::trim)
+            .filter(|l| l.starts_with("<property "))
+            .collect();
+        assert_eq!(
+            properties,
+            vec![
+                "<property name=\"invariant: cursor_bounds\" \
+                 value=\"pass (tick 0): Cursor stays within bounds\"/>",
+                "<property name=\"invariant: screen_not_contains\" \
+                 value=\"fail (tick 3): Screen must not contain &apos;error&apos;\"/>",
+            ]
+        );
+        assert!(xml.contains(
+            "name=\"outcome\">\n      <properties>\n        \
+             <property name=\"invariant: screen_not_contains\""
+        ));
+    }
+
+    #[test]
+    fn tap_reports_violation_with_diagnostics() {
+        let tap = to_tap(&failing_trace());
+        let lines: Vec<&str> = tap.lines().collect();
+
+        assert_eq!(lines[0], "TAP version 13");
+        assert_eq!(lines[1], "1..4");
+        assert_eq!(lines[2], "ok 1 - step 0: wait_ticks");
+        assert_eq!(lines[3], "ok 2 - invariant: cursor_bounds");
+        assert_eq!(lines[4], "not ok 3 - invariant: screen_not_contains");
+        assert_eq!(lines[5], "  ---");
+        assert_eq!(lines[6], "  type: invariant_violation");
+        assert_eq!(lines[7], "  message: \"Found \\\"error\\\" on screen\"");
+        assert_eq!(
+            lines[8],
+            "  details: \"Screen must not contain 'error' (step 1, tick 3)\""
+        );
+        assert_eq!(lines[9], "  ...");
+        assert_eq!(lines[10], "not ok 4 - outcome");
+    }
+
+    #[test]
+    fn passing_trace_has_no_failures() {
+        let mut builder = TraceBuilder::new(Scenario::default(), 42);
+        builder.set_outcome(TraceOutcome::Success {
+            exit_code: 0,
+            total_ticks: 5,
+        });
+        let xml = to_junit(&builder.build());
+
+        assert!(xml.contains("failures=\"0\""));
+        assert!(!xml.contains("<failure"));
+    }
+
+    #[test]
+    fn json_summary_has_outcome_and_invariants() {
+        let json = to_json(&failing_trace(), 1);
+        assert_eq!(json["scenario"], "login <flow>");
+        assert_eq!(json["outcome"]["status"], "InvariantViolation");
+        assert_eq!(json["exit_code"], 1);
+        assert_eq!(json["steps"], 1);
+        assert_eq!(json["invariants"].as_array().unwrap().len(), 2);
+        assert_eq!(json["invariants"][1]["satisfied"], false);
+    }
+}
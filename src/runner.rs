@@ -8,15 +8,27 @@
 #![allow(clippy::result_large_err)]
 
 use crate::determinism::DeterministicScheduler;
-use crate::invariants::{BuiltInInvariant, InvariantContext, InvariantEngine};
+use crate::fuzzy::FuzzyAlgorithm;
+use crate::invariants::{
+    BuiltInInvariant, InvariantContext, InvariantEngine, InvariantResult, SignalDelivery,
+};
 use crate::io_loop::IoLoop;
 use crate::keys::KeyInjector;
-use crate::process::{ProcessConfig, PtyProcess};
-use crate::scenario::{InvariantRef, Scenario, Step};
+use crate::process::{
+    resolve_environment, ProcessConfig, ProcessError, PtyProcess, SIGTERM_TIMEOUT_MS,
+};
+use crate::pty::PtyError;
+use crate::scenario::{
+    frozen_epoch, CellAttrCheck, InvariantRef, KeySequence, OutputAssertions, Scenario,
+    ScenarioInvariant, ScenarioStep, SgrState, Step, StepSource, StepStream,
+};
 use crate::screen::Screen;
-use crate::timing::TimingController;
-use crate::trace::{Trace, TraceBuilder, TraceOutcome};
+use crate::timing::{TimingController, TimingMode};
+use crate::trace::{
+    RedactionPolicy, ScheduleEvent, Trace, TraceBuilder, TraceFormat, TraceOutcome,
+};
 use regex::Regex;
+use std::collections::HashMap;
 use std::path::Path;
 
 // ============================================================================
@@ -43,13 +55,43 @@ impl RunResult {
 // RunnerConfig
 // ============================================================================
 
+/// Tick budget for a run when neither the config nor the scenario sets one
+pub const DEFAULT_MAX_TICKS: u64 = 10000;
+
 #[derive(Debug, Clone)]
 pub struct RunnerConfig {
     pub trace_path: Option<String>,
     pub verbose: bool,
-    pub max_ticks: u64,
+    /// Tick budget for the whole run; falls back to the scenario's
+    /// `timeout_ms`, then [`DEFAULT_MAX_TICKS`]
+    pub max_ticks: Option<u64>,
     pub tick_delay_ms: u64,
     pub seed: Option<u64>,
+    /// Accept mismatching pinned snapshots so they can be rewritten
+    pub update_snapshots: bool,
+    /// Overwrite mismatching screenshot baselines with the current screen
+    pub update_baselines: bool,
+    /// Record non-deterministic wall-clock timings for each step
+    pub record_wallclock: bool,
+    /// Capture the screen's tab stops at each checkpoint
+    pub record_tab_stops: bool,
+    /// Sample the cursor position after each step
+    pub record_cursor_trail: bool,
+    /// Which run of the scenario this is (1-based), recorded in the trace
+    pub attempt: u32,
+    /// Run the command with plain pipes instead of a PTY
+    pub no_pty: bool,
+    /// Whether ticks are deterministic or follow the wall clock
+    pub timing_mode: TimingMode,
+    /// Environment variables to mask in saved traces
+    pub redaction: RedactionPolicy,
+    /// End the run once the process has printed more than this many bytes
+    pub max_output_bytes: Option<u64>,
+    /// Compile step patterns without the `regex` crate's size limit
+    pub lift_regex_size_limit: bool,
+    /// How long a process still running at the end of the run gets to exit
+    /// after SIGTERM before it is sent SIGKILL
+    pub term_timeout_ms: u64,
 }
 
 impl Default for RunnerConfig {
@@ -57,9 +99,21 @@ impl Default for RunnerConfig {
         Self {
             trace_path: None,
             verbose: false,
-            max_ticks: 10000,
+            max_ticks: None,
             tick_delay_ms: 0,
+            update_snapshots: false,
+            update_baselines: false,
+            record_wallclock: false,
+            record_tab_stops: false,
+            record_cursor_trail: false,
+            attempt: 1,
+            no_pty: false,
+            timing_mode: TimingMode::Deterministic,
+            redaction: RedactionPolicy::default(),
             seed: None,
+            max_output_bytes: None,
+            lift_regex_size_limit: false,
+            term_timeout_ms: SIGTERM_TIMEOUT_MS,
         }
     }
 }
@@ -73,8 +127,29 @@ pub fn run_scenario(scenario: &Scenario, config: &RunnerConfig) -> RunResult {
     let seed = determine_seed(config.seed, scenario.seed);
     let mut scheduler = DeterministicScheduler::new(seed);
     let mut timing = TimingController::new(seed);
+    timing.set_mode(config.timing_mode);
+    timing.set_tick_delay(config.tick_delay_ms);
+
+    let (proc_config, mut trace_builder) =
+        initialize_components(scenario, config, &scheduler, seed);
+    trace_builder.set_attempts(config.attempt);
+    if config.record_wallclock {
+        trace_builder.enable_wallclock();
+    }
+    if config.record_tab_stops {
+        trace_builder.enable_tab_stops();
+    }
+    if config.record_cursor_trail {
+        trace_builder.enable_cursor_trail();
+    }
+    start_trace_stream(&mut trace_builder, config);
 
-    let (proc_config, mut trace_builder) = initialize_components(scenario, &scheduler, seed);
+    if config.no_pty {
+        eprintln!(
+            "Warning: running without a PTY; steps that need a terminal are skipped \
+             with a warning in the trace"
+        );
+    }
 
     // Handle process spawn failure gracefully instead of panicking
     let mut process = match spawn_process_safe(&proc_config, &mut trace_builder) {
@@ -82,7 +157,15 @@ pub fn run_scenario(scenario: &Scenario, config: &RunnerConfig) -> RunResult {
         Err(run_result) => return run_result, // Early return with error result
     };
 
-    let mut io = IoLoop::new();
+    let mut io = IoLoop::new().with_transcript(!scenario.output_assertions.is_empty());
+    if let Some(jitter) = scenario.io_jitter {
+        // Drawn after the initial RNG state is recorded, so replay sees the
+        // same chunks
+        io = io.with_jitter(jitter, scheduler.random_u64().unwrap_or(seed));
+    }
+    if let Some(limit) = config.max_output_bytes {
+        io = io.with_output_limit(limit);
+    }
     let mut screen = Screen::new(
         scenario.terminal.cols as usize,
         scenario.terminal.rows as usize,
@@ -93,7 +176,7 @@ pub fn run_scenario(scenario: &Scenario, config: &RunnerConfig) -> RunResult {
     let mut invariant_engine = build_invariant_engine(&scenario.invariants);
 
     // Phase 3: Execute scenario steps
-    let (step_index, timed_out, step_error, last_screen_hash, no_output_ticks) = execute_step_loop(
+    let (loop_state, timed_out, step_error) = execute_step_loop(
         scenario,
         config,
         &mut process,
@@ -104,37 +187,55 @@ pub fn run_scenario(scenario: &Scenario, config: &RunnerConfig) -> RunResult {
         &mut trace_builder,
         &mut invariant_engine,
     );
+    // Without a PTY, closing stdin lets line-reading programs see EOF
+    process.close_input();
 
     // Phase 4: Final invariant evaluation
     evaluate_final_invariants(
         &mut invariant_engine,
         &mut process,
         &screen,
-        step_index,
-        scheduler.now(),
-        last_screen_hash,
-        no_output_ticks,
+        &loop_state,
+        timing.now(),
         &mut trace_builder,
     );
 
+    // Catch output printed after the last step before checking the stream
+    let _ = io.read_available(&process);
+    let output_results = evaluate_output_assertions(
+        &scenario.output_assertions,
+        io.transcript().unwrap_or_default(),
+        loop_state.step_index,
+        scheduler.now(),
+    );
+    record_invariant_results(&output_results, &mut trace_builder);
+
     // Phase 5: Determine outcome and build trace
     let outcome = determine_outcome(
         &step_error,
         timed_out,
         &invariant_engine,
+        &output_results,
         &mut process,
-        config.max_ticks,
-        scheduler.now(),
-        step_index,
+        determine_max_ticks(config.max_ticks, scenario.timeout_ms),
+        timing.now(),
+        loop_state.step_index,
     );
-    let exit_code = exit_code_from_outcome(&outcome);
+    let outcome = check_expected_exit(outcome, scenario.expect_exit, loop_state.step_index);
+    let outcome = with_screen_preview(outcome, &screen);
+    let exit_code = run_exit_code(&outcome, scenario.expect_exit);
 
     trace_builder.set_outcome(outcome);
     trace_builder.set_final_screen_hash(Some(screen.state_hash()));
     trace_builder.set_total_ticks(scheduler.now());
+    trace_builder.set_max_idle_gap_ticks(loop_state.max_idle_gap_ticks);
 
+    // A streamed trace is already on disk; `build` writes its footer
+    let streamed = trace_builder.is_streaming();
     let trace = trace_builder.build();
-    save_trace(&trace, config.trace_path.as_deref());
+    if !streamed {
+        save_trace(&trace, config);
+    }
 
     RunResult {
         trace,
@@ -143,6 +244,42 @@ pub fn run_scenario(scenario: &Scenario, config: &RunnerConfig) -> RunResult {
     }
 }
 
+/// Run a scenario, re-running it up to `retries` more times while it fails
+/// with an error or timeout (e.g. a process-spawn race)
+///
+/// Invariant violations and unexpected exit codes are real failures and are
+/// never retried. The returned trace records how many attempts were made.
+pub fn run_scenario_with_retries(
+    scenario: &Scenario,
+    config: &RunnerConfig,
+    retries: u32,
+) -> RunResult {
+    retry_run(retries, |attempt| {
+        let config = RunnerConfig {
+            attempt,
+            ..config.clone()
+        };
+        run_scenario(scenario, &config)
+    })
+}
+
+fn retry_run(retries: u32, mut run: impl FnMut(u32) -> RunResult) -> RunResult {
+    let mut attempt = 1;
+    loop {
+        let result = run(attempt);
+        // A wrong exit code is the program's answer, not a flaky run
+        let retryable = match &result.trace.outcome {
+            TraceOutcome::Error { message, .. } => !is_unexpected_exit(message),
+            TraceOutcome::Timeout { .. } => true,
+            _ => false,
+        };
+        if result.success || !retryable || attempt > retries {
+            return result;
+        }
+        attempt += 1;
+    }
+}
+
 // ============================================================================
 // Phase 1: Initialization
 // ============================================================================
@@ -154,8 +291,17 @@ fn determine_seed(config_seed: Option<u64>, scenario_seed: Option<u64>) -> u64 {
     })
 }
 
+/// Tick budget for a run, with the scenario's `timeout_ms` converted at
+/// 10 ms per tick like step timeouts
+fn determine_max_ticks(config_max_ticks: Option<u64>, scenario_timeout_ms: Option<u64>) -> u64 {
+    config_max_ticks
+        .or(scenario_timeout_ms.map(|ms| ms / 10))
+        .unwrap_or(DEFAULT_MAX_TICKS)
+}
+
 fn initialize_components(
     scenario: &Scenario,
+    config: &RunnerConfig,
     scheduler: &DeterministicScheduler,
     seed: u64,
 ) -> (ProcessConfig, TraceBuilder) {
@@ -170,13 +316,34 @@ fn initialize_components(
     } else {
         Some(scenario.env.clone())
     };
+    // Validation rejects malformed values before a run gets here
+    let env = match scenario
+        .freeze_time
+        .as_deref()
+        .map(|f| frozen_epoch(f, seed))
+    {
+        Some(Ok(epoch)) => Some(frozen_clock_env(&env, epoch)),
+        _ => env,
+    };
 
     let proc_config = ProcessConfig {
         program: scenario.command.program().to_string(),
         args: scenario.command.args(),
         env,
-        cwd: None,
+        cwd: scenario
+            .command
+            .cwd()
+            .or(scenario.cwd.as_deref())
+            .map(String::from),
         pty_config,
+        separate_stderr: scenario.terminal.separate_stderr,
+        pipes: config.no_pty,
+        cpu_limit_secs: scenario.limits.cpu_secs,
+        memory_limit_bytes: scenario
+            .limits
+            .memory_mb
+            .map(|mb| mb.saturating_mul(1024 * 1024)),
+        term_timeout_ms: config.term_timeout_ms,
     };
 
     let mut trace_builder = TraceBuilder::new(scenario.clone(), seed);
@@ -185,13 +352,32 @@ fn initialize_components(
     (proc_config, trace_builder)
 }
 
+/// The child environment with `SOURCE_DATE_EPOCH`, `TZ` and libfaketime's
+/// `FAKETIME` pinned to `epoch`, unless the scenario sets them itself
+fn frozen_clock_env(env: &Option<HashMap<String, String>>, epoch: i64) -> HashMap<String, String> {
+    let mut env = resolve_environment(env);
+    let faketime = chrono::DateTime::from_timestamp(epoch, 0)
+        .map(|time| time.format("@%Y-%m-%d %H:%M:%S").to_string());
+    let pinned = [
+        ("SOURCE_DATE_EPOCH", Some(epoch.to_string())),
+        ("TZ", Some("UTC".to_string())),
+        ("FAKETIME", faketime),
+    ];
+    for (key, value) in pinned {
+        if let Some(value) = value {
+            env.entry(key.to_string()).or_insert(value);
+        }
+    }
+    env
+}
+
 /// Spawn a process, handling errors gracefully
 fn spawn_process_safe(
     proc_config: &ProcessConfig,
     trace_builder: &mut TraceBuilder,
 ) -> Result<PtyProcess, RunResult> {
     PtyProcess::spawn(proc_config).map_err(|e| {
-        let trace = trace_builder.build_error(format!("Failed to spawn process: {}", e));
+        let trace = trace_builder.build_error(spawn_error_message(&e));
         RunResult {
             trace,
             exit_code: -1,
@@ -200,80 +386,290 @@ fn spawn_process_safe(
     })
 }
 
+/// Describe a spawn failure, calling out PTY allocation separately since it
+/// usually means the environment (not the command) is the problem
+fn spawn_error_message(e: &ProcessError) -> String {
+    match e {
+        ProcessError::Pty(PtyError::AllocationFailed(errno)) => format!(
+            "PTY allocation failed ({}): is /dev/ptmx available? \
+             Use --no-pty to run without a terminal",
+            errno
+        ),
+        other => format!("Failed to spawn process: {}", other),
+    }
+}
+
+/// Steps read lazily from the scenario's `steps_file`, if any
+fn streamed_steps(scenario: &Scenario) -> Box<dyn Iterator<Item = Result<ScenarioStep, String>>> {
+    match &scenario.steps_file {
+        Some(path) => match StepStream::open(path) {
+            Ok(stream) => Box::new(stream),
+            Err(e) => Box::new(std::iter::once(Err(e))),
+        },
+        None => Box::new(std::iter::empty()),
+    }
+}
+
+/// Walks a scenario's steps, following `goto_if` jumps between inline steps
+/// and entering `repeat` and `if_screen` blocks
+///
+/// Inline steps are addressed by index so they can be revisited; steps from
+/// a `steps_file` are streamed once after the inline steps finish.
+struct StepCursor<'a> {
+    inline: &'a [ScenarioStep],
+    streamed: Box<dyn Iterator<Item = Result<ScenarioStep, String>>>,
+    pc: usize,
+    labels: HashMap<&'a str, usize>,
+    /// Consecutive jumps taken by each `goto_if`, keyed by step index
+    jumps: HashMap<usize, u32>,
+    /// Active nested blocks, innermost last
+    blocks: Vec<BlockFrame>,
+}
+
+/// Progress through a nested block (a `repeat` body or `if_screen` branch)
+struct BlockFrame {
+    steps: Vec<ScenarioStep>,
+    position: usize,
+    remaining: u32,
+}
+
+impl<'a> StepCursor<'a> {
+    fn new(scenario: &'a Scenario) -> Self {
+        Self::over(&scenario.steps, streamed_steps(scenario))
+    }
+
+    /// Walk `inline`, then whatever `streamed` yields
+    fn over(
+        inline: &'a [ScenarioStep],
+        streamed: Box<dyn Iterator<Item = Result<ScenarioStep, String>>>,
+    ) -> Self {
+        let labels = inline
+            .iter()
+            .enumerate()
+            .filter_map(|(i, entry)| match &entry.step {
+                Step::Label { name } => Some((name.as_str(), i)),
+                _ => None,
+            })
+            .collect();
+        Self {
+            inline,
+            streamed,
+            pc: 0,
+            labels,
+            jumps: HashMap::new(),
+            blocks: Vec::new(),
+        }
+    }
+
+    /// Next step to run, with its inline index (`None` for streamed steps
+    /// and steps inside a `repeat`)
+    fn next_step(&mut self) -> Option<Result<(Option<usize>, ScenarioStep), String>> {
+        loop {
+            let (index, entry) = if let Some(frame) = self.blocks.last_mut() {
+                if let Some(entry) = frame.steps.get(frame.position) {
+                    frame.position += 1;
+                    (None, entry.clone())
+                } else {
+                    frame.remaining -= 1;
+                    frame.position = 0;
+                    if frame.remaining == 0 {
+                        self.blocks.pop();
+                    }
+                    continue;
+                }
+            } else if let Some(entry) = self.inline.get(self.pc) {
+                self.pc += 1;
+                (Some(self.pc - 1), entry.clone())
+            } else {
+                match self.streamed.next()? {
+                    Ok(entry) => (None, entry),
+                    Err(e) => return Some(Err(e)),
+                }
+            };
+
+            match entry.step {
+                Step::Repeat { count, steps } => self.enter_block(steps, count),
+                _ => return Some(Ok((index, entry))),
+            }
+        }
+    }
+
+    /// Run `steps` next, `count` times over
+    fn enter_block(&mut self, steps: Vec<ScenarioStep>, count: u32) {
+        if count > 0 && !steps.is_empty() {
+            self.blocks.push(BlockFrame {
+                steps,
+                position: 0,
+                remaining: count,
+            });
+        }
+    }
+
+    /// Enter the `if_screen` branch selected by the current screen
+    fn if_screen(
+        &mut self,
+        pattern: &str,
+        then: &[ScenarioStep],
+        otherwise: &[ScenarioStep],
+        screen: &Screen,
+        config: &RunnerConfig,
+    ) -> Result<(), String> {
+        let regex = compile_pattern(pattern, config)?;
+        let branch = if regex.is_match(screen.text()) {
+            then
+        } else {
+            otherwise
+        };
+        self.enter_block(branch.to_vec(), 1);
+        Ok(())
+    }
+
+    /// Decide whether a `goto_if` at `index` jumps back to its label
+    fn goto_if(
+        &mut self,
+        index: Option<usize>,
+        label: &str,
+        pattern: &str,
+        max_iterations: u32,
+        screen: &Screen,
+        config: &RunnerConfig,
+    ) -> Result<(), String> {
+        let index = index
+            .ok_or_else(|| "goto_if is only supported in top-level inline steps".to_string())?;
+        let target = *self
+            .labels
+            .get(label)
+            .ok_or_else(|| format!("goto_if: unknown label '{}'", label))?;
+        let regex = compile_pattern(pattern, config)?;
+
+        if regex.is_match(screen.text()) {
+            self.jumps.remove(&index);
+            return Ok(());
+        }
+
+        let count = self.jumps.entry(index).or_insert(0);
+        if *count >= max_iterations {
+            return Err(format!(
+                "goto_if '{}': pattern '{}' still absent after {} iterations",
+                label, pattern, max_iterations
+            ));
+        }
+        *count += 1;
+        self.pc = target;
+        Ok(())
+    }
+}
+
 // ============================================================================
 // Phase 2: Invariant Setup
 // ============================================================================
 
-fn check_custom_invariants(_invariants: &[InvariantRef], _trace_builder: &mut TraceBuilder) {
+fn check_custom_invariants(_invariants: &[ScenarioInvariant], _trace_builder: &mut TraceBuilder) {
     // Custom invariants are now supported via BuiltInInvariant::Custom
     // This function is kept for any pre-flight validation if needed
     // Currently, custom invariants are handled directly in build_invariant_engine
 }
 
-fn build_invariant_engine(invariants: &[InvariantRef]) -> InvariantEngine {
+fn build_invariant_engine(invariants: &[ScenarioInvariant]) -> InvariantEngine {
     let mut engine = InvariantEngine::new();
 
-    if invariants.is_empty() {
-        return engine;
+    for entry in invariants {
+        let builtin = to_builtin_invariant(&entry.invariant);
+        engine.add_invariant_with_severity(builtin.to_evaluator(), entry.effective_severity());
     }
+    engine
+}
 
-    let builtins: Vec<BuiltInInvariant> = invariants
-        .iter()
-        .map(|inv| match inv {
-            InvariantRef::CursorBounds => BuiltInInvariant::CursorBounds,
-            InvariantRef::NoDeadlock { timeout_ms } => {
-                let ticks = timeout_ms.unwrap_or(1000) / 10;
-                BuiltInInvariant::NoDeadlock {
-                    timeout_ticks: ticks.max(10),
-                }
+fn to_builtin_invariant(inv: &InvariantRef) -> BuiltInInvariant {
+    match inv {
+        InvariantRef::CursorBounds => BuiltInInvariant::CursorBounds,
+        InvariantRef::NoDeadlock { timeout_ms } => {
+            let ticks = timeout_ms.unwrap_or(1000) / 10;
+            BuiltInInvariant::NoDeadlock {
+                timeout_ticks: ticks.max(10),
+            }
+        }
+        InvariantRef::SignalHandled { signal } => BuiltInInvariant::SignalHandled {
+            signal: format!("{:?}", signal).to_uppercase(),
+        },
+        InvariantRef::ScreenContains { pattern } => BuiltInInvariant::ScreenContains {
+            pattern: pattern.clone(),
+        },
+        InvariantRef::ScreenNotContains { pattern } => BuiltInInvariant::ScreenNotContains {
+            pattern: pattern.clone(),
+        },
+        InvariantRef::NoOutputAfterExit => BuiltInInvariant::NoOutputAfterExit,
+        InvariantRef::ProcessTerminatedCleanly { allowed_signals } => {
+            BuiltInInvariant::ProcessTerminatedCleanly {
+                allowed_signals: allowed_signals.clone(),
             }
-            InvariantRef::SignalHandled { signal } => BuiltInInvariant::SignalHandled {
+        }
+        InvariantRef::AltScreenRestored => BuiltInInvariant::AltScreenRestored,
+        InvariantRef::ScreenStable { min_ticks } => BuiltInInvariant::ScreenStable {
+            min_ticks: *min_ticks,
+        },
+        InvariantRef::CursorInRegion {
+            top,
+            left,
+            bottom,
+            right,
+        } => BuiltInInvariant::CursorInRegion {
+            top: *top,
+            left: *left,
+            bottom: *bottom,
+            right: *right,
+        },
+        InvariantRef::ViewportValid => BuiltInInvariant::ViewportValid,
+        InvariantRef::NoBell => BuiltInInvariant::NoBell,
+        InvariantRef::NoControlChars => BuiltInInvariant::NoControlChars,
+        InvariantRef::NoPendingEscapeAtAssert => BuiltInInvariant::NoPendingEscapeAtAssert,
+        InvariantRef::ScrollbackBounded { max_lines } => BuiltInInvariant::ScrollbackBounded {
+            max_lines: *max_lines,
+        },
+        InvariantRef::ResponseTime { max_ticks } => BuiltInInvariant::ResponseTime {
+            max_ticks: *max_ticks,
+        },
+        InvariantRef::MaxLatency { max_ticks } => BuiltInInvariant::MaxLatency {
+            max_ticks: *max_ticks,
+        },
+        InvariantRef::Custom {
+            name,
+            pattern,
+            should_contain,
+            expected_row,
+            expected_col,
+            description,
+            message,
+        } => BuiltInInvariant::Custom {
+            name: name.clone(),
+            pattern: pattern.clone(),
+            should_contain: *should_contain,
+            expected_row: *expected_row,
+            expected_col: *expected_col,
+            description: description
+                .clone()
+                .or(Some(format!("Custom invariant: {}", name))),
+            message: message.clone(),
+        },
+        InvariantRef::CustomRegex(spec) => BuiltInInvariant::CustomRegex(spec.clone()),
+        InvariantRef::JsonPath(spec) => BuiltInInvariant::JsonPath(spec.clone()),
+        InvariantRef::SignalHandledCorrectly { signal, behavior } => {
+            BuiltInInvariant::SignalHandledCorrectly {
                 signal: format!("{:?}", signal).to_uppercase(),
-            },
-            InvariantRef::ScreenContains { pattern } => BuiltInInvariant::ScreenContains {
-                pattern: pattern.clone(),
-            },
-            InvariantRef::ScreenNotContains { pattern } => BuiltInInvariant::ScreenNotContains {
-                pattern: pattern.clone(),
-            },
-            InvariantRef::NoOutputAfterExit => BuiltInInvariant::NoOutputAfterExit,
-            InvariantRef::ProcessTerminatedCleanly { allowed_signals } => {
-                BuiltInInvariant::ProcessTerminatedCleanly {
-                    allowed_signals: allowed_signals.clone(),
-                }
+                behavior: behavior.clone(),
             }
-            InvariantRef::ScreenStable { min_ticks } => BuiltInInvariant::ScreenStable {
-                min_ticks: *min_ticks,
-            },
-            InvariantRef::ViewportValid => BuiltInInvariant::ViewportValid,
-            InvariantRef::ResponseTime { max_ticks } => BuiltInInvariant::ResponseTime {
-                max_ticks: *max_ticks,
-            },
-            InvariantRef::MaxLatency { max_ticks } => BuiltInInvariant::MaxLatency {
-                max_ticks: *max_ticks,
-            },
-            InvariantRef::Custom {
-                name,
-                pattern,
-                should_contain,
-                expected_row,
-                expected_col,
-                description,
-            } => BuiltInInvariant::Custom {
-                name: name.clone(),
-                pattern: pattern.clone(),
-                should_contain: *should_contain,
-                expected_row: *expected_row,
-                expected_col: *expected_col,
-                description: description
-                    .clone()
-                    .or(Some(format!("Custom invariant: {}", name))),
-            },
-        })
-        .collect();
-
-    engine.add_builtin_invariants(&builtins);
-    engine
+        }
+        InvariantRef::SignalAcknowledged {
+            signal,
+            within_ticks,
+        } => BuiltInInvariant::SignalAcknowledged {
+            signal: format!("{:?}", signal).to_uppercase(),
+            within_ticks: *within_ticks,
+        },
+        InvariantRef::StartupOutput { within_ticks } => BuiltInInvariant::StartupOutput {
+            within_ticks: *within_ticks,
+        },
+    }
 }
 
 // ============================================================================
@@ -284,6 +680,67 @@ struct LoopState {
     step_index: usize,
     last_screen_hash: Option<u64>,
     no_output_ticks: u64,
+    /// Tick at the end of the last step that changed the screen
+    last_change_tick: u64,
+    /// Most ticks seen between screen changes during the run
+    max_idle_gap_ticks: u64,
+    /// Most recent signal sent by a `send_signal` step (e.g. "SIGTERM")
+    last_signal: Option<String>,
+    /// When that signal was delivered and whether the screen changed since
+    signal_delivery: Option<SignalDelivery>,
+}
+
+/// Which of a scenario's step lists is running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StepPhase {
+    Setup,
+    Main,
+    Teardown,
+}
+
+impl StepPhase {
+    /// Scenario field the phase's steps are listed under
+    fn field(self) -> &'static str {
+        match self {
+            StepPhase::Setup => "setup",
+            StepPhase::Main => "steps",
+            StepPhase::Teardown => "teardown",
+        }
+    }
+}
+
+/// Cursors over the setup, main and teardown steps, in run order
+fn phase_cursors(scenario: &Scenario) -> [(StepPhase, StepCursor<'_>); 3] {
+    [
+        (
+            StepPhase::Setup,
+            StepCursor::over(&scenario.setup, Box::new(std::iter::empty())),
+        ),
+        (StepPhase::Main, StepCursor::new(scenario)),
+        (
+            StepPhase::Teardown,
+            StepCursor::over(&scenario.teardown, Box::new(std::iter::empty())),
+        ),
+    ]
+}
+
+/// The steps a run would execute, one numbered line each, without spawning
+/// anything
+///
+/// `repeat` blocks are expanded and `steps_file` is read in. `if_screen` and
+/// `goto_if` depend on the screen at run time, so they are listed as is.
+pub fn format_step_plan(scenario: &Scenario) -> Result<String, String> {
+    let mut plan = String::new();
+    let mut number = 0;
+    for (phase, mut cursor) in phase_cursors(scenario) {
+        while let Some(next) = cursor.next_step() {
+            let (_, entry) = next?;
+            let step = serde_json::to_string(&entry).map_err(|e| e.to_string())?;
+            number += 1;
+            plan.push_str(&format!("{:>4}  {:<8}  {}\n", number, phase.field(), step));
+        }
+    }
+    Ok(plan)
 }
 
 fn execute_step_loop(
@@ -296,82 +753,208 @@ fn execute_step_loop(
     timing: &mut TimingController,
     trace_builder: &mut TraceBuilder,
     invariant_engine: &mut InvariantEngine,
-) -> (usize, bool, Option<String>, Option<u64>, u64) {
+) -> (LoopState, bool, Option<String>) {
+    let max_ticks = determine_max_ticks(config.max_ticks, scenario.timeout_ms);
     let mut state = LoopState {
         step_index: 0,
         last_screen_hash: None,
         no_output_ticks: 0,
+        last_change_tick: 0,
+        max_idle_gap_ticks: 0,
+        last_signal: None,
+        signal_delivery: None,
     };
     let mut timed_out = false;
     let mut step_error = None;
+    let mut setup_failed = false;
 
     trace_builder.add_checkpoint("initial", scheduler, Some(screen));
 
-    for step in &scenario.steps {
-        // Check timeout - use >= to trigger at exactly max_ticks
-        if scheduler.now() >= config.max_ticks {
-            timed_out = true;
-            break;
+    for (phase, mut cursor) in phase_cursors(scenario) {
+        // Teardown runs no matter what came before, and is not itself
+        // held to the run's tick budget or invariants
+        let teardown = phase == StepPhase::Teardown;
+        if setup_failed && !teardown {
+            continue;
         }
 
-        // Evaluate invariants before step
-        let mut ctx = InvariantContext {
-            screen: Some(screen),
-            process,
-            step: state.step_index,
-            tick: scheduler.now(),
-            _is_replay: false,
-            last_screen_hash: state.last_screen_hash,
-            no_output_ticks: state.no_output_ticks,
-            expected_signal: None,
-        };
-        record_invariant_results(invariant_engine.evaluate(&mut ctx), trace_builder);
-
-        // Execute step and record output
-        trace_builder.start_step(step.clone(), Some(screen), scheduler);
-        step_error = execute_and_record_step(
-            step,
-            process,
-            io,
-            screen,
-            scheduler,
-            timing,
-            config,
-            trace_builder,
-        );
+        while let Some(next) = cursor.next_step() {
+            let (
+                index,
+                ScenarioStep {
+                    step,
+                    timeout_ms,
+                    comment,
+                },
+            ) = match next {
+                Ok(next) => next,
+                Err(e) => {
+                    if !teardown || step_error.is_none() {
+                        step_error = Some(e);
+                    }
+                    break;
+                }
+            };
 
-        // Update screen state tracking
-        let current_hash = screen.state_hash();
-        if Some(current_hash) == state.last_screen_hash {
-            state.no_output_ticks += 1;
-        } else {
-            state.no_output_ticks = 0;
-        }
-        state.last_screen_hash = Some(current_hash);
+            if !teardown {
+                // Check timeout - use >= to trigger at exactly max_ticks
+                if timing.now() >= max_ticks {
+                    timed_out = true;
+                    break;
+                }
 
-        // Record checkpoint
-        trace_builder.end_step(Some(screen), scheduler);
-        trace_builder.add_checkpoint(
-            &format!("after_step_{}", state.step_index),
-            scheduler,
-            Some(screen),
-        );
+                // Evaluate invariants before step
+                let mut ctx = InvariantContext {
+                    screen: Some(screen),
+                    process,
+                    step: state.step_index,
+                    tick: timing.now(),
+                    _is_replay: false,
+                    last_screen_hash: state.last_screen_hash,
+                    no_output_ticks: state.no_output_ticks,
+                    expected_signal: state.last_signal.clone(),
+                    signal_delivery: state.signal_delivery,
+                    asserting: is_screen_assertion(&step),
+                    run_ended: false,
+                };
+                record_invariant_results(invariant_engine.evaluate(&mut ctx), trace_builder);
+            }
+
+            // Execute step and record output
+            let step_tick = timing.now();
+            trace_builder.start_step(step.clone(), Some(screen), scheduler);
+            if let Some(comment) = &comment {
+                trace_builder.record_comment(comment);
+            }
+            if let Some(index) = index {
+                trace_builder.record_source(StepSource {
+                    phase: phase.field().to_string(),
+                    index,
+                });
+            }
+            let mut error = execute_and_record_step(
+                &step,
+                timeout_ms,
+                process,
+                io,
+                screen,
+                scheduler,
+                timing,
+                config,
+                trace_builder,
+            );
+            if let Step::SendSignal { signal } = &step {
+                state.last_signal = Some(format!("{:?}", signal).to_uppercase());
+                if error.is_none() {
+                    trace_builder.record_event(ScheduleEvent::Signal {
+                        signal: signal.to_nix_signal() as i32,
+                        tick: step_tick,
+                    });
+                    state.signal_delivery = Some(SignalDelivery {
+                        tick: step_tick,
+                        elapsed_ticks: 0,
+                        screen_changed: false,
+                    });
+                }
+            }
+            if let (Step::Resize { cols, rows }, None, true) =
+                (&step, &error, process.has_terminal())
+            {
+                trace_builder.record_event(ScheduleEvent::Resize {
+                    cols: *cols,
+                    rows: *rows,
+                    tick: step_tick,
+                });
+            }
+            // A step that overran its own budget stops the run there
+            let mut halt = error.as_deref().is_some_and(is_step_timeout);
+            if !teardown && io.output_limit_exceeded() {
+                trace_builder.record_error(OUTPUT_LIMIT_EXCEEDED);
+                error = Some(OUTPUT_LIMIT_EXCEEDED.to_string());
+                halt = true;
+            }
+            if let Step::GotoIf {
+                label,
+                pattern,
+                max_iterations,
+            } = &step
+            {
+                if error.is_none() {
+                    if let Err(e) =
+                        cursor.goto_if(index, label, pattern, *max_iterations, screen, config)
+                    {
+                        trace_builder.record_error(&e);
+                        error = Some(e);
+                        halt = true;
+                    }
+                }
+            }
+            if let Step::IfScreen {
+                pattern,
+                then,
+                else_,
+            } = &step
+            {
+                if error.is_none() {
+                    if let Err(e) = cursor.if_screen(pattern, then, else_, screen, config) {
+                        trace_builder.record_error(&e);
+                        error = Some(e);
+                    }
+                }
+            }
+
+            // Update screen state tracking
+            let current_hash = screen.state_hash();
+            if let Some(delivery) = state.signal_delivery.as_mut() {
+                delivery.elapsed_ticks = timing.now().saturating_sub(delivery.tick);
+                delivery.screen_changed |=
+                    state.last_screen_hash.is_some_and(|h| h != current_hash);
+            }
+            if Some(current_hash) == state.last_screen_hash {
+                state.no_output_ticks += 1;
+            } else {
+                state.no_output_ticks = 0;
+                state.last_change_tick = timing.now();
+            }
+            state.max_idle_gap_ticks = state
+                .max_idle_gap_ticks
+                .max(timing.now() - state.last_change_tick);
+            state.last_screen_hash = Some(current_hash);
+
+            // Record checkpoint
+            trace_builder.end_step(Some(screen), scheduler);
+            trace_builder.add_checkpoint(
+                &format!("after_step_{}", state.step_index),
+                scheduler,
+                Some(screen),
+            );
+
+            // A teardown failure is reported only if nothing failed before it
+            let failed = error.is_some();
+            if !teardown || step_error.is_none() {
+                step_error = error;
+            }
+            if teardown {
+                state.step_index += 1;
+                continue;
+            }
+            if halt || (phase == StepPhase::Setup && failed) {
+                break;
+            }
+            state.step_index += 1;
 
-        state.step_index += 1;
+            // Check invariant violations
+            if !invariant_engine.all_satisfied() {
+                break;
+            }
+        }
 
-        // Check invariant violations
-        if !invariant_engine.all_satisfied() {
-            break;
+        if phase == StepPhase::Setup {
+            setup_failed = step_error.is_some() || timed_out || !invariant_engine.all_satisfied();
         }
     }
 
-    (
-        state.step_index,
-        timed_out,
-        step_error,
-        state.last_screen_hash,
-        state.no_output_ticks,
-    )
+    (state, timed_out, step_error)
 }
 
 fn record_invariant_results(
@@ -386,8 +969,24 @@ fn record_invariant_results(
     }
 }
 
+const STEP_TIMEOUT_PREFIX: &str = "Step exceeded its timeout_ms";
+
+/// Error for a run stopped by [`RunnerConfig::max_output_bytes`]
+const OUTPUT_LIMIT_EXCEEDED: &str = "output limit exceeded";
+
+fn is_step_timeout(error: &str) -> bool {
+    error.starts_with(STEP_TIMEOUT_PREFIX)
+}
+
+const UNEXPECTED_EXIT_PREFIX: &str = "Expected exit code";
+
+fn is_unexpected_exit(error: &str) -> bool {
+    error.starts_with(UNEXPECTED_EXIT_PREFIX)
+}
+
 fn execute_and_record_step(
     step: &Step,
+    timeout_ms: Option<u64>,
     process: &mut PtyProcess,
     io: &mut IoLoop,
     screen: &mut Screen,
@@ -396,12 +995,35 @@ fn execute_and_record_step(
     config: &RunnerConfig,
     trace_builder: &mut TraceBuilder,
 ) -> Option<String> {
-    let result = execute_step(step, process, io, screen, scheduler, timing, config);
+    // Same conversion as wait timeouts: 10 ms per tick
+    timing.set_budget(timeout_ms.map(|ms| ms / 10));
+    let mut result = execute_step(step, process, io, screen, scheduler, timing, config);
+    if let Step::AssertScreenHash { .. } = step {
+        // The screen is unchanged since the comparison; output fed below
+        // would otherwise end up in what `--update-snapshots` writes back
+        trace_builder.record_asserted_screen_hash(screen.state_hash());
+    }
+    if let Some(ms) = timeout_ms.filter(|_| timing.budget_exceeded()) {
+        // Keep the step's own failure (e.g. a wait timeout) if it has one
+        if !matches!(result, StepResult::Error(_)) {
+            result = StepResult::Error(format!(
+                "{} of {} ms ({} ticks)",
+                STEP_TIMEOUT_PREFIX,
+                ms,
+                ms / 10
+            ));
+        }
+    }
+    timing.set_budget(None);
 
     match result {
         StepResult::Ok => {
             let _ = io.read_available(process);
             let output = io.take_output();
+            // Keep the screen in sync so later steps see this output. The
+            // step's after_screen_hash now includes it, so traces recorded
+            // before this diverge under `replay --verify-steps` or `--live`
+            screen.process(&output);
             trace_builder.record_pty_output(&output);
             None
         }
@@ -409,6 +1031,13 @@ fn execute_and_record_step(
             trace_builder.record_pty_output(&output);
             None
         }
+        StepResult::Skipped(reason) => {
+            if config.verbose {
+                eprintln!("[DEBUG] {}", reason);
+            }
+            trace_builder.record_warning(&reason);
+            None
+        }
         StepResult::Error(e) => {
             trace_builder.record_error(&e);
             Some(e)
@@ -420,25 +1049,81 @@ fn execute_and_record_step(
 // Phase 4: Final Evaluation
 // ============================================================================
 
+/// Check run-level output assertions against everything the process printed
+fn evaluate_output_assertions(
+    assertions: &OutputAssertions,
+    output: &[u8],
+    step: usize,
+    tick: u64,
+) -> Vec<InvariantResult> {
+    let text = String::from_utf8_lossy(output);
+    let mut results = Vec::new();
+
+    for needle in &assertions.must_contain {
+        let found = text.contains(needle.as_str());
+        results.push(InvariantResult::new(
+            "output_must_contain",
+            found,
+            &format!("Output must contain '{}'", needle),
+            (!found).then(|| format!("'{}' never appeared in the output", needle)),
+            step,
+            tick,
+        ));
+    }
+
+    for needle in &assertions.must_not_contain {
+        let position = text.find(needle.as_str());
+        results.push(InvariantResult::new(
+            "output_must_not_contain",
+            position.is_none(),
+            &format!("Output must not contain '{}'", needle),
+            position.map(|pos| format!("'{}' appeared at offset {}", needle, pos)),
+            step,
+            tick,
+        ));
+    }
+
+    results
+}
+
+/// Whether a step checks the screen as it is, without waiting for output
+fn is_screen_assertion(step: &Step) -> bool {
+    matches!(
+        step,
+        Step::AssertScreen { .. }
+            | Step::AssertRegion { .. }
+            | Step::AssertNotScreen { .. }
+            | Step::AssertCursor { .. }
+            | Step::AssertScreenshot { .. }
+            | Step::AssertScreenHash { .. }
+            | Step::AssertSgrState { .. }
+            | Step::AssertCellAttr { .. }
+            | Step::AssertGraphics { .. }
+            | Step::AssertCursorHidden
+            | Step::Snapshot { path: Some(_), .. }
+    )
+}
+
 fn evaluate_final_invariants(
     invariant_engine: &mut InvariantEngine,
     process: &mut PtyProcess,
     screen: &Screen,
-    step_index: usize,
+    state: &LoopState,
     tick: u64,
-    last_screen_hash: Option<u64>,
-    no_output_ticks: u64,
     trace_builder: &mut TraceBuilder,
 ) {
     let mut ctx = InvariantContext {
         screen: Some(screen),
         process,
-        step: step_index,
+        step: state.step_index,
         tick,
         _is_replay: false,
-        last_screen_hash,
-        no_output_ticks,
-        expected_signal: None,
+        last_screen_hash: state.last_screen_hash,
+        no_output_ticks: state.no_output_ticks,
+        expected_signal: state.last_signal.clone(),
+        signal_delivery: state.signal_delivery,
+        asserting: false,
+        run_ended: true,
     };
     for result in invariant_engine.evaluate(&mut ctx) {
         trace_builder.record_invariant_result(result);
@@ -453,6 +1138,7 @@ fn determine_outcome(
     step_error: &Option<String>,
     timed_out: bool,
     invariant_engine: &InvariantEngine,
+    output_results: &[InvariantResult],
     process: &mut PtyProcess,
     max_ticks: u64,
     elapsed_ticks: u64,
@@ -462,6 +1148,7 @@ fn determine_outcome(
         return TraceOutcome::Error {
             message: e.clone(),
             step_index,
+            screen_preview: String::new(),
         };
     }
 
@@ -469,10 +1156,17 @@ fn determine_outcome(
         return TraceOutcome::Timeout {
             max_ticks,
             elapsed_ticks,
+            screen_preview: String::new(),
         };
     }
 
-    if let Some(violation) = invariant_engine.violations().first() {
+    let output_violation = output_results.iter().find(|r| r.is_error());
+    if let Some(violation) = invariant_engine
+        .errors()
+        .first()
+        .copied()
+        .or(output_violation)
+    {
         // We can't access checkpoints from here directly, so use a placeholder
         return TraceOutcome::InvariantViolation {
             invariant_name: violation.name.clone(),
@@ -491,50 +1185,92 @@ fn determine_outcome(
             exit_code: code,
             total_ticks: elapsed_ticks,
         },
-        Some(crate::process::ExitReason::Signaled(sig)) => {
-            use nix::sys::signal::Signal;
-            let signal_name = match Signal::try_from(sig) {
-                Ok(Signal::SIGINT) => "SIGINT",
-                Ok(Signal::SIGKILL) => "SIGKILL",
-                Ok(Signal::SIGTERM) => "SIGTERM",
-                Ok(Signal::SIGHUP) => "SIGHUP",
-                Ok(Signal::SIGQUIT) => "SIGQUIT",
-                Ok(Signal::SIGABRT) => "SIGABRT",
-                Ok(Signal::SIGFPE) => "SIGFPE",
-                Ok(Signal::SIGSEGV) => "SIGSEGV",
-                Ok(Signal::SIGPIPE) => "SIGPIPE",
-                Ok(Signal::SIGALRM) => "SIGALRM",
-                Ok(Signal::SIGUSR1) => "SIGUSR1",
-                Ok(Signal::SIGUSR2) => "SIGUSR2",
-                Ok(Signal::SIGCHLD) => "SIGCHLD",
-                Ok(Signal::SIGCONT) => "SIGCONT",
-                Ok(Signal::SIGSTOP) => "SIGSTOP",
-                Ok(Signal::SIGTSTP) => "SIGTSTP",
-                Ok(Signal::SIGTTIN) => "SIGTTIN",
-                Ok(Signal::SIGTTOU) => "SIGTTOU",
-                Ok(Signal::SIGBUS) => "SIGBUS",
-                Ok(Signal::SIGSYS) => "SIGSYS",
-                Ok(Signal::SIGTRAP) => "SIGTRAP",
-                Ok(Signal::SIGURG) => "SIGURG",
-                Ok(Signal::SIGVTALRM) => "SIGVTALRM",
-                Ok(Signal::SIGXCPU) => "SIGXCPU",
-                Ok(Signal::SIGXFSZ) => "SIGXFSZ",
-                Ok(Signal::SIGWINCH) => "SIGWINCH",
-                _ => "UNKNOWN",
-            }
-            .to_string();
-            TraceOutcome::Signaled {
-                signal: sig,
-                signal_name,
-            }
-        }
+        Some(crate::process::ExitReason::Signaled(sig)) => TraceOutcome::Signaled {
+            signal: sig,
+            signal_name: signal_name(sig),
+        },
         Some(crate::process::ExitReason::Running) | None => TraceOutcome::Error {
             message: "Process did not exit or exit status unavailable".to_string(),
             step_index,
+            screen_preview: String::new(),
         },
     }
 }
 
+/// Attach the start of the screen text to a timeout or error outcome, to
+/// show where the run stalled
+fn with_screen_preview(mut outcome: TraceOutcome, screen: &Screen) -> TraceOutcome {
+    if let TraceOutcome::Timeout { screen_preview, .. }
+    | TraceOutcome::Error { screen_preview, .. } = &mut outcome
+    {
+        *screen_preview = truncate_screen_preview(screen.text().trim_end());
+    }
+    outcome
+}
+
+/// Name of a signal number, e.g. "SIGTERM"
+fn signal_name(sig: i32) -> String {
+    use nix::sys::signal::Signal;
+    match Signal::try_from(sig) {
+        Ok(Signal::SIGINT) => "SIGINT",
+        Ok(Signal::SIGKILL) => "SIGKILL",
+        Ok(Signal::SIGTERM) => "SIGTERM",
+        Ok(Signal::SIGHUP) => "SIGHUP",
+        Ok(Signal::SIGQUIT) => "SIGQUIT",
+        Ok(Signal::SIGABRT) => "SIGABRT",
+        Ok(Signal::SIGFPE) => "SIGFPE",
+        Ok(Signal::SIGSEGV) => "SIGSEGV",
+        Ok(Signal::SIGPIPE) => "SIGPIPE",
+        Ok(Signal::SIGALRM) => "SIGALRM",
+        Ok(Signal::SIGUSR1) => "SIGUSR1",
+        Ok(Signal::SIGUSR2) => "SIGUSR2",
+        Ok(Signal::SIGCHLD) => "SIGCHLD",
+        Ok(Signal::SIGCONT) => "SIGCONT",
+        Ok(Signal::SIGSTOP) => "SIGSTOP",
+        Ok(Signal::SIGTSTP) => "SIGTSTP",
+        Ok(Signal::SIGTTIN) => "SIGTTIN",
+        Ok(Signal::SIGTTOU) => "SIGTTOU",
+        Ok(Signal::SIGBUS) => "SIGBUS",
+        Ok(Signal::SIGSYS) => "SIGSYS",
+        Ok(Signal::SIGTRAP) => "SIGTRAP",
+        Ok(Signal::SIGURG) => "SIGURG",
+        Ok(Signal::SIGVTALRM) => "SIGVTALRM",
+        Ok(Signal::SIGXCPU) => "SIGXCPU",
+        Ok(Signal::SIGXFSZ) => "SIGXFSZ",
+        Ok(Signal::SIGWINCH) => "SIGWINCH",
+        _ => "UNKNOWN",
+    }
+    .to_string()
+}
+
+/// Turn a clean exit with the wrong code into an error when the scenario
+/// sets `expect_exit`
+fn check_expected_exit(
+    outcome: TraceOutcome,
+    expect_exit: Option<i32>,
+    step_index: usize,
+) -> TraceOutcome {
+    match (outcome, expect_exit) {
+        (TraceOutcome::Success { exit_code, .. }, Some(expected)) if exit_code != expected => {
+            TraceOutcome::Error {
+                message: format!("{} {}, got {}", UNEXPECTED_EXIT_PREFIX, expected, exit_code),
+                step_index,
+                screen_preview: String::new(),
+            }
+        }
+        (outcome, _) => outcome,
+    }
+}
+
+/// Exit code reported for a run: 0 when the program exited with the
+/// scenario's expected code, otherwise derived from the outcome
+fn run_exit_code(outcome: &TraceOutcome, expect_exit: Option<i32>) -> i32 {
+    match (outcome, expect_exit) {
+        (TraceOutcome::Success { exit_code, .. }, Some(expected)) if *exit_code == expected => 0,
+        _ => exit_code_from_outcome(outcome),
+    }
+}
+
 fn exit_code_from_outcome(outcome: &TraceOutcome) -> i32 {
     // Use positive exit codes in the reserved range (124-125)
     // Unix exit codes: 0=success, 1=general error, 2= misuse,
@@ -558,10 +1294,27 @@ fn exit_code_from_outcome(outcome: &TraceOutcome) -> i32 {
 // Trace Finalization
 // ============================================================================
 
-fn save_trace(trace: &Trace, path: Option<&str>) {
-    if let Some(p) = path {
+/// Stream steps to the trace file as they finish, for `.jsonl` trace paths
+fn start_trace_stream(trace_builder: &mut TraceBuilder, config: &RunnerConfig) {
+    let Some(path) = config.trace_path.as_deref().map(Path::new) else {
+        return;
+    };
+    if TraceFormat::from_path(path) != TraceFormat::JsonLines {
+        return;
+    }
+    if let Err(e) = trace_builder.stream_to(path, &config.redaction) {
+        eprintln!(
+            "Warning: Failed to stream trace to {}: {}",
+            path.display(),
+            e
+        );
+    }
+}
+
+fn save_trace(trace: &Trace, config: &RunnerConfig) {
+    if let Some(p) = &config.trace_path {
         let path = Path::new(p);
-        if let Err(e) = crate::trace::save_trace(trace, path) {
+        if let Err(e) = crate::trace::save_trace(trace, path, &config.redaction) {
             eprintln!("Warning: Failed to save trace to {}: {}", path.display(), e);
         }
     }
@@ -575,6 +1328,8 @@ enum StepResult {
     Ok,
     Output(Vec<u8>),
     Error(String),
+    /// The step was not run, e.g. it needs a terminal the child lacks
+    Skipped(String),
 }
 
 fn execute_step(
@@ -592,17 +1347,28 @@ fn execute_step(
         Step::WaitFor {
             pattern,
             timeout_ms,
-        } => execute_wait_for(pattern, *timeout_ms, process, io, screen, timing, config),
+            case_insensitive,
+        } => execute_wait_for(
+            &with_case(pattern, *case_insensitive),
+            *timeout_ms,
+            process,
+            io,
+            screen,
+            timing,
+            config,
+        ),
 
         Step::WaitForFuzzy {
             pattern,
             max_distance,
             min_similarity,
             timeout_ms,
+            algorithm,
         } => execute_wait_for_fuzzy(
             pattern,
             *max_distance,
             *min_similarity,
+            *algorithm,
             *timeout_ms,
             process,
             io,
@@ -632,25 +1398,83 @@ fn execute_step(
             }
         }
 
+        Step::Resize { .. } if !process.has_terminal() => {
+            StepResult::Skipped("resize skipped: the process has no PTY".to_string())
+        }
+
         Step::Resize { cols, rows } => match process.resize(*cols, *rows) {
-            Ok(_) => StepResult::Ok,
+            Ok(_) => {
+                // Keep the model the same size as the child's terminal
+                screen.resize(*cols as usize, *rows as usize);
+                StepResult::Ok
+            }
             Err(e) => StepResult::Error(e.to_string()),
         },
 
-        Step::AssertScreen { pattern, .. } => execute_assert_screen(pattern, process, io, screen),
-
-        Step::AssertCursor { row, col } => execute_assert_cursor(screen, *row, *col),
-
-        Step::Snapshot { .. } => StepResult::Ok,
-
-        Step::CheckInvariant { .. } => StepResult::Ok,
-
-        Step::MouseClick {
+        Step::AssertScreen {
+            pattern,
+            anywhere,
             row,
-            col,
-            button,
-            enable_tracking,
-        } => execute_mouse_click(*row, *col, *button, *enable_tracking, &keys),
+            case_insensitive,
+        } => execute_assert_screen(
+            &with_case(pattern, *case_insensitive),
+            *anywhere,
+            *row,
+            process,
+            io,
+            screen,
+            config,
+        ),
+
+        Step::AssertRegion {
+            top,
+            left,
+            bottom,
+            right,
+            pattern,
+        } => execute_assert_region(
+            (*top, *left, *bottom, *right),
+            pattern,
+            process,
+            io,
+            screen,
+            config,
+        ),
+
+        Step::AssertCursor { row, col } => execute_assert_cursor(screen, *row, *col),
+
+        Step::Snapshot {
+            name,
+            path: Some(path),
+        } => {
+            let _ = io.read_available(process);
+            let output = io.take_output();
+            screen.process(&output);
+            match execute_snapshot(name, path, screen, config) {
+                StepResult::Ok => StepResult::Output(output),
+                other => other,
+            }
+        }
+        Step::Snapshot { path: None, .. } => StepResult::Ok,
+
+        Step::CheckInvariant { .. } => StepResult::Ok,
+
+        Step::MouseClick {
+            row,
+            col,
+            button,
+            enable_tracking,
+        } => execute_mouse_click(*row, *col, *button, *enable_tracking, &keys),
+
+        Step::ClickText { pattern, button } => {
+            let _ = io.read_available(process);
+            let output = io.take_output();
+            screen.process(&output);
+            match execute_click_text(pattern, *button, screen, &keys, config) {
+                StepResult::Ok => StepResult::Output(output),
+                other => other,
+            }
+        }
 
         Step::MouseScroll {
             row,
@@ -665,7 +1489,102 @@ fn execute_step(
             timeout_ms,
         } => execute_wait_screen(pattern, *timeout_ms, process, io, screen, timing, config),
 
-        Step::AssertNotScreen { pattern } => execute_assert_not_screen(pattern, screen),
+        Step::WaitForStable {
+            stable_ticks,
+            timeout_ms,
+        } => execute_wait_for_stable(*stable_ticks, *timeout_ms, process, io, screen, timing),
+
+        Step::AssertNotScreen { pattern } => execute_assert_not_screen(pattern, screen, config),
+
+        Step::Expect {
+            wait_for,
+            then_assert,
+            timeout_ms,
+        } => execute_expect(
+            wait_for,
+            then_assert,
+            *timeout_ms,
+            process,
+            io,
+            screen,
+            timing,
+            config,
+        ),
+
+        Step::AssertScreenHash { hash } => execute_assert_screen_hash(*hash, screen, config),
+
+        Step::AssertNoEcho { .. } if !process.has_terminal() => StepResult::Skipped(
+            "assert_no_echo skipped: echo is a PTY setting and the process has none".to_string(),
+        ),
+
+        Step::AssertNoEcho { keys: key_seq } => {
+            execute_assert_no_echo(key_seq, &keys, process, io, screen, timing)
+        }
+
+        Step::AssertGraphics {
+            protocol,
+            min_count,
+        } => {
+            let _ = io.read_available(process);
+            let output = io.take_output();
+            screen.process(&output);
+            let count = screen.graphics().count(*protocol);
+            if count < *min_count {
+                let protocol = protocol.map_or("inline".to_string(), |p| format!("{:?}", p));
+                StepResult::Error(format!(
+                    "Expected at least {} {} image(s), saw {}",
+                    min_count, protocol, count
+                ))
+            } else {
+                StepResult::Output(output)
+            }
+        }
+
+        Step::AssertCursorHidden => {
+            let _ = io.read_available(process);
+            let output = io.take_output();
+            screen.process(&output);
+            if screen.cursor_visible() {
+                StepResult::Error("Cursor is visible; expected it to be hidden".to_string())
+            } else {
+                StepResult::Output(output)
+            }
+        }
+
+        Step::AssertStderr { pattern } => execute_assert_stderr(pattern, process, io, config),
+
+        Step::Label { .. } => StepResult::Ok,
+
+        // Expanded into its inner steps by the step loop
+        Step::Repeat { .. } => StepResult::Ok,
+
+        Step::GotoIf { .. } | Step::IfScreen { .. } => {
+            // Refresh the screen; the step loop picks where to go next
+            let _ = io.read_available(process);
+            let output = io.take_output();
+            screen.process(&output);
+            StepResult::Output(output)
+        }
+
+        Step::AssertSgrState { expected } => {
+            let _ = io.read_available(process);
+            let output = io.take_output();
+            screen.process(&output);
+            match check_sgr_state(expected, screen) {
+                StepResult::Ok => StepResult::Output(output),
+                other => other,
+            }
+        }
+
+        Step::AssertCellAttr { row, col, expected } => {
+            let _ = io.read_available(process);
+            let output = io.take_output();
+            screen.process(&output);
+            match check_cell_attr(*row, *col, expected, screen) {
+                StepResult::Ok => StepResult::Output(output),
+                other => other,
+            }
+        }
 
         Step::TakeScreenshot { path, description } => {
             execute_take_screenshot(path, description.clone(), screen, timing)
@@ -677,78 +1596,29 @@ fn execute_step(
             ignore_regions,
             compare_colors,
             compare_text,
+            ignore_trailing_blanks,
+            ignore_cursor,
+            diff_char,
         } => execute_assert_screenshot(
             path,
             *max_differences,
             ignore_regions.clone(),
             *compare_colors,
             *compare_text,
+            *ignore_trailing_blanks,
+            *ignore_cursor,
+            *diff_char,
             screen,
             timing,
+            config,
         ),
     }
 }
 
-/// Check if a regex pattern might cause catastrophic backtracking
-/// Returns None if safe, Some(message) if potentially unsafe
-fn check_regex_complexity(pattern: &str) -> Option<String> {
-    // Count nesting depth of quantifiers that can cause exponential backtracking
-    let mut nest_level = 0;
-    let mut max_nest = 0;
-    let mut in_bracket = false;
-    let mut prev_char = '\0';
-
-    for c in pattern.chars() {
-        match c {
-            '[' => in_bracket = true,
-            ']' => in_bracket = false,
-            '(' if !in_bracket => {
-                nest_level += 1;
-                max_nest = max_nest.max(nest_level);
-            }
-            ')' if !in_bracket => {
-                if nest_level > 0 {
-                    nest_level -= 1;
-                }
-            }
-            '+' | '*' | '?' if prev_char != '\\' && !in_bracket => {
-                // Quantifier after something - check if we're nested
-                if nest_level > 2 {
-                    return Some(format!(
-                        "Regex has deeply nested quantifiers (nest level {}), which may cause catastrophic backtracking",
-                        nest_level
-                    ));
-                }
-            }
-            '{' if prev_char != '\\' && !in_bracket => {
-                // Could be {n,m} quantifier, check for nesting
-                if nest_level > 2 {
-                    return Some("Regex has nested quantifiers with {} syntax, which may cause catastrophic backtracking".to_string());
-                }
-            }
-            _ => {}
-        }
-        prev_char = c;
-    }
-
-    // Check for obviously problematic patterns
-    let problematic = [
-        r"\(\S+\)\+",      // (something)+
-        r"\[\S+\]\+",      // [something]+
-        r"\(\S+\s*\)\+\?", // Nested optional groups
-        r"^\(\S+\|\)",     // Left-recursive alternation at start
-    ];
-
-    for pat in &problematic {
-        if pattern.contains(pat) {
-            return Some(format!(
-                "Regex contains potentially catastrophic pattern: {}",
-                pat
-            ));
-        }
-    }
-
-    None
+/// Compile a step pattern as [`Scenario::validate_with`] does for this run
+fn compile_pattern(pattern: &str, config: &RunnerConfig) -> Result<Regex, String> {
+    crate::scenario::compile_pattern(pattern, config.lift_regex_size_limit)
+        .map_err(|e| format!("Invalid regex: {}", e))
 }
 
 fn execute_wait_for(
@@ -762,14 +1632,9 @@ fn execute_wait_for(
 ) -> StepResult {
     let timeout_ticks = timeout_ms.unwrap_or(5000) / 10;
 
-    // Check regex complexity before compiling
-    if let Some(msg) = check_regex_complexity(pattern) {
-        return StepResult::Error(format!("Unsafe regex pattern: {}", msg));
-    }
-
-    let regex = match Regex::new(pattern) {
+    let regex = match compile_pattern(pattern, config) {
         Ok(r) => r,
-        Err(e) => return StepResult::Error(format!("Invalid regex: {}", e)),
+        Err(e) => return StepResult::Error(e),
     };
 
     let mut ticks_waited = 0u64;
@@ -780,7 +1645,7 @@ fn execute_wait_for(
     loop {
         if ticks_waited > timeout_ticks {
             let screen_text = screen.text();
-            let preview = truncate_screen_preview(&screen_text);
+            let preview = truncate_screen_preview(screen_text);
             if config.verbose {
                 eprintln!(
                     "[DEBUG] wait_for TIMEOUT: ticks_waited={}, timeout_ticks={}, screen_text_len={}",
@@ -796,7 +1661,7 @@ fn execute_wait_for(
         screen.process(&output);
 
         let screen_text = screen.text();
-        let has_pattern = regex.is_match(&screen_text);
+        let has_pattern = regex.is_match(screen_text);
 
         if has_pattern {
             if config.verbose {
@@ -808,7 +1673,7 @@ fn execute_wait_for(
             return StepResult::Ok;
         }
 
-        let _ = timing.wait_ticks(1);
+        idle_tick(process, io, timing);
         ticks_waited += 1;
 
         if config.verbose && ticks_waited % 50000 == 0 && ticks_waited <= timeout_ticks {
@@ -825,6 +1690,7 @@ fn execute_wait_for_fuzzy(
     pattern: &str,
     max_distance: usize,
     min_similarity: Option<f64>,
+    algorithm: FuzzyAlgorithm,
     timeout_ms: Option<u64>,
     process: &mut PtyProcess,
     io: &mut IoLoop,
@@ -832,7 +1698,7 @@ fn execute_wait_for_fuzzy(
     timing: &mut TimingController,
     config: &RunnerConfig,
 ) -> StepResult {
-    use crate::fuzzy::contains_fuzzy;
+    use crate::fuzzy::contains_fuzzy_with;
 
     let timeout_ticks = timeout_ms.unwrap_or(5000) / 10;
     let effective_max_distance = if let Some(similarity) = min_similarity {
@@ -857,7 +1723,7 @@ fn execute_wait_for_fuzzy(
     loop {
         if ticks_waited > timeout_ticks {
             let screen_text = screen.text();
-            let preview = truncate_screen_preview(&screen_text);
+            let preview = truncate_screen_preview(screen_text);
             if config.verbose {
                 eprintln!(
                     "[DEBUG] wait_for_fuzzy TIMEOUT: ticks_waited={}, pattern='{}'",
@@ -878,7 +1744,9 @@ fn execute_wait_for_fuzzy(
         let screen_text = screen.text();
 
         // Check for fuzzy match
-        if let Some(fuzzy_match) = contains_fuzzy(&screen_text, pattern, effective_max_distance) {
+        if let Some(fuzzy_match) =
+            contains_fuzzy_with(screen_text, pattern, effective_max_distance, algorithm)
+        {
             let actual_similarity = fuzzy_match.similarity;
             let distance = fuzzy_match.distance;
 
@@ -905,11 +1773,22 @@ fn execute_wait_for_fuzzy(
             }
         }
 
-        let _ = timing.wait_ticks(1);
+        idle_tick(process, io, timing);
         ticks_waited += 1;
     }
 }
 
+/// Longest a wait loop blocks on the PTY before its next tick
+const IDLE_POLL_MS: i32 = 1;
+
+/// Advance a wait loop by one tick, first giving the process up to
+/// `IDLE_POLL_MS` of real time to produce output so the tick budget isn't
+/// spent before it has had a chance to run
+fn idle_tick(process: &PtyProcess, io: &IoLoop, timing: &mut TimingController) {
+    let _ = io.poll(process, IDLE_POLL_MS);
+    let _ = timing.wait_ticks(1);
+}
+
 fn truncate_screen_preview(text: &str) -> String {
     if text.len() <= 200 {
         return text.to_string();
@@ -925,30 +1804,92 @@ fn truncate_screen_preview(text: &str) -> String {
     }
 }
 
+/// Prefix a pattern with the `(?i)` flag when matching case-insensitively
+///
+/// Inline flags later in the pattern still apply, so `(?-i)` can opt a part
+/// back into exact case.
+fn with_case(pattern: &str, case_insensitive: bool) -> String {
+    if case_insensitive {
+        format!("(?i){}", pattern)
+    } else {
+        pattern.to_string()
+    }
+}
+
+/// Assert a pattern matches the screen, or only `row` of it
+///
+/// Unless `anywhere` is set the pattern must match a whole line, with
+/// trailing blanks ignored.
 fn execute_assert_screen(
     pattern: &str,
+    anywhere: bool,
+    row: Option<usize>,
     process: &mut PtyProcess,
     io: &mut IoLoop,
     screen: &mut Screen,
+    config: &RunnerConfig,
 ) -> StepResult {
-    // Check regex complexity before compiling
-    if let Some(msg) = check_regex_complexity(pattern) {
-        return StepResult::Error(format!("Unsafe regex pattern: {}", msg));
+    let anchored;
+    let source = if anywhere {
+        pattern
+    } else {
+        anchored = format!("(?m)^(?:{})$", pattern);
+        &anchored
+    };
+    let regex = match compile_pattern(source, config) {
+        Ok(r) => r,
+        Err(e) => return StepResult::Error(e),
+    };
+
+    let _ = io.read_available(process);
+    let output = io.take_output();
+    screen.process(&output);
+
+    let rows = screen.size().1;
+    let matched = match row {
+        Some(row) if row >= rows => {
+            return StepResult::Error(format!("Row {} is outside the {}-row screen", row, rows));
+        }
+        Some(row) if anywhere => regex.is_match(&screen.row_text(row)),
+        Some(row) => regex.is_match(screen.row_text(row).trim_end()),
+        None if anywhere => regex.is_match(screen.text()),
+        None => regex.is_match(&screen.text_trimmed()),
+    };
+    if !matched {
+        return StepResult::Error(match row {
+            Some(row) => format!("Row {} does not match pattern: {}", row, pattern),
+            None => format!("Screen does not match pattern: {}", pattern),
+        });
     }
+    StepResult::Ok
+}
 
-    let regex = match Regex::new(pattern) {
+/// Assert a pattern matches the text of a rectangular screen area
+fn execute_assert_region(
+    (top, left, bottom, right): (usize, usize, usize, usize),
+    pattern: &str,
+    process: &mut PtyProcess,
+    io: &mut IoLoop,
+    screen: &mut Screen,
+    config: &RunnerConfig,
+) -> StepResult {
+    let regex = match compile_pattern(pattern, config) {
         Ok(r) => r,
-        Err(e) => return StepResult::Error(format!("Invalid regex: {}", e)),
+        Err(e) => return StepResult::Error(e),
     };
 
     let _ = io.read_available(process);
     let output = io.take_output();
     screen.process(&output);
 
-    if !regex.is_match(&screen.text()) {
-        return StepResult::Error(format!("Screen does not match pattern: {}", pattern));
+    let text = screen.region_text(top, left, bottom, right);
+    if !regex.is_match(&text) {
+        return StepResult::Error(format!(
+            "Region ({}, {})-({}, {}) does not match pattern: {}\nRegion content:\n{}",
+            top, left, bottom, right, pattern, text
+        ));
     }
-    StepResult::Ok
+    StepResult::Output(output)
 }
 
 fn execute_assert_cursor(screen: &Screen, expected_row: usize, expected_col: usize) -> StepResult {
@@ -984,7 +1925,7 @@ fn execute_mouse_click(
         match enable_mouse_tracking(keys) {
             StepResult::Ok => {}
             StepResult::Error(e) => return StepResult::Error(e),
-            StepResult::Output(_) => unreachable!(),
+            StepResult::Output(_) | StepResult::Skipped(_) => unreachable!(),
         }
     }
 
@@ -1015,6 +1956,24 @@ fn execute_mouse_click(
     }
 }
 
+/// Click the first cell of a pattern's first match on screen
+fn execute_click_text(
+    pattern: &str,
+    button: u8,
+    screen: &Screen,
+    keys: &KeyInjector,
+    config: &RunnerConfig,
+) -> StepResult {
+    if let Err(e) = compile_pattern(pattern, config) {
+        return StepResult::Error(e);
+    }
+
+    let Some((row, col)) = screen.find(pattern) else {
+        return StepResult::Error(format!("Text not found on screen: {}", pattern));
+    };
+    execute_mouse_click(row as u16, col as u16, button, true, keys)
+}
+
 /// Encode a coordinate for SGR mouse protocol.
 ///
 /// Returns a String containing the UTF-8 encoded coordinate:
@@ -1046,7 +2005,7 @@ fn execute_mouse_scroll(
         match enable_mouse_tracking(keys) {
             StepResult::Ok => {}
             StepResult::Error(e) => return StepResult::Error(e),
-            StepResult::Output(_) => unreachable!(),
+            StepResult::Output(_) | StepResult::Skipped(_) => unreachable!(),
         }
     }
 
@@ -1081,25 +2040,65 @@ fn execute_mouse_scroll(
 }
 
 /// Wait for pattern in screen content (checks screen state, not stream)
-fn execute_wait_screen(
-    pattern: &str,
+/// Wait until the screen hash stays the same for `stable_ticks` ticks in a row
+fn execute_wait_for_stable(
+    stable_ticks: u64,
     timeout_ms: Option<u64>,
     process: &mut PtyProcess,
     io: &mut IoLoop,
     screen: &mut Screen,
     timing: &mut TimingController,
-    _config: &RunnerConfig,
 ) -> StepResult {
     let timeout_ticks = timeout_ms.unwrap_or(5000) / 10;
 
-    // Check regex complexity before compiling
-    if let Some(msg) = check_regex_complexity(pattern) {
-        return StepResult::Error(format!("Unsafe regex pattern: {}", msg));
+    let mut ticks_waited = 0u64;
+    let mut unchanged = 0u64;
+    let mut last_hash = screen.state_hash();
+
+    loop {
+        let _ = io.read_available(process);
+        let output = io.take_output();
+        screen.process(&output);
+
+        let hash = screen.state_hash();
+        if hash == last_hash {
+            unchanged += 1;
+        } else {
+            unchanged = 0;
+            last_hash = hash;
+        }
+        if unchanged >= stable_ticks {
+            return StepResult::Ok;
+        }
+
+        if ticks_waited >= timeout_ticks {
+            return StepResult::Error(format!(
+                "Screen still changing after {} ticks (needed {} stable). Screen preview:\n{}",
+                ticks_waited,
+                stable_ticks,
+                truncate_screen_preview(screen.text())
+            ));
+        }
+
+        idle_tick(process, io, timing);
+        ticks_waited += 1;
     }
+}
+
+fn execute_wait_screen(
+    pattern: &str,
+    timeout_ms: Option<u64>,
+    process: &mut PtyProcess,
+    io: &mut IoLoop,
+    screen: &mut Screen,
+    timing: &mut TimingController,
+    config: &RunnerConfig,
+) -> StepResult {
+    let timeout_ticks = timeout_ms.unwrap_or(5000) / 10;
 
-    let regex = match Regex::new(pattern) {
+    let regex = match compile_pattern(pattern, config) {
         Ok(r) => r,
-        Err(e) => return StepResult::Error(format!("Invalid regex: {}", e)),
+        Err(e) => return StepResult::Error(e),
     };
 
     let mut ticks_waited = 0u64;
@@ -1111,14 +2110,14 @@ fn execute_wait_screen(
         screen.process(&output);
 
         // Check if pattern is in screen content
-        if regex.is_match(&screen.text()) {
+        if regex.is_match(screen.text()) {
             return StepResult::Ok;
         }
 
         // Check timeout
         if ticks_waited >= timeout_ticks {
             let screen_text = screen.text();
-            let preview = truncate_screen_preview(&screen_text);
+            let preview = truncate_screen_preview(screen_text);
             return StepResult::Error(format!(
                 "wait_screen timeout after {} ticks. Screen preview:\n{}",
                 ticks_waited, preview
@@ -1126,14 +2125,63 @@ fn execute_wait_screen(
         }
 
         // Wait for next tick
-        let _ = timing.wait_ticks(1);
+        idle_tick(process, io, timing);
         ticks_waited += 1;
     }
 }
 
-/// Assert screen does NOT contain pattern
-fn execute_assert_not_screen(pattern: &str, screen: &Screen) -> StepResult {
-    if screen.text().contains(pattern) {
+/// Wait for `wait_for`, then check every `then_assert` pattern against the
+/// screen it appeared on, reporting all failed assertions together
+fn execute_expect(
+    wait_for: &str,
+    then_assert: &[String],
+    timeout_ms: Option<u64>,
+    process: &mut PtyProcess,
+    io: &mut IoLoop,
+    screen: &mut Screen,
+    timing: &mut TimingController,
+    config: &RunnerConfig,
+) -> StepResult {
+    if let StepResult::Error(e) =
+        execute_wait_for(wait_for, timeout_ms, process, io, screen, timing, config)
+    {
+        return StepResult::Error(e);
+    }
+
+    let failures: Vec<String> = then_assert
+        .iter()
+        .filter_map(|pattern| {
+            match execute_assert_screen(pattern, true, None, process, io, screen, config) {
+                StepResult::Error(e) => Some(e),
+                _ => None,
+            }
+        })
+        .collect();
+    if !failures.is_empty() {
+        return StepResult::Error(format!(
+            "expect: '{}' appeared but {} of {} assertions failed:\n  {}",
+            wait_for,
+            failures.len(),
+            then_assert.len(),
+            failures.join("\n  ")
+        ));
+    }
+    StepResult::Ok
+}
+
+/// Assert screen does NOT match pattern
+///
+/// Patterns without regex metacharacters are checked as plain substrings.
+fn execute_assert_not_screen(pattern: &str, screen: &Screen, config: &RunnerConfig) -> StepResult {
+    let found = if regex::escape(pattern) == pattern {
+        screen.text().contains(pattern)
+    } else {
+        match compile_pattern(pattern, config) {
+            Ok(regex) => regex.is_match(screen.text()),
+            Err(e) => return StepResult::Error(e),
+        }
+    };
+    if found {
         return StepResult::Error(format!(
             "Screen contains pattern '{}' but should not",
             pattern
@@ -1142,49 +2190,268 @@ fn execute_assert_not_screen(pattern: &str, screen: &Screen) -> StepResult {
     StepResult::Ok
 }
 
-/// Take a screenshot of the current screen state
-fn execute_take_screenshot(
-    path: &str,
-    description: Option<String>,
-    screen: &Screen,
-    timing: &TimingController,
+/// Assert the screen state hash equals a pinned value
+///
+/// In update mode a mismatch is reported but accepted, so the new hash can be
+/// collected from the trace with [`screen_hash_updates`].
+fn execute_assert_stderr(
+    pattern: &str,
+    process: &mut PtyProcess,
+    io: &mut IoLoop,
+    config: &RunnerConfig,
 ) -> StepResult {
-    use crate::screenshot::Screenshot;
-    use std::fs;
+    let regex = match compile_pattern(pattern, config) {
+        Ok(r) => r,
+        Err(e) => return StepResult::Error(e),
+    };
 
-    // Validate path to prevent path traversal attacks
-    let path_obj = std::path::Path::new(path);
-    if path_obj.is_absolute() {
-        // Allow absolute paths but warn about security implications
-        // In production, you might want to restrict this to a sandbox directory
-    } else if path_obj.starts_with("..") {
-        return StepResult::Error(
-            "Path traversal (../) is not allowed for screenshots".to_string(),
-        );
+    // Stdout read here stays buffered for the next screen update
+    let _ = io.read_available(process);
+    if !regex.is_match(&String::from_utf8_lossy(io.stderr())) {
+        return StepResult::Error(format!("Stderr does not match pattern: {}", pattern));
+    }
+    StepResult::Ok
+}
+
+/// Screen text as stored in a snapshot file: trailing blanks trimmed from
+/// each row and trailing empty rows dropped
+fn snapshot_text(screen: &Screen) -> String {
+    let text = screen.text_trimmed();
+    if text.is_empty() {
+        text
+    } else {
+        text + "\n"
     }
+}
 
-    // Normalize and check for embedded path traversal
-    let normalized = match path_obj.canonicalize() {
-        Ok(p) => p,
-        Err(_) => {
-            // If canonicalize fails, do basic validation
-            let clean = std::path::Path::new(path).components().fold(
-                std::path::PathBuf::new(),
-                |mut acc, comp| {
-                    if comp == std::path::Component::ParentDir {
-                        acc.pop();
-                    } else {
-                        acc.push(comp);
-                    }
-                    acc
-                },
-            );
-            if clean.to_string_lossy().contains("..") {
-                return StepResult::Error(
-                    "Path traversal (../) is not allowed for screenshots".to_string(),
-                );
+/// Compare the screen against a golden text file, writing the file when it
+/// doesn't exist yet or snapshots are being updated
+fn execute_snapshot(name: &str, path: &str, screen: &Screen, config: &RunnerConfig) -> StepResult {
+    let path = std::path::Path::new(path);
+    if path.is_relative()
+        && path
+            .components()
+            .any(|c| c == std::path::Component::ParentDir)
+    {
+        return StepResult::Error("Path traversal (../) is not allowed for snapshots".to_string());
+    }
+
+    let actual = snapshot_text(screen);
+    if config.update_snapshots || !path.exists() {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            if let Err(e) = std::fs::create_dir_all(dir) {
+                return StepResult::Error(format!("Failed to create snapshot directory: {}", e));
             }
-            clean
+        }
+        return match std::fs::write(path, &actual) {
+            Ok(()) => StepResult::Ok,
+            Err(e) => StepResult::Error(format!("Failed to write snapshot: {}", e)),
+        };
+    }
+
+    let expected = match std::fs::read_to_string(path) {
+        Ok(expected) => expected,
+        Err(e) => return StepResult::Error(format!("Failed to read snapshot: {}", e)),
+    };
+    if expected == actual {
+        return StepResult::Ok;
+    }
+    let diff = crate::diff::unified_diff(&expected, &actual, &path.display().to_string(), "screen");
+    StepResult::Error(format!(
+        "Snapshot '{}' does not match {}:\n{}",
+        name,
+        path.display(),
+        diff
+    ))
+}
+
+fn execute_assert_screen_hash(expected: u64, screen: &Screen, config: &RunnerConfig) -> StepResult {
+    let actual = screen.state_hash();
+    if actual == expected {
+        return StepResult::Ok;
+    }
+    if config.update_snapshots {
+        if config.verbose {
+            eprintln!(
+                "[DEBUG] assert_screen_hash: updating {} -> {}",
+                expected, actual
+            );
+        }
+        return StepResult::Ok;
+    }
+    StepResult::Error(format!(
+        "Screen hash mismatch: expected {}, got {}",
+        expected, actual
+    ))
+}
+
+/// Compare the screen's current rendition against an expected SGR state
+fn check_sgr_state(expected: &SgrState, screen: &Screen) -> StepResult {
+    let expected = expected.to_attrs();
+    let actual = screen.current_attrs();
+    if actual == expected {
+        return StepResult::Ok;
+    }
+    StepResult::Error(format!(
+        "SGR state mismatch: expected {:?}, got {:?}",
+        expected, actual
+    ))
+}
+
+/// Compare the checked attributes of one cell against expectations
+fn check_cell_attr(
+    row: usize,
+    col: usize,
+    expected: &CellAttrCheck,
+    screen: &Screen,
+) -> StepResult {
+    let Some(cell) = screen.get_cell(row, col) else {
+        return StepResult::Error(format!("Cell ({}, {}) is off screen", row, col));
+    };
+    let mismatches = expected.mismatches(&cell.attrs);
+    if mismatches.is_empty() {
+        return StepResult::Ok;
+    }
+    StepResult::Error(format!(
+        "Cell ({}, {}) '{}' attribute mismatch: {}",
+        row,
+        col,
+        cell.ch,
+        mismatches.join(", ")
+    ))
+}
+
+/// Ticks to keep reading after typing, giving the line discipline time to echo
+const ECHO_SETTLE_TICKS: u64 = 10;
+
+/// Type keys and fail if the terminal echoes them
+///
+/// Fails if the `ECHO` termios flag is set when typing, or if the printable
+/// part of the keys shows up on screen more often than before typing.
+fn execute_assert_no_echo(
+    key_seq: &KeySequence,
+    keys: &KeyInjector,
+    process: &PtyProcess,
+    io: &mut IoLoop,
+    screen: &mut Screen,
+    timing: &mut TimingController,
+) -> StepResult {
+    let echo_enabled = process.echo_enabled().unwrap_or(false);
+
+    let typed: String = match key_seq {
+        KeySequence::Text(text) => text.chars().filter(|c| !c.is_control()).collect(),
+        KeySequence::Special(_) => String::new(),
+    };
+    let count = |screen: &Screen| {
+        if typed.is_empty() {
+            0
+        } else {
+            screen.text().matches(typed.as_str()).count()
+        }
+    };
+    let before = count(screen);
+
+    if let Err(e) = keys.inject_raw(&key_seq.to_bytes()) {
+        return StepResult::Error(e.to_string());
+    }
+
+    let mut output = Vec::new();
+    for _ in 0..ECHO_SETTLE_TICKS {
+        let _ = io.read_available(process);
+        let chunk = io.take_output();
+        screen.process(&chunk);
+        output.extend(chunk);
+        idle_tick(process, io, timing);
+    }
+
+    if echo_enabled {
+        return StepResult::Error(
+            "Input echo is enabled on the terminal (ECHO flag set)".to_string(),
+        );
+    }
+    if count(screen) > before {
+        return StepResult::Error(format!("Typed keys were echoed to the screen: {:?}", typed));
+    }
+    StepResult::Output(output)
+}
+
+/// Collect `assert_screen_hash` steps whose pinned hash differs from the observed one
+///
+/// Returns `(source, observed_hash)` pairs suitable for
+/// [`crate::scenario::rewrite_screen_hashes`]. A step revisited by `goto_if`
+/// keeps what it saw last. Mismatching steps from a `repeat` or `if_screen`
+/// block or a `steps_file` have no single place to write to and are an error.
+pub fn screen_hash_updates(trace: &Trace) -> Result<Vec<(StepSource, u64)>, String> {
+    let mut updates: Vec<(StepSource, u64)> = Vec::new();
+    for step in &trace.steps {
+        let (Step::AssertScreenHash { hash }, Some(actual)) =
+            (&step.step, step.asserted_screen_hash)
+        else {
+            continue;
+        };
+        match &step.source {
+            Some(source) => {
+                updates.retain(|(s, _)| s != source);
+                if *hash != actual {
+                    updates.push((source.clone(), actual));
+                }
+            }
+            None if *hash != actual => {
+                return Err(format!(
+                    "Step {} comes from a repeat or if_screen block or a steps_file; \
+                     update its hash by hand to {}",
+                    step.index, actual
+                ));
+            }
+            None => {}
+        }
+    }
+    Ok(updates)
+}
+
+/// Take a screenshot of the current screen state
+fn execute_take_screenshot(
+    path: &str,
+    description: Option<String>,
+    screen: &Screen,
+    timing: &TimingController,
+) -> StepResult {
+    use crate::screenshot::Screenshot;
+    use std::fs;
+
+    // Validate path to prevent path traversal attacks
+    let path_obj = std::path::Path::new(path);
+    if path_obj.is_absolute() {
+        // Allow absolute paths but warn about security implications
+        // In production, you might want to restrict this to a sandbox directory
+    } else if path_obj.starts_with("..") {
+        return StepResult::Error(
+            "Path traversal (../) is not allowed for screenshots".to_string(),
+        );
+    }
+
+    // Normalize and check for embedded path traversal
+    let normalized = match path_obj.canonicalize() {
+        Ok(p) => p,
+        Err(_) => {
+            // If canonicalize fails, do basic validation
+            let clean = std::path::Path::new(path).components().fold(
+                std::path::PathBuf::new(),
+                |mut acc, comp| {
+                    if comp == std::path::Component::ParentDir {
+                        acc.pop();
+                    } else {
+                        acc.push(comp);
+                    }
+                    acc
+                },
+            );
+            if clean.to_string_lossy().contains("..") {
+                return StepResult::Error(
+                    "Path traversal (../) is not allowed for screenshots".to_string(),
+                );
+            }
+            clean
         }
     };
 
@@ -1238,21 +2505,44 @@ fn execute_take_screenshot(
     StepResult::Ok
 }
 
+/// Rewrite a screenshot baseline with the current screen
+fn update_screenshot_baseline(
+    path: &str,
+    screen: &Screen,
+    timing: &TimingController,
+) -> StepResult {
+    let result = execute_take_screenshot(path, None, screen, timing);
+    if matches!(result, StepResult::Ok) {
+        eprintln!("Updated screenshot baseline: {}", path);
+    }
+    result
+}
+
 /// Assert screen matches a baseline screenshot
+///
+/// With [`RunnerConfig::update_baselines`], a mismatching or missing baseline
+/// is replaced by the current screen instead of failing.
 fn execute_assert_screenshot(
     path: &str,
     max_differences: usize,
     ignore_regions: Vec<crate::scenario::IgnoreRegionConfig>,
     compare_colors: bool,
     compare_text: bool,
+    ignore_trailing_blanks: bool,
+    ignore_cursor: bool,
+    diff_char: Option<char>,
     screen: &Screen,
     timing: &TimingController,
+    config: &RunnerConfig,
 ) -> StepResult {
-    use crate::screenshot::{compare_screenshots, DiffConfig, IgnoreRegion, Screenshot};
+    use crate::screenshot::{compare_screenshots, diff_grid, DiffConfig, IgnoreRegion, Screenshot};
 
     // Load baseline screenshot
     let content = match std::fs::read_to_string(path) {
         Ok(c) => c,
+        Err(_) if config.update_baselines => {
+            return update_screenshot_baseline(path, screen, timing);
+        }
         Err(e) => {
             return StepResult::Error(format!("Failed to read baseline screenshot: {}", e));
         }
@@ -1275,24 +2565,29 @@ fn execute_assert_screenshot(
         ignore_regions_list.push(IgnoreRegion::new(r.top, r.left, r.bottom, r.right));
     }
 
-    let config = DiffConfig {
+    let diff_config = DiffConfig {
         ignore_regions: ignore_regions_list,
         max_differences,
         compare_colors,
         compare_text,
-        compare_cursor: true,
-        diff_char: '?',
+        compare_cursor: !ignore_cursor,
+        ignore_trailing_blanks,
+        diff_char: diff_char.unwrap_or('?'),
     };
 
-    let result = compare_screenshots(&baseline, &actual, &config);
+    let result = compare_screenshots(&baseline, &actual, &diff_config);
 
     if result.matches {
         StepResult::Ok
+    } else if config.update_baselines {
+        update_screenshot_baseline(path, screen, timing)
     } else {
         let mut error_msg = format!(
-            "Screenshot mismatch: {} different cells, similarity={:.2}%",
+            "Screenshot mismatch: {} different cells in {} region(s), similarity={:.2}%, structural={:.2}%",
             result.different_cells,
-            result.similarity * 100.0
+            result.regions.len(),
+            result.similarity * 100.0,
+            result.structural_similarity * 100.0
         );
 
         if result.size_mismatch {
@@ -1309,6 +2604,11 @@ fn execute_assert_screenshot(
             ));
         }
 
+        if diff_char.is_some() && !result.size_mismatch {
+            error_msg.push_str(":\n");
+            error_msg.push_str(&diff_grid(&baseline, &result, &diff_config));
+        }
+
         StepResult::Error(error_msg)
     }
 }
@@ -1333,6 +2633,87 @@ pub fn replay_trace(trace: &crate::trace::Trace, _config: &RunnerConfig) -> Resu
     }
 }
 
+/// Re-drive a recorded trace and verify every step's screen hashes
+///
+/// The scenario is executed again with the recorded seed, and each step's
+/// before/after screen hash is compared against the recorded `TraceStep`.
+/// Returns the index of the first diverging step, if any.
+pub fn replay_trace_steps(
+    trace: &crate::trace::Trace,
+    config: &RunnerConfig,
+    halt_on_divergence: bool,
+) -> Result<Option<usize>, String> {
+    if config.timing_mode == TimingMode::RealTime {
+        return Err(REAL_TIME_REPLAY_ERROR.to_string());
+    }
+    let replay_config = RunnerConfig {
+        seed: Some(trace.seed),
+        trace_path: None,
+        ..config.clone()
+    };
+    let rerun = run_scenario(&trace.scenario, &replay_config);
+
+    let mut replay = crate::trace::ReplayEngine::new(trace);
+    replay.set_halt_on_divergence(halt_on_divergence);
+    let first_divergence = replay.verify_steps(&rerun.trace.steps);
+
+    if first_divergence.is_some() && trace.is_older_format() {
+        return Err(trace.older_format_error());
+    }
+    for div in replay.divergences() {
+        eprintln!("Divergence at step {}: {:?}", div.step_index, div.kind);
+        eprintln!("  Expected: {}", div.expected);
+        eprintln!("  Actual: {}", div.actual);
+        eprintln!("  Context: {}", div.context);
+    }
+
+    if rerun.trace.steps.len() != trace.steps.len() && first_divergence.is_none() {
+        return Err(format!(
+            "Replay executed {} steps, trace recorded {}",
+            rerun.trace.steps.len(),
+            trace.steps.len()
+        ));
+    }
+
+    Ok(first_divergence)
+}
+
+/// Replays depend on tick-for-tick reproducible runs
+const REAL_TIME_REPLAY_ERROR: &str =
+    "Replay needs deterministic timing; real-time runs are not reproducible";
+
+/// Re-execute a trace's scenario and compare the live run against it
+///
+/// The process is spawned again with the trace's seed. Returns the live
+/// run's outcome if every checkpoint screen hash and the exit status match,
+/// or a `ReplayDivergence` outcome describing the first mismatch. Runs that
+/// can't be replayed, and older-format traces that diverge, are an error.
+pub fn replay_live(
+    trace: &crate::trace::Trace,
+    config: &RunnerConfig,
+) -> Result<TraceOutcome, String> {
+    if config.timing_mode == TimingMode::RealTime {
+        return Err(REAL_TIME_REPLAY_ERROR.to_string());
+    }
+    let replay_config = RunnerConfig {
+        seed: Some(trace.seed),
+        trace_path: None,
+        ..config.clone()
+    };
+    let rerun = run_scenario(&trace.scenario, &replay_config);
+
+    let mut replay = crate::trace::ReplayEngine::new(trace);
+    match replay.verify_live(&rerun.trace) {
+        Some(_) if trace.is_older_format() => Err(trace.older_format_error()),
+        Some(div) => Ok(TraceOutcome::ReplayDivergence {
+            expected: div.expected,
+            actual: div.actual,
+            context: format!("Step {}: {}", div.step_index, div.context),
+        }),
+        None => Ok(rerun.trace.outcome),
+    }
+}
+
 // ============================================================================
 // Tests
 // ============================================================================
@@ -1341,6 +2722,7 @@ pub fn replay_trace(trace: &crate::trace::Trace, _config: &RunnerConfig) -> Resu
 mod tests {
     use super::*;
     use crate::scenario::{Command, TerminalConfig};
+    use crate::screen::Color;
     use std::collections::HashMap;
     use tempfile::TempDir;
 
@@ -1352,19 +2734,21 @@ mod tests {
             command: Command::Simple("echo hello".to_string()),
             terminal: TerminalConfig::default(),
             env: HashMap::new(),
-            steps: vec![Step::WaitTicks { ticks: 10 }],
+            steps: vec![Step::WaitTicks { ticks: 10 }.into()],
             invariants: vec![],
             seed: Some(42),
             timeout_ms: Some(1000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let config = RunnerConfig {
             trace_path: None,
             verbose: false,
-            max_ticks: 1000,
+            max_ticks: Some(1000),
             tick_delay_ms: 0,
             seed: Some(42),
+            ..RunnerConfig::default()
         };
 
         let result = run_scenario(&scenario, &config);
@@ -1384,25 +2768,31 @@ mod tests {
                 Step::WaitFor {
                     pattern: "test".to_string(),
                     timeout_ms: Some(1000),
-                },
+                    case_insensitive: false,
+                }
+                .into(),
                 Step::AssertScreen {
                     pattern: "test".to_string(),
                     anywhere: true,
                     row: None,
-                },
+                    case_insensitive: false,
+                }
+                .into(),
             ],
-            invariants: vec![crate::scenario::InvariantRef::CursorBounds],
+            invariants: vec![crate::scenario::InvariantRef::CursorBounds.into()],
             seed: Some(42),
             timeout_ms: Some(1000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let config = RunnerConfig {
             trace_path: None,
             verbose: false,
-            max_ticks: 1000,
+            max_ticks: Some(1000),
             tick_delay_ms: 0,
             seed: Some(42),
+            ..RunnerConfig::default()
         };
 
         let result = run_scenario(&scenario, &config);
@@ -1423,6 +2813,7 @@ mod tests {
             seed: Some(42),
             timeout_ms: Some(1000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         // Use a unique temp file instead of hardcoded path to avoid race conditions
@@ -1432,9 +2823,10 @@ mod tests {
         let config = RunnerConfig {
             trace_path: Some(temp_path.to_string_lossy().to_string()),
             verbose: false,
-            max_ticks: 1000,
+            max_ticks: Some(1000),
             tick_delay_ms: 0,
             seed: Some(42),
+            ..RunnerConfig::default()
         };
 
         let result = run_scenario(&scenario, &config);
@@ -1459,24 +2851,28 @@ mod tests {
             steps: vec![Step::WaitFor {
                 pattern: "hello_world".to_string(),
                 timeout_ms: Some(5000),
-            }],
+                case_insensitive: false,
+            }
+            .into()],
             invariants: vec![],
             seed: Some(42),
             timeout_ms: Some(1000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let config = RunnerConfig {
             trace_path: None,
             verbose: false,
-            max_ticks: 1000,
+            max_ticks: Some(1000),
             tick_delay_ms: 0,
             seed: Some(42),
+            ..RunnerConfig::default()
         };
 
         let result = run_scenario(&scenario, &config);
         assert_eq!(result.exit_code, 0, "Echo should succeed with exit code 0");
-        assert!(result.trace.steps.len() >= 1);
+        assert!(!result.trace.steps.is_empty());
     }
 
     #[test]
@@ -1493,30 +2889,36 @@ mod tests {
             env: HashMap::new(),
             steps: vec![
                 // Wait for process to initialize and read command to be ready
-                Step::WaitTicks { ticks: 50 },
+                Step::WaitTicks { ticks: 50 }.into(),
                 Step::SendKeys {
                     keys: KeySequence::Text("test_value".to_string()),
-                },
+                }
+                .into(),
                 Step::SendKeys {
                     keys: KeySequence::Text("\n".to_string()),
-                },
+                }
+                .into(),
                 Step::WaitFor {
                     pattern: "test_value".to_string(),
                     timeout_ms: Some(5000),
-                },
+                    case_insensitive: false,
+                }
+                .into(),
             ],
             invariants: vec![],
             seed: Some(42),
             timeout_ms: Some(10000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let config = RunnerConfig {
             trace_path: None,
             verbose: false,
-            max_ticks: 10000,
+            max_ticks: Some(10000),
             tick_delay_ms: 0,
             seed: Some(42),
+            ..RunnerConfig::default()
         };
 
         let result = run_scenario(&scenario, &config);
@@ -1535,21 +2937,24 @@ mod tests {
                 Step::Resize {
                     cols: 120,
                     rows: 40,
-                },
-                Step::WaitTicks { ticks: 5 },
+                }
+                .into(),
+                Step::WaitTicks { ticks: 5 }.into(),
             ],
             invariants: vec![],
             seed: Some(42),
             timeout_ms: Some(1000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let config = RunnerConfig {
             trace_path: None,
             verbose: false,
-            max_ticks: 1000,
+            max_ticks: Some(1000),
             tick_delay_ms: 0,
             seed: Some(42),
+            ..RunnerConfig::default()
         };
 
         let result = run_scenario(&scenario, &config);
@@ -1566,24 +2971,27 @@ mod tests {
             terminal: TerminalConfig::default(),
             env: HashMap::new(),
             steps: vec![
-                Step::WaitTicks { ticks: 10 },
+                Step::WaitTicks { ticks: 10 }.into(),
                 Step::SendSignal {
                     signal: SignalName::Sigterm,
-                },
-                Step::WaitTicks { ticks: 5 },
+                }
+                .into(),
+                Step::WaitTicks { ticks: 5 }.into(),
             ],
             invariants: vec![],
             seed: Some(42),
             timeout_ms: Some(1000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let config = RunnerConfig {
             trace_path: None,
             verbose: false,
-            max_ticks: 1000,
+            max_ticks: Some(1000),
             tick_delay_ms: 0,
             seed: Some(42),
+            ..RunnerConfig::default()
         };
 
         let result = run_scenario(&scenario, &config);
@@ -1605,19 +3013,23 @@ mod tests {
             steps: vec![Step::WaitFor {
                 pattern: "line2".to_string(),
                 timeout_ms: Some(5000),
-            }],
+                case_insensitive: false,
+            }
+            .into()],
             invariants: vec![],
             seed: Some(42),
             timeout_ms: Some(1000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let config = RunnerConfig {
             trace_path: None,
             verbose: false,
-            max_ticks: 1000,
+            max_ticks: Some(1000),
             tick_delay_ms: 0,
             seed: Some(42),
+            ..RunnerConfig::default()
         };
 
         let result = run_scenario(&scenario, &config);
@@ -1635,26 +3047,31 @@ mod tests {
             steps: vec![Step::WaitFor {
                 pattern: "test".to_string(),
                 timeout_ms: Some(5000),
-            }],
+                case_insensitive: false,
+            }
+            .into()],
             invariants: vec![],
             seed: Some(42),
             timeout_ms: Some(1000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let config1 = RunnerConfig {
             trace_path: None,
             verbose: false,
-            max_ticks: 1000,
+            max_ticks: Some(1000),
             tick_delay_ms: 0,
             seed: Some(42),
+            ..RunnerConfig::default()
         };
         let config2 = RunnerConfig {
             trace_path: None,
             verbose: false,
-            max_ticks: 1000,
+            max_ticks: Some(1000),
             tick_delay_ms: 0,
             seed: Some(42),
+            ..RunnerConfig::default()
         };
 
         let result1 = run_scenario(&scenario, &config1);
@@ -1681,26 +3098,30 @@ mod tests {
             steps: vec![Step::WaitFor {
                 pattern: "test".to_string(),
                 timeout_ms: Some(5000),
-            }],
+                case_insensitive: false,
+            }
+            .into()],
             invariants: vec![],
             seed: Some(42),
             timeout_ms: Some(1000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let config = RunnerConfig {
             trace_path: None,
             verbose: false,
-            max_ticks: 1000,
+            max_ticks: Some(1000),
             tick_delay_ms: 0,
             seed: Some(42),
+            ..RunnerConfig::default()
         };
 
         let result = run_scenario(&scenario, &config);
 
         assert!(result.exit_code == 0);
         assert!(!result.trace.version.is_empty());
-        assert!(result.trace.steps.len() >= 1);
+        assert!(!result.trace.steps.is_empty());
     }
 
     #[test]
@@ -1715,25 +3136,31 @@ mod tests {
                 Step::WaitFor {
                     pattern: "expected_content".to_string(),
                     timeout_ms: Some(5000),
-                },
+                    case_insensitive: false,
+                }
+                .into(),
                 Step::AssertScreen {
                     pattern: "expected_content".to_string(),
                     anywhere: true,
                     row: None,
-                },
+                    case_insensitive: false,
+                }
+                .into(),
             ],
             invariants: vec![],
             seed: Some(42),
             timeout_ms: Some(1000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let config = RunnerConfig {
             trace_path: None,
             verbose: false,
-            max_ticks: 1000,
+            max_ticks: Some(1000),
             tick_delay_ms: 0,
             seed: Some(42),
+            ..RunnerConfig::default()
         };
 
         let result = run_scenario(&scenario, &config);
@@ -1742,4 +3169,2296 @@ mod tests {
             "Assert screen should pass when pattern exists"
         );
     }
+
+    fn screen_hash_scenario(hash: u64) -> Scenario {
+        Scenario {
+            name: "pinned hash".to_string(),
+            command: Command::Simple("true".to_string()),
+            steps: vec![Step::AssertScreenHash { hash }.into()],
+            seed: Some(42),
+            timeout_ms: Some(1000),
+            ..Scenario::default()
+        }
+    }
+
+    #[test]
+    fn assert_screen_hash_pinned() {
+        let blank = Screen::new(80, 24).state_hash();
+        let config = RunnerConfig {
+            seed: Some(42),
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&screen_hash_scenario(blank), &config);
+        assert!(result.trace.steps[0].error.is_none());
+        assert!(screen_hash_updates(&result.trace).unwrap().is_empty());
+
+        let result = run_scenario(&screen_hash_scenario(blank ^ 1), &config);
+        let error = result.trace.steps[0].error.as_deref().unwrap();
+        assert!(error.contains("Screen hash mismatch"), "{}", error);
+        assert!(!result.success);
+    }
+
+    #[test]
+    fn assert_screen_hash_update_mode() {
+        let blank = Screen::new(80, 24).state_hash();
+        let config = RunnerConfig {
+            seed: Some(42),
+            update_snapshots: true,
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&screen_hash_scenario(7), &config);
+        assert!(result.trace.steps[0].error.is_none());
+        let updates = screen_hash_updates(&result.trace).unwrap();
+        let source = StepSource {
+            phase: "steps".to_string(),
+            index: 0,
+        };
+        assert_eq!(updates, vec![(source, blank)]);
+
+        let yaml = "name: pinned\ncommand: \"true\"\nsteps:\n  - action: assert_screen_hash\n    hash: 7\n";
+        let rewritten = crate::scenario::rewrite_screen_hashes(yaml, &updates).unwrap();
+        let scenario = Scenario::_from_yaml(&rewritten).unwrap();
+        assert!(matches!(scenario.steps[0].step, Step::AssertScreenHash { hash } if hash == blank));
+    }
+
+    #[test]
+    fn screen_hash_updates_locate_steps_after_setup_and_repeat() {
+        let blank = Screen::new(80, 24).state_hash();
+        let config = RunnerConfig {
+            seed: Some(42),
+            update_snapshots: true,
+            ..RunnerConfig::default()
+        };
+        let yaml = r#"
+name: pinned
+command: "true"
+setup:
+  - action: wait_ticks
+    ticks: 1
+steps:
+  - action: repeat
+    count: 2
+    steps:
+      - action: wait_ticks
+        ticks: 1
+  # blank once the loop is done
+  - action: assert_screen_hash
+    hash: 7
+"#;
+
+        let result = run_scenario(&Scenario::_from_yaml(yaml).unwrap(), &config);
+        // Fourth step run, but the second entry of `steps`
+        assert_eq!(result.trace.steps[3].index, 3);
+        let mut trace = result.trace;
+        // Output read after the assertion doesn't leak into the update
+        trace.steps[3].after_screen_hash = Some(1);
+        let updates = screen_hash_updates(&trace).unwrap();
+        let source = StepSource {
+            phase: "steps".to_string(),
+            index: 1,
+        };
+        assert_eq!(updates, vec![(source, blank)]);
+
+        let rewritten = crate::scenario::rewrite_screen_hashes(yaml, &updates).unwrap();
+        assert_eq!(
+            rewritten,
+            yaml.replace("hash: 7", &format!("hash: {}", blank))
+        );
+
+        // A pinned step inside the repeat has no single place to rewrite
+        let nested = yaml.replace(
+            "      - action: wait_ticks\n        ticks: 1\n",
+            "      - action: assert_screen_hash\n        hash: 7\n",
+        );
+        let result = run_scenario(&Scenario::_from_yaml(&nested).unwrap(), &config);
+        let error = screen_hash_updates(&result.trace).unwrap_err();
+        assert!(error.contains("repeat"), "{}", error);
+    }
+
+    #[test]
+    fn streamed_steps_file_executes_every_step() {
+        use std::io::Write;
+
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("steps.ndjson");
+        let mut file = std::fs::File::create(&path).unwrap();
+        for _ in 0..2_000 {
+            writeln!(file, r#"{{"action": "wait_ticks", "ticks": 1}}"#).unwrap();
+        }
+        drop(file);
+
+        let scenario = Scenario {
+            name: "streamed".to_string(),
+            command: Command::Simple("true".to_string()),
+            steps: vec![Step::WaitTicks { ticks: 1 }.into()],
+            steps_file: Some(path.to_string_lossy().to_string()),
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            seed: Some(42),
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&scenario, &config);
+        assert_eq!(result.trace.steps.len(), 2_001);
+        assert!(result.trace.steps.iter().all(|s| s.error.is_none()));
+    }
+
+    #[test]
+    fn max_idle_gap_reflects_pause() {
+        // Output stops after the first line; the following steps see nothing new
+        let scenario = Scenario {
+            name: "pause".to_string(),
+            command: Command::Simple("echo before; sleep 1".to_string()),
+            steps: vec![
+                Step::WaitFor {
+                    pattern: "before".to_string(),
+                    timeout_ms: Some(5000),
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::WaitTicks { ticks: 5 }.into(),
+                Step::WaitTicks { ticks: 10 }.into(),
+                Step::WaitTicks { ticks: 20 }.into(),
+            ],
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            seed: Some(42),
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&scenario, &config);
+        assert!(result.trace.steps.iter().all(|s| s.error.is_none()));
+        // The three waits after the output stops, in ticks
+        assert_eq!(result.trace.max_idle_gap_ticks, 35);
+    }
+
+    #[test]
+    fn fatal_override_halts_on_one_warning_invariant() {
+        let warning = |pattern: &str, fatal: Option<bool>| ScenarioInvariant {
+            invariant: InvariantRef::ScreenContains {
+                pattern: pattern.to_string(),
+            },
+            severity: crate::invariants::Severity::Warning,
+            fatal,
+        };
+        let scenario = Scenario {
+            name: "fatal".to_string(),
+            command: Command::Simple("true".to_string()),
+            steps: vec![
+                Step::WaitTicks { ticks: 1 }.into(),
+                Step::WaitTicks { ticks: 1 }.into(),
+            ],
+            invariants: vec![warning("advisory", None), warning("required", Some(true))],
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert_eq!(result.trace.steps.len(), 1);
+        assert!(matches!(
+            result.trace.outcome,
+            TraceOutcome::InvariantViolation { .. }
+        ));
+
+        // Without the fatal one, the advisory violation alone doesn't halt
+        let scenario = Scenario {
+            invariants: vec![warning("advisory", None)],
+            ..scenario
+        };
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert_eq!(result.trace.steps.len(), 2);
+        assert!(result.trace.invariant_results.iter().any(|r| r.violation()));
+        assert!(!matches!(
+            result.trace.outcome,
+            TraceOutcome::InvariantViolation { .. }
+        ));
+    }
+
+    #[test]
+    fn pending_escape_at_assert_warns() {
+        let pending_escape_warnings = |output: &str| {
+            let scenario = Scenario {
+                name: "pending-escape".to_string(),
+                command: Command::Simple(format!("printf '{}'", output)),
+                steps: vec![
+                    Step::WaitFor {
+                        pattern: "ready".to_string(),
+                        timeout_ms: Some(5000),
+                        case_insensitive: false,
+                    }
+                    .into(),
+                    Step::AssertScreen {
+                        pattern: "ready".to_string(),
+                        anywhere: true,
+                        row: None,
+                        case_insensitive: false,
+                    }
+                    .into(),
+                ],
+                invariants: vec![ScenarioInvariant {
+                    invariant: InvariantRef::NoPendingEscapeAtAssert,
+                    severity: crate::invariants::Severity::Warning,
+                    fatal: None,
+                }],
+                ..Scenario::default()
+            };
+            let result = run_scenario(&scenario, &RunnerConfig::default());
+            assert!(result.success, "{:?}", result.trace.outcome);
+            result
+                .trace
+                .invariant_results
+                .iter()
+                .filter(|r| r.name == "no_pending_escape_at_assert" && r.violation())
+                .count()
+        };
+
+        assert_eq!(pending_escape_warnings("ready\\033["), 1);
+        assert_eq!(pending_escape_warnings("ready\\033[1m"), 0);
+    }
+
+    #[test]
+    fn warning_invariant_does_not_stop_execution() {
+        let scenario = Scenario {
+            name: "warning".to_string(),
+            command: Command::Simple("true".to_string()),
+            steps: vec![
+                Step::WaitTicks { ticks: 1 }.into(),
+                Step::WaitTicks { ticks: 1 }.into(),
+            ],
+            invariants: vec![ScenarioInvariant {
+                invariant: InvariantRef::ScreenContains {
+                    pattern: "never printed".to_string(),
+                },
+                severity: crate::invariants::Severity::Warning,
+                fatal: None,
+            }],
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            seed: Some(42),
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&scenario, &config);
+        assert_eq!(result.trace.steps.len(), 2);
+        assert!(result.trace.invariant_results.iter().any(|r| r.violation()));
+        assert!(!matches!(
+            result.trace.outcome,
+            TraceOutcome::InvariantViolation { .. }
+        ));
+
+        // The same invariant at error severity halts after the first step
+        let scenario = Scenario {
+            invariants: vec![InvariantRef::ScreenContains {
+                pattern: "never printed".to_string(),
+            }
+            .into()],
+            ..scenario
+        };
+        let result = run_scenario(&scenario, &config);
+        assert_eq!(result.trace.steps.len(), 1);
+        assert!(matches!(
+            result.trace.outcome,
+            TraceOutcome::InvariantViolation { .. }
+        ));
+    }
+
+    fn no_echo_scenario(command: &str, prompt: &str) -> Scenario {
+        Scenario {
+            name: "no echo".to_string(),
+            command: Command::Simple(command.to_string()),
+            steps: vec![
+                Step::WaitFor {
+                    pattern: prompt.to_string(),
+                    timeout_ms: Some(5000),
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::AssertNoEcho {
+                    keys: KeySequence::Text("hunter2\n".to_string()),
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        }
+    }
+
+    #[test]
+    fn assert_no_echo_passes_at_silent_prompt() {
+        let scenario = no_echo_scenario(
+            "stty -echo; printf 'Password: '; read pw; echo done",
+            "Password:",
+        );
+        let config = RunnerConfig {
+            seed: Some(42),
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&scenario, &config);
+        assert_eq!(result.trace.steps.len(), 2);
+        assert!(result.trace.steps.iter().all(|s| s.error.is_none()));
+    }
+
+    #[test]
+    fn assert_no_echo_fails_at_echoing_prompt() {
+        let scenario = no_echo_scenario("stty echo; printf 'Name: '; read name", "Name:");
+        let config = RunnerConfig {
+            seed: Some(42),
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&scenario, &config);
+        let error = result.trace.steps[1].error.as_deref().unwrap();
+        assert!(error.contains("echo"), "{}", error);
+        assert!(!result.success);
+    }
+
+    #[test]
+    fn jsonl_trace_streams_one_record_per_step() {
+        let dir = TempDir::new().unwrap();
+        let trace_path = dir.path().join("trace.jsonl");
+        let scenario = Scenario {
+            name: "streaming".to_string(),
+            command: Command::Simple("echo one; echo two".to_string()),
+            steps: vec![
+                Step::WaitFor {
+                    pattern: "two".to_string(),
+                    timeout_ms: Some(5000),
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::WaitTicks { ticks: 2 }.into(),
+                Step::WaitTicks { ticks: 2 }.into(),
+            ],
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            trace_path: Some(trace_path.to_string_lossy().to_string()),
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&scenario, &config);
+        assert!(
+            result.success,
+            "{:?} {:?}",
+            result.trace.outcome, result.trace.steps
+        );
+
+        let content = std::fs::read_to_string(&trace_path).unwrap();
+        let records: Vec<serde_json::Value> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        let kinds: Vec<&str> = records
+            .iter()
+            .map(|r| r["record"].as_str().unwrap())
+            .collect();
+        assert_eq!(kinds, ["header", "step", "step", "step", "footer"]);
+        assert_eq!(records[4]["outcome"]["status"], "Success");
+
+        let loaded = crate::trace::load_trace(&trace_path).unwrap();
+        assert_eq!(loaded.steps.len(), 3);
+        assert!(loaded.outcome.is_success());
+    }
+
+    #[test]
+    fn saved_trace_redacts_secret_env() {
+        let dir = TempDir::new().unwrap();
+        let trace_path = dir.path().join("trace.json");
+        let scenario = Scenario {
+            name: "redaction".to_string(),
+            command: Command::Simple("echo \"token=$GITHUB_TOKEN\"".to_string()),
+            env: HashMap::from([
+                ("GITHUB_TOKEN".to_string(), "ghp_real".to_string()),
+                ("PATH".to_string(), "/usr/bin:/bin".to_string()),
+            ]),
+            steps: vec![Step::WaitFor {
+                pattern: "token=ghp_real".to_string(),
+                timeout_ms: Some(5000),
+                case_insensitive: false,
+            }
+            .into()],
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            trace_path: Some(trace_path.to_string_lossy().to_string()),
+            ..RunnerConfig::default()
+        };
+
+        // The process saw the real value
+        let result = run_scenario(&scenario, &config);
+        assert!(
+            result.success,
+            "{:?} {:?}",
+            result.trace.outcome, result.trace.steps
+        );
+        assert_eq!(result.trace.scenario.env["GITHUB_TOKEN"], "ghp_real");
+
+        let saved = crate::trace::load_trace(&trace_path).unwrap();
+        assert_eq!(saved.scenario.env["GITHUB_TOKEN"], crate::trace::REDACTED);
+        assert_eq!(saved.scenario.env["PATH"], "/usr/bin:/bin");
+        let raw = std::fs::read_to_string(&trace_path).unwrap();
+        assert!(!raw.contains("\"ghp_real\""));
+    }
+
+    #[test]
+    fn saved_trace_masks_interpolated_secrets() {
+        let dir = TempDir::new().unwrap();
+        // As if `${GITHUB_TOKEN}` had been resolved in the command and pattern
+        let scenario = Scenario {
+            name: "interpolated".to_string(),
+            command: Command::Simple("echo token=ghp_real".to_string()),
+            steps: vec![Step::WaitFor {
+                pattern: "token=ghp_real".to_string(),
+                timeout_ms: Some(5000),
+                case_insensitive: false,
+            }
+            .into()],
+            ..Scenario::default()
+        };
+
+        for name in ["trace.json", "trace.jsonl"] {
+            let trace_path = dir.path().join(name);
+            let config = RunnerConfig {
+                trace_path: Some(trace_path.to_string_lossy().to_string()),
+                redaction: RedactionPolicy::default().with_values(["ghp_real".to_string()]),
+                ..RunnerConfig::default()
+            };
+
+            let result = run_scenario(&scenario, &config);
+            assert!(result.success, "{:?}", result.trace.outcome);
+            let raw = std::fs::read_to_string(&trace_path).unwrap();
+            assert!(!raw.contains("ghp_real"), "{}", name);
+
+            let saved = crate::trace::load_trace(&trace_path).unwrap();
+            assert!(matches!(
+                &saved.scenario.command,
+                Command::Simple(c) if c == "echo token=***"
+            ));
+            assert!(matches!(
+                &saved.steps[0].step,
+                Step::WaitFor { pattern, .. } if pattern == "token=***"
+            ));
+        }
+    }
+
+    #[test]
+    fn output_must_not_contain_catches_transient_error() {
+        // The error is printed and then erased, so the final screen and any
+        // later screen assertion never see it
+        let scenario = Scenario {
+            name: "transient-error".to_string(),
+            command: Command::Simple(
+                "printf 'FATAL: oops'; printf '\\r\\033[2K'; echo ok".to_string(),
+            ),
+            steps: vec![Step::WaitFor {
+                pattern: "ok".to_string(),
+                timeout_ms: Some(5000),
+                case_insensitive: false,
+            }
+            .into()],
+            output_assertions: OutputAssertions {
+                must_contain: vec!["ok".to_string()],
+                must_not_contain: vec!["FATAL".to_string()],
+            },
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(matches!(
+            &result.trace.outcome,
+            TraceOutcome::InvariantViolation { invariant_name, .. }
+                if invariant_name == "output_must_not_contain"
+        ));
+        let contain = result
+            .trace
+            .invariant_results
+            .iter()
+            .find(|r| r.name == "output_must_contain")
+            .unwrap();
+        assert!(contain.satisfied);
+
+        let clean = Scenario {
+            output_assertions: OutputAssertions {
+                must_contain: vec!["ok".to_string()],
+                must_not_contain: vec!["PANIC".to_string()],
+            },
+            ..scenario
+        };
+        let result = run_scenario(&clean, &RunnerConfig::default());
+        assert!(
+            result.success,
+            "{:?} {:?}",
+            result.trace.outcome, result.trace.steps
+        );
+    }
+
+    #[test]
+    fn case_insensitive_flag_matches_any_case() {
+        let scenario = |case_insensitive| Scenario {
+            name: "case".to_string(),
+            command: Command::Simple("echo HELLO".to_string()),
+            steps: vec![
+                Step::WaitFor {
+                    pattern: "hello".to_string(),
+                    timeout_ms: Some(500),
+                    case_insensitive,
+                }
+                .into(),
+                Step::AssertScreen {
+                    pattern: "^hello".to_string(),
+                    anywhere: true,
+                    row: None,
+                    case_insensitive,
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&scenario(true), &RunnerConfig::default());
+        assert!(result.success, "{:?}", result.trace.outcome);
+        assert!(!run_scenario(&scenario(false), &RunnerConfig::default()).success);
+
+        // Composes with inline flags in the pattern
+        let regex = Regex::new(&with_case("hello (?-i:World)", true)).unwrap();
+        assert!(regex.is_match("HELLO World"));
+        assert!(!regex.is_match("HELLO WORLD"));
+    }
+
+    #[test]
+    fn assert_graphics_detects_sixel_output() {
+        let scenario = |protocol| Scenario {
+            name: "graphics".to_string(),
+            command: Command::Simple(r"printf '\033P0;0;0q#0~\033\\done'".to_string()),
+            steps: vec![
+                Step::WaitFor {
+                    pattern: "done".to_string(),
+                    timeout_ms: Some(500),
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::AssertGraphics {
+                    protocol,
+                    min_count: 1,
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&scenario(None), &RunnerConfig::default());
+        assert!(result.success, "{:?}", result.trace.outcome);
+        let kitty = scenario(Some(crate::screen::GraphicsProtocol::Kitty));
+        assert!(!run_scenario(&kitty, &RunnerConfig::default()).success);
+    }
+
+    #[test]
+    fn assert_region_only_matches_inside_area() {
+        let scenario = |pattern: &str| Scenario {
+            name: "region".to_string(),
+            command: Command::Simple("printf 'left  right'".to_string()),
+            steps: vec![
+                Step::WaitFor {
+                    pattern: "right".to_string(),
+                    timeout_ms: Some(500),
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::AssertRegion {
+                    top: 0,
+                    left: 0,
+                    bottom: 0,
+                    right: 5,
+                    pattern: pattern.to_string(),
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&scenario("left"), &RunnerConfig::default());
+        assert!(result.success, "{:?}", result.trace.outcome);
+        // On screen, but outside the region
+        assert!(!run_scenario(&scenario("right"), &RunnerConfig::default()).success);
+    }
+
+    #[test]
+    fn replay_refuses_real_time_mode() {
+        let scenario = Scenario {
+            name: "real-time".to_string(),
+            command: Command::Simple("echo hello".to_string()),
+            steps: vec![Step::WaitFor {
+                pattern: "hello".to_string(),
+                timeout_ms: Some(5000),
+                case_insensitive: false,
+            }
+            .into()],
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            timing_mode: TimingMode::RealTime,
+            ..RunnerConfig::default()
+        };
+        let result = run_scenario(&scenario, &config);
+        assert!(result.success, "{:?}", result.trace.outcome);
+
+        let err = replay_trace_steps(&result.trace, &config, true).unwrap_err();
+        assert!(err.contains("deterministic timing"), "{}", err);
+        let err = replay_live(&result.trace, &config).unwrap_err();
+        assert!(err.contains("deterministic timing"), "{}", err);
+    }
+
+    #[test]
+    fn live_replay_matches_and_detects_tampering() {
+        let scenario = Scenario {
+            name: "live-replay".to_string(),
+            command: Command::Simple("echo hello".to_string()),
+            steps: vec![Step::WaitFor {
+                pattern: "hello".to_string(),
+                timeout_ms: Some(5000),
+                case_insensitive: false,
+            }
+            .into()],
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            seed: Some(7),
+            ..RunnerConfig::default()
+        };
+        let recorded = run_scenario(&scenario, &config).trace;
+
+        let outcome = replay_live(&recorded, &config).unwrap();
+        assert!(
+            matches!(outcome, TraceOutcome::Success { exit_code: 0, .. }),
+            "live replay diverged: {:?}",
+            outcome
+        );
+
+        let mut tampered = recorded.clone();
+        let last = tampered.checkpoints.last_mut().unwrap();
+        last.screen_hash = last.screen_hash.map(|h| h ^ 1);
+        match replay_live(&tampered, &config).unwrap() {
+            TraceOutcome::ReplayDivergence { context, .. } => {
+                assert!(context.contains("screen hash mismatch"), "{}", context);
+            }
+            other => panic!("expected divergence, got {:?}", other),
+        }
+
+        // An older trace still replays while it matches, and a mismatch is
+        // put down to its format
+        let mut older = recorded.clone();
+        older.version = "1.0.0".to_string();
+        assert!(replay_live(&older, &config).is_ok());
+        let step = &mut older.steps[0];
+        step.after_screen_hash = step.after_screen_hash.map(|h| h ^ 1);
+        let err = replay_trace_steps(&older, &config, true).unwrap_err();
+        assert!(err.contains("older format 1.0.0"), "{}", err);
+        let last = older.checkpoints.last_mut().unwrap();
+        last.screen_hash = last.screen_hash.map(|h| h ^ 1);
+        let err = replay_live(&older, &config).unwrap_err();
+        assert!(err.contains("older format 1.0.0"), "{}", err);
+
+        let mut tampered = recorded;
+        tampered.outcome = TraceOutcome::Success {
+            exit_code: 1,
+            total_ticks: 0,
+        };
+        match replay_live(&tampered, &config).unwrap() {
+            TraceOutcome::ReplayDivergence {
+                expected, actual, ..
+            } => {
+                assert_eq!(expected, "exit code 1");
+                assert_eq!(actual, "exit code 0");
+            }
+            other => panic!("expected divergence, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn pty_allocation_failure_has_specific_diagnostic() {
+        let err = ProcessError::Pty(PtyError::AllocationFailed(nix::Error::ENOENT));
+        let msg = spawn_error_message(&err);
+        assert!(msg.starts_with("PTY allocation failed"));
+        assert!(msg.contains("/dev/ptmx"));
+        assert!(msg.contains("--no-pty"));
+
+        let other = spawn_error_message(&ProcessError::ExecFailed(nix::Error::ENOENT));
+        assert!(other.starts_with("Failed to spawn process"));
+        assert!(!other.contains("/dev/ptmx"));
+    }
+
+    #[test]
+    fn goto_if_polls_until_pattern_or_bound() {
+        let poll = |command: &str, max_iterations: u32| Scenario {
+            name: "poll".to_string(),
+            command: Command::Simple(command.to_string()),
+            steps: vec![
+                Step::Label {
+                    name: "poll".to_string(),
+                }
+                .into(),
+                Step::WaitTicks { ticks: 1 }.into(),
+                Step::GotoIf {
+                    label: "poll".to_string(),
+                    pattern: "ready".to_string(),
+                    max_iterations,
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(
+            &poll("sleep 0.1; echo ready", 10_000),
+            &RunnerConfig::default(),
+        );
+        assert!(
+            result.success,
+            "{:?} {:?}",
+            result.trace.outcome, result.trace.steps
+        );
+        // The loop ran more than once before the pattern appeared
+        assert!(result.trace.steps.len() > 3);
+
+        let result = run_scenario(&poll("echo waiting", 3), &RunnerConfig::default());
+        assert!(!result.success);
+        // Initial pass plus three jumps, then the bound is hit
+        assert_eq!(result.trace.steps.len(), 12);
+        assert!(matches!(
+            &result.trace.outcome,
+            TraceOutcome::Error { message, .. } if message.contains("still absent after 3 iterations")
+        ));
+    }
+
+    #[test]
+    fn cursor_hidden_during_redraw() {
+        let redraw = |hide: &str| Scenario {
+            name: "redraw".to_string(),
+            command: Command::Simple(format!(
+                "printf '{}redrawing'; sleep 0.3; printf '\\033[?25hdone'",
+                hide
+            )),
+            steps: vec![
+                Step::WaitFor {
+                    pattern: "redrawing".to_string(),
+                    timeout_ms: Some(5000),
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::AssertCursorHidden.into(),
+            ],
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&redraw("\\033[?25l"), &RunnerConfig::default());
+        assert!(
+            result.success,
+            "{:?} {:?}",
+            result.trace.outcome, result.trace.steps
+        );
+
+        let result = run_scenario(&redraw(""), &RunnerConfig::default());
+        assert!(matches!(
+            &result.trace.outcome,
+            TraceOutcome::Error { message, .. } if message.contains("Cursor is visible")
+        ));
+    }
+
+    #[test]
+    fn if_screen_runs_matching_branch() {
+        let confirm = |prompt: &str, expected: &str| Scenario {
+            name: "if-screen".to_string(),
+            command: Command::Simple(format!(
+                "printf '{} > '; read a; echo \"answer=$a\"",
+                prompt
+            )),
+            steps: vec![
+                Step::WaitFor {
+                    pattern: ">".to_string(),
+                    timeout_ms: Some(5000),
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::IfScreen {
+                    pattern: r"Continue\?".to_string(),
+                    then: vec![Step::SendKeys {
+                        keys: KeySequence::Text("y\n".to_string()),
+                    }
+                    .into()],
+                    else_: vec![Step::SendKeys {
+                        keys: KeySequence::Text("n\n".to_string()),
+                    }
+                    .into()],
+                }
+                .into(),
+                Step::WaitFor {
+                    pattern: format!("answer={}", expected),
+                    timeout_ms: Some(5000),
+                    case_insensitive: false,
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&confirm("Continue?", "y"), &RunnerConfig::default());
+        assert!(
+            result.success,
+            "{:?} {:?}",
+            result.trace.outcome, result.trace.steps
+        );
+        // The if_screen step plus the one branch step it entered
+        assert_eq!(result.trace.steps.len(), 4);
+
+        let result = run_scenario(&confirm("Ready", "n"), &RunnerConfig::default());
+        assert!(
+            result.success,
+            "{:?} {:?}",
+            result.trace.outcome, result.trace.steps
+        );
+    }
+
+    #[test]
+    fn repeat_runs_block_count_times() {
+        let scenario = Scenario {
+            name: "repeat".to_string(),
+            command: Command::Simple("sleep 0.1".to_string()),
+            steps: vec![Step::Repeat {
+                count: 3,
+                steps: vec![
+                    Step::SendKeys {
+                        keys: KeySequence::Text("j".to_string()),
+                    }
+                    .into(),
+                    Step::WaitTicks { ticks: 1 }.into(),
+                ],
+            }
+            .into()],
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(
+            result.success,
+            "{:?} {:?}",
+            result.trace.outcome, result.trace.steps
+        );
+        assert_eq!(result.trace.steps.len(), 6);
+        let sends = result
+            .trace
+            .steps
+            .iter()
+            .filter(|s| matches!(s.step, Step::SendKeys { .. }))
+            .count();
+        assert_eq!(sends, 3);
+        // Initial checkpoint plus one per executed step
+        assert_eq!(result.trace.checkpoints.len(), 7);
+    }
+
+    #[test]
+    fn tab_stops_captured_in_checkpoints_when_enabled() {
+        let scenario = Scenario {
+            name: "tab-stops".to_string(),
+            command: Command::Simple("echo hi".to_string()),
+            steps: vec![Step::WaitFor {
+                pattern: "hi".to_string(),
+                timeout_ms: Some(5000),
+                case_insensitive: false,
+            }
+            .into()],
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(result
+            .trace
+            .checkpoints
+            .iter()
+            .all(|c| c.tab_stops.is_none()));
+
+        let config = RunnerConfig {
+            record_tab_stops: true,
+            ..RunnerConfig::default()
+        };
+        let result = run_scenario(&scenario, &config);
+        let expected: Vec<usize> = (8..80).step_by(8).collect();
+        assert!(result
+            .trace
+            .checkpoints
+            .iter()
+            .all(|c| c.tab_stops.as_ref() == Some(&expected)));
+    }
+
+    #[test]
+    fn retries_rerun_spawn_failures_but_not_violations() {
+        let good = Scenario {
+            name: "retry".to_string(),
+            command: Command::Simple("echo ready".to_string()),
+            steps: vec![Step::WaitFor {
+                pattern: "ready".to_string(),
+                timeout_ms: Some(5000),
+                case_insensitive: false,
+            }
+            .into()],
+            ..Scenario::default()
+        };
+        // A NUL byte in the command makes the spawn itself fail
+        let unspawnable = Scenario {
+            command: Command::Simple("echo \0".to_string()),
+            ..good.clone()
+        };
+        let flaky = |attempt: u32| {
+            let scenario = if attempt == 1 { &unspawnable } else { &good };
+            let config = RunnerConfig {
+                attempt,
+                ..RunnerConfig::default()
+            };
+            run_scenario(scenario, &config)
+        };
+
+        let result = retry_run(0, flaky);
+        assert!(!result.success);
+        assert_eq!(result.trace.attempts, 1);
+
+        let result = retry_run(1, flaky);
+        assert!(result.success, "{:?}", result.trace.outcome);
+        assert_eq!(result.trace.attempts, 2);
+
+        let violating = Scenario {
+            invariants: vec![InvariantRef::ScreenContains {
+                pattern: "never shown".to_string(),
+            }
+            .into()],
+            ..good.clone()
+        };
+        let mut runs = 0;
+        let result = retry_run(3, |_| {
+            runs += 1;
+            run_scenario(&violating, &RunnerConfig::default())
+        });
+        assert!(matches!(
+            result.trace.outcome,
+            TraceOutcome::InvariantViolation { .. }
+        ));
+        assert_eq!(runs, 1);
+
+        let wrong_exit = Scenario {
+            expect_exit: Some(3),
+            ..good
+        };
+        let mut runs = 0;
+        let result = retry_run(3, |_| {
+            runs += 1;
+            run_scenario(&wrong_exit, &RunnerConfig::default())
+        });
+        assert!(
+            matches!(&result.trace.outcome, TraceOutcome::Error { message, .. }
+                if message == "Expected exit code 3, got 0"),
+            "{:?}",
+            result.trace.outcome
+        );
+        assert_eq!(runs, 1);
+    }
+
+    #[test]
+    fn separate_stderr_keeps_streams_apart() {
+        let scenario = Scenario {
+            name: "stderr".to_string(),
+            command: Command::Simple("echo err 1>&2; echo out".to_string()),
+            terminal: crate::scenario::TerminalConfig {
+                separate_stderr: true,
+                ..Default::default()
+            },
+            steps: vec![
+                Step::WaitFor {
+                    pattern: "out".to_string(),
+                    timeout_ms: Some(5000),
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::AssertStderr {
+                    pattern: "^err\\n$".to_string(),
+                }
+                .into(),
+                Step::AssertNotScreen {
+                    pattern: "err".to_string(),
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+        assert!(scenario.validate().is_ok());
+
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(
+            result.success,
+            "{:?} {:?}",
+            result.trace.outcome, result.trace.steps
+        );
+
+        // Without the pipe, stderr lands on the screen
+        let merged = Scenario {
+            terminal: Default::default(),
+            steps: vec![Step::WaitFor {
+                pattern: "err".to_string(),
+                timeout_ms: Some(5000),
+                case_insensitive: false,
+            }
+            .into()],
+            ..scenario
+        };
+        assert!(merged.validate().is_ok());
+        assert!(run_scenario(&merged, &RunnerConfig::default()).success);
+    }
+
+    #[test]
+    #[allow(clippy::disallowed_types)]
+    fn tick_delay_slows_waits_down() {
+        let scenario = Scenario {
+            name: "tick-delay".to_string(),
+            command: Command::Simple("true".to_string()),
+            steps: vec![Step::WaitTicks { ticks: 20 }.into()],
+            ..Scenario::default()
+        };
+        let timed = |tick_delay_ms| {
+            let config = RunnerConfig {
+                tick_delay_ms,
+                ..RunnerConfig::default()
+            };
+            let start = std::time::Instant::now();
+            assert!(run_scenario(&scenario, &config).success);
+            start.elapsed()
+        };
+
+        let fast = timed(0);
+        let slow = timed(10);
+        assert!(slow >= std::time::Duration::from_millis(200), "{:?}", slow);
+        assert!(fast < slow, "{:?} vs {:?}", fast, slow);
+    }
+
+    #[test]
+    fn scenario_cwd_sets_process_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().canonicalize().unwrap();
+        let path = path.to_string_lossy().to_string();
+
+        let mut scenario = Scenario {
+            name: "cwd".to_string(),
+            command: Command::Simple("pwd".to_string()),
+            cwd: Some(path.clone()),
+            steps: vec![Step::WaitFor {
+                pattern: regex::escape(&path),
+                timeout_ms: Some(5000),
+                case_insensitive: false,
+            }
+            .into()],
+            ..Scenario::default()
+        };
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(result.success, "{:?}", result.trace.outcome);
+
+        scenario.cwd = None;
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(!result.success);
+    }
+
+    #[test]
+    fn memory_limit_kills_runaway_allocation() {
+        // Builds a 100 MB string in the shell
+        let scenario = Scenario {
+            name: "rlimit".to_string(),
+            command: Command::Simple(
+                "x=$(head -c 100000000 /dev/zero | tr '\\0' a); echo ${#x}".to_string(),
+            ),
+            steps: vec![Step::WaitTicks { ticks: 1 }.into()],
+            limits: crate::scenario::ResourceLimits {
+                memory_mb: Some(64),
+                ..Default::default()
+            },
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(
+            matches!(result.trace.outcome, TraceOutcome::Signaled { .. }),
+            "{:?}",
+            result.trace.outcome
+        );
+        assert!(!result.success);
+    }
+
+    #[test]
+    fn expect_exit_accepts_only_the_expected_code() {
+        let scenario = Scenario {
+            name: "expect-exit".to_string(),
+            command: Command::Simple("sh -c 'exit 3'".to_string()),
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(!result.success);
+        assert_eq!(result.exit_code, 3);
+
+        let expected = Scenario {
+            expect_exit: Some(3),
+            ..scenario.clone()
+        };
+        let result = run_scenario(&expected, &RunnerConfig::default());
+        assert!(result.success, "{:?}", result.trace.outcome);
+        assert!(matches!(
+            result.trace.outcome,
+            TraceOutcome::Success { exit_code: 3, .. }
+        ));
+
+        let mismatch = Scenario {
+            expect_exit: Some(0),
+            ..scenario
+        };
+        let result = run_scenario(&mismatch, &RunnerConfig::default());
+        assert!(!result.success);
+        assert!(matches!(
+            &result.trace.outcome,
+            TraceOutcome::Error { message, .. } if message == "Expected exit code 0, got 3"
+        ));
+    }
+
+    #[test]
+    fn cursor_trail_follows_moves() {
+        let scenario = Scenario {
+            name: "cursor-trail".to_string(),
+            command: Command::Simple(
+                "stty -echo; printf '\\033[3;5HA'; read a; printf '\\033[10;20HB'; read b; printf '\\033[HC'"
+                    .to_string(),
+            ),
+            steps: vec![
+                Step::WaitFor {
+                    pattern: "A".to_string(),
+                    timeout_ms: Some(5000),
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::SendKeys {
+                    keys: KeySequence::Text("\n".to_string()),
+                }
+                .into(),
+                Step::WaitFor {
+                    pattern: "B".to_string(),
+                    timeout_ms: Some(5000),
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::SendKeys {
+                    keys: KeySequence::Text("\n".to_string()),
+                }
+                .into(),
+                Step::WaitFor {
+                    pattern: "C".to_string(),
+                    timeout_ms: Some(5000),
+                    case_insensitive: false,
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            record_cursor_trail: true,
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&scenario, &config);
+        assert!(result.success, "{:?}", result.trace.outcome);
+        let trail: Vec<_> = result
+            .trace
+            .cursor_trail
+            .iter()
+            .map(|s| (s.row, s.col))
+            .collect();
+        assert_eq!(trail, vec![(2, 5), (9, 20), (0, 1)]);
+    }
+
+    #[test]
+    fn step_timeout_stops_run_at_that_step() {
+        let scenario = Scenario {
+            name: "step-timeout".to_string(),
+            command: Command::Simple("echo hi".to_string()),
+            steps: vec![
+                ScenarioStep {
+                    // Far beyond the global max_ticks
+                    step: Step::WaitTicks { ticks: 1_000_000 },
+                    timeout_ms: Some(100),
+                    comment: None,
+                },
+                Step::WaitFor {
+                    pattern: "hi".to_string(),
+                    timeout_ms: Some(1000),
+                    case_insensitive: false,
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(matches!(
+            &result.trace.outcome,
+            TraceOutcome::Error { message, step_index: 0, .. }
+                if message.contains("timeout_ms of 100 ms")
+        ));
+        assert_eq!(result.trace.steps.len(), 1);
+    }
+
+    #[test]
+    fn no_pty_runs_with_pipes() {
+        let scenario = Scenario {
+            name: "no-pty".to_string(),
+            command: Command::Simple("read line; echo \"got $line\"; exit 3".to_string()),
+            steps: vec![
+                Step::SendKeys {
+                    keys: KeySequence::Text("hello\n".to_string()),
+                }
+                .into(),
+                Step::WaitFor {
+                    pattern: "got hello".to_string(),
+                    timeout_ms: Some(1000),
+                    case_insensitive: false,
+                }
+                .into(),
+            ],
+            output_assertions: OutputAssertions {
+                must_contain: vec!["got hello\n".to_string()],
+                must_not_contain: vec![],
+            },
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            no_pty: true,
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&scenario, &config);
+        assert_eq!(result.exit_code, 3);
+        assert!(matches!(
+            result.trace.outcome,
+            TraceOutcome::Success { exit_code: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn no_pty_checks_the_screen_and_flags_skipped_steps() {
+        let scenario = Scenario {
+            name: "no-pty-screen".to_string(),
+            command: Command::Simple("echo ready; read line".to_string()),
+            steps: vec![
+                Step::WaitFor {
+                    pattern: "ready".to_string(),
+                    timeout_ms: Some(1000),
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::Resize {
+                    cols: 100,
+                    rows: 30,
+                }
+                .into(),
+                Step::AssertNoEcho {
+                    keys: KeySequence::Text("secret".to_string()),
+                }
+                .into(),
+                Step::AssertScreen {
+                    pattern: "missing".to_string(),
+                    anywhere: true,
+                    row: None,
+                    case_insensitive: false,
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            no_pty: true,
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&scenario, &config);
+        assert!(matches!(result.trace.outcome, TraceOutcome::Error { .. }));
+        let warnings: Vec<_> = result
+            .trace
+            .steps
+            .iter()
+            .map(|s| s.warning.as_deref())
+            .collect();
+        assert!(warnings[0].is_none());
+        assert!(warnings[1].is_some_and(|w| w.starts_with("resize skipped")));
+        assert!(warnings[2].is_some_and(|w| w.starts_with("assert_no_echo skipped")));
+        assert!(warnings[3].is_none());
+        assert!(result.trace.steps[3].error.is_some());
+    }
+
+    #[test]
+    fn sgr_state_matches_current_rendition() {
+        let mut screen = Screen::new(20, 5);
+        screen.process(b"\x1b[1;4mX");
+        let expected = SgrState {
+            bold: true,
+            underline: true,
+            ..SgrState::default()
+        };
+        assert!(matches!(
+            check_sgr_state(&expected, &screen),
+            StepResult::Ok
+        ));
+
+        screen.process(b"\x1b[0m");
+        assert!(matches!(
+            check_sgr_state(&expected, &screen),
+            StepResult::Error(_)
+        ));
+        assert!(matches!(
+            check_sgr_state(&SgrState::default(), &screen),
+            StepResult::Ok
+        ));
+    }
+
+    #[test]
+    fn cell_attr_checks_only_specified_fields() {
+        let mut screen = Screen::new(20, 5);
+        screen.process(b"\x1b[1;31mE\x1b[0;31mr");
+        let bold_red = CellAttrCheck {
+            bold: Some(true),
+            fg: Some(Color::Indexed(1)),
+            ..CellAttrCheck::default()
+        };
+        assert!(matches!(
+            check_cell_attr(0, 0, &bold_red, &screen),
+            StepResult::Ok
+        ));
+        // Red but not bold
+        assert!(matches!(
+            check_cell_attr(0, 1, &bold_red, &screen),
+            StepResult::Error(e) if e.contains("bold is false")
+        ));
+        assert!(matches!(
+            check_cell_attr(0, 1, &CellAttrCheck::default(), &screen),
+            StepResult::Ok
+        ));
+        assert!(matches!(
+            check_cell_attr(9, 0, &CellAttrCheck::default(), &screen),
+            StepResult::Error(_)
+        ));
+    }
+
+    #[test]
+    fn wallclock_is_recorded_and_ignored_by_replay() {
+        let scenario = Scenario {
+            name: "wallclock".to_string(),
+            command: Command::Simple("echo hi; sleep 0.05".to_string()),
+            steps: vec![
+                Step::WaitFor {
+                    pattern: "hi".to_string(),
+                    timeout_ms: Some(5000),
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::WaitTicks { ticks: 5 }.into(),
+                Step::WaitTicks { ticks: 5 }.into(),
+            ],
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            seed: Some(42),
+            record_wallclock: true,
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&scenario, &config);
+        let timings: Vec<_> = result
+            .trace
+            .steps
+            .iter()
+            .map(|s| s.wallclock.expect("wallclock recorded"))
+            .collect();
+        assert_eq!(timings.len(), 3);
+        for pair in timings.windows(2) {
+            assert!(pair[0].start_us <= pair[0].end_us);
+            assert!(pair[0].end_us <= pair[1].start_us);
+        }
+
+        // Replay without wall-clock recording still matches step for step
+        let replay_config = RunnerConfig {
+            record_wallclock: false,
+            ..config
+        };
+        assert_eq!(
+            replay_trace_steps(&result.trace, &replay_config, false),
+            Ok(None)
+        );
+
+        // Off by default
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(result.trace.steps.iter().all(|s| s.wallclock.is_none()));
+    }
+
+    #[test]
+    fn missing_steps_file_is_a_step_error() {
+        let scenario = Scenario {
+            name: "missing".to_string(),
+            command: Command::Simple("true".to_string()),
+            steps_file: Some("/nonexistent/steps.ndjson".to_string()),
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            seed: Some(42),
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&scenario, &config);
+        assert!(!result.success);
+        assert!(result.trace.steps.is_empty());
+    }
+
+    #[test]
+    fn send_signal_records_delivery_tick() {
+        use crate::scenario::SignalName;
+        let scenario = Scenario {
+            name: "signal-event".to_string(),
+            command: Command::Simple("sleep 30".to_string()),
+            steps: vec![
+                Step::WaitTicks { ticks: 7 }.into(),
+                Step::SendSignal {
+                    signal: SignalName::Sigint,
+                }
+                .into(),
+                Step::WaitTicks { ticks: 5 }.into(),
+            ],
+            invariants: vec![InvariantRef::SignalAcknowledged {
+                signal: SignalName::Sigint,
+                within_ticks: 10,
+            }
+            .into()],
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        // Sent right after the 7 ticks of waiting
+        assert!(matches!(
+            result.trace.events.as_slice(),
+            [ScheduleEvent::Signal { signal: 2, tick: 7 }]
+        ));
+        let acknowledged: Vec<_> = result
+            .trace
+            .invariant_results
+            .iter()
+            .filter(|r| r.name == "signal_acknowledged")
+            .collect();
+        assert!(!acknowledged.is_empty());
+        assert!(acknowledged.iter().all(|r| r.satisfied));
+    }
+
+    #[test]
+    fn signal_exit_allows_a_delayed_shutdown() {
+        use crate::invariants::SignalBehavior;
+        use crate::scenario::SignalName;
+        let scenario = Scenario {
+            name: "signal-grace".to_string(),
+            command: Command::Simple(
+                "trap 'sleep 0.2; exit 3' TERM; echo ready; while :; do sleep 0.05; done"
+                    .to_string(),
+            ),
+            steps: vec![
+                Step::WaitFor {
+                    pattern: "ready".to_string(),
+                    timeout_ms: Some(2000),
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::SendSignal {
+                    signal: SignalName::Sigterm,
+                }
+                .into(),
+                Step::WaitTicks { ticks: 1 }.into(),
+            ],
+            invariants: vec![InvariantRef::SignalHandledCorrectly {
+                signal: SignalName::Sigterm,
+                behavior: SignalBehavior::Exit {
+                    exit_code: Some(3),
+                    within_ticks: 50,
+                },
+            }
+            .into()],
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        let checks: Vec<_> = result
+            .trace
+            .invariant_results
+            .iter()
+            .filter(|r| r.name == "signal_handled_correctly")
+            .collect();
+        // Checked before the wait right after the signal, and at run end
+        // once the trap has exited
+        assert!(checks.len() >= 2);
+        assert!(checks.iter().all(|r| r.satisfied), "{:?}", checks);
+        let last = checks.last().unwrap().details.as_deref().unwrap();
+        assert!(last.contains("exited with code 3"), "{}", last);
+    }
+
+    #[test]
+    fn resize_step_resizes_the_model_screen() {
+        let scenario = Scenario {
+            name: "resize-model".to_string(),
+            command: Command::Simple(
+                r#"sh -c 'read x; stty size; printf "\033[99;999H*"'"#.to_string(),
+            ),
+            steps: vec![
+                Step::WaitTicks { ticks: 2 }.into(),
+                Step::Resize {
+                    cols: 120,
+                    rows: 40,
+                }
+                .into(),
+                Step::SendKeys {
+                    keys: KeySequence::Text("\n".to_string()),
+                }
+                .into(),
+                Step::WaitFor {
+                    pattern: "40 120".to_string(),
+                    timeout_ms: None,
+                    case_insensitive: false,
+                }
+                .into(),
+                // The bottom-right corner only exists at the new size
+                Step::AssertRegion {
+                    top: 39,
+                    left: 119,
+                    bottom: 39,
+                    right: 119,
+                    pattern: r"\*".to_string(),
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(
+            result.trace.steps.iter().all(|s| s.error.is_none()),
+            "{:?}",
+            result.trace.steps
+        );
+        assert!(matches!(
+            result.trace.events.as_slice(),
+            [ScheduleEvent::Resize {
+                cols: 120,
+                rows: 40,
+                tick: 2
+            }]
+        ));
+    }
+
+    #[test]
+    fn timeout_outcome_carries_screen_preview() {
+        let scenario = Scenario {
+            name: "stall".to_string(),
+            command: Command::Simple(
+                "sh -c 'i=0; while [ $i -lt 40 ]; do echo \"progress line $i\"; i=$((i+1)); done; sleep 1'"
+                    .to_string(),
+            ),
+            steps: vec![
+                Step::WaitFor {
+                    pattern: "progress line 39".to_string(),
+                    timeout_ms: None,
+                    case_insensitive: false,
+                }
+                .into(),
+                Step::WaitTicks { ticks: 50 }.into(),
+                Step::WaitTicks { ticks: 1 }.into(),
+            ],
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            max_ticks: Some(20),
+            ..RunnerConfig::default()
+        };
+
+        let result = run_scenario(&scenario, &config);
+        let TraceOutcome::Timeout { screen_preview, .. } = &result.trace.outcome else {
+            panic!("expected a timeout, got {:?}", result.trace.outcome);
+        };
+        assert!(screen_preview.contains("progress line"));
+        assert!(screen_preview.ends_with("..."));
+        assert!(screen_preview.len() <= 203);
+    }
+
+    #[test]
+    fn wait_for_stable_returns_once_redraws_settle() {
+        let stable = |command: &str| Scenario {
+            name: "stable".to_string(),
+            command: Command::Simple(command.to_string()),
+            steps: vec![
+                Step::WaitForStable {
+                    stable_ticks: 100,
+                    timeout_ms: Some(3000),
+                }
+                .into(),
+                Step::AssertScreen {
+                    pattern: "frame 5".to_string(),
+                    anywhere: true,
+                    row: None,
+                    case_insensitive: false,
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+
+        let scenario = stable(
+            r#"sh -c 'for i in 1 2 3 4 5; do printf "\rframe $i"; sleep 0.01; done; sleep 0.3'"#,
+        );
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(result.success, "{:?}", result.trace.outcome);
+
+        // A screen that never settles times out
+        let mut scenario = stable(r#"sh -c 'i=0; while :; do i=$((i+1)); printf "\r$i"; done'"#);
+        scenario.steps.truncate(1);
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(
+            matches!(
+                &result.trace.outcome,
+                TraceOutcome::Error { message, .. } if message.contains("still changing")
+            ),
+            "{:?}",
+            result.trace.outcome
+        );
+    }
+
+    #[test]
+    fn output_limit_ends_the_run() {
+        let scenario = Scenario {
+            name: "spew".to_string(),
+            command: Command::Simple("yes".to_string()),
+            steps: vec![Step::WaitScreen {
+                pattern: "never printed".to_string(),
+                timeout_ms: Some(2000),
+            }
+            .into()],
+            ..Scenario::default()
+        };
+        let config = RunnerConfig {
+            max_output_bytes: Some(64 * 1024),
+            ..RunnerConfig::default()
+        };
+        let result = run_scenario(&scenario, &config);
+        assert!(
+            matches!(
+                &result.trace.outcome,
+                TraceOutcome::Error { message, .. } if message == "output limit exceeded"
+            ),
+            "{:?}",
+            result.trace.outcome
+        );
+    }
+
+    #[test]
+    fn scenario_timeout_sets_the_tick_budget() {
+        let scenario = Scenario {
+            name: "budget".to_string(),
+            command: Command::Simple("sleep 1".to_string()),
+            steps: (0..10)
+                .map(|_| Step::WaitTicks { ticks: 5 }.into())
+                .collect(),
+            timeout_ms: Some(100),
+            ..Scenario::default()
+        };
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(
+            matches!(
+                result.trace.outcome,
+                TraceOutcome::Timeout { max_ticks: 10, .. }
+            ),
+            "{:?}",
+            result.trace.outcome
+        );
+
+        // An explicit budget wins over the scenario's
+        let config = RunnerConfig {
+            max_ticks: Some(30),
+            ..RunnerConfig::default()
+        };
+        let result = run_scenario(&scenario, &config);
+        assert!(matches!(
+            result.trace.outcome,
+            TraceOutcome::Timeout { max_ticks: 30, .. }
+        ));
+    }
+
+    #[test]
+    fn snapshot_step_writes_then_compares_golden_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("golden").join("screen.txt");
+        let snapshot = |command: &str| Scenario {
+            name: "snapshot".to_string(),
+            command: Command::Simple(command.to_string()),
+            steps: vec![
+                Step::WaitScreen {
+                    pattern: "done".to_string(),
+                    timeout_ms: Some(2000),
+                }
+                .into(),
+                Step::Snapshot {
+                    name: "final".to_string(),
+                    path: Some(path.to_string_lossy().to_string()),
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+
+        // First run creates the file
+        let result = run_scenario(
+            &snapshot("printf 'one\\ntwo done'"),
+            &RunnerConfig::default(),
+        );
+        assert!(result.success, "{:?}", result.trace.outcome);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo done\n");
+
+        // The same screen matches it
+        let result = run_scenario(
+            &snapshot("printf 'one\\ntwo done'"),
+            &RunnerConfig::default(),
+        );
+        assert!(result.success, "{:?}", result.trace.outcome);
+
+        // A different screen fails with a diff and leaves the file alone
+        let result = run_scenario(&snapshot("printf 'one\\n2 done'"), &RunnerConfig::default());
+        match &result.trace.outcome {
+            TraceOutcome::Error { message, .. } => {
+                assert!(message.contains("-two done\n+2 done\n"), "{}", message);
+            }
+            other => panic!("expected a snapshot mismatch, got {:?}", other),
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\ntwo done\n");
+
+        // Updating rewrites it
+        let config = RunnerConfig {
+            update_snapshots: true,
+            ..RunnerConfig::default()
+        };
+        let result = run_scenario(&snapshot("printf 'one\\n2 done'"), &config);
+        assert!(result.success, "{:?}", result.trace.outcome);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\n2 done\n");
+    }
+
+    #[test]
+    fn update_baselines_rewrites_mismatching_screenshot() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("baseline.yaml");
+        let path = path.to_string_lossy().to_string();
+        let scenario = |text: &str| Scenario {
+            name: "baseline".to_string(),
+            command: Command::Simple(format!("printf {}", text)),
+            steps: vec![
+                Step::WaitScreen {
+                    pattern: text.to_string(),
+                    timeout_ms: Some(2000),
+                }
+                .into(),
+                Step::AssertScreenshot {
+                    path: path.clone(),
+                    max_differences: 0,
+                    ignore_regions: Vec::new(),
+                    compare_colors: true,
+                    compare_text: true,
+                    ignore_trailing_blanks: false,
+                    ignore_cursor: false,
+                    diff_char: Some('#'),
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+        let mut screen = Screen::new(80, 24);
+        screen.process(b"stale");
+        let timing = TimingController::new(0);
+        assert!(matches!(
+            execute_take_screenshot(&path, None, &screen, &timing),
+            StepResult::Ok
+        ));
+
+        // Without the flag a mismatch still fails, mapping the changed cells
+        let result = run_scenario(&scenario("fresh"), &RunnerConfig::default());
+        assert!(matches!(
+            &result.trace.outcome,
+            TraceOutcome::Error { message, .. }
+                if message.contains("Screenshot mismatch") && message.contains("\n|#####   ")
+        ));
+
+        let update = RunnerConfig {
+            update_baselines: true,
+            ..RunnerConfig::default()
+        };
+        let result = run_scenario(&scenario("fresh"), &update);
+        assert!(result.success, "{:?}", result.trace.outcome);
+
+        let result = run_scenario(&scenario("fresh"), &RunnerConfig::default());
+        assert!(result.success, "{:?}", result.trace.outcome);
+    }
+
+    #[test]
+    fn startup_output_catches_silent_start() {
+        let startup = |command: &str, steps: Vec<ScenarioStep>| Scenario {
+            name: "startup".to_string(),
+            command: Command::Simple(command.to_string()),
+            steps,
+            invariants: vec![InvariantRef::StartupOutput { within_ticks: 5 }.into()],
+            ..Scenario::default()
+        };
+        let violated = |result: &RunResult| {
+            result
+                .trace
+                .invariant_results
+                .iter()
+                .any(|r| r.name == "startup_output" && r.violation())
+        };
+
+        let scenario = startup(
+            "sh -c 'sleep 0.3; echo ready'",
+            vec![
+                Step::WaitTicks { ticks: 20 }.into(),
+                Step::WaitTicks { ticks: 1 }.into(),
+            ],
+        );
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(violated(&result));
+
+        let scenario = startup(
+            "echo ready",
+            vec![
+                Step::WaitScreen {
+                    pattern: "ready".to_string(),
+                    timeout_ms: Some(2000),
+                }
+                .into(),
+                Step::WaitTicks { ticks: 20 }.into(),
+                Step::WaitTicks { ticks: 1 }.into(),
+            ],
+        );
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(!violated(&result), "{:?}", result.trace.invariant_results);
+    }
+
+    #[test]
+    fn assert_screenshot_can_ignore_the_cursor() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cursor.yaml");
+        let path = path.to_string_lossy().to_string();
+        let scenario = |ignore_cursor| Scenario {
+            name: "cursor".to_string(),
+            command: Command::Simple("printf same".to_string()),
+            steps: vec![
+                Step::WaitScreen {
+                    pattern: "same".to_string(),
+                    timeout_ms: Some(2000),
+                }
+                .into(),
+                Step::AssertScreenshot {
+                    path: path.clone(),
+                    max_differences: 0,
+                    ignore_regions: Vec::new(),
+                    compare_colors: true,
+                    compare_text: true,
+                    ignore_trailing_blanks: false,
+                    ignore_cursor,
+                    diff_char: None,
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+        // Same text, cursor parked elsewhere
+        let mut screen = Screen::new(80, 24);
+        screen.process(b"same\x1b[10;20H");
+        let timing = TimingController::new(0);
+        assert!(matches!(
+            execute_take_screenshot(&path, None, &screen, &timing),
+            StepResult::Ok
+        ));
+
+        let result = run_scenario(&scenario(false), &RunnerConfig::default());
+        assert!(matches!(
+            &result.trace.outcome,
+            TraceOutcome::Error { message, .. } if message.contains("CURSOR MISMATCH")
+        ));
+
+        let result = run_scenario(&scenario(true), &RunnerConfig::default());
+        assert!(result.success, "{:?}", result.trace.outcome);
+    }
+
+    #[test]
+    fn assert_not_screen_matches_regex() {
+        let config = RunnerConfig::default();
+        let mut screen = Screen::new(40, 2);
+        screen.process(b"all good");
+        assert!(matches!(
+            execute_assert_not_screen("err(or)?", &screen, &config),
+            StepResult::Ok
+        ));
+
+        screen.process(b"\r\nan error occurred");
+        assert!(matches!(
+            execute_assert_not_screen("err(or)?", &screen, &config),
+            StepResult::Error(e) if e.contains("should not")
+        ));
+        assert!(matches!(
+            execute_assert_not_screen("error", &screen, &config),
+            StepResult::Error(_)
+        ));
+        assert!(matches!(
+            execute_assert_not_screen("(", &screen, &config),
+            StepResult::Error(e) if e.starts_with("Invalid regex")
+        ));
+    }
+
+    #[test]
+    fn compile_pattern_enforces_size_limits() {
+        let config = RunnerConfig::default();
+        // Nested groups used to trip the old complexity heuristic
+        let nested = compile_pattern(r"((Total: (\d+)))|(((\w+)-)+(\w+))*", &config).unwrap();
+        assert!(nested.is_match("Total: 42"));
+        // Ordinary counted repetitions fit the crate's default limit
+        assert!(compile_pattern(r"\w{100}", &config).is_ok());
+
+        let huge = r"\w{1000}";
+        let err = compile_pattern(huge, &config).unwrap_err();
+        assert!(
+            err.starts_with("Invalid regex") && err.contains("size limit"),
+            "{}",
+            err
+        );
+
+        let lifted = RunnerConfig {
+            lift_regex_size_limit: true,
+            ..RunnerConfig::default()
+        };
+        assert!(compile_pattern(huge, &lifted).is_ok());
+    }
+
+    #[test]
+    fn oversized_pattern_fails_the_step() {
+        let scenario = Scenario {
+            name: "oversized-regex".to_string(),
+            command: Command::Simple("echo 'Total: 42'".to_string()),
+            steps: vec![Step::WaitFor {
+                pattern: r"\w{1000}".to_string(),
+                timeout_ms: Some(2000),
+                case_insensitive: false,
+            }
+            .into()],
+            ..Scenario::default()
+        };
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(matches!(
+            &result.trace.outcome,
+            TraceOutcome::Error { message, .. } if message.contains("size limit")
+        ));
+
+        let mut screen = Screen::new(40, 2);
+        screen.process(b"Total: 42");
+        assert!(matches!(
+            execute_assert_not_screen(r"((Total: (\d+)))", &screen, &RunnerConfig::default()),
+            StepResult::Error(e) if e.contains("should not")
+        ));
+
+        let scenario = Scenario {
+            name: "oversized-goto-if".to_string(),
+            command: Command::Simple("echo 'Total: 42'".to_string()),
+            steps: vec![
+                Step::Label {
+                    name: "poll".to_string(),
+                }
+                .into(),
+                Step::GotoIf {
+                    label: "poll".to_string(),
+                    pattern: r"\w{1000}".to_string(),
+                    max_iterations: 3,
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(matches!(
+            &result.trace.outcome,
+            TraceOutcome::Error { message, .. } if message.contains("size limit")
+        ));
+        // Rejected up front rather than after polling to the bound
+        assert_eq!(result.trace.steps.len(), 2);
+    }
+
+    #[test]
+    fn expect_waits_then_asserts() {
+        let run = |then_assert: &str| {
+            let yaml = format!(
+                r#"
+name: expect
+command: "echo 'version 1.2'; echo ready"
+steps:
+  - action: expect
+    wait_for: "ready"
+    then_assert: {}
+    timeout_ms: 2000
+"#,
+                then_assert
+            );
+            let scenario = Scenario::_from_yaml(&yaml).unwrap();
+            run_scenario(&scenario, &RunnerConfig::default())
+        };
+
+        let result = run(r"['version \d\.\d', 'ready']");
+        assert!(result.success, "{:?}", result.trace.outcome);
+
+        let result = run(r"['version \d', 'missing']");
+        assert!(
+            matches!(
+                &result.trace.outcome,
+                TraceOutcome::Error { message, .. }
+                    if message.contains("1 of 2 assertions failed") && message.contains("missing")
+            ),
+            "{:?}",
+            result.trace.outcome
+        );
+    }
+
+    #[test]
+    fn step_comments_are_recorded_in_the_trace() {
+        let yaml = r#"
+name: comments
+command: "echo welcome"
+steps:
+  - action: wait_for
+    pattern: "welcome"
+    comment: "banner shown"
+  - action: assert_screen
+    pattern: "logged in"
+    comment: "login submitted"
+"#;
+        let scenario = Scenario::_from_yaml(yaml).unwrap();
+        let yaml = scenario._to_yaml().unwrap();
+        assert!(yaml.contains("comment: login submitted"), "{}", yaml);
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("trace.json");
+        let config = RunnerConfig {
+            trace_path: Some(path.to_string_lossy().to_string()),
+            ..RunnerConfig::default()
+        };
+        let result = run_scenario(&scenario, &config);
+        assert!(!result.success);
+
+        let trace = crate::trace::load_trace(&path).unwrap();
+        assert_eq!(trace.steps[0].comment.as_deref(), Some("banner shown"));
+        assert_eq!(
+            crate::trace::failed_step_description(&trace).as_deref(),
+            Some("at step 1 'login submitted'")
+        );
+        let detail = crate::trace::format_trace_steps(&trace, 1..2);
+        assert!(
+            detail.contains("=== Step 1 'login submitted' ==="),
+            "{}",
+            detail
+        );
+    }
+
+    #[test]
+    fn assert_screen_honors_row_and_whole_line_matching() {
+        let outcome = |pattern: &str, anywhere: bool, row: Option<usize>| {
+            let scenario = Scenario {
+                name: "assert-screen-row".to_string(),
+                command: Command::Simple(r#"sh -c 'printf "Title\nStatus: Done\n"'"#.to_string()),
+                steps: vec![
+                    Step::WaitFor {
+                        pattern: "Done".to_string(),
+                        timeout_ms: Some(2000),
+                        case_insensitive: false,
+                    }
+                    .into(),
+                    Step::AssertScreen {
+                        pattern: pattern.to_string(),
+                        anywhere,
+                        row,
+                        case_insensitive: false,
+                    }
+                    .into(),
+                ],
+                ..Scenario::default()
+            };
+            run_scenario(&scenario, &RunnerConfig::default())
+                .trace
+                .outcome
+        };
+        let passes = |outcome: TraceOutcome| matches!(outcome, TraceOutcome::Success { .. });
+        let fails_with = |outcome: TraceOutcome, text: &str| matches!(outcome, TraceOutcome::Error { message, .. } if message.contains(text));
+
+        // Row-scoped matching
+        assert!(passes(outcome("Done", true, Some(1))));
+        assert!(fails_with(
+            outcome("Done", true, Some(0)),
+            "Row 0 does not match"
+        ));
+        assert!(fails_with(outcome("Done", true, Some(99)), "outside"));
+
+        // Whole-line anchoring, with the row padding ignored
+        assert!(passes(outcome(r"Status: \w+", false, None)));
+        assert!(fails_with(outcome("Done", false, None), "does not match"));
+        assert!(passes(outcome("Title", false, Some(0))));
+        assert!(fails_with(outcome("Tit", false, Some(0)), "Row 0"));
+    }
+
+    #[test]
+    fn freeze_time_injects_a_seeded_epoch() {
+        // Whether a run with `seed` prints exactly `epoch` as its frozen clock
+        let sees_epoch = |seed: u64, epoch: i64| {
+            let scenario = Scenario {
+                name: "frozen".to_string(),
+                command: Command::Simple(
+                    r#"sh -c 'echo "epoch=$SOURCE_DATE_EPOCH tz=$TZ"'"#.to_string(),
+                ),
+                steps: vec![Step::WaitFor {
+                    pattern: "tz=".to_string(),
+                    timeout_ms: Some(2000),
+                    case_insensitive: false,
+                }
+                .into()],
+                output_assertions: OutputAssertions {
+                    must_contain: vec![format!("epoch={} tz=UTC\r\n", epoch)],
+                    ..OutputAssertions::default()
+                },
+                seed: Some(seed),
+                freeze_time: Some("seed".to_string()),
+                ..Scenario::default()
+            };
+            run_scenario(&scenario, &RunnerConfig::default()).success
+        };
+
+        let epoch = frozen_epoch("seed", 42).unwrap();
+        assert!(sees_epoch(42, epoch));
+        assert!(sees_epoch(42, epoch));
+        assert!(!sees_epoch(43, epoch));
+    }
+
+    #[test]
+    fn click_text_clicks_the_label_position() {
+        // Prints a button at row 2, col 5 and dumps the bytes it receives
+        let scenario = Scenario {
+            name: "click-text".to_string(),
+            command: Command::Simple(
+                r#"sh -c 'stty raw -echo; printf "\033[3;6H[ OK ]"; od -An -tx1 -N14'"#.to_string(),
+            ),
+            steps: vec![
+                Step::WaitScreen {
+                    pattern: "OK".to_string(),
+                    timeout_ms: Some(2000),
+                }
+                .into(),
+                Step::ClickText {
+                    pattern: r"\[ OK \]".to_string(),
+                    button: 0,
+                }
+                .into(),
+                // Tracking enabled, then press at column 6, row 3 (1-based, +32)
+                Step::WaitScreen {
+                    pattern: "1b 5b 3f 31 30 30 36 68 1b 5b 4d 20 26 23".to_string(),
+                    timeout_ms: Some(2000),
+                }
+                .into(),
+            ],
+            ..Scenario::default()
+        };
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(result.success, "{:?}", result.trace.outcome);
+
+        let missing = Scenario {
+            command: Command::Simple("printf nothing".to_string()),
+            steps: vec![Step::ClickText {
+                pattern: "Cancel".to_string(),
+                button: 0,
+            }
+            .into()],
+            ..scenario
+        };
+        let result = run_scenario(&missing, &RunnerConfig::default());
+        assert!(matches!(
+            &result.trace.outcome,
+            TraceOutcome::Error { message, .. } if message.contains("Text not found")
+        ));
+    }
+
+    #[test]
+    fn teardown_runs_after_a_failed_step() {
+        let dir = TempDir::new().unwrap();
+        let marker = |name: &str| dir.path().join(name);
+        let touch = |name: &str| -> ScenarioStep {
+            Step::SendKeys {
+                keys: KeySequence::Text(format!(
+                    "touch {}; echo clean''ed\n",
+                    marker(name).display()
+                )),
+            }
+            .into()
+        };
+        let missing = || -> ScenarioStep {
+            Step::AssertScreen {
+                pattern: "never on screen".to_string(),
+                anywhere: true,
+                row: None,
+                case_insensitive: false,
+            }
+            .into()
+        };
+        let cleaned = || -> ScenarioStep {
+            Step::WaitFor {
+                pattern: "cleaned".to_string(),
+                timeout_ms: Some(5000),
+                case_insensitive: false,
+            }
+            .into()
+        };
+
+        let scenario = Scenario {
+            name: "teardown".to_string(),
+            command: Command::Simple("sh".to_string()),
+            steps: vec![missing()],
+            teardown: vec![touch("after_main"), cleaned()],
+            ..Scenario::default()
+        };
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(marker("after_main").exists());
+        assert!(
+            matches!(
+                &result.trace.outcome,
+                TraceOutcome::Error { message, .. } if message.contains("never on screen")
+            ),
+            "{:?}",
+            result.trace.outcome
+        );
+        assert_eq!(result.trace.steps.len(), 3);
+
+        // A failed setup skips the main steps but not the teardown
+        let scenario = Scenario {
+            name: "setup".to_string(),
+            command: Command::Simple("sh".to_string()),
+            setup: vec![missing()],
+            steps: vec![touch("main"), cleaned()],
+            teardown: vec![touch("after_setup"), cleaned()],
+            ..Scenario::default()
+        };
+        let result = run_scenario(&scenario, &RunnerConfig::default());
+        assert!(!marker("main").exists());
+        assert!(marker("after_setup").exists());
+        assert!(matches!(result.trace.outcome, TraceOutcome::Error { .. }));
+    }
 }
@@ -3,11 +3,18 @@
 //! This module provides a declarative format for defining interaction scenarios.
 //! No imperative scripting is allowed - all interactions are declared as data.
 
+use crate::fuzzy::FuzzyAlgorithm;
+use crate::invariants::{CustomRegexSpec, JsonPathSpec, Severity, SignalBehavior};
+use crate::io_loop::JitterConfig;
+use crate::screen::{AttrFlags, CellAttrs, Color, GraphicsProtocol};
+use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 
 /// A complete test scenario
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Scenario {
     /// Scenario name
     pub name: String,
@@ -27,17 +34,56 @@ pub struct Scenario {
     #[serde(default)]
     pub env: HashMap<String, String>,
 
+    /// Working directory for the process; a `cwd` on a full command wins
+    #[serde(default)]
+    pub cwd: Option<String>,
+
+    /// Steps run before `steps`; a failure here skips `steps`
+    #[serde(default)]
+    pub setup: Vec<ScenarioStep>,
+
     /// Sequence of steps to execute
-    pub steps: Vec<Step>,
+    #[serde(default)]
+    pub steps: Vec<ScenarioStep>,
+
+    /// Steps always run last, even after a failed setup or step
+    #[serde(default)]
+    pub teardown: Vec<ScenarioStep>,
+
+    /// NDJSON file of further steps (one JSON step per line), streamed one
+    /// at a time after `steps` instead of being loaded into memory
+    #[serde(default)]
+    pub steps_file: Option<String>,
 
     /// Invariants to check throughout execution
     #[serde(default)]
-    pub invariants: Vec<InvariantRef>,
+    pub invariants: Vec<ScenarioInvariant>,
+
+    /// Checks over the entire output stream, evaluated once at run end
+    #[serde(default)]
+    pub output_assertions: OutputAssertions,
+
+    /// Exit code the program must finish with; a run passes only on a match
+    #[serde(default)]
+    pub expect_exit: Option<i32>,
+
+    /// Resource limits applied to the spawned process
+    #[serde(default)]
+    pub limits: ResourceLimits,
 
     /// Random seed for deterministic replay
     #[serde(default)]
     pub seed: Option<u64>,
 
+    /// Pin the clock the program sees: an RFC 3339 time, epoch seconds, or
+    /// `seed` for a time derived from the run's seed
+    #[serde(default)]
+    pub freeze_time: Option<String>,
+
+    /// Deliver process output in seeded chunks and delays, like a slow link
+    #[serde(default)]
+    pub io_jitter: Option<JitterConfig>,
+
     /// Timeout in milliseconds
     #[serde(default)]
     pub timeout_ms: Option<u64>,
@@ -55,17 +101,26 @@ impl Default for Scenario {
             command: Command::Simple(String::new()),
             terminal: TerminalConfig::default(),
             env: HashMap::new(),
+            setup: Vec::new(),
             steps: Vec::new(),
+            teardown: Vec::new(),
             invariants: Vec::new(),
             seed: None,
+            freeze_time: None,
+            io_jitter: None,
             timeout_ms: Some(30000), // 30 second default timeout
             tags: Vec::new(),
+            steps_file: None,
+            output_assertions: OutputAssertions::default(),
+            expect_exit: None,
+            limits: ResourceLimits::default(),
+            cwd: None,
         }
     }
 }
 
 /// Command to execute
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum Command {
     /// Simple command string
@@ -104,6 +159,14 @@ impl Command {
         }
     }
 
+    /// Get the working directory set on the command, if any
+    pub fn cwd(&self) -> Option<&str> {
+        match self {
+            Command::Simple(_) => None,
+            Command::Full { cwd, .. } => cwd.as_deref(),
+        }
+    }
+
     /// Get the command as a single shell-safe string.
     ///
     /// For simple commands, returns the command as-is.
@@ -164,7 +227,7 @@ fn shell_escape(s: &str) -> String {
 }
 
 /// A tag for categorizing and filtering scenarios
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub struct Tag {
     /// Tag name (e.g., "slow", "network", "integration")
     pub name: String,
@@ -196,8 +259,41 @@ impl Tag {
     }
 }
 
+/// Run-level assertions over everything the process printed
+///
+/// Matched as plain substrings against the raw output stream decoded as
+/// (lossy) UTF-8, regardless of what is on screen at the end.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct OutputAssertions {
+    /// Text that must appear somewhere in the output
+    #[serde(default)]
+    pub must_contain: Vec<String>,
+    /// Text that must never appear in the output
+    #[serde(default)]
+    pub must_not_contain: Vec<String>,
+}
+
+impl OutputAssertions {
+    /// Whether there is anything to check
+    pub fn is_empty(&self) -> bool {
+        self.must_contain.is_empty() && self.must_not_contain.is_empty()
+    }
+}
+
+/// Resource limits for the process under test
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ResourceLimits {
+    /// CPU time in seconds; the process is killed by SIGXCPU when exceeded
+    #[serde(default)]
+    pub cpu_secs: Option<u64>,
+
+    /// Address space in megabytes; allocations beyond it fail
+    #[serde(default)]
+    pub memory_mb: Option<u64>,
+}
+
 /// Terminal configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct TerminalConfig {
     /// Number of columns
     #[serde(default = "default_cols")]
@@ -206,6 +302,11 @@ pub struct TerminalConfig {
     /// Number of rows
     #[serde(default = "default_rows")]
     pub rows: u16,
+
+    /// Send stderr to a separate pipe instead of the terminal, so it stays
+    /// off the screen and can be checked with `assert_stderr`
+    #[serde(default)]
+    pub separate_stderr: bool,
 }
 
 fn default_cols() -> u16 {
@@ -220,12 +321,113 @@ impl Default for TerminalConfig {
         Self {
             cols: default_cols(),
             rows: default_rows(),
+            separate_stderr: false,
+        }
+    }
+}
+
+/// Where a top-level step is written in a scenario
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepSource {
+    /// Step list holding the step: `setup`, `steps` or `teardown`
+    pub phase: String,
+    /// Index of the step within that list
+    pub index: usize,
+}
+
+/// A step entry in a scenario, with per-step options
+///
+/// Options sit alongside the step's own fields. `timeout_ms` is read from
+/// every step; wait steps also use it as their wait timeout.
+#[derive(Debug, Clone)]
+pub struct ScenarioStep {
+    /// The step to execute
+    pub step: Step,
+
+    /// Tick budget for the step in milliseconds (10 ms per tick)
+    pub timeout_ms: Option<u64>,
+
+    /// Human-readable note recorded with the step in the trace
+    pub comment: Option<String>,
+}
+
+impl From<Step> for ScenarioStep {
+    fn from(step: Step) -> Self {
+        Self {
+            step,
+            timeout_ms: None,
+            comment: None,
+        }
+    }
+}
+
+impl Serialize for ScenarioStep {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::Error;
+
+        let mut value = serde_json::to_value(&self.step).map_err(S::Error::custom)?;
+        if let (Some(timeout_ms), Some(fields)) = (self.timeout_ms, value.as_object_mut()) {
+            let slot = fields
+                .entry("timeout_ms")
+                .or_insert(serde_json::Value::Null);
+            if slot.is_null() {
+                *slot = timeout_ms.into();
+            }
         }
+        if let (Some(comment), Some(fields)) = (&self.comment, value.as_object_mut()) {
+            fields.insert("comment".to_string(), comment.clone().into());
+        }
+        value.serialize(serializer)
+    }
+}
+
+impl JsonSchema for ScenarioStep {
+    fn schema_name() -> String {
+        "ScenarioStep".to_string()
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+
+    // `timeout_ms` and `comment` sit among the step's own fields, which the
+    // schema leaves open
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        gen.subschema_for::<Step>()
+    }
+}
+
+impl<'de> Deserialize<'de> for ScenarioStep {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let timeout_ms = match value.get("timeout_ms") {
+            None | Some(serde_json::Value::Null) => None,
+            Some(v) => Some(
+                v.as_u64()
+                    .ok_or_else(|| D::Error::custom("timeout_ms must be a non-negative integer"))?,
+            ),
+        };
+        let comment = match value.get("comment") {
+            None | Some(serde_json::Value::Null) => None,
+            Some(v) => Some(
+                v.as_str()
+                    .ok_or_else(|| D::Error::custom("comment must be a string"))?
+                    .to_string(),
+            ),
+        };
+        let step = Step::deserialize(value).map_err(D::Error::custom)?;
+        Ok(Self {
+            step,
+            timeout_ms,
+            comment,
+        })
     }
 }
 
 /// A single step in the scenario
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "action")]
 pub enum Step {
     /// Wait for output matching a pattern
@@ -236,6 +438,9 @@ pub enum Step {
         /// Timeout in milliseconds
         #[serde(default)]
         timeout_ms: Option<u64>,
+        /// Match regardless of case, as if the pattern started with `(?i)`
+        #[serde(default)]
+        case_insensitive: bool,
     },
 
     /// Wait for a specific number of logical ticks
@@ -250,7 +455,7 @@ pub enum Step {
     WaitForFuzzy {
         /// Pattern to match (approximate)
         pattern: String,
-        /// Maximum edit distance (Levenshtein) for a match
+        /// Maximum edit distance for a match
         #[serde(default = "default_max_distance")]
         max_distance: usize,
         /// Minimum similarity ratio (0.0 to 1.0), overrides max_distance if set
@@ -259,6 +464,9 @@ pub enum Step {
         /// Timeout in milliseconds
         #[serde(default)]
         timeout_ms: Option<u64>,
+        /// How candidates are scored against the pattern
+        #[serde(default)]
+        algorithm: FuzzyAlgorithm,
     },
 
     /// Send keystrokes
@@ -289,12 +497,31 @@ pub enum Step {
     AssertScreen {
         /// Pattern to match
         pattern: String,
-        /// Whether to match anywhere on screen (default) or exact position
-        #[serde(default)]
+        /// Whether the pattern may match anywhere (default) or must match a
+        /// whole line, ignoring trailing blanks
+        #[serde(default = "default_true")]
         anywhere: bool,
-        /// Row to check (0-indexed, if not matching anywhere)
+        /// Only match against this row (0-indexed)
         #[serde(default)]
         row: Option<usize>,
+        /// Match regardless of case, as if the pattern started with `(?i)`
+        #[serde(default)]
+        case_insensitive: bool,
+    },
+
+    /// Assert a pattern matches within a rectangular area of the screen
+    #[serde(rename = "assert_region")]
+    AssertRegion {
+        /// First row of the area (0-indexed, inclusive)
+        top: usize,
+        /// First column of the area (0-indexed, inclusive)
+        left: usize,
+        /// Last row of the area (inclusive)
+        bottom: usize,
+        /// Last column of the area (inclusive)
+        right: usize,
+        /// Regex matched against the area's text, rows joined by newlines
+        pattern: String,
     },
 
     /// Assert cursor is at position
@@ -311,6 +538,10 @@ pub enum Step {
     Snapshot {
         /// Name for this snapshot
         name: String,
+        /// Golden text file the screen is written to on the first run (or
+        /// with `--update-snapshots`) and compared against afterwards
+        #[serde(default)]
+        path: Option<String>,
     },
 
     /// Check an invariant
@@ -335,6 +566,17 @@ pub enum Step {
         enable_tracking: bool,
     },
 
+    /// Click where a pattern first appears on screen (enables mouse
+    /// tracking first)
+    #[serde(rename = "click_text")]
+    ClickText {
+        /// Regex locating the label to click
+        pattern: String,
+        /// Button: 0=left, 1=middle, 2=right
+        #[serde(default = "default_mouse_button")]
+        button: u8,
+    },
+
     /// Send mouse scroll at position
     #[serde(rename = "mouse_scroll")]
     MouseScroll {
@@ -362,6 +604,17 @@ pub enum Step {
         timeout_ms: Option<u64>,
     },
 
+    /// Wait until the screen stops changing for a number of ticks
+    #[serde(rename = "wait_for_stable")]
+    WaitForStable {
+        /// Consecutive ticks the screen must stay unchanged
+        #[serde(default = "default_stable_ticks")]
+        stable_ticks: u64,
+        /// Timeout in milliseconds
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+
     /// Assert screen does NOT contain pattern
     #[serde(rename = "assert_not_screen")]
     AssertNotScreen {
@@ -369,6 +622,19 @@ pub enum Step {
         pattern: String,
     },
 
+    /// Wait for a pattern, then assert more patterns on the same screen
+    #[serde(rename = "expect")]
+    Expect {
+        /// Pattern to wait for (regex)
+        wait_for: String,
+        /// Patterns that must all match the screen once `wait_for` has
+        #[serde(default)]
+        then_assert: Vec<String>,
+        /// Timeout for the wait in milliseconds
+        #[serde(default)]
+        timeout_ms: Option<u64>,
+    },
+
     /// Capture a screenshot of the current screen state
     #[serde(rename = "take_screenshot")]
     TakeScreenshot {
@@ -396,11 +662,120 @@ pub enum Step {
         /// Whether to compare text (default true)
         #[serde(default = "default_true")]
         compare_text: bool,
+        /// Ignore attribute-only differences in trailing blank cells
+        #[serde(default)]
+        ignore_trailing_blanks: bool,
+        /// Don't compare cursor positions
+        #[serde(default)]
+        ignore_cursor: bool,
+        /// Include a map of differing cells, drawn with this character, in
+        /// the mismatch error
+        #[serde(default)]
+        diff_char: Option<char>,
+    },
+
+    /// Assert the screen's state hash equals a pinned value
+    #[serde(rename = "assert_screen_hash")]
+    AssertScreenHash {
+        /// Expected `Screen::state_hash` value
+        hash: u64,
+    },
+
+    /// Type keys and assert they are not echoed (e.g. at a password prompt)
+    #[serde(rename = "assert_no_echo")]
+    AssertNoEcho {
+        /// Keys to type
+        keys: KeySequence,
+    },
+
+    /// Assert the current rendition (the SGR state applied to new text)
+    #[serde(rename = "assert_sgr_state")]
+    AssertSgrState {
+        /// Expected rendition
+        #[serde(default)]
+        expected: SgrState,
+    },
+
+    /// Assert the styling of a single cell
+    #[serde(rename = "assert_cell_attr")]
+    AssertCellAttr {
+        /// Row (0-indexed)
+        row: usize,
+        /// Column (0-indexed)
+        col: usize,
+        /// Attributes to check; omitted ones are ignored
+        #[serde(flatten)]
+        expected: CellAttrCheck,
+    },
+
+    /// Assert that inline images (Sixel or Kitty graphics) were drawn
+    #[serde(rename = "assert_graphics")]
+    AssertGraphics {
+        /// Only count images of this protocol
+        #[serde(default)]
+        protocol: Option<GraphicsProtocol>,
+        /// Minimum number of images seen since the run started
+        #[serde(default = "default_min_images")]
+        min_count: u64,
+    },
+
+    /// Assert the cursor is currently hidden (DECTCEM off), e.g. mid-redraw
+    #[serde(rename = "assert_cursor_hidden")]
+    AssertCursorHidden,
+
+    /// Assert that everything written to stderr so far matches a pattern
+    /// (needs `terminal.separate_stderr`)
+    #[serde(rename = "assert_stderr")]
+    AssertStderr {
+        /// Regex to match against the stderr output
+        pattern: String,
+    },
+
+    /// Mark a position in the step list that `goto_if` can jump back to
+    #[serde(rename = "label")]
+    Label {
+        /// Label name (unique within the scenario)
+        name: String,
+    },
+
+    /// Jump back to a label while a pattern is absent from the screen
+    ///
+    /// The jump is taken at most `max_iterations` times in a row; if the
+    /// pattern is still absent after that, the step fails.
+    #[serde(rename = "goto_if")]
+    GotoIf {
+        /// Label to jump back to
+        label: String,
+        /// Regex pattern that ends the loop once it appears
+        pattern: String,
+        /// Maximum number of jumps before failing
+        max_iterations: u32,
+    },
+
+    /// Run one of two blocks of steps depending on the current screen
+    #[serde(rename = "if_screen")]
+    IfScreen {
+        /// Regex pattern checked against the screen text
+        pattern: String,
+        /// Steps to run if the pattern is on screen
+        then: Vec<ScenarioStep>,
+        /// Steps to run otherwise
+        #[serde(default, rename = "else")]
+        else_: Vec<ScenarioStep>,
+    },
+
+    /// Run a block of steps a fixed number of times
+    #[serde(rename = "repeat")]
+    Repeat {
+        /// Number of iterations
+        count: u32,
+        /// Steps to run on each iteration
+        steps: Vec<ScenarioStep>,
     },
 }
 
 /// Configuration for an ignore region during screenshot comparison
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct IgnoreRegionConfig {
     /// Top row (inclusive, 0-indexed)
     pub top: usize,
@@ -412,6 +787,113 @@ pub struct IgnoreRegionConfig {
     pub right: usize,
 }
 
+/// An expected SGR rendition; unset fields mean "off" / default color
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SgrState {
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub dim: bool,
+    #[serde(default)]
+    pub italic: bool,
+    #[serde(default)]
+    pub underline: bool,
+    #[serde(default)]
+    pub blink: bool,
+    #[serde(default)]
+    pub inverse: bool,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub strikethrough: bool,
+    /// Foreground color
+    #[serde(default)]
+    pub fg: Color,
+    /// Background color
+    #[serde(default)]
+    pub bg: Color,
+}
+
+impl SgrState {
+    /// Convert to the screen's attribute representation
+    pub fn to_attrs(&self) -> CellAttrs {
+        let mut flags = AttrFlags::empty();
+        flags.set(AttrFlags::BOLD, self.bold);
+        flags.set(AttrFlags::DIM, self.dim);
+        flags.set(AttrFlags::ITALIC, self.italic);
+        flags.set(AttrFlags::UNDERLINE, self.underline);
+        flags.set(AttrFlags::BLINK, self.blink);
+        flags.set(AttrFlags::INVERSE, self.inverse);
+        flags.set(AttrFlags::HIDDEN, self.hidden);
+        flags.set(AttrFlags::STRIKETHROUGH, self.strikethrough);
+        CellAttrs {
+            fg: self.fg,
+            bg: self.bg,
+            flags,
+        }
+    }
+}
+
+/// Expected attributes of a single cell; only the fields that are set are checked
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct CellAttrCheck {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bold: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dim: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub italic: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub underline: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blink: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inverse: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strikethrough: Option<bool>,
+    /// Foreground color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fg: Option<Color>,
+    /// Background color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bg: Option<Color>,
+}
+
+impl CellAttrCheck {
+    /// Describe every checked field that differs from `attrs`
+    pub fn mismatches(&self, attrs: &CellAttrs) -> Vec<String> {
+        let flags = [
+            ("bold", self.bold, AttrFlags::BOLD),
+            ("dim", self.dim, AttrFlags::DIM),
+            ("italic", self.italic, AttrFlags::ITALIC),
+            ("underline", self.underline, AttrFlags::UNDERLINE),
+            ("blink", self.blink, AttrFlags::BLINK),
+            ("inverse", self.inverse, AttrFlags::INVERSE),
+            ("hidden", self.hidden, AttrFlags::HIDDEN),
+            (
+                "strikethrough",
+                self.strikethrough,
+                AttrFlags::STRIKETHROUGH,
+            ),
+        ];
+        let mut mismatches = Vec::new();
+        for (name, expected, flag) in flags {
+            let actual = attrs.flags.contains(flag);
+            if expected.is_some_and(|expected| expected != actual) {
+                mismatches.push(format!("{} is {}", name, actual));
+            }
+        }
+        for (name, expected, actual) in [("fg", self.fg, attrs.fg), ("bg", self.bg, attrs.bg)] {
+            if let Some(expected) = expected.filter(|expected| *expected != actual) {
+                mismatches.push(format!("{} is {:?}, expected {:?}", name, actual, expected));
+            }
+        }
+        mismatches
+    }
+}
+
 fn default_max_diff_cells() -> usize {
     0
 }
@@ -421,7 +903,7 @@ fn default_true() -> bool {
 }
 
 /// Scroll direction for mouse scroll events
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum ScrollDirection {
     Up,
@@ -436,12 +918,16 @@ fn default_scroll_count() -> u8 {
     1
 }
 
+fn default_min_images() -> u64 {
+    1
+}
+
 fn default_max_distance() -> usize {
     3
 }
 
 /// Key sequence to send
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(untagged)]
 pub enum KeySequence {
     /// Plain text
@@ -451,7 +937,7 @@ pub enum KeySequence {
 }
 
 /// Special key names
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum SpecialKey {
     Enter,
@@ -538,7 +1024,7 @@ impl KeySequence {
 }
 
 /// Signal names
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum SignalName {
     Sigint,
@@ -547,6 +1033,7 @@ pub enum SignalName {
     Sigwinch,
     Sigstop,
     Sigcont,
+    Sighup,
 }
 
 impl SignalName {
@@ -561,18 +1048,69 @@ impl SignalName {
             SignalName::Sigwinch => Signal::SIGWINCH,
             SignalName::Sigstop => Signal::SIGSTOP,
             SignalName::Sigcont => Signal::SIGCONT,
+            SignalName::Sighup => Signal::SIGHUP,
+        }
+    }
+}
+
+/// An invariant entry in a scenario, with per-entry options
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ScenarioInvariant {
+    /// The invariant to check
+    #[serde(flatten)]
+    pub invariant: InvariantRef,
+
+    /// Severity of violations (`warning` violations don't halt execution)
+    #[serde(default)]
+    pub severity: Severity,
+
+    /// Whether a violation halts the run, overriding `severity`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fatal: Option<bool>,
+}
+
+impl ScenarioInvariant {
+    /// Severity after applying the `fatal` override
+    pub fn effective_severity(&self) -> Severity {
+        match self.fatal {
+            Some(true) => Severity::Error,
+            Some(false) => Severity::Warning,
+            None => self.severity,
+        }
+    }
+}
+
+impl From<InvariantRef> for ScenarioInvariant {
+    fn from(invariant: InvariantRef) -> Self {
+        Self {
+            invariant,
+            severity: Severity::Error,
+            fatal: None,
         }
     }
 }
 
 /// Reference to an invariant
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "type")]
 pub enum InvariantRef {
     /// Cursor must stay within bounds
     #[serde(rename = "cursor_bounds")]
     CursorBounds,
 
+    /// Cursor must stay inside a rectangular area, e.g. the focused pane
+    #[serde(rename = "cursor_in_region")]
+    CursorInRegion {
+        /// First row (0-indexed, inclusive)
+        top: usize,
+        /// First column (0-indexed, inclusive)
+        left: usize,
+        /// Last row (inclusive)
+        bottom: usize,
+        /// Last column (inclusive)
+        right: usize,
+    },
+
     /// No deadlock detection
     #[serde(rename = "no_deadlock")]
     NoDeadlock {
@@ -614,6 +1152,10 @@ pub enum InvariantRef {
         allowed_signals: Vec<i32>,
     },
 
+    /// Process must not exit while on the alternate screen
+    #[serde(rename = "alt_screen_restored")]
+    AltScreenRestored,
+
     /// Screen must be stable (not changing)
     #[serde(rename = "screen_stable")]
     ScreenStable {
@@ -626,6 +1168,25 @@ pub enum InvariantRef {
     #[serde(rename = "viewport_valid")]
     ViewportValid,
 
+    /// Application must never ring the terminal bell
+    #[serde(rename = "no_bell")]
+    NoBell,
+
+    /// Screen text must not contain stray control characters
+    #[serde(rename = "no_control_chars")]
+    NoControlChars,
+
+    /// Assertions must not run while an escape sequence is half-received
+    #[serde(rename = "no_pending_escape_at_assert")]
+    NoPendingEscapeAtAssert,
+
+    /// Scrollback history must stay within a line limit
+    #[serde(rename = "scrollback_bounded")]
+    ScrollbackBounded {
+        /// Maximum number of scrollback lines
+        max_lines: usize,
+    },
+
     /// Response time must be within limit
     #[serde(rename = "response_time")]
     ResponseTime {
@@ -660,6 +1221,43 @@ pub enum InvariantRef {
         /// Custom description for this invariant
         #[serde(default)]
         description: Option<String>,
+        /// Failure message template; supports `{tick}`, `{step}`,
+        /// `{cursor_row}`, `{cursor_col}` and `{screen_preview}`
+        #[serde(default)]
+        message: Option<String>,
+    },
+
+    /// Regex with constraints on named captures
+    #[serde(rename = "custom_regex")]
+    CustomRegex(CustomRegexSpec),
+
+    /// Condition on a JSON path into the last JSON value on screen
+    #[serde(rename = "json_path")]
+    JsonPath(JsonPathSpec),
+
+    /// Process must react to a sent signal with the given behavior
+    #[serde(rename = "signal_handled_correctly")]
+    SignalHandledCorrectly {
+        /// Signal the behavior applies to
+        signal: SignalName,
+        /// Expected reaction (exit, ignore, restart, custom)
+        behavior: SignalBehavior,
+    },
+
+    /// Screen or exit state must change within a tick budget after a signal
+    #[serde(rename = "signal_acknowledged")]
+    SignalAcknowledged {
+        /// Signal to watch for
+        signal: SignalName,
+        /// Ticks the process has to react once the signal is sent
+        within_ticks: u64,
+    },
+
+    /// Something must appear on the screen within a tick budget of the start
+    #[serde(rename = "startup_output")]
+    StartupOutput {
+        /// Ticks the process has to draw anything
+        within_ticks: u64,
     },
 }
 
@@ -671,6 +1269,171 @@ fn default_stable_ticks() -> u64 {
     10
 }
 
+/// Render an ignored field's path the way validation errors spell paths,
+/// e.g. `steps[2].pattern`
+fn ignored_path(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+
+    match path {
+        Path::Root => String::new(),
+        Path::Seq { parent, index } => format!("{}[{}]", ignored_path(parent), index),
+        Path::Map { parent, key } => match ignored_path(parent) {
+            parent if parent.is_empty() => key.clone(),
+            parent => format!("{}.{}", parent, key),
+        },
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => ignored_path(parent),
+    }
+}
+
+/// Fields each variant of an internally tagged enum accepts, keyed by the
+/// value of its `tag` field, as listed in the enum's JSON Schema
+fn variant_fields<T: JsonSchema>(tag: &str) -> HashMap<String, Vec<String>> {
+    let schema = serde_json::to_value(schemars::schema_for!(T)).unwrap_or_default();
+    let variants = schema["oneOf"].as_array().cloned().unwrap_or_default();
+    variants
+        .iter()
+        .filter_map(|variant| {
+            let properties = variant["properties"].as_object()?;
+            let name = properties.get(tag)?["enum"][0].as_str()?;
+            Some((name.to_string(), properties.keys().cloned().collect()))
+        })
+        .collect()
+}
+
+/// Unknown keys in the scenario's steps and invariants
+///
+/// Both are internally tagged enums that serde buffers before picking a
+/// variant (steps behind [`ScenarioStep`]'s own `Deserialize`, invariants
+/// behind a `flatten`), so `serde_ignored` never sees their keys. Entries
+/// without a known tag are left to the deserializer to reject.
+fn unknown_entry_fields(document: &serde_json::Value) -> Vec<ValidationError> {
+    let steps = variant_fields::<Step>("action");
+    let invariants = variant_fields::<InvariantRef>("type");
+    let mut unknown = Vec::new();
+    for section in ["setup", "steps", "teardown"] {
+        unknown_step_fields(&document[section], section, &steps, &mut unknown);
+    }
+    for (i, entry) in document["invariants"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .enumerate()
+    {
+        let Some(entry) = entry.as_object() else {
+            continue;
+        };
+        let Some(known) = entry
+            .get("type")
+            .and_then(|t| t.as_str())
+            .and_then(|t| invariants.get(t))
+        else {
+            continue;
+        };
+        for key in entry.keys() {
+            if !known.contains(key) && key != "severity" && key != "fatal" {
+                unknown.push(ValidationError {
+                    message: "Unknown field".to_string(),
+                    path: format!("invariants[{}].{}", i, key),
+                });
+            }
+        }
+    }
+    unknown
+}
+
+/// Check each step in `list` against `steps`, recursing into nested blocks
+fn unknown_step_fields(
+    list: &serde_json::Value,
+    path: &str,
+    steps: &HashMap<String, Vec<String>>,
+    unknown: &mut Vec<ValidationError>,
+) {
+    for (i, entry) in list.as_array().into_iter().flatten().enumerate() {
+        let Some(entry) = entry.as_object() else {
+            continue;
+        };
+        let Some(known) = entry
+            .get("action")
+            .and_then(|a| a.as_str())
+            .and_then(|a| steps.get(a))
+        else {
+            continue;
+        };
+        let step_path = format!("{}[{}]", path, i);
+        for (key, value) in entry {
+            if !known.contains(key) && key != "timeout_ms" && key != "comment" {
+                unknown.push(ValidationError {
+                    message: "Unknown field".to_string(),
+                    path: format!("{}.{}", step_path, key),
+                });
+            } else if value.is_array() {
+                unknown_step_fields(value, &format!("{}.{}", step_path, key), steps, unknown);
+            }
+        }
+    }
+}
+
+/// Earliest time a `freeze_time: seed` clock is set to (2000-01-01T00:00:00Z)
+const SEEDED_EPOCH_BASE: i64 = 946_684_800;
+
+/// Seconds that seeded clocks are spread over after the base (about 20 years)
+const SEEDED_EPOCH_SPAN: u64 = 20 * 365 * 24 * 60 * 60;
+
+/// Epoch seconds a `freeze_time` value pins the clock to
+///
+/// `seed` maps the run's seed to a time, so runs with the same seed see the
+/// same clock.
+pub fn frozen_epoch(freeze_time: &str, seed: u64) -> Result<i64, String> {
+    if freeze_time == "seed" {
+        return Ok(SEEDED_EPOCH_BASE + (seed % SEEDED_EPOCH_SPAN) as i64);
+    }
+    if let Ok(epoch) = freeze_time.parse::<i64>() {
+        return Ok(epoch);
+    }
+    chrono::DateTime::parse_from_rfc3339(freeze_time)
+        .map(|time| time.timestamp())
+        .map_err(|_| {
+            format!(
+                "Invalid freeze_time '{}': expected an RFC 3339 time, epoch seconds or 'seed'",
+                freeze_time
+            )
+        })
+}
+
+/// File format of a scenario
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScenarioFormat {
+    Yaml,
+    Json,
+    Toml,
+}
+
+impl ScenarioFormat {
+    /// Format by file extension: `.json` and `.toml`, otherwise YAML
+    pub fn from_path(path: &Path) -> Self {
+        match path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .as_deref()
+        {
+            Some("json") => Self::Json,
+            Some("toml") => Self::Toml,
+            _ => Self::Yaml,
+        }
+    }
+
+    /// Name for messages, e.g. "YAML"
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Yaml => "YAML",
+            Self::Json => "JSON",
+            Self::Toml => "TOML",
+        }
+    }
+}
+
 /// Validation error
 #[derive(Debug, Clone)]
 pub struct ValidationError {
@@ -697,6 +1460,76 @@ impl Scenario {
         serde_json::from_str(json)
     }
 
+    /// Load a scenario from TOML
+    pub fn _from_toml(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
+
+    /// Load a scenario in `format`
+    pub fn parse(content: &str, format: ScenarioFormat) -> Result<Self, String> {
+        match format {
+            ScenarioFormat::Yaml => Self::_from_yaml(content).map_err(|e| e.to_string()),
+            ScenarioFormat::Json => Self::_from_json(content).map_err(|e| e.to_string()),
+            ScenarioFormat::Toml => Self::_from_toml(content).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Like [`Scenario::parse`], also returning every field serde ignored
+    pub fn parse_strict(
+        content: &str,
+        format: ScenarioFormat,
+    ) -> Result<(Self, Vec<ValidationError>), String> {
+        match format {
+            ScenarioFormat::Yaml => Self::from_yaml_strict(content).map_err(|e| e.to_string()),
+            ScenarioFormat::Json => Self::from_json_strict(content).map_err(|e| e.to_string()),
+            ScenarioFormat::Toml => Self::deserialize_strict(
+                toml::Deserializer::new(content),
+                toml::from_str(content).ok(),
+            )
+            .map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Load a scenario from YAML, also returning every field serde ignored
+    pub fn from_yaml_strict(yaml: &str) -> Result<(Self, Vec<ValidationError>), serde_yaml::Error> {
+        Self::deserialize_strict(
+            serde_yaml::Deserializer::from_str(yaml),
+            serde_yaml::from_str(yaml).ok(),
+        )
+    }
+
+    /// Load a scenario from JSON, also returning every field serde ignored
+    pub fn from_json_strict(json: &str) -> Result<(Self, Vec<ValidationError>), serde_json::Error> {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        let parsed = Self::deserialize_strict(&mut deserializer, serde_json::from_str(json).ok())?;
+        deserializer.end()?;
+        Ok(parsed)
+    }
+
+    /// `document` is the same input as a plain value, used to check the keys
+    /// of steps and invariants that `serde_ignored` can't see
+    fn deserialize_strict<'de, D: serde::Deserializer<'de>>(
+        deserializer: D,
+        document: Option<serde_json::Value>,
+    ) -> Result<(Self, Vec<ValidationError>), D::Error> {
+        let mut unknown = Vec::new();
+        let scenario = serde_ignored::deserialize(deserializer, |path| {
+            unknown.push(ValidationError {
+                message: "Unknown field".to_string(),
+                path: ignored_path(&path),
+            })
+        })?;
+        if let Some(document) = document {
+            unknown.extend(unknown_entry_fields(&document));
+        }
+        Ok((scenario, unknown))
+    }
+
+    /// JSON Schema of the scenario format, for editor validation and completion
+    pub fn json_schema() -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(&schemars::schema_for!(Scenario))
+    }
+
     /// Serialize to YAML
     pub fn _to_yaml(&self) -> Result<String, serde_yaml::Error> {
         serde_yaml::to_string(self)
@@ -707,8 +1540,25 @@ impl Scenario {
         serde_json::to_string_pretty(self)
     }
 
+    /// Derive a seed from a hash of the scenario's content.
+    ///
+    /// The scenario is serialized through `serde_json::Value` so map keys are
+    /// sorted and the result doesn't depend on `HashMap` iteration order.
+    pub fn content_seed(&self) -> u64 {
+        let canonical = serde_json::to_value(self)
+            .map(|v| v.to_string())
+            .unwrap_or_default();
+        seahash::hash(canonical.as_bytes())
+    }
+
     /// Validate the scenario
     pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        self.validate_with(false)
+    }
+
+    /// Like [`Scenario::validate`], compiling step patterns without a size
+    /// limit when `lift_regex_size_limit` is set, as such runs do
+    pub fn validate_with(&self, lift_regex_size_limit: bool) -> Result<(), Vec<ValidationError>> {
         let mut errors = Vec::new();
 
         // Validate name
@@ -719,6 +1569,19 @@ impl Scenario {
             });
         }
 
+        if self.limits.cpu_secs == Some(0) {
+            errors.push(ValidationError {
+                message: "CPU limit must be > 0".to_string(),
+                path: "limits.cpu_secs".to_string(),
+            });
+        }
+        if self.limits.memory_mb == Some(0) {
+            errors.push(ValidationError {
+                message: "Memory limit must be > 0".to_string(),
+                path: "limits.memory_mb".to_string(),
+            });
+        }
+
         // Validate terminal config
         if self.terminal.cols == 0 {
             errors.push(ValidationError {
@@ -736,16 +1599,42 @@ impl Scenario {
         // Validate tags
         self.validate_tags(&mut errors);
 
+        if let Some(Err(message)) = self.freeze_time.as_deref().map(|f| frozen_epoch(f, 0)) {
+            errors.push(ValidationError {
+                message,
+                path: "freeze_time".to_string(),
+            });
+        }
+
         // Validate steps
-        if self.steps.is_empty() {
+        if self.steps.is_empty() && self.steps_file.is_none() {
             errors.push(ValidationError {
-                message: "Scenario must have at least one step".to_string(),
+                message: "Scenario must have at least one step or a steps_file".to_string(),
                 path: "steps".to_string(),
             });
         }
 
-        for (i, step) in self.steps.iter().enumerate() {
-            self.validate_step(step, &format!("steps[{}]", i), &mut errors);
+        for (steps, path) in self.step_lists() {
+            for (i, step) in steps.iter().enumerate() {
+                self.validate_step(
+                    &step.step,
+                    &format!("{}[{}]", path, i),
+                    lift_regex_size_limit,
+                    &mut errors,
+                );
+            }
+            Self::validate_labels(steps, path, lift_regex_size_limit, &mut errors);
+        }
+
+        for (i, inv) in self.invariants.iter().enumerate() {
+            if let InvariantRef::CustomRegex(spec) = &inv.invariant {
+                if let Err(message) = spec.compile() {
+                    errors.push(ValidationError {
+                        message,
+                        path: format!("invariants[{}]", i),
+                    });
+                }
+            }
         }
 
         if errors.is_empty() {
@@ -789,24 +1678,192 @@ impl Scenario {
         }
     }
 
-    fn validate_step(&self, step: &Step, path: &str, errors: &mut Vec<ValidationError>) {
-        match step {
-            Step::WaitFor { pattern, .. } => {
-                if pattern.is_empty() {
+    /// Resolve a relative `steps_file` against the directory of the scenario
+    /// file at `scenario_path`
+    pub fn resolve_paths(&mut self, scenario_path: &Path) {
+        let Some(steps_file) = &self.steps_file else {
+            return;
+        };
+        let path = Path::new(steps_file);
+        if path.is_relative() {
+            if let Some(dir) = scenario_path.parent() {
+                self.steps_file = Some(dir.join(path).to_string_lossy().to_string());
+            }
+        }
+    }
+
+    /// Substitute `${VAR}` and `${VAR:-default}` from the process environment
+    ///
+    /// Applies to the command, `env` values and step patterns. `$${` is a
+    /// literal `${`. Undefined variables without a default are errors.
+    pub fn resolve_env(&mut self) -> Result<(), Vec<ValidationError>> {
+        self.resolve_env_with(|name| std::env::var(name).ok())
+    }
+
+    /// Like [`Scenario::resolve_env`], looking variables up with `lookup`
+    pub fn resolve_env_with(
+        &mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        self.interpolated_fields(&mut |value, path| match interpolate_env(value, &lookup) {
+            Ok(resolved) => *value = resolved,
+            Err(message) => errors.push(ValidationError { message, path }),
+        });
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Like [`Scenario::resolve_env_with`], also returning the values
+    /// substituted for variables whose names `sensitive` matches
+    ///
+    /// A saved trace holds the resolved scenario, so these are what it must
+    /// mask (see [`crate::trace::RedactionPolicy::with_values`]).
+    pub fn resolve_env_secrets(
+        &mut self,
+        lookup: impl Fn(&str) -> Option<String>,
+        sensitive: impl Fn(&str) -> bool,
+    ) -> Result<Vec<String>, Vec<ValidationError>> {
+        let secrets = std::cell::RefCell::new(Vec::new());
+        self.resolve_env_with(|name| {
+            let value = lookup(name);
+            if sensitive(name) {
+                secrets.borrow_mut().extend(value.clone());
+            }
+            value
+        })?;
+        Ok(secrets.into_inner())
+    }
+
+    /// Replace each of `values` with `mask` in every field `${VAR}` is
+    /// expanded in
+    pub fn mask_values(&mut self, values: &[String], mask: &str) {
+        self.interpolated_fields(&mut |value, _| mask_values_in(value, values, mask));
+    }
+
+    /// Call `f` with each field `${VAR}` is expanded in and its path
+    fn interpolated_fields(&mut self, f: &mut impl FnMut(&mut String, String)) {
+        match &mut self.command {
+            Command::Simple(command) => f(command, "command".to_string()),
+            Command::Full {
+                program, args, cwd, ..
+            } => {
+                f(program, "command.program".to_string());
+                for (i, arg) in args.iter_mut().enumerate() {
+                    f(arg, format!("command.args[{}]", i));
+                }
+                if let Some(cwd) = cwd {
+                    f(cwd, "command.cwd".to_string());
+                }
+            }
+        }
+        if let Some(cwd) = &mut self.cwd {
+            f(cwd, "cwd".to_string());
+        }
+        for (key, value) in self.env.iter_mut() {
+            f(value, format!("env.{}", key));
+        }
+        resolve_step_patterns(&mut self.setup, "setup", f);
+        resolve_step_patterns(&mut self.steps, "steps", f);
+        resolve_step_patterns(&mut self.teardown, "teardown", f);
+    }
+
+    /// The setup, main and teardown step lists with their field names
+    fn step_lists(&self) -> [(&[ScenarioStep], &'static str); 3] {
+        [
+            (&self.setup, "setup"),
+            (&self.steps, "steps"),
+            (&self.teardown, "teardown"),
+        ]
+    }
+
+    /// Validate label names and `goto_if` targets within one step list
+    fn validate_labels(
+        steps: &[ScenarioStep],
+        path: &str,
+        lift_regex_size_limit: bool,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        use std::collections::HashSet;
+
+        let mut labels = HashSet::new();
+        for (i, step) in steps.iter().enumerate() {
+            if let Step::Label { name } = &step.step {
+                if name.trim().is_empty() {
                     errors.push(ValidationError {
-                        message: "Pattern cannot be empty".to_string(),
-                        path: format!("{}.pattern", path),
+                        message: "Label name cannot be empty".to_string(),
+                        path: format!("{}[{}].name", path, i),
+                    });
+                } else if !labels.insert(name.as_str()) {
+                    errors.push(ValidationError {
+                        message: format!("Duplicate label: '{}'", name),
+                        path: format!("{}[{}].name", path, i),
                     });
                 }
             }
-            Step::WaitTicks { ticks } => {
-                if *ticks == 0 {
+        }
+
+        for (i, step) in steps.iter().enumerate() {
+            if let Step::GotoIf {
+                label,
+                pattern,
+                max_iterations,
+            } = &step.step
+            {
+                if !labels.contains(label.as_str()) {
+                    errors.push(ValidationError {
+                        message: format!("Unknown label: '{}'", label),
+                        path: format!("{}[{}].label", path, i),
+                    });
+                }
+                check_pattern(
+                    pattern,
+                    format!("{}[{}].pattern", path, i),
+                    lift_regex_size_limit,
+                    errors,
+                );
+                if *max_iterations == 0 {
                     errors.push(ValidationError {
-                        message: "Ticks must be > 0".to_string(),
-                        path: format!("{}.ticks", path),
+                        message: "max_iterations must be > 0".to_string(),
+                        path: format!("{}[{}].max_iterations", path, i),
                     });
                 }
             }
+        }
+    }
+
+    fn validate_step(
+        &self,
+        step: &Step,
+        path: &str,
+        lift_regex_size_limit: bool,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        match step {
+            Step::WaitFor { pattern, .. } | Step::ClickText { pattern, .. }
+                if pattern.is_empty() =>
+            {
+                errors.push(ValidationError {
+                    message: "Pattern cannot be empty".to_string(),
+                    path: format!("{}.pattern", path),
+                });
+            }
+            Step::WaitTicks { ticks } if *ticks == 0 => {
+                errors.push(ValidationError {
+                    message: "Ticks must be > 0".to_string(),
+                    path: format!("{}.ticks", path),
+                });
+            }
+            Step::WaitForStable { stable_ticks, .. } if *stable_ticks == 0 => {
+                errors.push(ValidationError {
+                    message: "stable_ticks must be > 0".to_string(),
+                    path: format!("{}.stable_ticks", path),
+                });
+            }
             Step::Resize { cols, rows } => {
                 if *cols == 0 {
                     errors.push(ValidationError {
@@ -821,17 +1878,419 @@ impl Scenario {
                     });
                 }
             }
-            Step::Snapshot { name } => {
+            Step::Snapshot {
+                name,
+                path: snapshot_path,
+            } => {
                 if name.is_empty() {
                     errors.push(ValidationError {
                         message: "Snapshot name cannot be empty".to_string(),
                         path: format!("{}.name", path),
                     });
                 }
+                if snapshot_path.as_deref() == Some("") {
+                    errors.push(ValidationError {
+                        message: "Snapshot path cannot be empty".to_string(),
+                        path: format!("{}.path", path),
+                    });
+                }
+            }
+            Step::Repeat { count, steps } => {
+                if *count == 0 {
+                    errors.push(ValidationError {
+                        message: "Repeat count must be > 0".to_string(),
+                        path: format!("{}.count", path),
+                    });
+                }
+                if steps.is_empty() {
+                    errors.push(ValidationError {
+                        message: "Repeat must have at least one step".to_string(),
+                        path: format!("{}.steps", path),
+                    });
+                }
+                self.validate_block(
+                    steps,
+                    &format!("{}.steps", path),
+                    lift_regex_size_limit,
+                    errors,
+                );
+            }
+            Step::IfScreen {
+                pattern,
+                then,
+                else_,
+            } => {
+                check_pattern(
+                    pattern,
+                    format!("{}.pattern", path),
+                    lift_regex_size_limit,
+                    errors,
+                );
+                let then_path = format!("{}.then", path);
+                self.validate_block(then, &then_path, lift_regex_size_limit, errors);
+                let else_path = format!("{}.else", path);
+                self.validate_block(else_, &else_path, lift_regex_size_limit, errors);
+            }
+            Step::AssertRegion {
+                top,
+                left,
+                bottom,
+                right,
+                pattern,
+            } => {
+                if top > bottom || left > right {
+                    errors.push(ValidationError {
+                        message: "Region must have top <= bottom and left <= right".to_string(),
+                        path: path.to_string(),
+                    });
+                }
+                check_pattern(
+                    pattern,
+                    format!("{}.pattern", path),
+                    lift_regex_size_limit,
+                    errors,
+                );
+            }
+            Step::AssertStderr { pattern } => {
+                check_pattern(
+                    pattern,
+                    format!("{}.pattern", path),
+                    lift_regex_size_limit,
+                    errors,
+                );
+                if !self.terminal.separate_stderr {
+                    errors.push(ValidationError {
+                        message: "assert_stderr needs terminal.separate_stderr: true".to_string(),
+                        path: path.to_string(),
+                    });
+                }
+            }
+            Step::Expect {
+                wait_for,
+                then_assert,
+                ..
+            } => {
+                if wait_for.is_empty() {
+                    errors.push(ValidationError {
+                        message: "Pattern cannot be empty".to_string(),
+                        path: format!("{}.wait_for", path),
+                    });
+                }
+                let patterns = std::iter::once((format!("{}.wait_for", path), wait_for)).chain(
+                    then_assert
+                        .iter()
+                        .enumerate()
+                        .map(|(j, p)| (format!("{}.then_assert[{}]", path, j), p)),
+                );
+                for (pattern_path, pattern) in patterns {
+                    check_pattern(pattern, pattern_path, lift_regex_size_limit, errors);
+                }
             }
             _ => {}
         }
     }
+
+    /// Validate the steps of a nested block (`repeat` body or `if_screen` branch)
+    fn validate_block(
+        &self,
+        steps: &[ScenarioStep],
+        path: &str,
+        lift_regex_size_limit: bool,
+        errors: &mut Vec<ValidationError>,
+    ) {
+        for (i, entry) in steps.iter().enumerate() {
+            let inner = format!("{}[{}]", path, i);
+            if matches!(entry.step, Step::Label { .. } | Step::GotoIf { .. }) {
+                errors.push(ValidationError {
+                    message: "label and goto_if are only allowed at the top level".to_string(),
+                    path: inner.clone(),
+                });
+            }
+            self.validate_step(&entry.step, &inner, lift_regex_size_limit, errors);
+        }
+    }
+}
+
+/// Compile a step pattern
+///
+/// Matching is linear-time, so the only risk is a pattern whose compiled form
+/// is huge (e.g. large counted repetitions). Those fail with the `regex`
+/// crate's own size-limit error unless `lift_size_limit` is set.
+pub fn compile_pattern(pattern: &str, lift_size_limit: bool) -> Result<Regex, regex::Error> {
+    let mut builder = regex::RegexBuilder::new(pattern);
+    if lift_size_limit {
+        builder.size_limit(usize::MAX);
+    }
+    builder.build()
+}
+
+/// Report `pattern` at `path` if it doesn't compile the way runs compile it
+fn check_pattern(
+    pattern: &str,
+    path: String,
+    lift_size_limit: bool,
+    errors: &mut Vec<ValidationError>,
+) {
+    if let Err(e) = compile_pattern(pattern, lift_size_limit) {
+        errors.push(ValidationError {
+            message: format!("Invalid pattern: {}", e),
+            path,
+        });
+    }
+}
+
+impl Step {
+    /// Replace each of `values` with `mask` in the step's patterns
+    pub fn mask_values(&mut self, values: &[String], mask: &str) {
+        resolve_patterns(self, "", &mut |value, _| {
+            mask_values_in(value, values, mask)
+        });
+    }
+}
+
+/// Apply `resolve` to every step pattern, descending into nested blocks
+fn resolve_step_patterns(
+    steps: &mut [ScenarioStep],
+    path: &str,
+    resolve: &mut impl FnMut(&mut String, String),
+) {
+    for (i, entry) in steps.iter_mut().enumerate() {
+        resolve_patterns(&mut entry.step, &format!("{}[{}]", path, i), resolve);
+    }
+}
+
+/// Apply `resolve` to each pattern of one step, including nested blocks
+fn resolve_patterns(step: &mut Step, path: &str, resolve: &mut impl FnMut(&mut String, String)) {
+    match step {
+        Step::WaitFor { pattern, .. }
+        | Step::WaitForFuzzy { pattern, .. }
+        | Step::WaitScreen { pattern, .. }
+        | Step::AssertScreen { pattern, .. }
+        | Step::AssertRegion { pattern, .. }
+        | Step::AssertNotScreen { pattern }
+        | Step::ClickText { pattern, .. }
+        | Step::AssertStderr { pattern }
+        | Step::GotoIf { pattern, .. } => resolve(pattern, format!("{}.pattern", path)),
+        Step::Expect {
+            wait_for,
+            then_assert,
+            ..
+        } => {
+            resolve(wait_for, format!("{}.wait_for", path));
+            for (j, pattern) in then_assert.iter_mut().enumerate() {
+                resolve(pattern, format!("{}.then_assert[{}]", path, j));
+            }
+        }
+        Step::IfScreen {
+            pattern,
+            then,
+            else_,
+        } => {
+            resolve(pattern, format!("{}.pattern", path));
+            resolve_step_patterns(then, &format!("{}.then", path), resolve);
+            resolve_step_patterns(else_, &format!("{}.else", path), resolve);
+        }
+        Step::Repeat { steps, .. } => {
+            resolve_step_patterns(steps, &format!("{}.steps", path), resolve);
+        }
+        _ => {}
+    }
+}
+
+/// Replace each of `values` in `text` with `mask`
+fn mask_values_in(text: &mut String, values: &[String], mask: &str) {
+    for value in values {
+        if text.contains(value.as_str()) {
+            *text = text.replace(value.as_str(), mask);
+        }
+    }
+}
+
+/// Expand `${VAR}` / `${VAR:-default}` references in `input`
+///
+/// Text that isn't a well-formed reference (e.g. a regex `${2}`) is kept.
+fn interpolate_env(input: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(start) = rest.find("${") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        // `$${` escapes a literal `${`
+        if output.ends_with('$') {
+            output.pop();
+            output.push_str("${");
+            rest = after;
+            continue;
+        }
+
+        let Some(end) = after.find('}') else {
+            output.push_str(&rest[start..]);
+            return Ok(output);
+        };
+        let reference = &after[..end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        let is_name = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+        if is_name {
+            match lookup(name).or_else(|| default.map(str::to_string)) {
+                Some(value) => output.push_str(&value),
+                None => return Err(format!("Undefined environment variable: {}", name)),
+            }
+        } else {
+            output.push_str(&rest[start..start + 2 + end + 1]);
+        }
+        rest = &after[end + 1..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// Streaming reader for an NDJSON steps file
+///
+/// Steps are parsed lazily, one line at a time, so arbitrarily long step
+/// lists run in constant memory. Blank lines are skipped.
+pub struct StepStream {
+    reader: std::io::BufReader<std::fs::File>,
+    path: String,
+    line: String,
+    line_number: usize,
+}
+
+impl StepStream {
+    /// Open a steps file for streaming
+    pub fn open(path: &str) -> Result<Self, String> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("Failed to open steps file '{}': {}", path, e))?;
+        Ok(Self {
+            reader: std::io::BufReader::new(file),
+            path: path.to_string(),
+            line: String::new(),
+            line_number: 0,
+        })
+    }
+
+    /// Capacity of the internal line buffer (bounded by the longest line)
+    pub fn buffer_capacity(&self) -> usize {
+        self.line.capacity()
+    }
+}
+
+impl Iterator for StepStream {
+    type Item = Result<ScenarioStep, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        use std::io::BufRead;
+
+        loop {
+            self.line.clear();
+            self.line_number += 1;
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) if self.line.trim().is_empty() => continue,
+                Ok(_) => {
+                    return Some(serde_json::from_str(self.line.trim()).map_err(|e| {
+                        format!("{}:{}: invalid step: {}", self.path, self.line_number, e)
+                    }))
+                }
+                Err(e) => return Some(Err(format!("{}: {}", self.path, e))),
+            }
+        }
+    }
+}
+
+/// Rewrite pinned `assert_screen_hash` values in a YAML or JSON scenario file
+///
+/// `updates` pairs a step's location with its new hash. Only the number after
+/// each step's `hash` key is replaced, so comments and layout are kept. JSON
+/// is located with the YAML parser, which reads it in document order.
+pub fn rewrite_screen_hashes(
+    content: &str,
+    updates: &[(StepSource, u64)],
+) -> Result<String, String> {
+    let doc: serde_yaml::Value =
+        serde_yaml::from_str(content).map_err(|e| format!("Invalid scenario: {}", e))?;
+
+    let mut edits = Vec::new();
+    for (source, hash) in updates {
+        let path = format!("{}[{}]", source.phase, source.index);
+        let step = doc
+            .get(source.phase.as_str())
+            .and_then(|steps| steps.get(source.index))
+            .and_then(|step| step.as_mapping())
+            .ok_or_else(|| format!("{} not found", path))?;
+        let pinned = step
+            .get("hash")
+            .and_then(serde_yaml::Value::as_u64)
+            .filter(|_| {
+                step.get("action").and_then(serde_yaml::Value::as_str) == Some("assert_screen_hash")
+            })
+            .ok_or_else(|| format!("{} is not an assert_screen_hash step", path))?;
+
+        // Several steps may pin the same value (e.g. a placeholder 0), so find
+        // which of the document's `hash: <pinned>` entries belongs to this one
+        let mut seen = 0;
+        let mut ordinal = None;
+        find_pinned_hash(&doc, step, pinned, &mut seen, &mut ordinal);
+        let pattern =
+            Regex::new(&format!(r#"\bhash"?\s*:\s*({})\b"#, pinned)).map_err(|e| e.to_string())?;
+        let matches: Vec<_> = pattern
+            .captures_iter(content)
+            .filter_map(|c| c.get(1))
+            .collect();
+        match ordinal {
+            Some(ordinal) if matches.len() == seen => {
+                edits.push((matches[ordinal].range(), hash.to_string()));
+            }
+            _ => return Err(format!("Could not locate the hash of {} in the file", path)),
+        }
+    }
+
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    let mut content = content.to_string();
+    for (range, hash) in edits {
+        content.replace_range(range, &hash);
+    }
+    Ok(content)
+}
+
+/// Count `hash: <pinned>` entries in document order, noting the position of
+/// the one belonging to `target`
+fn find_pinned_hash(
+    value: &serde_yaml::Value,
+    target: &serde_yaml::Mapping,
+    pinned: u64,
+    seen: &mut usize,
+    ordinal: &mut Option<usize>,
+) {
+    match value {
+        serde_yaml::Value::Mapping(map) => {
+            for (key, child) in map {
+                if key.as_str() == Some("hash") && child.as_u64() == Some(pinned) {
+                    if std::ptr::eq(map, target) {
+                        *ordinal = Some(*seen);
+                    }
+                    *seen += 1;
+                }
+                find_pinned_hash(child, target, pinned, seen, ordinal);
+            }
+        }
+        serde_yaml::Value::Sequence(items) => {
+            for item in items {
+                find_pinned_hash(item, target, pinned, seen, ordinal);
+            }
+        }
+        serde_yaml::Value::Tagged(tagged) => {
+            find_pinned_hash(&tagged.value, target, pinned, seen, ordinal)
+        }
+        _ => {}
+    }
 }
 
 #[cfg(test)]
@@ -849,11 +2308,21 @@ steps:
     pattern: "hello"
   - action: send_keys
     keys: "exit\n"
+  - action: assert_screen
+    pattern: "bye"
 "#;
 
         let scenario = Scenario::_from_yaml(yaml).unwrap();
         assert_eq!(scenario.name, "test scenario");
-        assert_eq!(scenario.steps.len(), 2);
+        assert_eq!(scenario.steps.len(), 3);
+        assert!(matches!(
+            scenario.steps[2].step,
+            Step::AssertScreen {
+                anywhere: true,
+                row: None,
+                ..
+            }
+        ));
     }
 
     #[test]
@@ -872,6 +2341,537 @@ steps:
         assert_eq!(scenario.steps.len(), 2);
     }
 
+    #[test]
+    fn parse_toml_scenario() {
+        let yaml = r#"
+name: "menu"
+command: "./menu"
+terminal:
+  cols: 100
+  rows: 30
+env:
+  LANG: "C"
+steps:
+  - action: wait_for
+    pattern: "Choose"
+    timeout_ms: 500
+  - action: send_keys
+    keys: "2\n"
+invariants:
+  - type: cursor_bounds
+"#;
+        let toml = r#"
+name = "menu"
+command = "./menu"
+
+[terminal]
+cols = 100
+rows = 30
+
+[env]
+LANG = "C"
+
+[[steps]]
+action = "wait_for"
+pattern = "Choose"
+timeout_ms = 500
+
+[[steps]]
+action = "send_keys"
+keys = "2\n"
+
+[[invariants]]
+type = "cursor_bounds"
+"#;
+
+        let from_yaml = Scenario::_from_yaml(yaml).unwrap();
+        let from_toml = Scenario::parse(toml, ScenarioFormat::Toml).unwrap();
+        assert_eq!(
+            serde_json::to_value(&from_toml).unwrap(),
+            serde_json::to_value(&from_yaml).unwrap()
+        );
+        assert_eq!(
+            ScenarioFormat::from_path(Path::new("menu.TOML")),
+            ScenarioFormat::Toml
+        );
+    }
+
+    #[test]
+    fn parse_custom_regex_invariant() {
+        let yaml = r#"
+name: "progress"
+command: "./download"
+steps:
+  - action: wait_for
+    pattern: "%"
+invariants:
+  - type: custom_regex
+    name: progress_in_range
+    pattern: '(?P<pct>\d+)%'
+    constraints:
+      - capture: pct
+        condition: '^(100|[1-9]?\d)$'
+"#;
+
+        let scenario = Scenario::_from_yaml(yaml).unwrap();
+        match &scenario.invariants[0].invariant {
+            InvariantRef::CustomRegex(spec) => {
+                assert_eq!(spec.name, "progress_in_range");
+                assert_eq!(spec.constraints.len(), 1);
+                assert_eq!(spec.constraints[0].capture, "pct");
+                assert!(spec.constraints[0].must_match);
+            }
+            other => panic!("Expected custom_regex invariant, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn validate_rejects_invalid_custom_regex() {
+        let yaml = r#"
+name: "progress"
+command: "./download"
+steps:
+  - action: wait_for
+    pattern: "%"
+invariants:
+  - type: custom_regex
+    name: progress_in_range
+    pattern: '(?P<pct>\d+)%'
+    constraints:
+      - capture: pct
+        condition: '^(100'
+"#;
+
+        let errors = Scenario::_from_yaml(yaml).unwrap().validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "invariants[0]");
+        assert!(
+            errors[0]
+                .message
+                .starts_with("Invalid condition for capture 'pct'"),
+            "{}",
+            errors[0].message
+        );
+    }
+
+    #[test]
+    fn content_seed_is_stable_and_content_dependent() {
+        let yaml = r#"
+name: "seeded"
+command: "./app"
+env:
+  A: "1"
+  B: "2"
+  C: "3"
+steps:
+  - action: send_keys
+    keys: "hello"
+"#;
+        let first = Scenario::_from_yaml(yaml).unwrap();
+        let second = Scenario::_from_yaml(yaml).unwrap();
+        assert_eq!(first.content_seed(), second.content_seed());
+
+        let modified = Scenario::_from_yaml(&yaml.replace("hello", "hello!")).unwrap();
+        assert_ne!(first.content_seed(), modified.content_seed());
+    }
+
+    #[test]
+    fn parse_assert_sgr_state() {
+        let yaml = r#"
+name: "sgr"
+command: "true"
+steps:
+  - action: assert_sgr_state
+    expected:
+      bold: true
+      fg: {Indexed: 1}
+  - action: assert_sgr_state
+"#;
+
+        let scenario = Scenario::_from_yaml(yaml).unwrap();
+        match &scenario.steps[0].step {
+            Step::AssertSgrState { expected } => {
+                let attrs = expected.to_attrs();
+                assert_eq!(attrs.flags, AttrFlags::BOLD);
+                assert_eq!(attrs.fg, Color::Indexed(1));
+                assert_eq!(attrs.bg, Color::Default);
+            }
+            other => panic!("unexpected step: {:?}", other),
+        }
+        assert!(matches!(
+            &scenario.steps[1].step,
+            Step::AssertSgrState { expected } if *expected == SgrState::default()
+        ));
+    }
+
+    #[test]
+    fn parse_assert_cell_attr() {
+        let yaml = r#"
+name: "cell"
+command: "true"
+steps:
+  - action: assert_cell_attr
+    row: 2
+    col: 0
+    bold: true
+    fg: {Indexed: 1}
+"#;
+
+        let scenario = Scenario::_from_yaml(yaml).unwrap();
+        match &scenario.steps[0].step {
+            Step::AssertCellAttr { row, col, expected } => {
+                assert_eq!((*row, *col), (2, 0));
+                assert_eq!(expected.bold, Some(true));
+                assert_eq!(expected.fg, Some(Color::Indexed(1)));
+                assert_eq!(expected.bg, None);
+            }
+            other => panic!("unexpected step: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_step_timeout_ms() {
+        let yaml = r#"
+name: "timeouts"
+command: "true"
+steps:
+  - action: wait_ticks
+    ticks: 100
+    timeout_ms: 200
+  - action: wait_for
+    pattern: "ready"
+    timeout_ms: 500
+  - action: send_keys
+    keys: "q"
+"#;
+
+        let scenario = Scenario::_from_yaml(yaml).unwrap();
+        assert_eq!(scenario.steps[0].timeout_ms, Some(200));
+        assert!(matches!(
+            scenario.steps[0].step,
+            Step::WaitTicks { ticks: 100 }
+        ));
+        // Wait steps share the field with their own wait timeout
+        assert_eq!(scenario.steps[1].timeout_ms, Some(500));
+        assert!(matches!(
+            scenario.steps[1].step,
+            Step::WaitFor {
+                timeout_ms: Some(500),
+                ..
+            }
+        ));
+        assert_eq!(scenario.steps[2].timeout_ms, None);
+
+        let json = serde_json::to_string(&scenario.steps).unwrap();
+        let reparsed: Vec<ScenarioStep> = serde_json::from_str(&json).unwrap();
+        assert_eq!(reparsed[0].timeout_ms, Some(200));
+        assert_eq!(reparsed[1].timeout_ms, Some(500));
+        assert_eq!(reparsed[2].timeout_ms, None);
+    }
+
+    #[test]
+    fn validate_compiles_patterns_like_runs() {
+        let yaml = r#"
+name: "oversized"
+command: "true"
+steps:
+  - action: if_screen
+    pattern: "\\w{1000}"
+    then: []
+"#;
+        let scenario = Scenario::_from_yaml(yaml).unwrap();
+        let errors = scenario.validate().unwrap_err();
+        assert_eq!(errors[0].path, "steps[0].pattern");
+        assert!(errors[0].message.contains("size limit"), "{}", errors[0]);
+        assert!(scenario.validate_with(true).is_ok());
+    }
+
+    #[test]
+    fn validate_recurses_into_repeat() {
+        let yaml = r#"
+name: "repeat"
+command: "true"
+steps:
+  - action: repeat
+    count: 2
+    steps:
+      - action: send_keys
+        keys: "j"
+      - action: repeat
+        count: 0
+        steps:
+          - action: wait_ticks
+            ticks: 0
+"#;
+
+        let errors = Scenario::_from_yaml(yaml).unwrap().validate().unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "steps[0].steps[1].count",
+                "steps[0].steps[1].steps[0].ticks"
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_covers_setup_and_teardown() {
+        let yaml = r#"
+name: "fixtures"
+command: "true"
+setup:
+  - action: wait_ticks
+    ticks: 0
+steps:
+  - action: send_keys
+    keys: "q"
+teardown:
+  - action: wait_for
+    pattern: ""
+"#;
+
+        let errors = Scenario::_from_yaml(yaml).unwrap().validate().unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["setup[0].ticks", "teardown[0].pattern"]);
+    }
+
+    #[test]
+    fn strict_parse_reports_unknown_fields() {
+        let yaml = r#"
+name: "typo"
+command: "true"
+terminal:
+  colz: 100
+steps:
+  - action: send_keys
+    keys: "q"
+invarients:
+  - type: cursor_bounds
+"#;
+
+        let (scenario, unknown) = Scenario::from_yaml_strict(yaml).unwrap();
+        assert!(scenario.invariants.is_empty());
+        let paths: Vec<&str> = unknown.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["terminal.colz", "invarients"]);
+
+        let (_, unknown) =
+            Scenario::from_json_strict(r#"{"name": "x", "command": "true", "stepz": []}"#).unwrap();
+        assert_eq!(unknown[0].path, "stepz");
+    }
+
+    #[test]
+    fn strict_parse_reports_unknown_fields_in_steps_and_invariants() {
+        let yaml = r#"
+name: "typo"
+command: "true"
+steps:
+  - action: wait_for
+    patern: "ready"
+    pattern: "ready"
+    timeot_ms: 500
+  - action: repeat
+    count: 2
+    steps:
+      - action: send_keys
+        keys: "q"
+        coment: "quit"
+invariants:
+  - type: cursor_bounds
+    severty: warning
+"#;
+
+        let (_, unknown) = Scenario::from_yaml_strict(yaml).unwrap();
+        let mut paths: Vec<&str> = unknown.iter().map(|e| e.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                "invariants[0].severty",
+                "steps[0].patern",
+                "steps[0].timeot_ms",
+                "steps[1].steps[0].coment",
+            ]
+        );
+    }
+
+    #[test]
+    fn json_schema_describes_steps_and_invariants() {
+        let schema: serde_json::Value =
+            serde_json::from_str(&Scenario::json_schema().unwrap()).unwrap();
+        let properties = &schema["properties"];
+        assert!(properties.get("steps").is_some());
+        assert!(properties.get("invariants").is_some());
+        assert!(schema["definitions"].get("Step").is_some());
+    }
+
+    #[test]
+    fn frozen_epoch_accepts_times_epochs_and_seed() {
+        assert_eq!(frozen_epoch("2024-01-01T00:00:00Z", 7), Ok(1_704_067_200));
+        assert_eq!(frozen_epoch("1700000000", 7), Ok(1_700_000_000));
+        assert_eq!(frozen_epoch("seed", 7), frozen_epoch("seed", 7));
+        assert_ne!(frozen_epoch("seed", 7), frozen_epoch("seed", 8));
+        assert!(frozen_epoch("yesterday", 7).is_err());
+    }
+
+    #[test]
+    fn validate_checks_both_if_screen_branches() {
+        let yaml = r#"
+name: "branch"
+command: "true"
+steps:
+  - action: if_screen
+    pattern: "("
+    then:
+      - action: label
+        name: "inner"
+    else:
+      - action: resize
+        cols: 0
+        rows: 24
+"#;
+
+        let errors = Scenario::_from_yaml(yaml).unwrap().validate().unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "steps[0].pattern",
+                "steps[0].then[0]",
+                "steps[0].else[0].cols"
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_env_substitutes_variables() {
+        let yaml = r#"
+name: "env"
+command: "${APP_DIR}/bin/app --port ${PORT:-8080} $${literal}"
+env:
+  DATA: "${APP_DIR}/data"
+steps:
+  - action: wait_for
+    pattern: "listening on ${PORT:-8080}$"
+  - action: send_keys
+    keys: "${Enter}"
+"#;
+        let lookup = |name: &str| (name == "APP_DIR").then(|| "/opt/app".to_string());
+
+        let mut scenario = Scenario::_from_yaml(yaml).unwrap();
+        scenario.resolve_env_with(lookup).unwrap();
+        assert!(matches!(
+            &scenario.command,
+            Command::Simple(c) if c == "/opt/app/bin/app --port 8080 ${literal}"
+        ));
+        assert_eq!(scenario.env["DATA"], "/opt/app/data");
+        assert!(matches!(
+            &scenario.steps[0].step,
+            Step::WaitFor { pattern, .. } if pattern == "listening on 8080$"
+        ));
+        // Key names are not environment references
+        assert!(matches!(
+            &scenario.steps[1].step,
+            Step::SendKeys { keys: KeySequence::Text(t) } if t == "${Enter}"
+        ));
+
+        let mut scenario =
+            Scenario::_from_yaml(&yaml.replace("${PORT:-8080} $", "${PORT} $")).unwrap();
+        let errors = scenario.resolve_env_with(lookup).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "command");
+        assert!(errors[0].message.contains("PORT"));
+    }
+
+    #[test]
+    fn resolve_env_secrets_returns_sensitive_values() {
+        let yaml = r#"
+name: "env"
+command: "deploy --token ${API_TOKEN} --to ${HOST}"
+steps:
+  - action: wait_for
+    pattern: "${API_TOKEN} accepted"
+"#;
+        let lookup = |name: &str| match name {
+            "API_TOKEN" => Some("s3cr3t".to_string()),
+            "HOST" => Some("example.com".to_string()),
+            _ => None,
+        };
+
+        let mut scenario = Scenario::_from_yaml(yaml).unwrap();
+        let secrets = scenario
+            .resolve_env_secrets(lookup, |name| name.contains("TOKEN"))
+            .unwrap();
+        assert_eq!(secrets, ["s3cr3t", "s3cr3t"]);
+
+        scenario.mask_values(&secrets, "***");
+        assert!(matches!(
+            &scenario.command,
+            Command::Simple(c) if c == "deploy --token *** --to example.com"
+        ));
+        assert!(matches!(
+            &scenario.steps[0].step,
+            Step::WaitFor { pattern, .. } if pattern == "*** accepted"
+        ));
+    }
+
+    #[test]
+    fn validate_goto_if_labels() {
+        let yaml = r#"
+name: "loop"
+command: "true"
+steps:
+  - action: label
+    name: "top"
+  - action: label
+    name: "top"
+  - action: goto_if
+    label: "missing"
+    pattern: "("
+    max_iterations: 0
+"#;
+
+        let errors = Scenario::_from_yaml(yaml).unwrap().validate().unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "steps[1].name",
+                "steps[2].label",
+                "steps[2].pattern",
+                "steps[2].max_iterations"
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_invariant_severity() {
+        let yaml = r#"
+name: "severity"
+command: "true"
+steps:
+  - action: wait_ticks
+    ticks: 1
+invariants:
+  - type: cursor_bounds
+  - type: screen_contains
+    pattern: "ready"
+    severity: warning
+"#;
+
+        let scenario = Scenario::_from_yaml(yaml).unwrap();
+        assert_eq!(scenario.invariants[0].severity, Severity::Error);
+        assert!(matches!(
+            scenario.invariants[0].invariant,
+            InvariantRef::CursorBounds
+        ));
+        assert_eq!(scenario.invariants[1].severity, Severity::Warning);
+        assert!(matches!(
+            &scenario.invariants[1].invariant,
+            InvariantRef::ScreenContains { pattern } if pattern == "ready"
+        ));
+    }
+
     #[test]
     fn validate_empty_name() {
         let scenario = Scenario {
@@ -880,11 +2880,12 @@ steps:
             command: Command::Simple("echo".to_string()),
             terminal: TerminalConfig::default(),
             env: HashMap::new(),
-            steps: vec![Step::WaitTicks { ticks: 1 }],
+            steps: vec![Step::WaitTicks { ticks: 1 }.into()],
             invariants: vec![],
             seed: None,
             timeout_ms: None,
             tags: vec![],
+            ..Scenario::default()
         };
 
         let result = scenario.validate();
@@ -906,6 +2907,7 @@ steps:
             seed: None,
             timeout_ms: None,
             tags: vec![],
+            ..Scenario::default()
         };
 
         let result = scenario.validate();
@@ -924,15 +2926,19 @@ steps:
                 Step::WaitFor {
                     pattern: "hello".to_string(),
                     timeout_ms: None,
-                },
+                    case_insensitive: false,
+                }
+                .into(),
                 Step::SendKeys {
                     keys: KeySequence::Text("exit\n".to_string()),
-                },
+                }
+                .into(),
             ],
-            invariants: vec![InvariantRef::CursorBounds],
+            invariants: vec![InvariantRef::CursorBounds.into()],
             seed: Some(42),
             timeout_ms: Some(5000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         assert!(scenario.validate().is_ok());
@@ -963,11 +2969,12 @@ steps:
             command: Command::Simple("echo hello".to_string()),
             terminal: TerminalConfig::default(),
             env: HashMap::new(),
-            steps: vec![Step::WaitTicks { ticks: 1 }],
+            steps: vec![Step::WaitTicks { ticks: 1 }.into()],
             invariants: vec![],
             seed: None,
             timeout_ms: None,
             tags: vec![],
+            ..Scenario::default()
         };
 
         let scenario2 = Scenario {
@@ -976,11 +2983,12 @@ steps:
             command: Command::Simple("echo hello".to_string()),
             terminal: TerminalConfig::default(),
             env: HashMap::new(),
-            steps: vec![Step::WaitTicks { ticks: 1 }],
+            steps: vec![Step::WaitTicks { ticks: 1 }.into()],
             invariants: vec![],
             seed: None,
             timeout_ms: None,
             tags: vec![],
+            ..Scenario::default()
         };
 
         // Both should serialize to readable YAML that can be diffed
@@ -1014,10 +3022,11 @@ steps:
         let scenario = Scenario::_from_yaml(yaml).unwrap();
         // All steps are pure data declarations
         for step in &scenario.steps {
-            match step {
+            match &step.step {
                 Step::SendKeys { .. }
                 | Step::WaitFor { .. }
                 | Step::AssertScreen { .. }
+                | Step::AssertRegion { .. }
                 | Step::WaitTicks { .. }
                 | Step::SendSignal { .. }
                 | Step::Resize { .. }
@@ -1025,16 +3034,131 @@ steps:
                 | Step::Snapshot { .. }
                 | Step::CheckInvariant { .. }
                 | Step::MouseClick { .. }
+                | Step::ClickText { .. }
                 | Step::MouseScroll { .. }
                 | Step::WaitScreen { .. }
+                | Step::WaitForStable { .. }
                 | Step::AssertNotScreen { .. }
+                | Step::Expect { .. }
                 | Step::WaitForFuzzy { .. }
                 | Step::TakeScreenshot { .. }
-                | Step::AssertScreenshot { .. } => {}
+                | Step::AssertScreenshot { .. }
+                | Step::AssertScreenHash { .. }
+                | Step::AssertNoEcho { .. }
+                | Step::AssertSgrState { .. }
+                | Step::AssertCellAttr { .. }
+                | Step::AssertGraphics { .. }
+                | Step::AssertCursorHidden
+                | Step::AssertStderr { .. }
+                | Step::Label { .. }
+                | Step::GotoIf { .. }
+                | Step::Repeat { .. }
+                | Step::IfScreen { .. } => {}
             }
         }
     }
 
+    fn source(phase: &str, index: usize) -> StepSource {
+        StepSource {
+            phase: phase.to_string(),
+            index,
+        }
+    }
+
+    #[test]
+    fn rewrite_screen_hashes_yaml() {
+        let yaml = r#"
+name: "pinned"
+command: "echo hi"
+setup:
+  - action: assert_screen_hash
+    hash: 1 # placeholder
+steps:
+  - action: wait_for
+    pattern: "hi"
+  # the prompt after startup
+  - action: assert_screen_hash
+    hash: 1
+"#;
+
+        let updated =
+            rewrite_screen_hashes(yaml, &[(source("steps", 1), 12345678901234567890)]).unwrap();
+        assert_eq!(
+            updated,
+            yaml.replace("    hash: 1\n", "    hash: 12345678901234567890\n")
+        );
+        assert!(updated.contains("hash: 1 # placeholder"));
+        assert!(updated.contains("# the prompt after startup"));
+
+        let updated = rewrite_screen_hashes(yaml, &[(source("setup", 0), 9)]).unwrap();
+        assert!(updated.contains("hash: 9 # placeholder"));
+        assert!(rewrite_screen_hashes(yaml, &[(source("steps", 0), 9)]).is_err());
+    }
+
+    #[test]
+    fn rewrite_screen_hashes_json() {
+        let json = r#"{"name": "pinned", "command": "echo", "steps": [{"action": "assert_screen_hash", "hash": 1}]}"#;
+
+        let updated = rewrite_screen_hashes(json, &[(source("steps", 0), 42)]).unwrap();
+        assert_eq!(updated, json.replace("\"hash\": 1", "\"hash\": 42"));
+
+        assert!(rewrite_screen_hashes(json, &[(source("steps", 3), 42)]).is_err());
+    }
+
+    #[test]
+    fn step_stream_reads_ndjson_lazily() {
+        use std::io::Write;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("steps.ndjson");
+        let mut file = std::fs::File::create(&path).unwrap();
+        for _ in 0..10_000 {
+            writeln!(file, r#"{{"action": "wait_ticks", "ticks": 1}}"#).unwrap();
+        }
+        writeln!(file).unwrap();
+        drop(file);
+
+        let mut stream = StepStream::open(path.to_str().unwrap()).unwrap();
+        let mut count = 0;
+        for step in stream.by_ref() {
+            assert!(matches!(step.unwrap().step, Step::WaitTicks { ticks: 1 }));
+            count += 1;
+        }
+        assert_eq!(count, 10_000);
+        // Only one line is ever buffered
+        assert!(stream.buffer_capacity() < 1024);
+    }
+
+    #[test]
+    fn step_stream_reports_bad_line() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("steps.ndjson");
+        std::fs::write(
+            &path,
+            "{\"action\": \"wait_ticks\", \"ticks\": 1}\nnot json\n",
+        )
+        .unwrap();
+
+        let results: Vec<_> = StepStream::open(path.to_str().unwrap()).unwrap().collect();
+        assert!(results[0].is_ok());
+        assert!(results[1]
+            .as_ref()
+            .unwrap_err()
+            .contains(":2: invalid step"));
+    }
+
+    #[test]
+    fn steps_file_satisfies_step_requirement() {
+        let yaml = r#"
+name: "generated"
+command: "true"
+steps_file: "steps.ndjson"
+"#;
+        let scenario = Scenario::_from_yaml(yaml).unwrap();
+        assert!(scenario.steps.is_empty());
+        assert!(scenario.validate().is_ok());
+    }
+
     #[test]
     fn special_keys_work() {
         assert_eq!(SpecialKey::Enter.to_bytes(), vec![b'\r']);
@@ -1051,6 +3175,7 @@ steps:
             terminal: TerminalConfig {
                 cols: 120,
                 rows: 40,
+                separate_stderr: false,
             },
             env: {
                 let mut m = HashMap::new();
@@ -1061,15 +3186,19 @@ steps:
                 Step::WaitFor {
                     pattern: "hello".to_string(),
                     timeout_ms: Some(1000),
-                },
+                    case_insensitive: false,
+                }
+                .into(),
                 Step::SendKeys {
                     keys: KeySequence::Text("test".to_string()),
-                },
+                }
+                .into(),
             ],
-            invariants: vec![InvariantRef::CursorBounds],
+            invariants: vec![InvariantRef::CursorBounds.into()],
             seed: Some(12345),
             timeout_ms: Some(5000),
             tags: vec![],
+            ..Scenario::default()
         };
 
         let yaml = scenario._to_yaml().unwrap();
@@ -4,8 +4,9 @@
 //! with scrollback buffer, cursor tracking, and dirty line management.
 
 use crate::ansi::{AnsiEvent, AnsiParser, CsiSequence, EscSequence, OscSequence};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashSet, VecDeque};
+use std::collections::{BTreeSet, HashSet, VecDeque};
 
 /// A single cell in the terminal grid
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -42,6 +43,13 @@ impl Cell {
     pub fn is_empty(&self) -> bool {
         self.ch == ' ' && self.attrs == CellAttrs::default() && self.hyperlink.is_none()
     }
+
+    /// The (foreground, background) colors this cell is displayed with
+    ///
+    /// See [`CellAttrs::effective_colors`].
+    pub fn effective_colors(&self) -> (Color, Color) {
+        self.attrs.effective_colors()
+    }
 }
 
 impl Default for Cell {
@@ -51,7 +59,7 @@ impl Default for Cell {
 }
 
 /// Color specification for terminal cells
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default, JsonSchema)]
 pub enum Color {
     /// Default terminal color
     #[default]
@@ -93,6 +101,39 @@ impl CellAttrs {
         matches!(self.bg, Color::Default)
     }
 
+    /// The (foreground, background) colors as a terminal displays them
+    ///
+    /// Inverse swaps the two, and hidden text takes the background color
+    /// so it can't be seen.
+    pub fn effective_colors(&self) -> (Color, Color) {
+        let (mut fg, bg) = if self.flags.contains(AttrFlags::INVERSE) {
+            (self.bg, self.fg)
+        } else {
+            (self.fg, self.bg)
+        };
+        if self.flags.contains(AttrFlags::HIDDEN) {
+            fg = bg;
+        }
+        (fg, bg)
+    }
+
+    /// These attributes with inverse applied to the colors, for export
+    ///
+    /// The default colors depend on the terminal's palette, so inverse
+    /// involving one is left as a flag for the terminal to apply.
+    fn resolved(&self) -> CellAttrs {
+        let inverse = self.flags.contains(AttrFlags::INVERSE);
+        if inverse && (self.is_default_fg() || self.is_default_bg()) {
+            return *self;
+        }
+        let (fg, bg) = self.effective_colors();
+        CellAttrs {
+            fg,
+            bg,
+            flags: self.flags - AttrFlags::INVERSE,
+        }
+    }
+
     /// Compute a combined hash value for this attribute set.
     ///
     /// This produces a single u64 that represents all attribute components
@@ -146,6 +187,61 @@ bitflags::bitflags! {
     }
 }
 
+/// Inline image protocols recognised in the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum GraphicsProtocol {
+    /// Sixel images (`DCS ... q <data> ST`)
+    Sixel,
+    /// Kitty graphics commands (`APC G <data> ST`)
+    Kitty,
+}
+
+/// Inline images seen so far; payloads are counted, not decoded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GraphicsStats {
+    /// Number of Sixel images
+    pub sixel: u64,
+    /// Number of Kitty graphics commands
+    pub kitty: u64,
+    /// Total payload bytes across all image sequences
+    pub bytes: u64,
+}
+
+impl GraphicsStats {
+    /// Number of images of `protocol`, or of any protocol
+    pub fn count(&self, protocol: Option<GraphicsProtocol>) -> u64 {
+        match protocol {
+            Some(GraphicsProtocol::Sixel) => self.sixel,
+            Some(GraphicsProtocol::Kitty) => self.kitty,
+            None => self.sixel + self.kitty,
+        }
+    }
+}
+
+/// A mouse report (SGR 1006 form, `CSI < b ; x ; y M/m`) seen in the stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MouseEvent {
+    /// Button code, including any modifier and motion bits
+    pub button: u16,
+    /// Row (0-indexed from top)
+    pub row: usize,
+    /// Column (0-indexed from left)
+    pub col: usize,
+    /// Whether this is a press (`M`) rather than a release (`m`)
+    pub pressed: bool,
+}
+
+/// State saved by DECSC and reinstated by DECRC
+#[derive(Debug, Clone, Copy)]
+struct SavedState {
+    cursor: Cursor,
+    attrs: CellAttrs,
+    origin_mode: bool,
+    charsets: [Charset; 2],
+    shifted_out: bool,
+}
+
 /// Cursor position
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Cursor {
@@ -229,6 +325,69 @@ impl Row {
     }
 }
 
+/// A character set designated into G0 or G1 (SCS)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Charset {
+    #[default]
+    Ascii,
+    /// DEC special graphics (`ESC ( 0`), used for line drawing
+    DecSpecialGraphics,
+}
+
+impl Charset {
+    fn from_designator(byte: u8) -> Self {
+        match byte {
+            b'0' => Self::DecSpecialGraphics,
+            _ => Self::Ascii,
+        }
+    }
+
+    fn translate(self, ch: char) -> char {
+        if self == Self::Ascii {
+            return ch;
+        }
+        match ch {
+            '`' => '◆',
+            'a' => '▒',
+            'b' => '␉',
+            'c' => '␌',
+            'd' => '␍',
+            'e' => '␊',
+            'f' => '°',
+            'g' => '±',
+            'h' => '␤',
+            'i' => '␋',
+            'j' => '┘',
+            'k' => '┐',
+            'l' => '┌',
+            'm' => '└',
+            'n' => '┼',
+            'o' => '⎺',
+            'p' => '⎻',
+            'q' => '─',
+            'r' => '⎼',
+            's' => '⎽',
+            't' => '├',
+            'u' => '┤',
+            'v' => '┴',
+            'w' => '┬',
+            'x' => '│',
+            'y' => '≤',
+            'z' => '≥',
+            '{' => 'π',
+            '|' => '≠',
+            '}' => '£',
+            '~' => '·',
+            other => other,
+        }
+    }
+}
+
+/// Default tab stops (every 8 columns) in `from..to`
+fn default_tab_stops(from: usize, to: usize) -> BTreeSet<usize> {
+    (from..to).filter(|col| col % 8 == 0 && *col > 0).collect()
+}
+
 /// Terminal screen with grid and scrollback
 pub struct Screen {
     /// Current visible grid
@@ -237,13 +396,16 @@ pub struct Screen {
     scrollback: VecDeque<Row>,
     /// Maximum scrollback lines
     max_scrollback: usize,
+    /// Lines that have left the top of the primary screen, including ones
+    /// since trimmed from (or never kept in) the scrollback
+    scrolled_lines: usize,
     /// Screen dimensions
     cols: usize,
     rows: usize,
     /// Cursor position
     cursor: Cursor,
-    /// Saved cursor position
-    saved_cursor: Option<Cursor>,
+    /// Cursor, rendition, origin mode and charsets saved by DECSC
+    saved_state: Option<SavedState>,
     /// Current cell attributes for new characters
     current_attrs: CellAttrs,
     /// Current hyperlink URI (OSC 8), if active
@@ -260,6 +422,28 @@ pub struct Screen {
     dirty_lines: HashSet<usize>,
     /// Whether dirty tracking is enabled
     dirty_tracking_enabled: bool,
+    /// Number of BEL characters received
+    bell_count: u64,
+    /// Inline images received
+    graphics: GraphicsStats,
+    /// Whether reverse video (DECSCNM, mode 5) is active
+    reverse_video: bool,
+    /// Whether the cursor is shown (DECTCEM, mode 25)
+    cursor_visible: bool,
+    /// Whether cursor addressing is relative to the scroll region (DECOM)
+    origin_mode: bool,
+    /// Character sets designated into G0 and G1
+    charsets: [Charset; 2],
+    /// Whether G1 is invoked (SO) rather than G0 (SI)
+    shifted_out: bool,
+    /// Most recent SGR mouse report
+    last_mouse_event: Option<MouseEvent>,
+    /// Columns with a tab stop
+    tab_stops: BTreeSet<usize>,
+    /// Rendered visible text, cleared whenever the grid may have changed
+    text_cache: std::cell::OnceCell<String>,
+    /// How many times the visible text was rendered (not served from cache)
+    text_renders: std::cell::Cell<u64>,
 }
 
 impl Screen {
@@ -270,10 +454,11 @@ impl Screen {
             grid,
             scrollback: VecDeque::new(),
             max_scrollback: 10000,
+            scrolled_lines: 0,
             cols,
             rows,
             cursor: Cursor::new(),
-            saved_cursor: None,
+            saved_state: None,
             current_attrs: CellAttrs::new(),
             current_hyperlink: None,
             alternate_screen: false,
@@ -282,6 +467,17 @@ impl Screen {
             parser: AnsiParser::new(),
             dirty_lines: HashSet::new(),
             dirty_tracking_enabled: false,
+            bell_count: 0,
+            graphics: GraphicsStats::default(),
+            reverse_video: false,
+            cursor_visible: true,
+            origin_mode: false,
+            charsets: [Charset::Ascii; 2],
+            shifted_out: false,
+            last_mouse_event: None,
+            tab_stops: default_tab_stops(0, cols),
+            text_cache: std::cell::OnceCell::new(),
+            text_renders: std::cell::Cell::new(0),
         }
     }
 
@@ -297,16 +493,88 @@ impl Screen {
         self.cursor
     }
 
+    /// Get the current rendition applied to newly written text
+    pub fn current_attrs(&self) -> CellAttrs {
+        self.current_attrs
+    }
+
     /// Get a cell at a position
     pub fn get_cell(&self, row: usize, col: usize) -> Option<&Cell> {
         self.grid.get(row)?.get(col)
     }
 
+    /// Whether an incomplete escape sequence is buffered in the parser
+    pub fn has_pending_escape(&self) -> bool {
+        self.parser.in_escape_sequence()
+    }
+
+    /// Get the number of BEL (0x07) characters received so far
+    pub fn bell_count(&self) -> u64 {
+        self.bell_count
+    }
+
+    /// Inline images (Sixel, Kitty graphics) received so far
+    pub fn graphics(&self) -> GraphicsStats {
+        self.graphics
+    }
+
+    /// Whether reverse video (DECSCNM) is active for the whole screen
+    pub fn reverse_video(&self) -> bool {
+        self.reverse_video
+    }
+
+    /// Whether the alternate screen buffer (mode 1049) is active
+    pub fn is_alternate_screen(&self) -> bool {
+        self.alternate_screen
+    }
+
+    /// Whether the cursor is shown (DECTCEM)
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// The most recent SGR mouse report, which never touches the grid
+    pub fn last_mouse_event(&self) -> Option<MouseEvent> {
+        self.last_mouse_event
+    }
+
+    /// Columns with a tab stop, in ascending order
+    pub fn tab_stops(&self) -> Vec<usize> {
+        self.tab_stops.iter().copied().collect()
+    }
+
+    /// Set a tab stop at a column
+    pub fn set_tab_stop(&mut self, col: usize) {
+        if col < self.cols {
+            self.tab_stops.insert(col);
+        }
+    }
+
+    /// Clear the tab stop at a column, if any
+    pub fn clear_tab_stop(&mut self, col: usize) {
+        self.tab_stops.remove(&col);
+    }
+
+    /// Clear every tab stop
+    pub fn clear_all_tab_stops(&mut self) {
+        self.tab_stops.clear();
+    }
+
     /// Get scrollback length
     pub fn scrollback_len(&self) -> usize {
         self.scrollback.len()
     }
 
+    /// Cursor row counted from the first line of all output: lines scrolled
+    /// off the top plus the visible row.
+    ///
+    /// Only grows during plain output, even once the scrollback is trimmed.
+    /// On the alternate screen nothing scrolls into history, so the count
+    /// stays frozen at its primary-screen value.
+    pub fn absolute_cursor_row(&self) -> usize {
+        self.scrolled_lines + self.cursor.row
+    }
+
     /// Set maximum scrollback lines
     pub fn set_max_scrollback(&mut self, max: usize) {
         self.max_scrollback = max;
@@ -318,6 +586,7 @@ impl Screen {
 
     /// Resize the screen
     pub fn resize(&mut self, cols: usize, rows: usize) {
+        self.text_cache.take();
         // Resize existing rows
         for row in &mut self.grid {
             row.resize(cols);
@@ -326,13 +595,32 @@ impl Screen {
             row.resize(cols);
         }
 
-        // Add or remove rows
+        // Growing pulls the newest history back into the top of the grid, as
+        // xterm and tmux do, moving the cursor down with its content; the
+        // alternate screen has no history of its own
         while self.grid.len() < rows {
-            self.grid.push(Row::new(cols));
+            let pulled = if self.alternate_screen {
+                None
+            } else {
+                self.scrollback.pop_back()
+            };
+            match pulled {
+                Some(row) => {
+                    self.grid.insert(0, row);
+                    self.cursor.row += 1;
+                    self.scrolled_lines = self.scrolled_lines.saturating_sub(1);
+                }
+                None => self.grid.push(Row::new(cols)),
+            }
         }
+
+        // Shrinking moves rows off the top
         while self.grid.len() > rows {
             // Move removed rows to scrollback
             let removed = self.grid.remove(0);
+            if !self.alternate_screen {
+                self.scrolled_lines += 1;
+            }
             if !removed.is_empty() {
                 self.scrollback.push_back(removed);
                 if self.scrollback.len() > self.max_scrollback {
@@ -341,6 +629,13 @@ impl Screen {
             }
         }
 
+        // New columns get the default stops; stops past the edge are dropped
+        if cols > self.cols {
+            self.tab_stops.extend(default_tab_stops(self.cols, cols));
+        } else {
+            self.tab_stops.retain(|&col| col < cols);
+        }
+
         self.cols = cols;
         self.rows = rows;
 
@@ -357,8 +652,47 @@ impl Screen {
         self.cursor.col = self.cursor.col.min(self.cols.saturating_sub(1));
     }
 
+    /// Move the cursor to the top-left corner, or of the scroll region in
+    /// origin mode
+    fn home_cursor(&mut self) {
+        self.cursor.row = if self.origin_mode {
+            self.scroll_region.0
+        } else {
+            0
+        };
+        self.cursor.col = 0;
+    }
+
+    /// DECSC: remember the cursor, rendition, origin mode and charsets
+    fn save_state(&mut self) {
+        self.saved_state = Some(SavedState {
+            cursor: self.cursor,
+            attrs: self.current_attrs,
+            origin_mode: self.origin_mode,
+            charsets: self.charsets,
+            shifted_out: self.shifted_out,
+        });
+    }
+
+    /// DECRC: reinstate what [`Screen::save_state`] remembered
+    fn restore_state(&mut self) {
+        if let Some(saved) = self.saved_state.take() {
+            self.cursor = saved.cursor;
+            self.current_attrs = saved.attrs;
+            self.origin_mode = saved.origin_mode;
+            self.charsets = saved.charsets;
+            self.shifted_out = saved.shifted_out;
+            self.clamp_cursor();
+        }
+    }
+
     /// Process raw bytes
     pub fn process(&mut self, data: &[u8]) {
+        // Wait loops feed empty reads every tick; those leave the text alone
+        if data.is_empty() {
+            return;
+        }
+        self.text_cache.take();
         let events = self.parser.parse(data);
         for event in events {
             self.handle_event(event);
@@ -373,8 +707,29 @@ impl Screen {
             AnsiEvent::Csi(csi) => self.handle_csi(csi),
             AnsiEvent::Esc(esc) => self.handle_esc(esc),
             AnsiEvent::Osc(osc) => self.handle_osc(osc),
-            AnsiEvent::Dcs(_) => {} // Ignore DCS for now
-            AnsiEvent::Apc(_) => {} // Ignore APC for now
+            AnsiEvent::Dcs(data) => self.handle_dcs(&data),
+            AnsiEvent::Apc(data) => self.handle_apc(&data),
+        }
+    }
+
+    /// Handle DCS strings; only Sixel images are recognised, by their `q`
+    /// final byte after optional numeric parameters
+    fn handle_dcs(&mut self, data: &[u8]) {
+        let params = data
+            .iter()
+            .take_while(|b| b.is_ascii_digit() || **b == b';')
+            .count();
+        if data.get(params) == Some(&b'q') {
+            self.graphics.sixel += 1;
+            self.graphics.bytes += (data.len() - params - 1) as u64;
+        }
+    }
+
+    /// Handle APC strings; only Kitty graphics commands (`G...`) are recognised
+    fn handle_apc(&mut self, data: &[u8]) {
+        if let Some((b'G', payload)) = data.split_first() {
+            self.graphics.kitty += 1;
+            self.graphics.bytes += payload.len() as u64;
         }
     }
 
@@ -415,6 +770,7 @@ impl Screen {
 
     /// Print a character at the cursor position
     fn print_char(&mut self, ch: char) {
+        let ch = self.charsets[usize::from(self.shifted_out)].translate(ch);
         if self.cursor.col >= self.cols {
             // Wrap to next line
             self.cursor.col = 0;
@@ -443,18 +799,22 @@ impl Screen {
     fn execute(&mut self, code: u8) {
         match code {
             // BEL - Bell
-            0x07 => {}
+            0x07 => self.bell_count += 1,
             // BS - Backspace
-            0x08 => {
-                if self.cursor.col > 0 {
-                    self.cursor.col -= 1;
-                    self.mark_dirty(self.cursor.row);
-                }
+            0x08 if self.cursor.col > 0 => {
+                self.cursor.col -= 1;
+                self.mark_dirty(self.cursor.row);
             }
             // HT - Horizontal Tab
             0x09 => {
-                let next_tab = ((self.cursor.col / 8) + 1) * 8;
-                self.cursor.col = next_tab.min(self.cols.saturating_sub(1));
+                let last_col = self.cols.saturating_sub(1);
+                let next_tab = self
+                    .tab_stops
+                    .range(self.cursor.col + 1..)
+                    .next()
+                    .copied()
+                    .unwrap_or(last_col);
+                self.cursor.col = next_tab.min(last_col);
                 self.mark_dirty(self.cursor.row);
             }
             // LF, VT, FF - Line Feed (and variants)
@@ -471,12 +831,26 @@ impl Screen {
                 self.cursor.col = 0;
                 self.mark_dirty(self.cursor.row);
             }
+            // SO - Shift Out (invoke G1)
+            0x0e => self.shifted_out = true,
+            // SI - Shift In (invoke G0)
+            0x0f => self.shifted_out = false,
             _ => {}
         }
     }
 
     /// Handle CSI sequence
     fn handle_csi(&mut self, csi: CsiSequence) {
+        // SGR mouse report, not DL or SGR
+        if csi.private_marker == Some(b'<') && matches!(csi.final_byte, b'M' | b'm') {
+            self.last_mouse_event = Some(MouseEvent {
+                button: csi.param(0, 0),
+                row: (csi.param(2, 1) as usize).saturating_sub(1),
+                col: (csi.param(1, 1) as usize).saturating_sub(1),
+                pressed: csi.final_byte == b'M',
+            });
+            return;
+        }
         match csi.final_byte {
             // CUU - Cursor Up
             b'A' => {
@@ -532,7 +906,11 @@ impl Screen {
             b'H' | b'f' => {
                 let row = csi.param(0, 1) as usize;
                 let col = csi.param(1, 1) as usize;
-                self.cursor.row = row.saturating_sub(1).min(self.rows.saturating_sub(1));
+                self.cursor.row = if self.origin_mode {
+                    (self.scroll_region.0 + row.saturating_sub(1)).min(self.scroll_region.1)
+                } else {
+                    row.saturating_sub(1).min(self.rows.saturating_sub(1))
+                };
                 self.cursor.col = col.saturating_sub(1).min(self.cols.saturating_sub(1));
                 self.mark_dirty(self.cursor.row);
             }
@@ -599,9 +977,14 @@ impl Screen {
                 if top < bottom {
                     self.scroll_region = (top, bottom);
                 }
-                self.cursor.row = 0;
-                self.cursor.col = 0;
+                self.home_cursor();
             }
+            // TBC - Tab Clear (0: at the cursor column, 3: all)
+            b'g' => match csi.param(0, 0) {
+                0 => self.clear_tab_stop(self.cursor.col),
+                3 => self.clear_all_tab_stops(),
+                _ => {}
+            },
             // Private modes
             b'h' | b'l' if csi.private_marker == Some(b'?') => {
                 let set = csi.final_byte == b'h';
@@ -645,43 +1028,39 @@ impl Screen {
                 29 => self.current_attrs.flags.remove(AttrFlags::STRIKETHROUGH),
                 // Standard foreground colors (0-7)
                 30..=37 => self.current_attrs.fg = Color::Indexed(params[i] - 30),
-                38 => {
+                38 if i + 1 < params.len() => {
                     // Extended foreground color
-                    if i + 1 < params.len() {
-                        if params[i + 1] == 5 && i + 2 < params.len() {
-                            // 256-color: 38;5;N
-                            self.current_attrs.fg = Color::Indexed(params[i + 2]);
-                            i += 2;
-                        } else if params[i + 1] == 2 && i + 4 < params.len() {
-                            // Truecolor: 38;2;R;G;B
-                            self.current_attrs.fg = Color::Rgb(
-                                params[i + 2] as u8,
-                                params[i + 3] as u8,
-                                params[i + 4] as u8,
-                            );
-                            i += 4;
-                        }
+                    if params[i + 1] == 5 && i + 2 < params.len() {
+                        // 256-color: 38;5;N
+                        self.current_attrs.fg = Color::Indexed(params[i + 2]);
+                        i += 2;
+                    } else if params[i + 1] == 2 && i + 4 < params.len() {
+                        // Truecolor: 38;2;R;G;B
+                        self.current_attrs.fg = Color::Rgb(
+                            params[i + 2] as u8,
+                            params[i + 3] as u8,
+                            params[i + 4] as u8,
+                        );
+                        i += 4;
                     }
                 }
                 39 => self.current_attrs.fg = Color::Default, // Default foreground
                 // Standard background colors (0-7)
                 40..=47 => self.current_attrs.bg = Color::Indexed(params[i] - 40),
-                48 => {
+                48 if i + 1 < params.len() => {
                     // Extended background color
-                    if i + 1 < params.len() {
-                        if params[i + 1] == 5 && i + 2 < params.len() {
-                            // 256-color: 48;5;N
-                            self.current_attrs.bg = Color::Indexed(params[i + 2]);
-                            i += 2;
-                        } else if params[i + 1] == 2 && i + 4 < params.len() {
-                            // Truecolor: 48;2;R;G;B
-                            self.current_attrs.bg = Color::Rgb(
-                                params[i + 2] as u8,
-                                params[i + 3] as u8,
-                                params[i + 4] as u8,
-                            );
-                            i += 4;
-                        }
+                    if params[i + 1] == 5 && i + 2 < params.len() {
+                        // 256-color: 48;5;N
+                        self.current_attrs.bg = Color::Indexed(params[i + 2]);
+                        i += 2;
+                    } else if params[i + 1] == 2 && i + 4 < params.len() {
+                        // Truecolor: 48;2;R;G;B
+                        self.current_attrs.bg = Color::Rgb(
+                            params[i + 2] as u8,
+                            params[i + 3] as u8,
+                            params[i + 4] as u8,
+                        );
+                        i += 4;
                     }
                 }
                 49 => self.current_attrs.bg = Color::Default, // Default background
@@ -702,12 +1081,22 @@ impl Screen {
             1 => {}
             // DECCOLM - 80/132 Column Mode
             3 => {}
+            // DECSCNM - Reverse Video (whole screen)
+            5 if self.reverse_video != set => {
+                self.reverse_video = set;
+                for row in 0..self.rows {
+                    self.mark_dirty(row);
+                }
+            }
             // DECOM - Origin Mode
-            6 => {}
+            6 => {
+                self.origin_mode = set;
+                self.home_cursor();
+            }
             // DECAWM - Auto Wrap Mode
             7 => {}
             // DECTCEM - Text Cursor Enable Mode
-            25 => {}
+            25 => self.cursor_visible = set,
             // Alternate screen buffer
             1047 | 1049 => {
                 if set && !self.alternate_screen {
@@ -730,10 +1119,9 @@ impl Screen {
             // Save cursor for alternate screen
             1048 => {
                 if set {
-                    self.saved_cursor = Some(self.cursor);
-                } else if let Some(cursor) = self.saved_cursor.take() {
-                    self.cursor = cursor;
-                    self.clamp_cursor();
+                    self.save_state();
+                } else {
+                    self.restore_state();
                 }
             }
             _ => {}
@@ -743,15 +1131,8 @@ impl Screen {
     /// Handle ESC sequence
     fn handle_esc(&mut self, esc: EscSequence) {
         match esc {
-            EscSequence::SaveCursor => {
-                self.saved_cursor = Some(self.cursor);
-            }
-            EscSequence::RestoreCursor => {
-                if let Some(cursor) = self.saved_cursor.take() {
-                    self.cursor = cursor;
-                    self.clamp_cursor();
-                }
-            }
+            EscSequence::SaveCursor => self.save_state(),
+            EscSequence::RestoreCursor => self.restore_state(),
             EscSequence::Index => {
                 self.cursor.row += 1;
                 if self.cursor.row > self.scroll_region.1 {
@@ -777,6 +1158,11 @@ impl Screen {
             EscSequence::Reset => {
                 self.reset();
             }
+            EscSequence::TabSet => {
+                self.set_tab_stop(self.cursor.col);
+            }
+            EscSequence::DesignateG0(byte) => self.charsets[0] = Charset::from_designator(byte),
+            EscSequence::DesignateG1(byte) => self.charsets[1] = Charset::from_designator(byte),
             _ => {}
         }
     }
@@ -851,8 +1237,14 @@ impl Screen {
         for _ in 0..n {
             if top < self.grid.len() && top <= bottom {
                 let removed = self.grid.remove(top);
-                // Add to scrollback if we're scrolling the entire screen
-                if top == 0 && !self.alternate_screen && !removed.is_empty() {
+                // Add to scrollback only if the region covers the entire screen;
+                // lines leaving a partial region (even one anchored at row 0)
+                // are discarded, as on real terminals
+                let full_height = top == 0 && bottom + 1 >= self.rows;
+                if full_height && !self.alternate_screen {
+                    self.scrolled_lines += 1;
+                }
+                if full_height && !self.alternate_screen && !removed.is_empty() {
                     self.scrollback.push_back(removed);
                     if self.scrollback.len() > self.max_scrollback {
                         self.scrollback.pop_front(); // O(1) with VecDeque
@@ -991,14 +1383,23 @@ impl Screen {
 
     /// Reset screen to initial state
     pub fn reset(&mut self) {
+        self.text_cache.take();
         self.grid = (0..self.rows).map(|_| Row::new(self.cols)).collect();
         self.scrollback.clear();
+        self.scrolled_lines = 0;
         self.cursor = Cursor::new();
-        self.saved_cursor = None;
+        self.saved_state = None;
+        self.origin_mode = false;
+        self.charsets = [Charset::Ascii; 2];
+        self.shifted_out = false;
         self.current_attrs = CellAttrs::new();
         self.alternate_screen = false;
         self.saved_primary = None;
         self.scroll_region = (0, self.rows.saturating_sub(1));
+        self.reverse_video = false;
+        self.cursor_visible = true;
+        self.last_mouse_event = None;
+        self.tab_stops = default_tab_stops(0, self.cols);
         self.parser.reset();
     }
 
@@ -1010,11 +1411,69 @@ impl Screen {
             .unwrap_or_default()
     }
 
+    /// Get the text of a rectangular area, rows joined by newlines
+    ///
+    /// Bounds are inclusive and clamped to the screen, so an area hanging
+    /// off the edge yields only its visible part.
+    pub fn region_text(&self, top: usize, left: usize, bottom: usize, right: usize) -> String {
+        let bottom = bottom.min(self.rows.saturating_sub(1));
+        let right = right.min(self.cols.saturating_sub(1));
+        if top > bottom || left > right {
+            return String::new();
+        }
+        self.grid[top..=bottom]
+            .iter()
+            .map(|row| {
+                row.cells[left..=right]
+                    .iter()
+                    .map(|c| c.ch)
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
     /// Get all visible text
     ///
-    /// Optimized to use a single pre-allocated String with capacity,
-    /// avoiding intermediate Vec<String> allocations.
-    pub fn text(&self) -> String {
+    /// The text is rendered once and cached until the screen next changes,
+    /// so polling it every tick doesn't reallocate.
+    pub fn text(&self) -> &str {
+        self.text_cache.get_or_init(|| self.render_text())
+    }
+
+    /// Get the visible text without padding
+    ///
+    /// Trailing spaces are trimmed from each row and trailing blank rows
+    /// dropped, so `$` anchors at the end of the printed text.
+    pub fn text_trimmed(&self) -> String {
+        let rows: Vec<&str> = self.text().lines().map(str::trim_end).collect();
+        let used = rows
+            .iter()
+            .rposition(|row| !row.is_empty())
+            .map_or(0, |i| i + 1);
+        rows[..used].join("\n")
+    }
+
+    /// Find where a regex first matches the visible text
+    ///
+    /// Returns the (row, col) of the match's first cell, or `None` if the
+    /// pattern doesn't match or isn't a valid regex. Rows are joined by
+    /// newlines, so a match can span rows.
+    pub fn find(&self, pattern: &str) -> Option<(usize, usize)> {
+        let regex = regex::Regex::new(pattern).ok()?;
+        let text = self.text();
+        let start = regex.find(text)?.start();
+
+        // Every cell renders as exactly one char
+        let before = &text[..start];
+        let row = before.matches('\n').count();
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Some((row, before[line_start..].chars().count()))
+    }
+
+    /// Render the visible grid into a single pre-allocated String
+    fn render_text(&self) -> String {
+        self.text_renders.set(self.text_renders.get() + 1);
         // Pre-calculate total size for a single allocation
         // Each row has `cols` characters, plus 1 for newline (except last row)
         let total_size = self.rows.saturating_mul(self.cols + 1).saturating_sub(1);
@@ -1031,6 +1490,38 @@ impl Screen {
         result
     }
 
+    /// Render the visible grid as text with SGR escape sequences.
+    ///
+    /// Attributes are emitted only where they change, and each row ends with
+    /// an SGR reset. With reverse video (DECSCNM) active, every cell is
+    /// rendered with inverse toggled, as a terminal would display it.
+    /// Inverse cells are written with their colors swapped.
+    pub fn to_ansi(&self) -> String {
+        let mut result = String::new();
+        for (i, row) in self.grid.iter().enumerate() {
+            if i > 0 {
+                result.push_str("\r\n");
+            }
+            let mut current = CellAttrs::default();
+            for cell in &row.cells {
+                let mut attrs = cell.attrs;
+                if self.reverse_video {
+                    attrs.flags.toggle(AttrFlags::INVERSE);
+                }
+                let attrs = attrs.resolved();
+                if attrs != current {
+                    result.push_str(&sgr_sequence(&attrs));
+                    current = attrs;
+                }
+                result.push(cell.ch);
+            }
+            if current != CellAttrs::default() {
+                result.push_str("\x1b[0m");
+            }
+        }
+        result
+    }
+
     /// Compute a stable hash of the visual terminal state.
     ///
     /// This hash includes:
@@ -1119,6 +1610,12 @@ impl Screen {
         mix_u64(&mut v0, &mut v1, &mut v2, &mut v3, self.cursor.row as u64);
         mix_u64(&mut v0, &mut v1, &mut v2, &mut v3, self.cursor.col as u64);
 
+        // Reverse video changes every cell's appearance; only mixed in when
+        // active so hashes of normal screens are unaffected
+        if include_attrs && self.reverse_video {
+            mix_u64(&mut v0, &mut v1, &mut v2, &mut v3, 0x5ec5c4);
+        }
+
         // Hash all visible cells
         for row in &self.grid {
             for cell in &row.cells {
@@ -1178,6 +1675,9 @@ impl Screen {
         if self.cursor != other.cursor {
             return false;
         }
+        if self.reverse_video != other.reverse_video {
+            return false;
+        }
 
         for (row1, row2) in self.grid.iter().zip(other.grid.iter()) {
             for (cell1, cell2) in row1.cells.iter().zip(row2.cells.iter()) {
@@ -1191,10 +1691,275 @@ impl Screen {
     }
 }
 
+/// Build an SGR sequence that sets exactly the given attributes
+fn sgr_sequence(attrs: &CellAttrs) -> String {
+    let mut params = vec!["0".to_string()];
+    let flags = [
+        (AttrFlags::BOLD, "1"),
+        (AttrFlags::DIM, "2"),
+        (AttrFlags::ITALIC, "3"),
+        (AttrFlags::UNDERLINE, "4"),
+        (AttrFlags::BLINK, "5"),
+        (AttrFlags::INVERSE, "7"),
+        (AttrFlags::HIDDEN, "8"),
+        (AttrFlags::STRIKETHROUGH, "9"),
+    ];
+    for (flag, code) in flags {
+        if attrs.flags.contains(flag) {
+            params.push(code.to_string());
+        }
+    }
+    push_color_params(&mut params, attrs.fg, 30);
+    push_color_params(&mut params, attrs.bg, 40);
+    format!("\x1b[{}m", params.join(";"))
+}
+
+/// Append SGR parameters for a color (`base` is 30 for fg, 40 for bg)
+fn push_color_params(params: &mut Vec<String>, color: Color, base: u16) {
+    match color {
+        Color::Default => {}
+        Color::Indexed(n) if n < 8 => params.push((base + n).to_string()),
+        Color::Indexed(n) if n < 16 => params.push((base + 60 + n - 8).to_string()),
+        Color::Indexed(n) => params.push(format!("{};5;{}", base + 8, n)),
+        Color::Rgb(r, g, b) => params.push(format!("{};2;{};{};{}", base + 8, r, g, b)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn reverse_video_mode_inverts_rendering_and_hash() {
+        let mut screen = Screen::new(10, 2);
+        screen.process(b"hi");
+        let normal_hash = screen.state_hash();
+        assert_eq!(screen.to_ansi(), "hi        \r\n          ");
+
+        screen.process(b"\x1b[?5h");
+        assert!(screen.reverse_video());
+        assert_ne!(screen.state_hash(), normal_hash);
+        // Text is unchanged; only appearance is
+        assert_eq!(screen.text_hash(), {
+            let mut plain = Screen::new(10, 2);
+            plain.process(b"hi");
+            plain.text_hash()
+        });
+        assert_eq!(
+            screen.to_ansi(),
+            "\x1b[0;7mhi        \x1b[0m\r\n\x1b[0;7m          \x1b[0m"
+        );
+
+        // Cells already inverse render as normal under reverse video
+        screen.process(b"\x1b[7mX");
+        assert!(screen.to_ansi().starts_with("\x1b[0;7mhi\x1b[0mX\x1b[0;7m"));
+
+        screen.process(b"\x1b[?5l");
+        assert!(!screen.reverse_video());
+    }
+
+    #[test]
+    fn text_is_cached_until_the_screen_changes() {
+        let mut screen = Screen::new(10, 2);
+        screen.process(b"hello");
+        assert_eq!(screen.text(), "hello     \n          ");
+        assert_eq!(screen.text_renders.get(), 1);
+
+        // Repeated reads and empty input reuse the rendered text
+        for _ in 0..100 {
+            screen.process(b"");
+            assert_eq!(screen.text(), "hello     \n          ");
+        }
+        assert_eq!(screen.text_renders.get(), 1);
+
+        // Any change re-renders, and the result matches a fresh screen
+        screen.process(b"\r\nworld");
+        assert_eq!(screen.text(), "hello     \nworld     ");
+        assert_eq!(screen.text_renders.get(), 2);
+        screen.resize(6, 2);
+        assert_eq!(screen.text(), "hello \nworld ");
+        screen.reset();
+        assert_eq!(screen.text(), Screen::new(6, 2).text());
+        assert_eq!(screen.text_renders.get(), 4);
+    }
+
+    #[test]
+    fn graphics_sequences_are_counted() {
+        let mut screen = Screen::new(20, 5);
+        screen.process(b"\x1bP\x1b\\\x1b_Hello\x1b\\");
+        assert_eq!(screen.graphics(), GraphicsStats::default());
+
+        // Minimal Sixel: one red pixel column
+        screen.process(b"before\x1bP0;0;0q#0;2;100;0;0#0~\x1b\\after");
+        assert_eq!(screen.graphics().sixel, 1);
+        assert_eq!(screen.graphics().bytes, 15);
+        assert!(screen.text().starts_with("beforeafter"));
+
+        screen.process(b"\x1b_Ga=T,f=100;AAAA\x1b\\");
+        let graphics = screen.graphics();
+        assert_eq!(graphics.kitty, 1);
+        assert_eq!(graphics.count(None), 2);
+        assert_eq!(graphics.count(Some(GraphicsProtocol::Kitty)), 1);
+    }
+
+    #[test]
+    fn region_text_extracts_sub_grid() {
+        let mut screen = Screen::new(10, 3);
+        screen.process(b"abcdefghij\r\nklmnopqrst\r\nuvwxyz");
+        assert_eq!(screen.region_text(0, 2, 1, 4), "cde\nmno");
+        // Clamped to the screen edges
+        assert_eq!(screen.region_text(2, 4, 9, 99), "yz    ");
+        assert_eq!(screen.region_text(3, 0, 5, 9), "");
+    }
+
+    #[test]
+    fn dectcem_tracks_cursor_visibility() {
+        let mut screen = Screen::new(10, 2);
+        assert!(screen.cursor_visible());
+        screen.process(b"\x1b[?25l");
+        assert!(!screen.cursor_visible());
+        screen.process(b"\x1b[?25h");
+        assert!(screen.cursor_visible());
+        screen.process(b"\x1b[?25l\x1bc");
+        assert!(screen.cursor_visible());
+    }
+
+    #[test]
+    fn tab_stops_are_exposed_and_honored() {
+        let mut screen = Screen::new(20, 2);
+        assert_eq!(screen.tab_stops(), vec![8, 16]);
+
+        screen.clear_all_tab_stops();
+        screen.set_tab_stop(5);
+        assert_eq!(screen.tab_stops(), vec![5]);
+        screen.process(b"\tX");
+        assert_eq!(screen.row_text(0).find('X'), Some(5));
+
+        screen.resize(30, 2);
+        assert_eq!(screen.tab_stops(), vec![5, 24]);
+        screen.reset();
+        assert_eq!(screen.tab_stops(), vec![8, 16, 24]);
+    }
+
+    #[test]
+    fn absolute_cursor_row_counts_scrolled_lines() {
+        let mut screen = Screen::new(20, 5);
+        screen.set_max_scrollback(3);
+
+        let mut last = 0;
+        for i in 0..20 {
+            screen.process(format!("line {}\r\n", i).as_bytes());
+            assert!(screen.absolute_cursor_row() >= last);
+            last = screen.absolute_cursor_row();
+        }
+        // Blank lines and lines trimmed from the scrollback still count
+        screen.process(b"\r\n\r\n");
+        assert_eq!(screen.scrollback_len(), 3);
+        assert_eq!(screen.absolute_cursor_row(), 22);
+    }
+
+    #[test]
+    fn absolute_cursor_row_is_frozen_on_alternate_screen() {
+        let mut screen = Screen::new(20, 5);
+        for i in 0..10 {
+            screen.process(format!("line {}\r\n", i).as_bytes());
+        }
+        assert_eq!(screen.absolute_cursor_row(), 10);
+
+        // Only the alternate screen's own cursor row moves the count
+        screen.process(b"\x1b[?1049h");
+        assert_eq!(screen.absolute_cursor_row(), 6);
+        for i in 0..10 {
+            screen.process(format!("alt {}\r\n", i).as_bytes());
+        }
+        assert_eq!(screen.absolute_cursor_row(), 6 + 4);
+
+        screen.process(b"\x1b[?1049l");
+        assert_eq!(screen.absolute_cursor_row(), 10);
+    }
+
+    #[test]
+    fn resize_taller_pulls_lines_from_scrollback() {
+        let mut screen = Screen::new(20, 24);
+        let lines: Vec<String> = (0..30).map(|i| format!("Line {}", i)).collect();
+        screen.process(lines.join("\r\n").as_bytes());
+        assert_eq!(screen.scrollback_len(), 6);
+        assert!(screen.row_text(0).starts_with("Line 6"));
+
+        screen.resize(20, 30);
+        assert_eq!(screen.scrollback_len(), 0);
+        assert!(screen.row_text(0).starts_with("Line 0 "));
+        assert!(screen.row_text(29).starts_with("Line 29"));
+        assert_eq!(screen.cursor().row, 29);
+
+        // Without history, new rows are blank at the bottom
+        screen.resize(20, 32);
+        assert!(screen.row_text(0).starts_with("Line 0 "));
+        assert!(screen.row_text(31).trim().is_empty());
+        assert_eq!(screen.cursor().row, 29);
+    }
+
+    #[test]
+    fn hts_and_tbc_edit_tab_stops() {
+        let mut screen = Screen::new(20, 2);
+        // Clear all, then set a stop at column 5 with HTS
+        screen.process(b"\x1b[3g\x1b[6G\x1bH\r\tX");
+        assert_eq!(screen.tab_stops(), vec![5]);
+        assert_eq!(screen.row_text(0).find('X'), Some(5));
+
+        // TBC 0 clears only the stop under the cursor
+        screen.reset();
+        screen.process(b"\x1b[9G\x1b[g");
+        assert_eq!(screen.tab_stops(), vec![16]);
+        screen.reset();
+        assert_eq!(screen.tab_stops(), vec![8, 16]);
+    }
+
+    #[test]
+    fn to_ansi_emits_colors() {
+        let mut screen = Screen::new(4, 1);
+        screen.process(b"\x1b[1;31mA\x1b[0;38;5;200mB\x1b[38;2;1;2;3;44mC\x1b[0mD");
+        assert_eq!(
+            screen.to_ansi(),
+            "\x1b[0;1;31mA\x1b[0;38;5;200mB\x1b[0;38;2;1;2;3;44mC\x1b[0mD"
+        );
+    }
+
+    #[test]
+    fn to_ansi_swaps_inverse_colors() {
+        let mut screen = Screen::new(2, 1);
+        screen.process(b"\x1b[7;31;40mA\x1b[0;8;32;44mB");
+
+        let cell = screen.get_cell(0, 0).unwrap();
+        assert_eq!(
+            cell.effective_colors(),
+            (Color::Indexed(0), Color::Indexed(1))
+        );
+        let hidden = screen.get_cell(0, 1).unwrap();
+        assert_eq!(
+            hidden.effective_colors(),
+            (Color::Indexed(4), Color::Indexed(4))
+        );
+        assert_eq!(screen.to_ansi(), "\x1b[0;30;41mA\x1b[0;8;34;44mB\x1b[0m");
+    }
+
+    #[test]
+    fn find_returns_match_coordinates() {
+        let mut screen = Screen::new(20, 4);
+        screen.process("héllo\r\n\x1b[3;6H[ OK ]".as_bytes());
+
+        let (row, col) = screen.find(r"\[ OK \]").unwrap();
+        assert_eq!((row, col), (2, 5));
+        let found: String = (col..col + 6)
+            .map(|c| screen.get_cell(row, c).unwrap().ch)
+            .collect();
+        assert_eq!(found, "[ OK ]");
+
+        assert_eq!(screen.find("llo"), Some((0, 2)));
+        assert_eq!(screen.find("missing"), None);
+        assert_eq!(screen.find("("), None);
+    }
+
     #[test]
     fn basic_print() {
         let mut screen = Screen::new(80, 24);
@@ -1334,6 +2099,47 @@ mod tests {
         assert_eq!(screen.scroll_region, (2, 6));
     }
 
+    #[test]
+    fn partial_top_anchored_region_skips_scrollback() {
+        let mut screen = Screen::new(20, 10);
+
+        // Region covers rows 1-5 only, leaving a status area below
+        screen.process(b"\x1b[1;5r");
+        for i in 0..10 {
+            screen.process(format!("Line {}\r\n", i).as_bytes());
+        }
+
+        assert_eq!(screen.scrollback_len(), 0);
+        assert!(screen.row_text(3).starts_with("Line 9"));
+    }
+
+    #[test]
+    fn full_height_region_fills_scrollback() {
+        let mut screen = Screen::new(20, 10);
+
+        // Explicit full-screen region behaves like no region
+        screen.process(b"\x1b[1;10r");
+        for i in 0..12 {
+            screen.process(format!("Line {}\r\n", i).as_bytes());
+        }
+
+        assert_eq!(screen.scrollback_len(), 3);
+    }
+
+    #[test]
+    fn bell_is_counted() {
+        let mut screen = Screen::new(80, 24);
+        assert_eq!(screen.bell_count(), 0);
+
+        screen.process(b"ding\x07 dong\x07");
+        assert_eq!(screen.bell_count(), 2);
+        assert!(screen.row_text(0).starts_with("ding dong"));
+
+        // OSC sequences terminated by BEL are not bells
+        screen.process(b"\x1b]0;title\x07");
+        assert_eq!(screen.bell_count(), 2);
+    }
+
     #[test]
     fn alternate_screen() {
         let mut screen = Screen::new(80, 24);
@@ -1344,7 +2150,7 @@ mod tests {
 
         // Switch to alternate screen
         screen.process(b"\x1b[?1049h");
-        assert!(screen.alternate_screen);
+        assert!(screen.is_alternate_screen());
 
         // Write in alternate screen
         screen.process(b"Alternate Content");
@@ -1352,7 +2158,7 @@ mod tests {
 
         // Switch back to primary
         screen.process(b"\x1b[?1049l");
-        assert!(!screen.alternate_screen);
+        assert!(!screen.is_alternate_screen());
 
         // Primary content should be restored
         assert!(
@@ -2054,4 +2860,68 @@ mod tests {
         let screen = Screen::new(1000, 500);
         assert_eq!(screen.size(), (1000, 500));
     }
+
+    #[test]
+    fn sgr_mouse_report_is_decoded() {
+        let mut screen = Screen::new(20, 5);
+        screen.process(b"one\r\ntwo");
+        assert_eq!(screen.last_mouse_event(), None);
+
+        screen.process(b"\x1b[<0;12;2M");
+        assert_eq!(
+            screen.last_mouse_event(),
+            Some(MouseEvent {
+                button: 0,
+                row: 1,
+                col: 11,
+                pressed: true
+            })
+        );
+        // Not taken as DL: the rows are untouched
+        assert_eq!(screen.row_text(0).trim_end(), "one");
+        assert_eq!(screen.row_text(1).trim_end(), "two");
+
+        screen.process(b"\x1b[<2;1;1m");
+        let event = screen.last_mouse_event().unwrap();
+        assert_eq!((event.button, event.row, event.col), (2, 0, 0));
+        assert!(!event.pressed);
+        assert_eq!(screen.current_attrs(), CellAttrs::new());
+    }
+
+    #[test]
+    fn decrc_restores_rendition_and_origin_mode() {
+        let mut screen = Screen::new(20, 10);
+        screen.process(b"\x1b[1;31m\x1b7\x1b[0mplain\x1b8X");
+        let cell = screen.get_cell(0, 0).unwrap();
+        assert_eq!(cell.ch, 'X');
+        assert!(cell.attrs.flags.contains(AttrFlags::BOLD));
+        assert_eq!(cell.attrs.fg, Color::Indexed(1));
+
+        // Origin mode is saved too: CUP is relative to the margins again
+        screen.process(b"\x1b[3;8r\x1b[?6h\x1b7\x1b[?6l\x1b8\x1b[2;1HY");
+        assert_eq!(screen.row_text(3).trim_end(), "Y");
+    }
+
+    #[test]
+    fn dec_special_graphics_draws_boxes() {
+        let mut screen = Screen::new(20, 3);
+        screen.process(b"\x1b(0lqk\x1b(B lqk");
+        assert_eq!(screen.row_text(0).trim_end(), "┌─┐ lqk");
+
+        // SO/SI switch between G1 and G0
+        screen.process(b"\r\n\x1b)0x\x0ex\x0fx");
+        assert_eq!(screen.row_text(1).trim_end(), "x│x");
+    }
+
+    #[test]
+    fn text_trimmed_drops_padding() {
+        let mut screen = Screen::new(20, 5);
+        screen.process(b"Done");
+        let anchored = regex::Regex::new("^Done$").unwrap();
+        assert!(!anchored.is_match(screen.text()));
+        assert!(anchored.is_match(&screen.text_trimmed()));
+
+        screen.process(b"\r\n\r\n  x  ");
+        assert_eq!(screen.text_trimmed(), "Done\n\n  x");
+    }
 }
@@ -7,7 +7,7 @@
 //! - Support ignore regions for dynamic content (clock, cursor)
 //! - Configure comparison thresholds
 
-use crate::screen::{Cell, CellAttrs, Color, Screen};
+use crate::screen::{AttrFlags, Cell, CellAttrs, Color, Screen};
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
 
@@ -37,11 +37,12 @@ impl Screenshot {
         for row in 0..rows {
             let mut row_cells = Vec::with_capacity(cols);
             for col in 0..cols {
-                if let Some(cell) = screen.get_cell(row, col) {
-                    row_cells.push(cell.clone());
-                } else {
-                    row_cells.push(Cell::new());
+                let mut cell = screen.get_cell(row, col).cloned().unwrap_or_default();
+                // Reverse video (DECSCNM) inverts every cell's appearance
+                if screen.reverse_video() {
+                    cell.attrs.flags.toggle(AttrFlags::INVERSE);
                 }
+                row_cells.push(cell);
             }
             cells.push(row_cells);
         }
@@ -109,7 +110,10 @@ pub struct DiffConfig {
     pub compare_text: bool,
     /// Whether to compare cursor position
     pub compare_cursor: bool,
-    /// Character to use for showing differences (for text output)
+    /// Treat blank cells at the end of each row as equal regardless of
+    /// their attributes
+    pub ignore_trailing_blanks: bool,
+    /// Character marking differing cells in [`diff_grid`]
     pub diff_char: char,
 }
 
@@ -121,6 +125,7 @@ impl Default for DiffConfig {
             compare_colors: true,
             compare_text: true,
             compare_cursor: true,
+            ignore_trailing_blanks: false,
             diff_char: '?',
         }
     }
@@ -143,6 +148,26 @@ pub struct DiffResult {
     pub cursor_mismatch: bool,
     /// Similarity ratio (0.0 to 1.0)
     pub similarity: f64,
+    /// Contiguous clusters of differing cells
+    pub regions: Vec<DiffRegion>,
+    /// Similarity weighted by region size, so one changed block scores lower
+    /// than the same number of scattered cells (0.0 to 1.0)
+    pub structural_similarity: f64,
+}
+
+/// A contiguous (4-connected) cluster of differing cells
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffRegion {
+    /// Top row of the bounding box
+    pub top: usize,
+    /// Left column of the bounding box
+    pub left: usize,
+    /// Bottom row of the bounding box (inclusive)
+    pub bottom: usize,
+    /// Right column of the bounding box (inclusive)
+    pub right: usize,
+    /// Number of differing cells in the cluster
+    pub cells: usize,
 }
 
 /// Severity of a cell difference
@@ -191,6 +216,8 @@ pub fn compare_screenshots(
             size_mismatch: true,
             cursor_mismatch: false,
             similarity: 0.0,
+            regions: Vec::new(),
+            structural_similarity: 0.0,
         };
     }
 
@@ -208,12 +235,24 @@ pub fn compare_screenshots(
     let empty_cell = Cell::new();
 
     for row in 0..baseline.rows {
+        // Blank on both sides from here to the end of the row
+        let trailing_start = if config.ignore_trailing_blanks {
+            trailing_blank_start(baseline, row).max(trailing_blank_start(actual, row))
+        } else {
+            baseline.cols
+        };
+
         for col in 0..baseline.cols {
             // Check if this cell is in an ignore region
             if config.ignore_regions.iter().any(|r| r.contains(row, col)) {
                 continue;
             }
 
+            if col >= trailing_start {
+                matching_cells += 1;
+                continue;
+            }
+
             let baseline_cell = baseline.get(row, col).unwrap_or(&empty_cell);
             let actual_cell = actual.get(row, col).unwrap_or(&empty_cell);
 
@@ -248,6 +287,9 @@ pub fn compare_screenshots(
 
     let matches = different_cells <= config.max_differences && !cursor_mismatch;
 
+    let regions = cluster_differences(&differences, baseline.rows, baseline.cols);
+    let structural_similarity = region_weighted_similarity(&regions, total_cells);
+
     DiffResult {
         matches,
         different_cells,
@@ -256,10 +298,89 @@ pub fn compare_screenshots(
         size_mismatch: false,
         cursor_mismatch,
         similarity,
+        regions,
+        structural_similarity,
+    }
+}
+
+/// Group differing cells into 4-connected regions
+fn cluster_differences(differences: &[CellDiff], rows: usize, cols: usize) -> Vec<DiffRegion> {
+    let mut differs = vec![false; rows * cols];
+    for diff in differences {
+        differs[diff.row * cols + diff.col] = true;
+    }
+
+    let mut regions = Vec::new();
+    let mut stack = Vec::new();
+    for diff in differences {
+        let start = diff.row * cols + diff.col;
+        if !differs[start] {
+            continue;
+        }
+        differs[start] = false;
+        stack.push((diff.row, diff.col));
+
+        let mut region = DiffRegion {
+            top: diff.row,
+            left: diff.col,
+            bottom: diff.row,
+            right: diff.col,
+            cells: 0,
+        };
+        while let Some((row, col)) = stack.pop() {
+            region.cells += 1;
+            region.top = region.top.min(row);
+            region.left = region.left.min(col);
+            region.bottom = region.bottom.max(row);
+            region.right = region.right.max(col);
+
+            let neighbors = [
+                (row.wrapping_sub(1), col),
+                (row + 1, col),
+                (row, col.wrapping_sub(1)),
+                (row, col + 1),
+            ];
+            for (r, c) in neighbors {
+                if r < rows && c < cols && differs[r * cols + c] {
+                    differs[r * cols + c] = false;
+                    stack.push((r, c));
+                }
+            }
+        }
+        regions.push(region);
     }
+
+    regions
+}
+
+/// Score regions so that clustered changes cost more than isolated ones.
+///
+/// A region of `n` cells costs `n * (1 + log2(n))`: a lone cell costs 1,
+/// while each cell of a 16-cell block costs 5.
+fn region_weighted_similarity(regions: &[DiffRegion], total_cells: usize) -> f64 {
+    if total_cells == 0 {
+        return 1.0;
+    }
+    let cost: f64 = regions
+        .iter()
+        .map(|r| {
+            let n = r.cells as f64;
+            n * (1.0 + n.log2())
+        })
+        .sum();
+    (1.0 - cost / total_cells as f64).max(0.0)
 }
 
 /// Compute the severity of difference between two cells
+/// Column where a row's trailing run of blank cells begins
+fn trailing_blank_start(shot: &Screenshot, row: usize) -> usize {
+    let mut start = shot.cols;
+    while start > 0 && shot.get(row, start - 1).is_none_or(|c| c.ch == ' ') {
+        start -= 1;
+    }
+    start
+}
+
 fn compute_severity(baseline: &Cell, actual: &Cell, config: &DiffConfig) -> DiffSeverity {
     let text_diff = config.compare_text && baseline.ch != actual.ch;
     let attr_diff = config.compare_colors && baseline.attrs != actual.attrs;
@@ -272,6 +393,32 @@ fn compute_severity(baseline: &Cell, actual: &Cell, config: &DiffConfig) -> Diff
     }
 }
 
+/// Render a map of where two screenshots differ
+///
+/// Each differing cell is drawn as `config.diff_char` and every other cell as
+/// a space, inside a `+--+` frame so positions can be read off. Screenshots
+/// of different sizes have no map and give an empty string.
+pub fn diff_grid(baseline: &Screenshot, result: &DiffResult, config: &DiffConfig) -> String {
+    if result.size_mismatch {
+        return String::new();
+    }
+
+    let mut grid = vec![vec![' '; baseline.cols]; baseline.rows];
+    for diff in &result.differences {
+        grid[diff.row][diff.col] = config.diff_char;
+    }
+
+    let border = format!("+{}+\n", "-".repeat(baseline.cols));
+    let mut output = border.clone();
+    for row in grid {
+        output.push('|');
+        output.extend(row);
+        output.push_str("|\n");
+    }
+    output.push_str(&border);
+    output
+}
+
 /// Generate a visual diff output showing differences
 pub fn generate_diff_output(baseline: &Screenshot, actual: &Screenshot) -> String {
     let mut output = String::new();
@@ -472,6 +619,50 @@ mod tests {
         assert_eq!(result.different_cells, 1);
     }
 
+    #[test]
+    fn trailing_blank_attributes_ignored_with_flag() {
+        let baseline = make_screenshot(
+            vec![vec![
+                make_cell('a', -1, -1),
+                make_cell(' ', -1, -1),
+                make_cell(' ', -1, -1),
+            ]],
+            (0, 0),
+        );
+        let actual = make_screenshot(
+            vec![vec![
+                make_cell('a', -1, -1),
+                make_cell(' ', -1, 4),
+                make_cell(' ', 2, 4),
+            ]],
+            (0, 0),
+        );
+
+        let result = compare_screenshots(&baseline, &actual, &DiffConfig::default());
+        assert!(!result.matches);
+        assert_eq!(result.different_cells, 2);
+
+        let config = DiffConfig {
+            ignore_trailing_blanks: true,
+            ..DiffConfig::default()
+        };
+        let result = compare_screenshots(&baseline, &actual, &config);
+        assert!(result.matches);
+        assert_eq!(result.different_cells, 0);
+
+        // A blank followed by text is not trailing
+        let actual = make_screenshot(
+            vec![vec![
+                make_cell('a', -1, -1),
+                make_cell(' ', -1, 4),
+                make_cell('b', -1, -1),
+            ]],
+            (0, 0),
+        );
+        let result = compare_screenshots(&baseline, &actual, &config);
+        assert_eq!(result.different_cells, 2);
+    }
+
     #[test]
     fn test_max_differences_threshold() {
         let baseline = make_screenshot(
@@ -483,8 +674,10 @@ mod tests {
             (0, 0),
         );
 
-        let mut config = DiffConfig::default();
-        config.max_differences = 1;
+        let config = DiffConfig {
+            max_differences: 1,
+            ..DiffConfig::default()
+        };
 
         let result = compare_screenshots(&baseline, &actual, &config);
 
@@ -543,6 +736,64 @@ mod tests {
         assert!((sim - 0.875).abs() < 0.001);
     }
 
+    #[test]
+    fn clustered_differences_score_lower_than_scattered() {
+        let blank = || vec![vec![make_cell(' ', -1, -1); 20]; 10];
+        let baseline = make_screenshot(blank(), (0, 0));
+
+        // A 4x4 changed block
+        let mut block = blank();
+        for row in block.iter_mut().skip(2).take(4) {
+            for cell in row.iter_mut().skip(3).take(4) {
+                *cell = make_cell('#', -1, -1);
+            }
+        }
+        // The same 16 cells, none of them adjacent
+        let mut scattered = blank();
+        for i in 0..16 {
+            scattered[(i / 8) * 2][(i % 8) * 2] = make_cell('#', -1, -1);
+        }
+
+        let config = DiffConfig::default();
+        let block = compare_screenshots(&baseline, &make_screenshot(block, (0, 0)), &config);
+        let scattered =
+            compare_screenshots(&baseline, &make_screenshot(scattered, (0, 0)), &config);
+
+        assert_eq!(block.different_cells, 16);
+        assert_eq!(scattered.different_cells, 16);
+        assert_eq!(block.similarity, scattered.similarity);
+
+        assert_eq!(
+            block.regions,
+            vec![DiffRegion {
+                top: 2,
+                left: 3,
+                bottom: 5,
+                right: 6,
+                cells: 16,
+            }]
+        );
+        assert_eq!(scattered.regions.len(), 16);
+        assert!(scattered.regions.iter().all(|r| r.cells == 1));
+
+        assert!(block.structural_similarity < scattered.structural_similarity);
+        assert!((scattered.structural_similarity - scattered.similarity).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reverse_video_screen_differs_from_normal() {
+        let mut normal = Screen::new(10, 2);
+        normal.process(b"hi");
+        let mut reversed = Screen::new(10, 2);
+        reversed.process(b"hi\x1b[?5h");
+
+        let baseline = Screenshot::from_screen(&normal, 0);
+        let actual = Screenshot::from_screen(&reversed, 0);
+        let result = compare_screenshots(&baseline, &actual, &DiffConfig::default());
+        assert_eq!(result.different_cells, 20);
+        assert_eq!(result.max_severity, DiffSeverity::AttrOnly);
+    }
+
     #[test]
     fn test_generate_diff_output() {
         let baseline = make_screenshot(vec![vec![make_cell('a', 1, 2)]], (0, 0));
@@ -555,4 +806,21 @@ mod tests {
         assert!(output.contains("Actual cursor"));
         assert!(output.contains("?"));
     }
+
+    #[test]
+    fn diff_grid_marks_changed_cells() {
+        let row = |text: &str| text.chars().map(|c| make_cell(c, -1, -1)).collect();
+        let baseline = make_screenshot(vec![row("abcd"), row("efgh"), row("ijkl")], (0, 0));
+        let actual = make_screenshot(vec![row("abXd"), row("efgh"), row("Yjkl")], (0, 0));
+        let config = DiffConfig {
+            diff_char: '#',
+            ..DiffConfig::default()
+        };
+
+        let result = compare_screenshots(&baseline, &actual, &config);
+        assert_eq!(
+            diff_grid(&baseline, &result, &config),
+            "+----+\n|  # |\n|    |\n|#   |\n+----+\n"
+        );
+    }
 }
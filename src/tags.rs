@@ -77,6 +77,23 @@ impl TagFilter {
         parser.parse()
     }
 
+    /// Match scenarios with any of `include` (or any scenario, when it is
+    /// empty) and none of `exclude`
+    pub fn include_exclude(include: &[String], exclude: &[String]) -> Self {
+        let mut parts = Vec::new();
+        if !include.is_empty() {
+            parts.push(TagFilter::HasAny(include.to_vec()));
+        }
+        if !exclude.is_empty() {
+            parts.push(TagFilter::HasNone(exclude.to_vec()));
+        }
+        match parts.len() {
+            0 => TagFilter::All,
+            1 => parts.remove(0),
+            _ => TagFilter::And(parts),
+        }
+    }
+
     /// Check if a scenario matches this filter
     pub fn matches(&self, scenario: &Scenario) -> bool {
         self.matches_tags(&scenario.tags)
@@ -388,7 +405,7 @@ pub fn calculate_tag_stats(scenarios: &[(Scenario, std::path::PathBuf)]) -> TagS
     }
 
     let mut most_common: Vec<(String, usize)> = tag_counts.into_iter().collect();
-    most_common.sort_by(|a, b| b.1.cmp(&a.1));
+    most_common.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
 
     TagStats {
         total_scenarios: scenarios.len(),
@@ -438,6 +455,7 @@ mod tests {
             seed: None,
             timeout_ms: None,
             tags,
+            ..Scenario::default()
         }
     }
 
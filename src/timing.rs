@@ -90,6 +90,48 @@ pub struct ExecutionCheckpoint {
     pub description: String,
 }
 
+/// Real time per tick in [`TimingMode::RealTime`], matching the runner's
+/// 10 ms per tick conversion of step and wait timeouts
+pub const REAL_TIME_TICK_MS: u64 = 10;
+
+/// How ticks relate to real time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimingMode {
+    /// Ticks advance only when waited for; runs are reproducible
+    #[default]
+    Deterministic,
+    /// Ticks follow the wall clock; waits sleep and timeouts are real, but
+    /// runs can't be replayed
+    RealTime,
+}
+
+/// Wall-clock reference point for [`TimingMode::RealTime`]
+#[allow(clippy::disallowed_types)]
+struct RealClock {
+    start: std::time::Instant,
+}
+
+#[allow(clippy::disallowed_types)]
+impl RealClock {
+    fn start() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.start.elapsed().as_millis() as u64
+    }
+
+    /// Sleep until `ms` milliseconds have passed since the start
+    fn sleep_until_ms(&self, ms: u64) {
+        let target = std::time::Duration::from_millis(ms);
+        if let Some(remaining) = target.checked_sub(self.start.elapsed()) {
+            std::thread::sleep(remaining);
+        }
+    }
+}
+
 /// Timing controller for deterministic scenario execution
 pub struct TimingController {
     /// Deterministic scheduler
@@ -102,6 +144,16 @@ pub struct TimingController {
     checkpoint_index: usize,
     /// Whether to halt on non-deterministic behavior
     halt_on_divergence: bool,
+    /// Tick budget for the current step, as (start tick, ticks allowed)
+    budget: Option<(u64, u64)>,
+    /// Whether a wait was cut short by the budget
+    budget_exceeded: bool,
+    /// Real time slept per tick; zero keeps waits instantaneous
+    tick_delay: std::time::Duration,
+    /// Whether ticks follow the wall clock
+    mode: TimingMode,
+    /// Start of the run, for real-time ticks
+    clock: RealClock,
 }
 
 impl TimingController {
@@ -113,6 +165,11 @@ impl TimingController {
             expected_checkpoints: None,
             checkpoint_index: 0,
             halt_on_divergence: true,
+            budget: None,
+            budget_exceeded: false,
+            tick_delay: std::time::Duration::ZERO,
+            mode: TimingMode::Deterministic,
+            clock: RealClock::start(),
         }
     }
 
@@ -124,17 +181,45 @@ impl TimingController {
             expected_checkpoints: Some(expected),
             checkpoint_index: 0,
             halt_on_divergence: true,
+            budget: None,
+            budget_exceeded: false,
+            tick_delay: std::time::Duration::ZERO,
+            mode: TimingMode::Deterministic,
+            clock: RealClock::start(),
         }
     }
 
+    /// Switch between deterministic and real-time ticks
+    pub fn set_mode(&mut self, mode: TimingMode) {
+        self.mode = mode;
+        self.clock = RealClock::start();
+    }
+
+    /// Get the timing mode
+    pub fn mode(&self) -> TimingMode {
+        self.mode
+    }
+
+    /// Sleep `ms` milliseconds of real time per tick, for slow-motion runs
+    /// and programs that need wall-clock time to respond
+    pub fn set_tick_delay(&mut self, ms: u64) {
+        self.tick_delay = std::time::Duration::from_millis(ms);
+    }
+
     /// Set whether to halt on non-deterministic behavior
     pub fn set_halt_on_divergence(&mut self, halt: bool) {
         self.halt_on_divergence = halt;
     }
 
     /// Get current tick count
+    ///
+    /// In real-time mode this is the wall-clock time since the mode was set,
+    /// in ticks of [`REAL_TIME_TICK_MS`].
     pub fn now(&self) -> u64 {
-        self.scheduler.now()
+        match self.mode {
+            TimingMode::Deterministic => self.scheduler.now(),
+            TimingMode::RealTime => self.clock.elapsed_ms() / REAL_TIME_TICK_MS,
+        }
     }
 
     /// Get the deterministic scheduler
@@ -145,11 +230,44 @@ impl TimingController {
     /// Advance time by a specific number of ticks
     pub fn wait_ticks(&mut self, ticks: u64) -> Result<(), TimingError> {
         for _ in 0..ticks {
+            if let Err(e) = self.check_budget() {
+                self.budget_exceeded = true;
+                return Err(e);
+            }
             self.scheduler.boundary(BoundaryKind::AfterInput);
+            if self.mode == TimingMode::RealTime {
+                let next = self.now() + 1;
+                self.clock.sleep_until_ms(next * REAL_TIME_TICK_MS);
+            }
+            if !self.tick_delay.is_zero() {
+                std::thread::sleep(self.tick_delay);
+            }
         }
         Ok(())
     }
 
+    /// Limit waiting to `ticks` from now; `None` removes the limit
+    pub fn set_budget(&mut self, ticks: Option<u64>) {
+        self.budget = ticks.map(|ticks| (self.now(), ticks));
+        self.budget_exceeded = false;
+    }
+
+    /// Whether a wait has been refused since the budget was set
+    pub fn budget_exceeded(&self) -> bool {
+        self.budget_exceeded
+    }
+
+    /// Fail once the current budget is used up
+    fn check_budget(&self) -> Result<(), TimingError> {
+        match self.budget {
+            Some((start, ticks)) if self.now() - start >= ticks => Err(TimingError::Timeout {
+                expected_ticks: ticks,
+                elapsed_ticks: self.now() - start,
+            }),
+            _ => Ok(()),
+        }
+    }
+
     /// Record a checkpoint at the current execution point
     pub fn checkpoint(
         &mut self,
@@ -244,6 +362,7 @@ impl TimingController {
     /// Reset the controller to initial state
     pub fn reset(&mut self, seed: u64) {
         let _ = self.scheduler.reset(seed);
+        self.clock = RealClock::start();
         self.checkpoints.clear();
         self.checkpoint_index = 0;
     }
@@ -275,7 +394,7 @@ impl ResizeController {
 
     /// Get the current PTY size
     pub fn current_size(process: &PtyProcess) -> (u16, u16) {
-        process.pty().size()
+        process.size()
     }
 }
 
@@ -628,4 +747,42 @@ mod tests {
         // RNG state should be different (different seeds)
         assert_ne!(cp1.rng_state, cp2.rng_state);
     }
+
+    #[test]
+    fn deterministic_mode_repeats_tick_sequence() {
+        let sequence = || {
+            let mut timing = TimingController::new(42);
+            [3, 1, 7, 0, 4]
+                .iter()
+                .map(|&ticks| {
+                    timing.wait_ticks(ticks).unwrap();
+                    timing.now()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sequence(), vec![3, 4, 11, 11, 15]);
+        assert_eq!(sequence(), sequence());
+    }
+
+    #[test]
+    fn real_time_mode_follows_the_clock() {
+        let mut timing = TimingController::new(42);
+        timing.set_mode(TimingMode::RealTime);
+        assert_eq!(timing.mode(), TimingMode::RealTime);
+        let start = timing.now();
+
+        timing.wait_ticks(5).unwrap();
+        assert!(timing.now() >= start + 5);
+
+        // Time passes without waiting, unlike deterministic ticks
+        std::thread::sleep(std::time::Duration::from_millis(5 * REAL_TIME_TICK_MS));
+        assert!(timing.now() >= start + 10);
+
+        // Budgets run out in real time too
+        timing.set_budget(Some(2));
+        std::thread::sleep(std::time::Duration::from_millis(3 * REAL_TIME_TICK_MS));
+        assert!(timing.wait_ticks(1).is_err());
+        assert!(timing.budget_exceeded());
+    }
 }
@@ -5,15 +5,22 @@
 
 use crate::determinism::DeterministicScheduler;
 use crate::invariants::InvariantResult;
-use crate::scenario::{Scenario, Step};
+use crate::scenario::{Scenario, Step, StepSource};
 use crate::screen::Screen;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt::Write as _;
 use std::fs::File;
-use std::io::{self, Write};
+use std::io::{self, BufRead, BufWriter, Write};
+use std::ops::Range;
 use std::path::Path;
 
 /// Trace version for forward compatibility
-pub const TRACE_VERSION: &str = "1.0.0";
+///
+/// 1.1.0: `after_screen_hash` includes output read once a step finished, so
+/// replays of 1.0.0 traces diverge on it
+pub const TRACE_VERSION: &str = "1.1.0";
 
 /// A complete execution trace
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,6 +47,55 @@ pub struct Trace {
     pub final_screen_hash: Option<u64>,
     /// Total ticks elapsed
     pub total_ticks: u64,
+    /// Most ticks between two steps that left the screen changed
+    #[serde(default)]
+    pub max_idle_gap_ticks: u64,
+    /// Cursor positions sampled after steps that moved it (only with
+    /// `--record-cursor-trail`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cursor_trail: Vec<CursorSample>,
+    /// Schedule events observed during the run (signals and resizes)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<ScheduleEvent>,
+    /// Which run of the scenario this trace records (above 1 only with
+    /// `--retries`)
+    #[serde(default = "default_attempts")]
+    pub attempts: u32,
+}
+
+fn default_attempts() -> u32 {
+    1
+}
+
+impl Trace {
+    /// Whether the trace was written in an older format than
+    /// [`TRACE_VERSION`], whose recorded hashes a replay can't reproduce
+    pub fn is_older_format(&self) -> bool {
+        let parts =
+            |v: &str| -> Vec<u64> { v.split('.').map(|p| p.parse().unwrap_or(0)).collect() };
+        parts(&self.version) < parts(TRACE_VERSION)
+    }
+
+    /// Why a replay of this older-format trace diverges
+    pub fn older_format_error(&self) -> String {
+        format!(
+            "trace from older format {} (this bte writes {}); record it again to replay it",
+            self.version, TRACE_VERSION
+        )
+    }
+}
+
+/// Cursor position after a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CursorSample {
+    /// Index of the step after which the position was sampled
+    pub step: usize,
+    /// Tick at the end of that step
+    pub tick: u64,
+    /// Cursor row (0-indexed)
+    pub row: usize,
+    /// Cursor column (0-indexed)
+    pub col: usize,
 }
 
 /// A single step in the trace
@@ -64,6 +120,57 @@ pub struct TraceStep {
     pub pty_output: Vec<u8>,
     /// Any error that occurred (if step failed)
     pub error: Option<String>,
+    /// Wall-clock timing (only with `--record-wallclock`).
+    ///
+    /// Not deterministic: never compared during replay.
+    #[serde(default)]
+    pub wallclock: Option<StepWallClock>,
+    /// The step's `comment` from the scenario
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// Where the step is written in the scenario; `None` for steps from a
+    /// `repeat` or `if_screen` block or a `steps_file`
+    #[serde(default)]
+    pub source: Option<StepSource>,
+    /// Why the step was skipped instead of run, e.g. a `resize` without
+    /// a PTY
+    #[serde(default)]
+    pub warning: Option<String>,
+    /// Screen hash an `assert_screen_hash` step compared against, before
+    /// output read after the assertion reached the screen
+    #[serde(default)]
+    pub asserted_screen_hash: Option<u64>,
+}
+
+/// Real elapsed time of a step, relative to the start of the run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepWallClock {
+    /// Microseconds from run start to the start of the step
+    pub start_us: u64,
+    /// Microseconds from run start to the end of the step
+    pub end_us: u64,
+}
+
+/// Wall-clock reference point for a run.
+///
+/// This is the only place traces touch real time; the values it produces
+/// live in [`StepWallClock`] and are kept apart from the tick fields.
+#[allow(clippy::disallowed_types)]
+struct WallClock {
+    start: std::time::Instant,
+}
+
+#[allow(clippy::disallowed_types)]
+impl WallClock {
+    fn start() -> Self {
+        Self {
+            start: std::time::Instant::now(),
+        }
+    }
+
+    fn elapsed_us(&self) -> u64 {
+        self.start.elapsed().as_micros() as u64
+    }
 }
 
 /// A checkpoint for replay verification
@@ -79,6 +186,9 @@ pub struct TraceCheckpoint {
     pub screen_hash: Option<u64>,
     /// Description of the checkpoint
     pub description: String,
+    /// Tab stop columns (only with `--record-tab-stops`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tab_stops: Option<Vec<usize>>,
 }
 
 /// Final outcome of trace execution
@@ -105,6 +215,9 @@ pub enum TraceOutcome {
         max_ticks: u64,
         /// Ticks elapsed before timeout
         elapsed_ticks: u64,
+        /// Start of the screen text when the run stalled
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        screen_preview: String,
     },
     /// Execution error
     Error {
@@ -112,6 +225,9 @@ pub enum TraceOutcome {
         message: String,
         /// Step where error occurred
         step_index: usize,
+        /// Start of the screen text when the error happened
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        screen_preview: String,
     },
     /// Process was killed by signal
     Signaled {
@@ -135,6 +251,28 @@ impl TraceOutcome {
     pub fn is_success(&self) -> bool {
         matches!(self, TraceOutcome::Success { .. })
     }
+
+    /// Exit code of the process, if it exited normally
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            TraceOutcome::Success { exit_code, .. } => Some(*exit_code),
+            _ => None,
+        }
+    }
+
+    /// Short description of how the run ended, e.g. "exit code 0"
+    fn exit_description(&self) -> String {
+        match self {
+            TraceOutcome::Success { exit_code, .. } => format!("exit code {}", exit_code),
+            TraceOutcome::InvariantViolation { invariant_name, .. } => {
+                format!("invariant violation ({})", invariant_name)
+            }
+            TraceOutcome::Timeout { .. } => "timeout".to_string(),
+            TraceOutcome::Error { message, .. } => format!("error ({})", message),
+            TraceOutcome::Signaled { signal_name, .. } => format!("signal {}", signal_name),
+            TraceOutcome::ReplayDivergence { .. } => "replay divergence".to_string(),
+        }
+    }
 }
 
 /// Maximum size of PTY output captured per step (1MB)
@@ -168,6 +306,16 @@ pub struct TraceBuilder {
     total_pty_bytes: usize,
     /// Tracks if a step is currently in progress (for state validation)
     step_in_progress: bool,
+    /// Wall-clock reference, when recording real timings
+    wallclock: Option<WallClock>,
+    /// Incremental on-disk writer, when streaming
+    stream: Option<TraceStream>,
+    /// What streamed steps are redacted with
+    redaction: RedactionPolicy,
+    /// Whether checkpoints capture the tab stops
+    record_tab_stops: bool,
+    /// Whether to sample the cursor after each step
+    record_cursor_trail: bool,
 }
 
 impl TraceBuilder {
@@ -190,10 +338,19 @@ impl TraceBuilder {
                 },
                 final_screen_hash: None,
                 total_ticks: 0,
+                max_idle_gap_ticks: 0,
+                cursor_trail: Vec::new(),
+                events: Vec::new(),
+                attempts: 1,
             },
             current_step_index: 0,
             total_pty_bytes: 0,
             step_in_progress: false,
+            wallclock: None,
+            stream: None,
+            redaction: RedactionPolicy::default(),
+            record_tab_stops: false,
+            record_cursor_trail: false,
         }
     }
 
@@ -246,6 +403,17 @@ impl TraceBuilder {
             invariant_violations: Vec::new(),
             pty_output: Vec::new(),
             error: None,
+            comment: None,
+            source: None,
+            warning: None,
+            asserted_screen_hash: None,
+            wallclock: self.wallclock.as_ref().map(|clock| {
+                let now = clock.elapsed_us();
+                StepWallClock {
+                    start_us: now,
+                    end_us: now,
+                }
+            }),
         });
         self.current_step_index += 1;
         self.step_in_progress = true;
@@ -280,10 +448,57 @@ impl TraceBuilder {
         if let Some(step) = self.trace.steps.last_mut() {
             step.end_tick = scheduler.now();
             step.after_screen_hash = screen.map(|s| s.state_hash());
+            if let (Some(timing), Some(clock)) = (step.wallclock.as_mut(), &self.wallclock) {
+                timing.end_us = clock.elapsed_us();
+            }
+            if let Some(cursor) = screen
+                .filter(|_| self.record_cursor_trail)
+                .map(Screen::cursor)
+            {
+                let trail = &mut self.trace.cursor_trail;
+                if trail.last().map(|s| (s.row, s.col)) != Some((cursor.row, cursor.col)) {
+                    trail.push(CursorSample {
+                        step: step.index,
+                        tick: step.end_tick,
+                        row: cursor.row,
+                        col: cursor.col,
+                    });
+                }
+            }
+
+            if let Some(stream) = self.stream.as_mut() {
+                match stream.write_step(&self.redaction.apply_step(step)) {
+                    // The output is on disk now; don't hold it in memory too
+                    Ok(()) => step.pty_output = Vec::new(),
+                    Err(e) => {
+                        eprintln!("Warning: Failed to stream trace step: {}", e);
+                        self.stream = None;
+                    }
+                }
+            }
         }
         self.step_in_progress = false;
     }
 
+    /// Stream the trace to `path` as JSON lines while it is built
+    ///
+    /// The header is written immediately and each step as soon as it ends;
+    /// `build` appends the footer. Streamed steps drop their `pty_output`
+    /// from memory, so only the file holds the full output; the rest of each
+    /// step stays in the trace.
+    pub fn stream_to(&mut self, path: &Path, policy: &RedactionPolicy) -> Result<(), io::Error> {
+        let mut stream = TraceStream::create(path)?;
+        stream.write_header(&policy.apply(&self.trace))?;
+        self.stream = Some(stream);
+        self.redaction = policy.clone();
+        Ok(())
+    }
+
+    /// Whether steps are being streamed to disk
+    pub fn is_streaming(&self) -> bool {
+        self.stream.is_some()
+    }
+
     /// Record an error in the current step
     pub fn record_error(&mut self, error: &str) {
         if let Some(step) = self.trace.steps.last_mut() {
@@ -291,6 +506,34 @@ impl TraceBuilder {
         }
     }
 
+    /// Attach the scenario's comment to the current step
+    pub fn record_comment(&mut self, comment: &str) {
+        if let Some(step) = self.trace.steps.last_mut() {
+            step.comment = Some(comment.to_string());
+        }
+    }
+
+    /// Note on the current step why it was skipped
+    pub fn record_warning(&mut self, warning: &str) {
+        if let Some(step) = self.trace.steps.last_mut() {
+            step.warning = Some(warning.to_string());
+        }
+    }
+
+    /// Record the screen hash the current `assert_screen_hash` step compared
+    pub fn record_asserted_screen_hash(&mut self, hash: u64) {
+        if let Some(step) = self.trace.steps.last_mut() {
+            step.asserted_screen_hash = Some(hash);
+        }
+    }
+
+    /// Record where the current step is written in the scenario
+    pub fn record_source(&mut self, source: StepSource) {
+        if let Some(step) = self.trace.steps.last_mut() {
+            step.source = Some(source);
+        }
+    }
+
     /// Record an invariant violation
     pub fn record_invariant_violation(&mut self, name: &str) {
         if let Some(step) = self.trace.steps.last_mut() {
@@ -313,6 +556,9 @@ impl TraceBuilder {
             rng_state,
             screen_hash: screen.map(|s| s.state_hash()),
             description: description.to_string(),
+            tab_stops: screen
+                .filter(|_| self.record_tab_stops)
+                .map(|s| s.tab_stops()),
         });
     }
 
@@ -336,6 +582,36 @@ impl TraceBuilder {
         self.trace.total_ticks = ticks;
     }
 
+    /// Start recording wall-clock timings for subsequent steps
+    pub fn enable_wallclock(&mut self) {
+        self.wallclock = Some(WallClock::start());
+    }
+
+    /// Sample the cursor after each subsequent step, keeping changes only
+    pub fn enable_cursor_trail(&mut self) {
+        self.record_cursor_trail = true;
+    }
+
+    /// Capture the screen's tab stops in subsequent checkpoints
+    pub fn enable_tab_stops(&mut self) {
+        self.record_tab_stops = true;
+    }
+
+    /// Record a schedule event (e.g. a signal delivery)
+    pub fn record_event(&mut self, event: ScheduleEvent) {
+        self.trace.events.push(event);
+    }
+
+    /// Set the longest idle gap observed during the run
+    pub fn set_max_idle_gap_ticks(&mut self, ticks: u64) {
+        self.trace.max_idle_gap_ticks = ticks;
+    }
+
+    /// Set which attempt at the scenario this trace records
+    pub fn set_attempts(&mut self, attempts: u32) {
+        self.trace.attempts = attempts;
+    }
+
     /// Get all checkpoints
     pub fn checkpoints(&self) -> &[TraceCheckpoint] {
         &self.trace.checkpoints
@@ -347,12 +623,18 @@ impl TraceBuilder {
         trace.outcome = TraceOutcome::Error {
             message,
             step_index: 0,
+            screen_preview: String::new(),
         };
         trace
     }
 
-    /// Build the final trace
-    pub fn build(self) -> Trace {
+    /// Build the final trace (writing the footer when streaming)
+    pub fn build(mut self) -> Trace {
+        if let Some(mut stream) = self.stream.take() {
+            if let Err(e) = stream.write_footer(&self.trace) {
+                eprintln!("Warning: Failed to finish streamed trace: {}", e);
+            }
+        }
         self.trace
     }
 
@@ -415,6 +697,10 @@ pub enum DivergenceKind {
     UnexpectedInvariantViolation,
     /// Checkpoint not found
     CheckpointNotFound,
+    /// Per-step before/after screen hash doesn't match
+    StepHashMismatch,
+    /// Process exit status doesn't match
+    ExitCodeMismatch,
 }
 
 impl<'a> ReplayEngine<'a> {
@@ -533,6 +819,139 @@ impl<'a> ReplayEngine<'a> {
         Ok(())
     }
 
+    /// Verify a re-driven step's screen hashes against the recorded `TraceStep`
+    ///
+    /// Compares the observed before/after screen hashes with the values stored
+    /// in the trace, so the first diverging step can be pinpointed instead of
+    /// only the next checkpoint. Hashes missing on either side are skipped.
+    pub fn verify_step(
+        &mut self,
+        step_index: usize,
+        before_screen_hash: Option<u64>,
+        after_screen_hash: Option<u64>,
+    ) -> Result<(), ReplayDivergence> {
+        let Some(expected) = self.trace.steps.get(step_index) else {
+            return Err(ReplayDivergence {
+                kind: DivergenceKind::StepHashMismatch,
+                expected: format!("step {}", step_index),
+                actual: format!("only {} steps recorded", self.trace.steps.len()),
+                context: "Step index out of bounds".to_string(),
+                step_index,
+                tick: self.tick,
+            });
+        };
+
+        let checks = [
+            ("before", expected.before_screen_hash, before_screen_hash),
+            ("after", expected.after_screen_hash, after_screen_hash),
+        ];
+
+        for (phase, expected_hash, actual_hash) in checks {
+            if let (Some(expected_hash), Some(actual_hash)) = (expected_hash, actual_hash) {
+                if expected_hash != actual_hash {
+                    let divergence = ReplayDivergence {
+                        kind: DivergenceKind::StepHashMismatch,
+                        expected: format!("0x{:x}", expected_hash),
+                        actual: format!("0x{:x}", actual_hash),
+                        context: format!("Step {}: {} screen hash mismatch", step_index, phase),
+                        step_index,
+                        tick: self.tick,
+                    };
+                    if self.halt_on_divergence {
+                        return Err(divergence);
+                    } else {
+                        self.divergences.push(divergence);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verify every re-driven step against the recorded trace
+    ///
+    /// Returns the index of the first step whose before/after hash diverged.
+    pub fn verify_steps(&mut self, observed: &[TraceStep]) -> Option<usize> {
+        let mut first_divergence = None;
+        for step in observed {
+            self.step_index = step.index;
+            self.tick = step.end_tick;
+            let recorded = self.divergences.len();
+            if let Err(divergence) =
+                self.verify_step(step.index, step.before_screen_hash, step.after_screen_hash)
+            {
+                self.divergences.push(divergence);
+            }
+            if self.divergences.len() > recorded {
+                first_divergence.get_or_insert(step.index);
+                if self.halt_on_divergence {
+                    break;
+                }
+            }
+        }
+        first_divergence
+    }
+
+    /// Compare a live re-run of the scenario against the recorded trace
+    ///
+    /// Checks each checkpoint's screen hash in order, then how the process
+    /// exited. Returns the first mismatch, which is also recorded.
+    pub fn verify_live(&mut self, rerun: &Trace) -> Option<ReplayDivergence> {
+        let divergence = self.first_live_divergence(rerun)?;
+        self.divergences.push(divergence.clone());
+        Some(divergence)
+    }
+
+    fn first_live_divergence(&self, rerun: &Trace) -> Option<ReplayDivergence> {
+        for (expected, actual) in self.expected_checkpoints.iter().zip(&rerun.checkpoints) {
+            if let (Some(expected_hash), Some(actual_hash)) =
+                (expected.screen_hash, actual.screen_hash)
+            {
+                if expected_hash != actual_hash {
+                    return Some(ReplayDivergence {
+                        kind: DivergenceKind::ScreenMismatch,
+                        expected: format!("0x{:x}", expected_hash),
+                        actual: format!("0x{:x}", actual_hash),
+                        context: format!(
+                            "Checkpoint '{}': screen hash mismatch",
+                            expected.description
+                        ),
+                        // Checkpoint 0 is the initial state; n follows step n - 1
+                        step_index: actual.index.saturating_sub(1),
+                        tick: actual.tick,
+                    });
+                }
+            }
+        }
+
+        if self.expected_checkpoints.len() != rerun.checkpoints.len() {
+            return Some(ReplayDivergence {
+                kind: DivergenceKind::CheckpointNotFound,
+                expected: format!("{} checkpoints", self.expected_checkpoints.len()),
+                actual: format!("{} checkpoints", rerun.checkpoints.len()),
+                context: "Live run recorded a different number of checkpoints".to_string(),
+                step_index: rerun.steps.len(),
+                tick: rerun.total_ticks,
+            });
+        }
+
+        let expected_exit = self.trace.outcome.exit_description();
+        let actual_exit = rerun.outcome.exit_description();
+        if expected_exit != actual_exit {
+            return Some(ReplayDivergence {
+                kind: DivergenceKind::ExitCodeMismatch,
+                expected: expected_exit,
+                actual: actual_exit,
+                context: "Process exit status mismatch".to_string(),
+                step_index: rerun.steps.len(),
+                tick: rerun.total_ticks,
+            });
+        }
+
+        None
+    }
+
     /// Verify screen content matches expected
     pub fn verify_screen(
         &self,
@@ -543,8 +962,8 @@ impl<'a> ReplayEngine<'a> {
         if !expected_screen.visual_equals(actual_screen) {
             return Err(ReplayDivergence {
                 kind: DivergenceKind::ScreenMismatch,
-                expected: expected_screen.text(),
-                actual: actual_screen.text(),
+                expected: expected_screen.text().to_string(),
+                actual: actual_screen.text().to_string(),
                 context: format!("Step {}: screen content mismatch", step_index),
                 step_index,
                 tick: self.tick,
@@ -584,22 +1003,443 @@ impl<'a> ReplayEngine<'a> {
     }
 }
 
-/// Load a trace from a file
+/// Magic bytes prefixing binary trace files
+const BINARY_TRACE_MAGIC: &[u8; 8] = b"BTETRACE";
+
+/// On-disk trace encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// Pretty-printed JSON (default, human readable)
+    Json,
+    /// Compact MessagePack with a magic header
+    Binary,
+    /// Gzip-compressed pretty-printed JSON
+    JsonGz,
+    /// One JSON record per line: header, steps, then an outcome footer
+    JsonLines,
+}
+
+/// One line of a JSON-lines trace
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum TraceRecord {
+    Header {
+        version: String,
+        created_at: String,
+        seed: u64,
+        scenario: Box<Scenario>,
+        initial_rng_state: u64,
+    },
+    Step(Box<TraceStep>),
+    Footer {
+        checkpoints: Vec<TraceCheckpoint>,
+        invariant_results: Vec<InvariantResult>,
+        outcome: TraceOutcome,
+        final_screen_hash: Option<u64>,
+        total_ticks: u64,
+        max_idle_gap_ticks: u64,
+        #[serde(default)]
+        cursor_trail: Vec<CursorSample>,
+        #[serde(default)]
+        events: Vec<ScheduleEvent>,
+        #[serde(default = "default_attempts")]
+        attempts: u32,
+    },
+}
+
+/// Writer for JSON-lines traces; every record is flushed as it is written
+/// so a crash leaves all completed steps on disk
+struct TraceStream {
+    writer: BufWriter<File>,
+}
+
+impl TraceStream {
+    fn create(path: &Path) -> Result<Self, io::Error> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+        })
+    }
+
+    fn write_record(&mut self, record: &TraceRecord) -> Result<(), io::Error> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    fn write_header(&mut self, trace: &Trace) -> Result<(), io::Error> {
+        self.write_record(&TraceRecord::Header {
+            version: trace.version.clone(),
+            created_at: trace.created_at.clone(),
+            seed: trace.seed,
+            scenario: Box::new(trace.scenario.clone()),
+            initial_rng_state: trace.initial_rng_state,
+        })
+    }
+
+    fn write_step(&mut self, step: &TraceStep) -> Result<(), io::Error> {
+        self.write_record(&TraceRecord::Step(Box::new(step.clone())))
+    }
+
+    fn write_footer(&mut self, trace: &Trace) -> Result<(), io::Error> {
+        self.write_record(&TraceRecord::Footer {
+            checkpoints: trace.checkpoints.clone(),
+            invariant_results: trace.invariant_results.clone(),
+            outcome: trace.outcome.clone(),
+            final_screen_hash: trace.final_screen_hash,
+            total_ticks: trace.total_ticks,
+            max_idle_gap_ticks: trace.max_idle_gap_ticks,
+            cursor_trail: trace.cursor_trail.clone(),
+            events: trace.events.clone(),
+            attempts: trace.attempts,
+        })
+    }
+}
+
+/// Reassemble a JSON-lines trace
+///
+/// A trace cut short before its footer (e.g. the run crashed) still loads,
+/// with every completed step and an error outcome.
+fn parse_trace_lines(data: &[u8]) -> Result<Trace, io::Error> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let mut lines = data
+        .lines()
+        .filter(|l| l.as_ref().map_or(true, |l| !l.is_empty()));
+
+    let header = lines
+        .next()
+        .ok_or_else(|| invalid("empty trace".to_string()))??;
+    let mut trace = match serde_json::from_str(&header)? {
+        TraceRecord::Header {
+            version,
+            created_at,
+            seed,
+            scenario,
+            initial_rng_state,
+        } => Trace {
+            version,
+            created_at,
+            seed,
+            scenario: *scenario,
+            initial_rng_state,
+            steps: Vec::new(),
+            checkpoints: Vec::new(),
+            invariant_results: Vec::new(),
+            outcome: TraceOutcome::Success {
+                exit_code: -1,
+                total_ticks: 0,
+            },
+            final_screen_hash: None,
+            total_ticks: 0,
+            max_idle_gap_ticks: 0,
+            cursor_trail: Vec::new(),
+            events: Vec::new(),
+            attempts: 1,
+        },
+        _ => return Err(invalid("trace must start with a header record".to_string())),
+    };
+
+    let mut complete = false;
+    for line in lines {
+        match serde_json::from_str(&line?)? {
+            TraceRecord::Step(step) => trace.steps.push(*step),
+            TraceRecord::Footer {
+                checkpoints,
+                invariant_results,
+                outcome,
+                final_screen_hash,
+                total_ticks,
+                max_idle_gap_ticks,
+                cursor_trail,
+                events,
+                attempts,
+            } => {
+                trace.cursor_trail = cursor_trail;
+                trace.events = events;
+                trace.attempts = attempts;
+                trace.checkpoints = checkpoints;
+                trace.invariant_results = invariant_results;
+                trace.outcome = outcome;
+                trace.final_screen_hash = final_screen_hash;
+                trace.total_ticks = total_ticks;
+                trace.max_idle_gap_ticks = max_idle_gap_ticks;
+                complete = true;
+            }
+            TraceRecord::Header { .. } => {
+                return Err(invalid("unexpected second header record".to_string()))
+            }
+        }
+    }
+
+    if !complete {
+        trace.outcome = TraceOutcome::Error {
+            message: "Trace is incomplete (no footer record)".to_string(),
+            step_index: trace.steps.len(),
+            screen_preview: String::new(),
+        };
+    }
+    Ok(trace)
+}
+
+impl TraceFormat {
+    /// Pick a format from a file extension (`.bin` is binary, `.json.gz` is
+    /// compressed JSON, anything else JSON)
+    pub fn from_path(path: &Path) -> Self {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if name.ends_with(".bin") {
+            TraceFormat::Binary
+        } else if name.ends_with(".json.gz") {
+            TraceFormat::JsonGz
+        } else if name.ends_with(".jsonl") {
+            TraceFormat::JsonLines
+        } else {
+            TraceFormat::Json
+        }
+    }
+}
+
+/// Gzip stream header
+const GZIP_MAGIC: &[u8; 2] = &[0x1f, 0x8b];
+
+/// Load a trace from a file, detecting binary and gzip traces by their magic bytes
 pub fn load_trace(path: &Path) -> Result<Trace, io::Error> {
-    let file = File::open(path)?;
-    let trace: Trace = serde_json::from_reader(file)?;
+    let mut data = std::fs::read(path)?;
+    if data.starts_with(GZIP_MAGIC) {
+        let mut decoded = Vec::new();
+        io::Read::read_to_end(&mut flate2::read::GzDecoder::new(&data[..]), &mut decoded)?;
+        data = decoded;
+    }
+    if let Some(payload) = data.strip_prefix(BINARY_TRACE_MAGIC) {
+        return rmp_serde::from_slice(payload)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e));
+    }
+    if data.starts_with(b"{\"record\"") {
+        return parse_trace_lines(&data);
+    }
+    let trace: Trace = serde_json::from_slice(&data)?;
     Ok(trace)
 }
 
-/// Save a trace to a file
-pub fn save_trace(trace: &Trace, path: &Path) -> Result<(), io::Error> {
+/// Placeholder written in place of redacted values
+pub const REDACTED: &str = "***";
+
+/// Key pattern for environment variables redacted by default
+const DEFAULT_REDACT_PATTERN: &str = "(?i)(token|secret|password|key)";
+
+/// Which scenario environment variables to mask when a trace is saved
+///
+/// Only the copy written to disk is redacted; the run itself and the
+/// in-memory trace keep the real values.
+#[derive(Debug, Clone)]
+pub struct RedactionPolicy {
+    key_patterns: Vec<Regex>,
+    /// Secret values masked wherever they were interpolated
+    values: Vec<String>,
+}
+
+impl Default for RedactionPolicy {
+    fn default() -> Self {
+        Self {
+            key_patterns: vec![Regex::new(DEFAULT_REDACT_PATTERN).expect("valid default pattern")],
+            values: Vec::new(),
+        }
+    }
+}
+
+impl RedactionPolicy {
+    /// Redact variables whose names match any of these regexes
+    pub fn from_patterns<S: AsRef<str>>(patterns: &[S]) -> Result<Self, regex::Error> {
+        let key_patterns = patterns
+            .iter()
+            .map(|p| Regex::new(p.as_ref()))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            key_patterns,
+            values: Vec::new(),
+        })
+    }
+
+    /// Also mask these values in the command, `cwd` and step patterns, e.g.
+    /// a `${GITHUB_TOKEN}` the scenario interpolated there
+    pub fn with_values(mut self, values: impl IntoIterator<Item = String>) -> Self {
+        self.values
+            .extend(values.into_iter().filter(|v| !v.is_empty()));
+        // Longest first, so a secret containing another is masked whole
+        self.values
+            .sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        self.values.dedup();
+        self
+    }
+
+    /// Whether a variable with this name should be masked
+    pub fn is_sensitive(&self, key: &str) -> bool {
+        self.key_patterns.iter().any(|re| re.is_match(key))
+    }
+
+    /// The trace as it should be written, with sensitive values masked
+    pub fn apply<'a>(&self, trace: &'a Trace) -> Cow<'a, Trace> {
+        if self.values.is_empty() && !trace.scenario.env.keys().any(|k| self.is_sensitive(k)) {
+            return Cow::Borrowed(trace);
+        }
+        let mut redacted = trace.clone();
+        for (key, value) in redacted.scenario.env.iter_mut() {
+            if self.is_sensitive(key) {
+                *value = REDACTED.to_string();
+            }
+        }
+        if !self.values.is_empty() {
+            redacted.scenario.mask_values(&self.values, REDACTED);
+            for step in &mut redacted.steps {
+                step.step.mask_values(&self.values, REDACTED);
+            }
+        }
+        Cow::Owned(redacted)
+    }
+
+    /// One step as it should be written
+    fn apply_step<'a>(&self, step: &'a TraceStep) -> Cow<'a, TraceStep> {
+        if self.values.is_empty() {
+            return Cow::Borrowed(step);
+        }
+        let mut redacted = step.clone();
+        redacted.step.mask_values(&self.values, REDACTED);
+        Cow::Owned(redacted)
+    }
+}
+
+/// Save a trace to a file, choosing the format from its extension
+pub fn save_trace(trace: &Trace, path: &Path, policy: &RedactionPolicy) -> Result<(), io::Error> {
+    save_trace_as(trace, path, TraceFormat::from_path(path), policy)
+}
+
+/// Save a trace to a file in an explicit format
+pub fn save_trace_as(
+    trace: &Trace,
+    path: &Path,
+    format: TraceFormat,
+    policy: &RedactionPolicy,
+) -> Result<(), io::Error> {
+    let trace = policy.apply(trace);
+    let trace = trace.as_ref();
     let mut file = File::create(path)?;
-    let json = serde_json::to_string_pretty(trace).map_err(io::Error::other)?;
-    file.write_all(json.as_bytes())?;
+    match format {
+        TraceFormat::Json => {
+            let json = serde_json::to_string_pretty(trace).map_err(io::Error::other)?;
+            file.write_all(json.as_bytes())?;
+        }
+        TraceFormat::Binary => {
+            let bytes = rmp_serde::to_vec_named(trace).map_err(io::Error::other)?;
+            file.write_all(BINARY_TRACE_MAGIC)?;
+            file.write_all(&bytes)?;
+        }
+        TraceFormat::JsonGz => {
+            let json = serde_json::to_string_pretty(trace).map_err(io::Error::other)?;
+            let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            encoder.write_all(json.as_bytes())?;
+            encoder.finish()?;
+        }
+        TraceFormat::JsonLines => {
+            let mut stream = TraceStream {
+                writer: BufWriter::new(file),
+            };
+            stream.write_header(trace)?;
+            for step in &trace.steps {
+                stream.write_step(step)?;
+            }
+            stream.write_footer(trace)?;
+        }
+    }
     Ok(())
 }
 
 /// Print trace summary to stdout
+/// Print the recorded cursor trail, one position per line
+pub fn print_cursor_trail(trace: &Trace) {
+    println!("=== Cursor Trail ===");
+    if trace.cursor_trail.is_empty() {
+        println!("(not recorded; run with --record-cursor-trail)");
+    }
+    for sample in &trace.cursor_trail {
+        println!(
+            "step {:>4}  tick {:>6}  row {:>3}  col {:>3}",
+            sample.step, sample.tick, sample.row, sample.col
+        );
+    }
+}
+
+/// Print the screen captured with a failed outcome, if any
+fn print_screen_preview(preview: &str) {
+    if !preview.is_empty() {
+        println!("Screen:");
+        for line in preview.lines() {
+            println!("  {}", line);
+        }
+    }
+}
+
+/// Where a failed run failed, by the comment of the last step that errored,
+/// e.g. `at step 3 'login submitted'`
+pub fn failed_step_description(trace: &Trace) -> Option<String> {
+    let step = trace.steps.iter().rev().find(|s| s.error.is_some())?;
+    let comment = step.comment.as_ref()?;
+    Some(format!("at step {} '{}'", step.index, comment))
+}
+
+/// Render PTY output as readable text.
+///
+/// Control codes are shown in caret notation (`^[` for ESC, `^M` for CR,
+/// `^?` for DEL) so escape sequences stay visible; line feeds still break
+/// lines. Invalid UTF-8 becomes U+FFFD.
+pub fn decode_output_preview(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for c in String::from_utf8_lossy(bytes).chars() {
+        match c {
+            '\n' => out.push('\n'),
+            '\x7f' => out.push_str("^?"),
+            c if c.is_ascii_control() => {
+                out.push('^');
+                out.push((c as u8 + b'@') as char);
+            }
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Render the full record of every step whose index falls in `range`
+pub fn format_trace_steps(trace: &Trace, range: Range<usize>) -> String {
+    let mut out = String::new();
+    for step in trace.steps.iter().filter(|s| range.contains(&s.index)) {
+        let action =
+            serde_json::to_string(&step.step).unwrap_or_else(|_| format!("{:?}", step.step));
+        let hash = |h: Option<u64>| h.map_or_else(|| "-".to_string(), |h| format!("{:016x}", h));
+        let _ = match &step.comment {
+            Some(comment) => writeln!(out, "=== Step {} '{}' ===", step.index, comment),
+            None => writeln!(out, "=== Step {} ===", step.index),
+        };
+        let _ = writeln!(out, "Step: {}", action);
+        let _ = writeln!(out, "Ticks: {}..{}", step.start_tick, step.end_tick);
+        let _ = writeln!(out, "Screen Hash Before: {}", hash(step.before_screen_hash));
+        let _ = writeln!(out, "Screen Hash After: {}", hash(step.after_screen_hash));
+        if let Some(error) = &step.error {
+            let _ = writeln!(out, "Error: {}", error);
+        }
+        for violation in &step.invariant_violations {
+            let _ = writeln!(out, "Violation: {}", violation);
+        }
+        let _ = writeln!(out, "Output: {} bytes", step.pty_output.len());
+        for line in decode_output_preview(&step.pty_output).lines() {
+            let _ = writeln!(out, "  {}", line);
+        }
+    }
+    if out.is_empty() {
+        out.push_str("(no matching steps)\n");
+    }
+    out
+}
+
 pub fn print_trace_summary(trace: &Trace) {
     println!("=== Trace Summary ===");
     println!("Version: {}", trace.version);
@@ -609,6 +1449,7 @@ pub fn print_trace_summary(trace: &Trace) {
     println!("Steps: {}", trace.steps.len());
     println!("Checkpoints: {}", trace.checkpoints.len());
     println!("Invariant Results: {}", trace.invariant_results.len());
+    println!("Max Idle Gap: {} ticks", trace.max_idle_gap_ticks);
 
     match &trace.outcome {
         TraceOutcome::Success {
@@ -630,18 +1471,25 @@ pub fn print_trace_summary(trace: &Trace) {
         TraceOutcome::Timeout {
             max_ticks,
             elapsed_ticks,
+            screen_preview,
         } => {
             println!("Status: TIMEOUT");
             println!("Max Ticks: {}", max_ticks);
             println!("Elapsed: {}", elapsed_ticks);
+            print_screen_preview(screen_preview);
         }
         TraceOutcome::Error {
             message,
             step_index,
+            screen_preview,
         } => {
             println!("Status: ERROR");
             println!("Message: {}", message);
             println!("Step: {}", step_index);
+            if let Some(location) = failed_step_description(trace) {
+                println!("Failed {}", location);
+            }
+            print_screen_preview(screen_preview);
         }
         TraceOutcome::Signaled {
             signal,
@@ -682,6 +1530,7 @@ mod tests {
             seed: Some(42),
             timeout_ms: Some(5000),
             tags: vec![],
+            ..Scenario::default()
         }
     }
 
@@ -701,7 +1550,7 @@ mod tests {
 
         let trace = builder.build();
 
-        assert_eq!(trace.version, "1.0.0");
+        assert_eq!(trace.version, TRACE_VERSION);
         assert_eq!(trace.seed, 42);
         assert_eq!(trace.steps.len(), 1);
         assert_eq!(trace.checkpoints.len(), 1);
@@ -717,7 +1566,7 @@ mod tests {
         let parsed: Trace = serde_json::from_str(&json).unwrap();
 
         assert_eq!(parsed.seed, 42);
-        assert_eq!(parsed.version, "1.0.0");
+        assert_eq!(parsed.version, TRACE_VERSION);
     }
 
     #[test]
@@ -732,6 +1581,152 @@ mod tests {
         assert_eq!(parsed.seed, 42);
     }
 
+    #[test]
+    fn trace_round_trips_in_json_and_binary() {
+        let mut scenario = create_test_scenario();
+        scenario.invariants = vec![crate::scenario::InvariantRef::CursorBounds.into()];
+        let mut builder = TraceBuilder::new(scenario, 42);
+        let scheduler = DeterministicScheduler::new(42);
+        let screen = Screen::new(80, 24);
+        for i in 0..20 {
+            builder.start_step(Step::WaitTicks { ticks: i }, Some(&screen), &scheduler);
+            builder.record_pty_output(&b"\x1b[1;32mhello\x1b[0m world\r\n".repeat(50));
+            builder.end_step(Some(&screen), &scheduler);
+        }
+        let trace = builder.build();
+        let expected = serde_json::to_value(&trace).unwrap();
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let json_path = dir.path().join("trace.json");
+        let bin_path = dir.path().join("trace.bin");
+        save_trace(&trace, &json_path, &RedactionPolicy::default()).unwrap();
+        save_trace(&trace, &bin_path, &RedactionPolicy::default()).unwrap();
+
+        for path in [&json_path, &bin_path] {
+            let loaded = load_trace(path).unwrap();
+            assert_eq!(serde_json::to_value(&loaded).unwrap(), expected);
+        }
+
+        // Detection is by magic bytes, not extension
+        let renamed = dir.path().join("trace.dat");
+        std::fs::rename(&bin_path, &renamed).unwrap();
+        assert_eq!(load_trace(&renamed).unwrap().steps.len(), 20);
+
+        let json_len = std::fs::metadata(&json_path).unwrap().len();
+        let bin_len = std::fs::metadata(&renamed).unwrap().len();
+        assert!(bin_len * 5 < json_len, "{} vs {}", bin_len, json_len);
+    }
+
+    #[test]
+    fn format_trace_steps_prints_the_selected_step() {
+        let mut builder = TraceBuilder::new(create_test_scenario(), 42);
+        let scheduler = DeterministicScheduler::new(42);
+        let screen = Screen::new(80, 24);
+        for ticks in [3, 5, 7] {
+            builder.start_step(Step::WaitTicks { ticks }, Some(&screen), &scheduler);
+            builder.record_pty_output(b"hello\r\n");
+            for _ in 0..ticks {
+                scheduler.boundary(crate::determinism::BoundaryKind::BeforePtyRead);
+            }
+            builder.end_step(Some(&screen), &scheduler);
+        }
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("trace.json");
+        save_trace(&builder.build(), &path, &RedactionPolicy::default()).unwrap();
+        let trace = load_trace(&path).unwrap();
+
+        let detail = format_trace_steps(&trace, 1..2);
+        assert!(detail.contains("=== Step 1 ==="), "{}", detail);
+        assert!(detail.contains("Ticks: 3..8"), "{}", detail);
+        assert!(detail.contains(r#""ticks":5"#), "{}", detail);
+        assert!(detail.contains("  hello^M\n"), "{}", detail);
+        assert!(!detail.contains("Step 0") && !detail.contains("Step 2"));
+
+        assert_eq!(
+            format_trace_steps(&trace, 1..usize::MAX)
+                .matches("===")
+                .count(),
+            4
+        );
+        assert_eq!(format_trace_steps(&trace, 9..10), "(no matching steps)\n");
+    }
+
+    #[test]
+    fn decode_output_preview_uses_caret_notation() {
+        assert_eq!(
+            decode_output_preview(b"\x1b[1;31mred\x1b[0m\r\nnext\tline\x7f"),
+            "^[[1;31mred^[[0m^M\nnext^Iline^?"
+        );
+        assert_eq!(
+            decode_output_preview(b"caf\xc3\xa9 \xff"),
+            "caf\u{e9} \u{fffd}"
+        );
+    }
+
+    #[test]
+    fn streamed_trace_survives_missing_footer() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("trace.jsonl");
+        let scheduler = DeterministicScheduler::new(42);
+        let screen = Screen::new(80, 24);
+
+        let mut builder = TraceBuilder::new(create_test_scenario(), 42);
+        builder
+            .stream_to(&path, &RedactionPolicy::default())
+            .unwrap();
+        for i in 1..=3 {
+            builder.start_step(Step::WaitTicks { ticks: i }, Some(&screen), &scheduler);
+            builder.record_pty_output(b"out");
+            builder.end_step(Some(&screen), &scheduler);
+        }
+
+        // Dropped without `build`, as when a run panics
+        drop(builder);
+        let partial = load_trace(&path).unwrap();
+        assert_eq!(partial.steps.len(), 3);
+        assert_eq!(partial.steps[2].pty_output, b"out");
+        assert!(matches!(
+            partial.outcome,
+            TraceOutcome::Error { step_index: 3, .. }
+        ));
+    }
+
+    #[test]
+    fn trace_round_trips_gzipped() {
+        let mut builder = TraceBuilder::new(create_test_scenario(), 42);
+        let scheduler = DeterministicScheduler::new(42);
+        let screen = Screen::new(80, 24);
+        for i in 0..20 {
+            builder.start_step(Step::WaitTicks { ticks: i }, Some(&screen), &scheduler);
+            builder.record_pty_output(&b"\x1b[1;32mhello\x1b[0m world\r\n".repeat(50));
+            builder.end_step(Some(&screen), &scheduler);
+        }
+        let trace = builder.build();
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let json_path = dir.path().join("trace.json");
+        let gz_path = dir.path().join("trace.json.gz");
+        save_trace(&trace, &json_path, &RedactionPolicy::default()).unwrap();
+        save_trace(&trace, &gz_path, &RedactionPolicy::default()).unwrap();
+
+        let compressed = std::fs::read(&gz_path).unwrap();
+        assert!(compressed.starts_with(GZIP_MAGIC));
+        let mut decompressed = Vec::new();
+        io::Read::read_to_end(
+            &mut flate2::read::GzDecoder::new(&compressed[..]),
+            &mut decompressed,
+        )
+        .unwrap();
+        assert_eq!(decompressed, std::fs::read(&json_path).unwrap());
+        assert!(compressed.len() * 10 < decompressed.len());
+
+        let loaded = load_trace(&gz_path).unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&trace).unwrap()
+        );
+    }
+
     #[test]
     fn replay_engine_verifies_checkpoints() {
         let scenario = create_test_scenario();
@@ -806,6 +1801,82 @@ mod tests {
         assert!(matches!(err.kind, DivergenceKind::RngMismatch));
     }
 
+    /// Drive a screen through a series of outputs, recording one step each
+    fn drive_steps(outputs: &[&[u8]], scheduler: &DeterministicScheduler) -> Vec<TraceStep> {
+        let mut builder = TraceBuilder::new(create_test_scenario(), 42);
+        let mut screen = Screen::new(80, 24);
+        for output in outputs {
+            builder.start_step(Step::WaitTicks { ticks: 1 }, Some(&screen), scheduler);
+            screen.process(output);
+            builder.end_step(Some(&screen), scheduler);
+        }
+        builder.build().steps
+    }
+
+    #[test]
+    fn replay_engine_verifies_matching_steps() {
+        let scheduler = DeterministicScheduler::new(42);
+        let outputs: &[&[u8]] = &[b"one\r\n", b"two\r\n", b"three\r\n"];
+
+        let mut trace = TraceBuilder::new(create_test_scenario(), 42).build();
+        trace.steps = drive_steps(outputs, &scheduler);
+
+        let observed = drive_steps(outputs, &scheduler);
+        let mut replay = ReplayEngine::new(&trace);
+        assert_eq!(replay.verify_steps(&observed), None);
+        assert!(replay.is_successful());
+    }
+
+    #[test]
+    fn replay_engine_pinpoints_first_divergent_step() {
+        let scheduler = DeterministicScheduler::new(42);
+
+        let mut trace = TraceBuilder::new(create_test_scenario(), 42).build();
+        trace.steps = drive_steps(&[b"one\r\n", b"two\r\n", b"three\r\n"], &scheduler);
+
+        // The modified app prints something different at step 1
+        let observed = drive_steps(&[b"one\r\n", b"TWO\r\n", b"three\r\n"], &scheduler);
+
+        let mut replay = ReplayEngine::new(&trace);
+        assert_eq!(replay.verify_steps(&observed), Some(1));
+
+        let first = &replay.divergences()[0];
+        assert!(matches!(first.kind, DivergenceKind::StepHashMismatch));
+        assert_eq!(first.step_index, 1);
+        assert!(first.context.contains("after screen hash mismatch"));
+    }
+
+    #[test]
+    fn replay_engine_collects_all_step_divergences() {
+        let scheduler = DeterministicScheduler::new(42);
+
+        let mut trace = TraceBuilder::new(create_test_scenario(), 42).build();
+        trace.steps = drive_steps(&[b"one\r\n", b"two\r\n", b"three\r\n"], &scheduler);
+        let observed = drive_steps(&[b"one\r\n", b"TWO\r\n", b"three\r\n"], &scheduler);
+
+        let mut replay = ReplayEngine::new(&trace);
+        replay.set_halt_on_divergence(false);
+        assert_eq!(replay.verify_steps(&observed), Some(1));
+
+        // Step 1 diverges after, step 2 diverges before and after
+        let steps: Vec<usize> = replay.divergences().iter().map(|d| d.step_index).collect();
+        assert_eq!(steps, vec![1, 2, 2]);
+        assert!(replay.divergences()[1]
+            .context
+            .contains("before screen hash"));
+    }
+
+    #[test]
+    fn replay_engine_reports_missing_step() {
+        let trace = TraceBuilder::new(create_test_scenario(), 42).build();
+        let mut replay = ReplayEngine::new(&trace);
+
+        let result = replay.verify_step(3, Some(1), Some(2));
+        let err = result.unwrap_err();
+        assert!(matches!(err.kind, DivergenceKind::StepHashMismatch));
+        assert_eq!(err.step_index, 3);
+    }
+
     #[test]
     fn trace_outcome_variants() {
         let success = TraceOutcome::Success {
@@ -829,7 +1900,7 @@ mod tests {
 
         // Save to temp file
         let path = Path::new("/tmp/test_trace.json");
-        save_trace(&trace, path).unwrap();
+        save_trace(&trace, path, &RedactionPolicy::default()).unwrap();
 
         // Load it back
         let loaded = load_trace(path).unwrap();
@@ -855,6 +1926,7 @@ mod tests {
             TraceOutcome::Error {
                 message,
                 step_index,
+                ..
             } => {
                 assert_eq!(message, &error_msg);
                 assert_eq!(*step_index, 0);
@@ -864,7 +1936,7 @@ mod tests {
 
         // Verify other fields are preserved
         assert_eq!(trace.seed, 42);
-        assert_eq!(trace.version, "1.0.0");
+        assert_eq!(trace.version, TRACE_VERSION);
     }
 }
 
@@ -1097,6 +2169,7 @@ mod sparse_replay_tests {
             seed: Some(42),
             timeout_ms: Some(1000),
             tags: vec![],
+            ..Scenario::default()
         }
     }
 
@@ -1330,6 +2403,15 @@ pub enum ScheduleEvent {
         /// Tick when delivered
         tick: u64,
     },
+    /// Terminal resized (the child gets SIGWINCH)
+    Resize {
+        /// New width
+        cols: u16,
+        /// New height
+        rows: u16,
+        /// Tick when resized
+        tick: u64,
+    },
     /// Blocking I/O operation
     BlockingIo {
         /// File descriptor
@@ -1498,6 +2580,7 @@ mod sparse_trace_tests {
             seed: Some(42),
             timeout_ms: Some(1000),
             tags: vec![],
+            ..Scenario::default()
         }
     }
 
@@ -1628,6 +2711,7 @@ mod sparse_trace_stress_tests {
             seed: Some(42),
             timeout_ms: Some(10000),
             tags: vec![],
+            ..Scenario::default()
         }
     }
 
@@ -1851,6 +2935,7 @@ mod sparse_trace_stress_tests {
                 ScheduleEvent::KeyInput { .. } => key_count += 1,
                 ScheduleEvent::Signal { .. } => signal_count += 1,
                 ScheduleEvent::BlockingIo { .. } => io_count += 1,
+                ScheduleEvent::Resize { .. } => unreachable!("no resizes recorded"),
             }
         }
 
@@ -211,7 +211,7 @@ fn parse_and_validate(yaml: &str) -> Result<(), Vec<String>> {
     let mut errors = Vec::new();
 
     // Check required fields
-    if scenario.get("name").is_none() || scenario["name"].as_str().map_or(true, |s| s.is_empty()) {
+    if scenario.get("name").is_none() || scenario["name"].as_str().is_none_or(|s| s.is_empty()) {
         errors.push("name: Scenario name cannot be empty".to_string());
     }
 
@@ -817,9 +817,9 @@ steps:
     let env = parsed["env"].as_mapping().expect("Should have env");
 
     assert_eq!(env.len(), 3);
-    assert!(env.contains_key(&serde_yaml::Value::String("VAR1".to_string())));
-    assert!(env.contains_key(&serde_yaml::Value::String("VAR2".to_string())));
-    assert!(env.contains_key(&serde_yaml::Value::String("VAR3".to_string())));
+    assert!(env.contains_key(serde_yaml::Value::String("VAR1".to_string())));
+    assert!(env.contains_key(serde_yaml::Value::String("VAR2".to_string())));
+    assert!(env.contains_key(serde_yaml::Value::String("VAR3".to_string())));
 }
 
 #[test]
@@ -869,3 +869,93 @@ steps:
     let result = parse_and_validate(yaml);
     assert!(result.is_ok());
 }
+
+#[test]
+fn test_run_json_format() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("echo.yaml");
+    std::fs::write(
+        &path,
+        r#"
+name: json-output
+command: echo hello
+steps:
+  - action: wait_for
+    pattern: hello
+    timeout_ms: 2000
+invariants:
+  - type: cursor_bounds
+"#,
+    )
+    .unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_bte"))
+        .args(["run", "--format", "json"])
+        .arg(&path)
+        .output()
+        .expect("Failed to run bte");
+    assert!(output.status.success(), "{:?}", output);
+
+    let json: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("stdout should be JSON");
+    assert_eq!(json["scenario"], "json-output");
+    assert_eq!(json["outcome"]["status"], "Success");
+    assert_eq!(json["exit_code"], 0);
+    assert_eq!(json["steps"], 1);
+    assert!(json["ticks"].is_u64());
+    assert!(!json["invariants"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_run_dry_run_prints_plan() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join("plan.yaml");
+    let trace = dir.path().join("trace.json");
+    let marker = dir.path().join("spawned");
+    std::fs::write(
+        &path,
+        format!(
+            r#"
+name: dry-run
+command: "touch {}"
+seed: 7
+steps:
+  - action: wait_for
+    pattern: "${{PROMPT:-ready}}"
+  - action: repeat
+    count: 2
+    steps:
+      - action: send_keys
+        keys: "x"
+teardown:
+  - action: send_signal
+    signal: SIGTERM
+"#,
+            marker.display()
+        ),
+    )
+    .unwrap();
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_bte"))
+        .args(["run", "--dry-run", "--output"])
+        .arg(&trace)
+        .arg(&path)
+        .output()
+        .expect("Failed to run bte");
+    assert!(output.status.success(), "{:?}", output);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Seed: 7"), "{}", stdout);
+    let steps: Vec<&str> = stdout
+        .lines()
+        .skip_while(|l| *l != "Steps:")
+        .skip(1)
+        .collect();
+    assert_eq!(steps.len(), 4, "{}", stdout);
+    assert!(steps[0].contains(r#""pattern":"ready""#), "{}", stdout);
+    assert!(steps[1].contains("send_keys") && steps[2].contains("send_keys"));
+    assert!(steps[3].contains("teardown") && steps[3].contains("SIGTERM"));
+
+    assert!(!trace.exists());
+    assert!(!marker.exists());
+}